@@ -0,0 +1,155 @@
+// Adoption of pre-existing resources for MyApp Controller
+// Takes ownership of unowned Deployments and Services when the MyApp opts in
+
+use crate::{create_owner_reference, publish_event, MyApp};
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::OwnerReference;
+use kube::api::{Api, Patch, PatchParams};
+use kube::runtime::events::EventType;
+use kube::{Client, Resource, ResourceExt};
+use serde::de::DeserializeOwned;
+use std::fmt::Debug;
+
+/// Annotation on the MyApp allowing it to adopt unowned children ("true")
+pub const ADOPT_ANNOTATION: &str = "myapps.example.com/adopt";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Ownership {
+    /// Controlled by this MyApp
+    Owned,
+    /// No controller owner reference
+    Unowned,
+    /// Controlled by another object, named here
+    Foreign(String),
+}
+
+pub fn ownership(refs: &[OwnerReference], uid: &str) -> Ownership {
+    match refs.iter().find(|r| r.controller == Some(true)) {
+        Some(r) if r.uid == uid => Ownership::Owned,
+        Some(r) => Ownership::Foreign(format!("{}/{}", r.kind, r.name)),
+        None => Ownership::Unowned,
+    }
+}
+
+pub fn adoption_allowed(myapp: &MyApp) -> bool {
+    // A dev session only manages what it created itself
+    crate::dev::sandbox().is_none()
+        && myapp
+            .annotations()
+            .get(ADOPT_ANNOTATION)
+            .is_some_and(|v| v == "true")
+}
+
+/// Make sure an existing child is controlled by the MyApp, adopting it if allowed
+pub async fn ensure_owned<K>(
+    myapp: &MyApp,
+    api: &Api<K>,
+    child: &K,
+    client: Client,
+) -> Result<(), kube::Error>
+where
+    K: Resource<DynamicType = ()> + Clone + DeserializeOwned + Debug,
+{
+    let name = child.name_any();
+    let kind = K::kind(&());
+    let uid = myapp.uid().unwrap_or_default();
+
+    match ownership(child.owner_references(), &uid) {
+        Ownership::Owned => Ok(()),
+        Ownership::Foreign(owner) => {
+            publish_event(
+                myapp,
+                client,
+                EventType::Warning,
+                "AdoptionConflict",
+                "Adopt",
+                format!("{} {} is controlled by {}", kind, name, owner),
+            )
+            .await;
+            Ok(())
+        }
+        Ownership::Unowned if !adoption_allowed(myapp) => {
+            tracing::info!(
+                "{} {} exists without an owner; annotate the MyApp with {}=true to adopt it",
+                kind,
+                name,
+                ADOPT_ANNOTATION
+            );
+            publish_event(
+                myapp,
+                client,
+                EventType::Warning,
+                "AdoptionRequired",
+                "Adopt",
+                format!(
+                    "{} {} is not owned by this MyApp; set {}=true to adopt it",
+                    kind, name, ADOPT_ANNOTATION
+                ),
+            )
+            .await;
+            Ok(())
+        }
+        Ownership::Unowned => {
+            let mut refs = child.owner_references().to_vec();
+            refs.push(create_owner_reference(myapp));
+            let patch = serde_json::json!({
+                "metadata": {
+                    "ownerReferences": refs,
+                    "labels": { "managed-by": "myapp-controller" }
+                }
+            });
+            api.patch(&name, &PatchParams::default(), &Patch::Merge(&patch))
+                .await?;
+            tracing::info!("Adopted {} {}", kind, name);
+            publish_event(
+                myapp,
+                client,
+                EventType::Normal,
+                "Adopted",
+                "Adopt",
+                format!("Adopted existing {} {}", kind, name),
+            )
+            .await;
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn owner(uid: &str, controller: bool) -> OwnerReference {
+        OwnerReference {
+            api_version: "example.com/v1".to_string(),
+            kind: "MyApp".to_string(),
+            name: "web".to_string(),
+            uid: uid.to_string(),
+            controller: Some(controller),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_ownership() {
+        assert_eq!(ownership(&[], "a"), Ownership::Unowned);
+        assert_eq!(ownership(&[owner("a", true)], "a"), Ownership::Owned);
+        assert_eq!(ownership(&[owner("b", false)], "a"), Ownership::Unowned);
+        assert_eq!(
+            ownership(&[owner("b", true)], "a"),
+            Ownership::Foreign("MyApp/web".to_string())
+        );
+    }
+
+    #[test]
+    fn test_adoption_requires_annotation() {
+        let mut myapp = MyApp::new("web", Default::default());
+        assert!(!adoption_allowed(&myapp));
+
+        myapp.metadata.annotations = Some(
+            [(ADOPT_ANNOTATION.to_string(), "true".to_string())]
+                .into_iter()
+                .collect(),
+        );
+        assert!(adoption_allowed(&myapp));
+    }
+}
@@ -0,0 +1,239 @@
+// Canary metric analysis for MyApp Controller
+// Queries Prometheus at the end of each canary step and gates promotion on the results
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Prometheus queries that must pass before the canary moves on
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct CanaryAnalysis {
+    /// Prometheus base URL (default: MYAPP_PROMETHEUS_URL)
+    #[serde(default)]
+    pub prometheus_url: Option<String>,
+
+    /// Metrics evaluated at the end of every step
+    #[serde(default)]
+    pub metrics: Vec<AnalysisMetric>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AnalysisMetric {
+    /// Name reported in status, e.g. error-rate
+    pub name: String,
+
+    /// PromQL returning a single value; {{namespace}} and {{canary}} (the canary
+    /// Deployment name) are substituted. Use `or vector(0)` for series that may not exist yet.
+    pub query: String,
+
+    /// Fail when the value is above this
+    #[serde(default)]
+    pub max: Option<f64>,
+
+    /// Fail when the value is below this
+    #[serde(default)]
+    pub min: Option<f64>,
+}
+
+/// Result of one metric at the last analysis
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct MetricResult {
+    pub name: String,
+
+    /// Queried value, absent when the query returned nothing or failed
+    #[serde(default)]
+    pub value: Option<f64>,
+
+    pub passed: bool,
+
+    #[serde(default)]
+    pub message: Option<String>,
+}
+
+/// Verdict over all metrics
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Verdict {
+    Pass,
+    Fail,
+    /// A query returned no data or errored; analysis is retried
+    Inconclusive,
+}
+
+impl CanaryAnalysis {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.metrics.is_empty() {
+            return Err("strategy.canary.analysis.metrics must not be empty".to_string());
+        }
+        if self
+            .metrics
+            .iter()
+            .any(|m| m.max.is_none() && m.min.is_none())
+        {
+            return Err("strategy.canary.analysis metrics need a min or max threshold".to_string());
+        }
+        Ok(())
+    }
+}
+
+impl AnalysisMetric {
+    pub fn render_query(&self, namespace: &str, canary: &str) -> String {
+        self.query
+            .replace("{{namespace}}", namespace)
+            .replace("{{canary}}", canary)
+    }
+
+    pub fn evaluate(&self, value: f64) -> MetricResult {
+        let message = match (self.min, self.max) {
+            (_, Some(max)) if value > max => {
+                Some(format!("{} is above the maximum {}", value, max))
+            }
+            (Some(min), _) if value < min => {
+                Some(format!("{} is below the minimum {}", value, min))
+            }
+            _ => None,
+        };
+        MetricResult {
+            name: self.name.clone(),
+            value: Some(value),
+            passed: message.is_none(),
+            message,
+        }
+    }
+
+    fn inconclusive(&self, message: String) -> MetricResult {
+        MetricResult {
+            name: self.name.clone(),
+            value: None,
+            passed: false,
+            message: Some(message),
+        }
+    }
+}
+
+pub fn verdict(results: &[MetricResult]) -> Verdict {
+    if results.iter().any(|r| r.value.is_some() && !r.passed) {
+        Verdict::Fail
+    } else if results.iter().any(|r| r.value.is_none()) {
+        Verdict::Inconclusive
+    } else {
+        Verdict::Pass
+    }
+}
+
+/// First sample of an instant-vector or scalar query response
+pub fn parse_query_response(body: &Value) -> Option<f64> {
+    let data = &body["data"];
+    let value = match data["resultType"].as_str() {
+        Some("scalar") => &data["result"],
+        _ => &data["result"][0]["value"],
+    };
+    value[1]
+        .as_str()?
+        .parse::<f64>()
+        .ok()
+        .filter(|v| !v.is_nan())
+}
+
+async fn query(http: &reqwest::Client, url: &str, promql: &str) -> Result<Option<f64>, String> {
+    let resp = http
+        .get(format!("{}/api/v1/query", url.trim_end_matches('/')))
+        .query(&[("query", promql)])
+        .send()
+        .await
+        .map_err(|e| e.to_string())?;
+    if !resp.status().is_success() {
+        return Err(format!("Prometheus returned {}", resp.status()));
+    }
+    let body: Value = resp.json().await.map_err(|e| e.to_string())?;
+    Ok(parse_query_response(&body))
+}
+
+/// Run every query against Prometheus
+pub async fn run(analysis: &CanaryAnalysis, namespace: &str, canary: &str) -> Vec<MetricResult> {
+    let Some(url) = analysis
+        .prometheus_url
+        .clone()
+        .or_else(|| std::env::var("MYAPP_PROMETHEUS_URL").ok())
+    else {
+        return analysis
+            .metrics
+            .iter()
+            .map(|m| m.inconclusive("no Prometheus URL configured".to_string()))
+            .collect();
+    };
+
+    let http = reqwest::Client::builder()
+        .timeout(std::time::Duration::from_secs(10))
+        .build()
+        .expect("failed to build Prometheus HTTP client");
+
+    let mut results = Vec::new();
+    for metric in &analysis.metrics {
+        let promql = metric.render_query(namespace, canary);
+        let result = match query(&http, &url, &promql).await {
+            Ok(Some(value)) => metric.evaluate(value),
+            Ok(None) => metric.inconclusive("query returned no data".to_string()),
+            Err(e) => metric.inconclusive(format!("query failed: {}", e)),
+        };
+        results.push(result);
+    }
+    results
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn error_rate() -> AnalysisMetric {
+        AnalysisMetric {
+            name: "error-rate".to_string(),
+            query: r#"sum(rate(http_errors{namespace="{{namespace}}",pod=~"{{canary}}-.*"}[5m]))"#
+                .to_string(),
+            max: Some(0.05),
+            min: None,
+        }
+    }
+
+    #[test]
+    fn test_render_and_evaluate() {
+        let metric = error_rate();
+        assert_eq!(
+            metric.render_query("shop", "web-canary"),
+            r#"sum(rate(http_errors{namespace="shop",pod=~"web-canary-.*"}[5m]))"#
+        );
+        assert!(metric.evaluate(0.01).passed);
+        assert!(!metric.evaluate(0.2).passed);
+    }
+
+    #[test]
+    fn test_parse_response_and_verdict() {
+        let vector = serde_json::json!({
+            "status": "success",
+            "data": { "resultType": "vector", "result": [ { "metric": {}, "value": [1700000000.0, "0.25"] } ] }
+        });
+        assert_eq!(parse_query_response(&vector), Some(0.25));
+
+        let empty = serde_json::json!({ "data": { "resultType": "vector", "result": [] } });
+        assert_eq!(parse_query_response(&empty), None);
+
+        let metric = error_rate();
+        assert_eq!(verdict(&[metric.evaluate(0.01)]), Verdict::Pass);
+        assert_eq!(
+            verdict(&[
+                metric.evaluate(0.01),
+                metric.inconclusive("no data".to_string())
+            ]),
+            Verdict::Inconclusive
+        );
+        assert_eq!(
+            verdict(&[
+                metric.evaluate(0.9),
+                metric.inconclusive("no data".to_string())
+            ]),
+            Verdict::Fail
+        );
+    }
+}
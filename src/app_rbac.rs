@@ -0,0 +1,280 @@
+// Per-app RBAC for MyApp Controller
+// spec.rbac.rules become a Role bound to a ServiceAccount `<myapp>-sa` that the pods run as,
+// so each app gets the API access it asks for and nothing more. The rules are checked against
+// the operator config's appRbacPolicy, whose denied rules default to Secrets, RBAC objects and
+// the escalate, bind and impersonate verbs. The controller holds no escalate or bind permission,
+// so the API server refuses Roles with rules the controller does not hold itself.
+
+use crate::deletion::{owned, MANAGED_BY_SELECTOR};
+use crate::{app_labels, child_name, create_owner_reference, MyApp, ReconcileError};
+use k8s_openapi::api::core::v1::ServiceAccount;
+use k8s_openapi::api::rbac::v1::{PolicyRule, Role, RoleBinding, RoleRef, Subject};
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta;
+use kube::api::{Api, DeleteParams, Patch, PatchParams};
+use kube::{Client, Resource, ResourceExt};
+use schemars::JsonSchema;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::fmt::Debug;
+
+/// One rule of a Role; "*" matches every group, resource or verb
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, JsonSchema, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct RbacRule {
+    /// API groups (the core group when empty)
+    #[serde(default)]
+    pub api_groups: Vec<String>,
+
+    pub resources: Vec<String>,
+
+    pub verbs: Vec<String>,
+
+    #[serde(default)]
+    pub resource_names: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, JsonSchema, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct AppRbac {
+    /// Namespaced permissions of the app's ServiceAccount
+    pub rules: Vec<RbacRule>,
+}
+
+impl RbacRule {
+    fn new(api_group: &str, resources: &[&str], verbs: &[&str]) -> Self {
+        let strings = |items: &[&str]| items.iter().map(|s| s.to_string()).collect();
+        Self {
+            api_groups: vec![api_group.to_string()],
+            resources: strings(resources),
+            verbs: strings(verbs),
+            resource_names: Vec::new(),
+        }
+    }
+
+    fn groups(&self) -> Vec<String> {
+        match self.api_groups.is_empty() {
+            true => vec![String::new()],
+            false => self.api_groups.clone(),
+        }
+    }
+
+    fn policy_rule(&self) -> PolicyRule {
+        PolicyRule {
+            api_groups: Some(self.groups()),
+            resources: Some(self.resources.clone()),
+            verbs: self.verbs.clone(),
+            resource_names: (!self.resource_names.is_empty()).then(|| self.resource_names.clone()),
+            ..Default::default()
+        }
+    }
+}
+
+impl AppRbac {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.rules.is_empty() {
+            return Err("rbac.rules needs at least one rule".to_string());
+        }
+        if self
+            .rules
+            .iter()
+            .any(|rule| rule.resources.is_empty() || rule.verbs.is_empty())
+        {
+            return Err("rbac.rules entries need resources and verbs".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// Rules no app may request unless the operator config says otherwise
+pub fn default_denied() -> Vec<RbacRule> {
+    vec![
+        RbacRule::new("", &["secrets"], &["*"]),
+        RbacRule::new("rbac.authorization.k8s.io", &["*"], &["*"]),
+        RbacRule::new("*", &["*"], &["escalate", "bind", "impersonate"]),
+    ]
+}
+
+/// Whether a denied entry covers a requested one; subresources fall under their resource
+fn covers(denied: &[String], requested: &[String]) -> bool {
+    requested.iter().any(|r| {
+        r == "*"
+            || denied
+                .iter()
+                .any(|d| d == "*" || d == r || r.starts_with(&format!("{}/", d)))
+    })
+}
+
+/// Refuse rules that overlap any of the `denied` ones
+pub fn check(rbac: &AppRbac, denied: &[RbacRule]) -> Result<(), String> {
+    for (i, rule) in rbac.rules.iter().enumerate() {
+        let conflict = denied.iter().find(|d| {
+            covers(&d.groups(), &rule.groups())
+                && covers(&d.resources, &rule.resources)
+                && covers(&d.verbs, &rule.verbs)
+        });
+        if let Some(d) = conflict {
+            return Err(format!(
+                "rbac.rules[{}] grants {} on {}, which the operator's appRbacPolicy denies ({} on {})",
+                i,
+                rule.verbs.join(","),
+                rule.resources.join(","),
+                d.verbs.join(","),
+                d.resources.join(",")
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// ServiceAccount the MyApp's pods run as, if it asks for API access
+pub fn service_account_name(myapp: &MyApp) -> Option<String> {
+    myapp.spec.rbac.as_ref().map(|_| child_name(myapp, "sa"))
+}
+
+fn metadata(myapp: &MyApp, suffix: &str) -> ObjectMeta {
+    ObjectMeta {
+        name: Some(child_name(myapp, suffix)),
+        namespace: myapp.namespace(),
+        labels: Some(app_labels(myapp)),
+        owner_references: Some(vec![create_owner_reference(myapp)]),
+        ..Default::default()
+    }
+}
+
+/// The ServiceAccount, Role and RoleBinding granting `rbac` to the MyApp's pods
+pub fn build(myapp: &MyApp, rbac: &AppRbac) -> (ServiceAccount, Role, RoleBinding) {
+    let account = ServiceAccount {
+        metadata: metadata(myapp, "sa"),
+        ..Default::default()
+    };
+    let role = Role {
+        metadata: metadata(myapp, "role"),
+        rules: Some(rbac.rules.iter().map(RbacRule::policy_rule).collect()),
+    };
+    let binding = RoleBinding {
+        metadata: metadata(myapp, "rolebinding"),
+        role_ref: RoleRef {
+            api_group: "rbac.authorization.k8s.io".to_string(),
+            kind: "Role".to_string(),
+            name: role.name_any(),
+        },
+        subjects: Some(vec![Subject {
+            kind: "ServiceAccount".to_string(),
+            name: account.name_any(),
+            namespace: myapp.namespace(),
+            ..Default::default()
+        }]),
+    };
+    (account, role, binding)
+}
+
+/// Delete the MyApp's objects of one kind other than `wanted`
+async fn prune<K>(api: &Api<K>, uid: &str, wanted: Option<&str>) -> Result<(), ReconcileError>
+where
+    K: Resource + Clone + DeserializeOwned + Debug,
+{
+    for stale in owned(api, MANAGED_BY_SELECTOR, uid).await? {
+        if wanted != Some(stale.name_any().as_str()) {
+            match api
+                .delete(&stale.name_any(), &DeleteParams::default())
+                .await
+            {
+                Ok(_) => tracing::info!("Deleted {}", stale.name_any()),
+                Err(kube::Error::Api(e)) if e.code == 404 => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Apply the MyApp's ServiceAccount, Role and RoleBinding, or remove them once spec.rbac is
+/// dropped
+pub async fn reconcile(
+    myapp: &MyApp,
+    denied: &[RbacRule],
+    client: Client,
+) -> Result<(), ReconcileError> {
+    let ns = myapp.namespace().unwrap();
+    let uid = myapp.uid().unwrap_or_default();
+    let accounts: Api<ServiceAccount> = Api::namespaced(client.clone(), &ns);
+    let roles: Api<Role> = Api::namespaced(client.clone(), &ns);
+    let bindings: Api<RoleBinding> = Api::namespaced(client, &ns);
+
+    let wanted = match &myapp.spec.rbac {
+        Some(rbac) => {
+            check(rbac, denied).map_err(ReconcileError::ValidationError)?;
+            let (account, role, binding) = build(myapp, rbac);
+            let params = PatchParams::apply("myapp-controller").force();
+            accounts
+                .patch(&account.name_any(), &params, &Patch::Apply(&account))
+                .await?;
+            roles
+                .patch(&role.name_any(), &params, &Patch::Apply(&role))
+                .await?;
+            bindings
+                .patch(&binding.name_any(), &params, &Patch::Apply(&binding))
+                .await?;
+            Some((account.name_any(), role.name_any(), binding.name_any()))
+        }
+        None => None,
+    };
+
+    prune(&bindings, &uid, wanted.as_ref().map(|w| w.2.as_str())).await?;
+    prune(&roles, &uid, wanted.as_ref().map(|w| w.1.as_str())).await?;
+    prune(&accounts, &uid, wanted.as_ref().map(|w| w.0.as_str())).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rbac(rule: RbacRule) -> AppRbac {
+        AppRbac { rules: vec![rule] }
+    }
+
+    #[test]
+    fn test_check_against_default_policy() {
+        let denied = default_denied();
+        let configmaps = RbacRule::new("", &["configmaps"], &["get", "list", "watch"]);
+        assert!(check(&rbac(configmaps), &denied).is_ok());
+
+        let leases = RbacRule::new("coordination.k8s.io", &["leases"], &["get", "update"]);
+        assert!(check(&rbac(leases.clone()), &denied).is_ok());
+
+        // A wildcard verb would include escalate
+        let any_verb = RbacRule {
+            verbs: vec!["*".to_string()],
+            ..leases
+        };
+        assert!(check(&rbac(any_verb), &denied).is_err());
+
+        let secrets = RbacRule {
+            resources: vec!["secrets".to_string()],
+            verbs: vec!["get".to_string()],
+            ..Default::default()
+        };
+        assert!(check(&rbac(secrets), &denied).is_err());
+
+        let everything = RbacRule::new("*", &["*"], &["list"]);
+        assert!(check(&rbac(everything), &denied).is_err());
+
+        let pods = RbacRule::new("", &["pods/exec"], &["create"]);
+        let no_pod_exec = [RbacRule::new("", &["pods"], &["create"])];
+        assert!(check(&rbac(pods), &no_pod_exec).is_err());
+    }
+
+    #[test]
+    fn test_build() {
+        let mut myapp = MyApp::new("web", Default::default());
+        myapp.metadata.namespace = Some("shop".to_string());
+        myapp.metadata.uid = Some("uid-1".to_string());
+        let spec = rbac(RbacRule::new("", &["configmaps"], &["get"]));
+        let (account, role, binding) = build(&myapp, &spec);
+        assert_eq!(account.name_any(), "web-sa");
+        assert_eq!(role.rules.unwrap()[0].api_groups, Some(vec![String::new()]));
+        assert_eq!(binding.role_ref.name, "web-role");
+        assert_eq!(binding.subjects.unwrap()[0].name, "web-sa");
+    }
+}
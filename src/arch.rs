@@ -0,0 +1,421 @@
+// Architecture selection for MyApp Controller
+// Reads the image's manifest list from its registry so pods only land on nodes that can run it
+
+use crate::cache::ClusterCache;
+use crate::webhooks::MUTATE;
+use k8s_openapi::api::core::v1::{Node, NodeSelectorRequirement};
+use kube::api::Api;
+use kube::runtime::watcher;
+use kube::Client;
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::Arc;
+use std::time::Duration;
+use thiserror::Error;
+
+pub const ARCH_LABEL: &str = "kubernetes.io/arch";
+
+/// Budget for all registry requests of one detection, short of the mutating webhook's timeout
+const DETECT_DEADLINE: Duration = Duration::from_secs(MUTATE.timeout_seconds as u64 - 3);
+
+const MANIFEST_ACCEPT: &str = "application/vnd.oci.image.index.v1+json, \
+    application/vnd.docker.distribution.manifest.list.v2+json, \
+    application/vnd.oci.image.manifest.v1+json, \
+    application/vnd.docker.distribution.manifest.v2+json";
+
+#[derive(Error, Debug)]
+pub enum RegistryError {
+    #[error("registry request failed: {0}")]
+    Http(#[from] reqwest::Error),
+
+    #[error("registry returned {status} for {url}")]
+    Status {
+        status: reqwest::StatusCode,
+        url: String,
+    },
+
+    #[error("unexpected manifest: {0}")]
+    Manifest(String),
+}
+
+/// An image reference split into the parts the registry API needs
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImageRef {
+    pub registry: String,
+    pub repository: String,
+    /// Tag or digest
+    pub reference: String,
+}
+
+impl ImageRef {
+    pub fn parse(image: &str) -> Self {
+        let (name, reference) = match image.split_once('@') {
+            // A tag next to the digest is informational only
+            Some((name, digest)) => (crate::image_policy::split_image(name).0, digest.to_string()),
+            None => {
+                let (name, tag) = crate::image_policy::split_image(image);
+                (name, tag.to_string())
+            }
+        };
+
+        let (registry, repository) = match name.split_once('/') {
+            Some((host, rest))
+                if host.contains('.') || host.contains(':') || host == "localhost" =>
+            {
+                (host.to_string(), rest.to_string())
+            }
+            Some(_) => ("docker.io".to_string(), name.to_string()),
+            None => ("docker.io".to_string(), format!("library/{}", name)),
+        };
+
+        Self {
+            registry,
+            repository,
+            reference,
+        }
+    }
+
+    fn base_url(&self) -> String {
+        let host = match self.registry.as_str() {
+            "docker.io" => "registry-1.docker.io",
+            other => other,
+        };
+        format!("https://{}/v2/{}", host, self.repository)
+    }
+}
+
+/// Parse a `WWW-Authenticate: Bearer realm="...",service="..."` challenge
+fn parse_challenge(header: &str) -> Option<BTreeMap<String, String>> {
+    let params = header.strip_prefix("Bearer ")?;
+    // Quoted values such as `scope="repository:app:pull,push"` may contain commas
+    let mut quoted = false;
+    Some(
+        params
+            .split(|c| {
+                quoted ^= c == '"';
+                c == ',' && !quoted
+            })
+            .filter_map(|pair| pair.split_once('='))
+            .map(|(k, v)| (k.trim().to_string(), v.trim().trim_matches('"').to_string()))
+            .collect(),
+    )
+}
+
+/// Architectures listed in an image index, skipping attestation entries
+pub fn index_architectures(index: &Value) -> BTreeSet<String> {
+    index["manifests"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|m| m["platform"]["architecture"].as_str())
+        .filter(|arch| *arch != "unknown")
+        .map(str::to_string)
+        .collect()
+}
+
+/// Username and password for a registry, as found in a docker config
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Credentials {
+    pub username: String,
+    pub password: String,
+}
+
+/// Client for the registry v2 API, anonymous unless credentials are passed
+#[derive(Clone)]
+pub struct RegistryClient {
+    http: reqwest::Client,
+}
+
+impl RegistryClient {
+    pub fn new() -> Self {
+        Self {
+            http: reqwest::Client::builder()
+                .timeout(std::time::Duration::from_secs(5))
+                .build()
+                .expect("failed to build registry HTTP client"),
+        }
+    }
+
+    /// GET a registry URL anonymously
+    async fn get(&self, url: &str, accept: &str) -> Result<reqwest::Response, RegistryError> {
+        self.request(reqwest::Method::GET, url, accept, None).await
+    }
+
+    /// Send a request to a registry URL, answering a bearer token or basic auth challenge
+    /// with `credentials` if one is issued
+    async fn request(
+        &self,
+        method: reqwest::Method,
+        url: &str,
+        accept: &str,
+        credentials: Option<&Credentials>,
+    ) -> Result<reqwest::Response, RegistryError> {
+        let request = || {
+            self.http
+                .request(method.clone(), url)
+                .header("Accept", accept)
+        };
+        let resp = request().send().await?;
+        if resp.status() != reqwest::StatusCode::UNAUTHORIZED {
+            return check_status(resp, url);
+        }
+
+        let header = resp
+            .headers()
+            .get("www-authenticate")
+            .and_then(|h| h.to_str().ok())
+            .unwrap_or_default();
+        let unauthorized = || RegistryError::Status {
+            status: reqwest::StatusCode::UNAUTHORIZED,
+            url: url.to_string(),
+        };
+
+        let authorized = if let Some(challenge) = parse_challenge(header) {
+            let realm = challenge.get("realm").cloned().unwrap_or_default();
+            let query: Vec<(&str, &str)> = ["service", "scope"]
+                .iter()
+                .filter_map(|k| challenge.get(*k).map(|v| (*k, v.as_str())))
+                .collect();
+            let mut token_request = self.http.get(&realm).query(&query);
+            if let Some(c) = credentials {
+                token_request = token_request.basic_auth(&c.username, Some(&c.password));
+            }
+            let token: Value = check_status(token_request.send().await?, &realm)?
+                .json()
+                .await?;
+            let token = token["token"]
+                .as_str()
+                .or_else(|| token["access_token"].as_str())
+                .unwrap_or_default();
+            request().bearer_auth(token)
+        } else {
+            match credentials {
+                Some(c) if header.starts_with("Basic") => {
+                    request().basic_auth(&c.username, Some(&c.password))
+                }
+                _ => return Err(unauthorized()),
+            }
+        };
+        check_status(authorized.send().await?, url)
+    }
+
+    /// Succeeds when the image's manifest can be fetched with `credentials`
+    pub async fn check_access(
+        &self,
+        image: &str,
+        credentials: Option<&Credentials>,
+    ) -> Result<(), RegistryError> {
+        let image = ImageRef::parse(image);
+        let url = format!("{}/manifests/{}", image.base_url(), image.reference);
+        self.request(reqwest::Method::HEAD, &url, MANIFEST_ACCEPT, credentials)
+            .await
+            .map(drop)
+    }
+
+    /// Tags published for the image's repository
+    pub async fn list_tags(&self, image: &ImageRef) -> Result<Vec<String>, RegistryError> {
+        let url = format!("{}/tags/list?n=1000", image.base_url());
+        let list: Value = self.get(&url, "application/json").await?.json().await?;
+        Ok(list["tags"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|t| t.as_str().map(str::to_string))
+            .collect())
+    }
+
+    /// Digest the image's tag currently points to, from the registry's Docker-Content-Digest
+    pub async fn resolve_digest(&self, image: &str) -> Result<String, RegistryError> {
+        let image = ImageRef::parse(image);
+        let url = format!("{}/manifests/{}", image.base_url(), image.reference);
+        let resp = self.get(&url, MANIFEST_ACCEPT).await?;
+        resp.headers()
+            .get("docker-content-digest")
+            .and_then(|h| h.to_str().ok())
+            .filter(|d| d.starts_with("sha256:"))
+            .map(str::to_string)
+            .ok_or_else(|| RegistryError::Manifest(format!("no digest returned for {}", url)))
+    }
+
+    /// Manifest of `reference` (a tag or digest) in the image's repository
+    pub async fn manifest(
+        &self,
+        image: &ImageRef,
+        reference: &str,
+    ) -> Result<Value, RegistryError> {
+        let url = format!("{}/manifests/{}", image.base_url(), reference);
+        Ok(self.get(&url, MANIFEST_ACCEPT).await?.json().await?)
+    }
+
+    /// Raw bytes of a blob in the image's repository
+    pub async fn blob(&self, image: &ImageRef, digest: &str) -> Result<Vec<u8>, RegistryError> {
+        let url = format!("{}/blobs/{}", image.base_url(), digest);
+        Ok(self.get(&url, "*/*").await?.bytes().await?.to_vec())
+    }
+
+    /// Architectures the image is published for
+    pub async fn image_architectures(
+        &self,
+        image: &str,
+    ) -> Result<BTreeSet<String>, RegistryError> {
+        let image = ImageRef::parse(image);
+        let manifest = self.manifest(&image, &image.reference).await?;
+
+        if manifest["manifests"].is_array() {
+            return Ok(index_architectures(&manifest));
+        }
+
+        // Single-platform image: the architecture lives in the config blob
+        let digest = manifest["config"]["digest"]
+            .as_str()
+            .ok_or_else(|| RegistryError::Manifest("no config digest".to_string()))?;
+        let config: Value = serde_json::from_slice(&self.blob(&image, digest).await?)
+            .map_err(|e| RegistryError::Manifest(format!("image config: {}", e)))?;
+
+        config["architecture"]
+            .as_str()
+            .map(|arch| BTreeSet::from([arch.to_string()]))
+            .ok_or_else(|| RegistryError::Manifest("no architecture in image config".to_string()))
+    }
+}
+
+fn check_status(resp: reqwest::Response, url: &str) -> Result<reqwest::Response, RegistryError> {
+    if resp.status().is_success() {
+        Ok(resp)
+    } else {
+        Err(RegistryError::Status {
+            status: resp.status(),
+            url: url.to_string(),
+        })
+    }
+}
+
+/// Architectures of the given nodes
+pub fn node_architectures(nodes: &[Arc<Node>]) -> BTreeSet<String> {
+    nodes
+        .iter()
+        .filter_map(|n| n.metadata.labels.as_ref()?.get(ARCH_LABEL).cloned())
+        .collect()
+}
+
+/// Watch of the labelled nodes, shared by all admission requests
+pub fn node_cache(client: Client, max_staleness: Duration) -> ClusterCache<Node> {
+    ClusterCache::spawn(
+        Api::all(client),
+        watcher::Config::default().labels(ARCH_LABEL),
+        max_staleness,
+    )
+}
+
+/// Registry and cluster lookups used by the admission webhooks
+#[derive(Clone)]
+pub struct ArchDetector {
+    pub registry: RegistryClient,
+    pub nodes: ClusterCache<Node>,
+}
+
+impl ArchDetector {
+    /// Enabled with `MYAPP_ARCH_DETECTION=true`
+    pub fn is_enabled_from_env() -> bool {
+        std::env::var("MYAPP_ARCH_DETECTION").is_ok_and(|v| v == "true" || v == "1")
+    }
+
+    /// Architectures to pin the image to, or None when the registry cannot tell us
+    pub async fn detect(&self, image: &str) -> Option<Vec<String>> {
+        let lookup = self.registry.image_architectures(image);
+        match tokio::time::timeout(DETECT_DEADLINE, lookup).await {
+            Ok(Ok(archs)) if !archs.is_empty() => Some(archs.into_iter().collect()),
+            Ok(Ok(_)) => None,
+            Ok(Err(e)) => {
+                tracing::warn!("Could not detect architectures of {}: {}", image, e);
+                None
+            }
+            Err(_) => {
+                tracing::warn!(
+                    "Gave up detecting architectures of {} after {:?}",
+                    image,
+                    DETECT_DEADLINE
+                );
+                None
+            }
+        }
+    }
+
+    /// Fail when no node in the cluster can run any of the architectures
+    pub async fn check_nodes(&self, architectures: &[String]) -> Result<(), String> {
+        let Some(nodes) = self.nodes.fresh_state() else {
+            return Ok(());
+        };
+        let nodes = node_architectures(&nodes);
+        if architectures.iter().any(|a| nodes.contains(a)) {
+            Ok(())
+        } else {
+            Err(format!(
+                "no nodes match image architectures [{}] (cluster has [{}])",
+                architectures.join(", "),
+                nodes.into_iter().collect::<Vec<_>>().join(", ")
+            ))
+        }
+    }
+}
+
+/// Node requirement restricting pods to the given architectures
+pub fn arch_requirement(architectures: &[String]) -> Option<NodeSelectorRequirement> {
+    (!architectures.is_empty()).then(|| NodeSelectorRequirement {
+        key: ARCH_LABEL.to_string(),
+        operator: "In".to_string(),
+        values: Some(architectures.to_vec()),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_image_ref() {
+        let nginx = ImageRef::parse("nginx:1.25");
+        assert_eq!(nginx.registry, "docker.io");
+        assert_eq!(nginx.repository, "library/nginx");
+        assert_eq!(nginx.reference, "1.25");
+
+        let ghcr = ImageRef::parse("ghcr.io/org/app@sha256:abc");
+        assert_eq!(ghcr.registry, "ghcr.io");
+        assert_eq!(ghcr.repository, "org/app");
+        assert_eq!(ghcr.reference, "sha256:abc");
+        assert_eq!(ImageRef::parse("ghcr.io/org/app:v2@sha256:abc"), ghcr);
+
+        let local = ImageRef::parse("localhost:5000/app:v1");
+        assert_eq!(local.registry, "localhost:5000");
+        assert_eq!(local.reference, "v1");
+    }
+
+    #[test]
+    fn test_index_architectures() {
+        let index = serde_json::json!({
+            "manifests": [
+                { "platform": { "architecture": "amd64", "os": "linux" } },
+                { "platform": { "architecture": "arm64", "os": "linux" } },
+                { "platform": { "architecture": "unknown", "os": "unknown" } }
+            ]
+        });
+        assert_eq!(
+            index_architectures(&index),
+            BTreeSet::from(["amd64".to_string(), "arm64".to_string()])
+        );
+
+        let challenge = parse_challenge(
+            r#"Bearer realm="https://auth.docker.io/token",service="registry.docker.io",scope="repository:library/nginx:pull""#,
+        )
+        .unwrap();
+        assert_eq!(challenge["realm"], "https://auth.docker.io/token");
+        assert_eq!(challenge["scope"], "repository:library/nginx:pull");
+
+        let challenge = parse_challenge(
+            r#"Bearer realm="https://ghcr.io/token",scope="repository:org/app:pull,push",service="ghcr.io""#,
+        )
+        .unwrap();
+        assert_eq!(challenge["scope"], "repository:org/app:pull,push");
+        assert_eq!(challenge["service"], "ghcr.io");
+    }
+}
@@ -0,0 +1,291 @@
+// Audit trail for MyApp Controller
+// With MYAPP_AUDIT=true the webhook records every accepted create or update that changes a
+// MyApp's spec: who made it (from the AdmissionRequest's userInfo), when, and the old and new
+// value of each changed top-level spec field. Each change becomes a SpecChanged Event on the
+// MyApp and, optionally, a JSON line appended to MYAPP_AUDIT_FILE and a key added to the
+// ConfigMap MYAPP_AUDIT_CONFIGMAP in the webhook's namespace. Dry-run requests are skipped.
+
+use crate::{publish_event, MyApp};
+use chrono::{DateTime, Utc};
+use k8s_openapi::api::core::v1::ConfigMap;
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta;
+use kube::api::{Api, Patch, PatchParams, PostParams};
+use kube::core::admission::{AdmissionRequest, Operation};
+use kube::runtime::events::EventType;
+use kube::Client;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::PathBuf;
+use tokio::io::AsyncWriteExt;
+
+/// Events notes are cut off by the API server past this length
+const MAX_NOTE: usize = 1024;
+
+/// Old and new value of one spec field; null when the field is unset
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Change {
+    pub old: Value,
+    pub new: Value,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditEntry {
+    pub timestamp: DateTime<Utc>,
+    pub namespace: String,
+    pub name: String,
+    pub operation: &'static str,
+    pub user: String,
+    pub groups: Vec<String>,
+    /// Admission request UID, unique per change
+    pub request: String,
+    pub changes: BTreeMap<String, Change>,
+}
+
+/// Changed top-level spec fields between `old` (none on create) and `new`
+pub fn changes(old: Option<&MyApp>, new: &MyApp) -> BTreeMap<String, Change> {
+    let fields = |myapp: Option<&MyApp>| match myapp.map(|m| serde_json::to_value(&m.spec)) {
+        Some(Ok(Value::Object(map))) => map,
+        _ => Default::default(),
+    };
+    let (old, new) = (fields(old), fields(Some(new)));
+    let keys: BTreeSet<&String> = old.keys().chain(new.keys()).collect();
+    keys.into_iter()
+        .filter_map(|key| {
+            let change = Change {
+                old: old.get(key).cloned().unwrap_or(Value::Null),
+                new: new.get(key).cloned().unwrap_or(Value::Null),
+            };
+            (change.old != change.new).then(|| (key.clone(), change))
+        })
+        .collect()
+}
+
+/// The audit record of `req`, with the MyApp it describes, if it changes a spec
+pub fn prepare(req: &AdmissionRequest<MyApp>) -> Option<(MyApp, AuditEntry)> {
+    let operation = match req.operation {
+        Operation::Create => "CREATE",
+        Operation::Update => "UPDATE",
+        _ => return None,
+    };
+    if req.dry_run {
+        return None;
+    }
+    let mut myapp = req.object.clone()?;
+    let changes = changes(req.old_object.as_ref(), &myapp);
+    if changes.is_empty() {
+        return None;
+    }
+    // Objects being created may only carry their namespace on the request
+    let namespace = myapp
+        .metadata
+        .namespace
+        .clone()
+        .or_else(|| req.namespace.clone())
+        .unwrap_or_default();
+    myapp.metadata.namespace = Some(namespace.clone());
+
+    let entry = AuditEntry {
+        timestamp: Utc::now(),
+        namespace,
+        name: req.name.clone(),
+        operation,
+        user: req.user_info.username.clone().unwrap_or_default(),
+        groups: req.user_info.groups.clone().unwrap_or_default(),
+        request: req.uid.clone(),
+        changes,
+    };
+    Some((myapp, entry))
+}
+
+impl AuditEntry {
+    /// `alice created` or `alice changed image (nginx:1.25 -> nginx:1.26), replicas`
+    pub fn note(&self) -> String {
+        let user = match self.user.is_empty() {
+            true => "unknown user",
+            false => &self.user,
+        };
+        let verb = match self.operation {
+            "CREATE" => return format!("{} created the MyApp", user),
+            _ => "changed",
+        };
+        let fields: Vec<String> = self
+            .changes
+            .iter()
+            .map(
+                |(field, change)| match (scalar(&change.old), scalar(&change.new)) {
+                    (Some(old), Some(new)) => format!("{} ({} -> {})", field, old, new),
+                    _ => field.clone(),
+                },
+            )
+            .collect();
+        let mut note = format!("{} {} {}", user, verb, fields.join(", "));
+        if note.len() > MAX_NOTE {
+            let mut end = MAX_NOTE - 3;
+            while !note.is_char_boundary(end) {
+                end -= 1;
+            }
+            note.truncate(end);
+            note.push_str("...");
+        }
+        note
+    }
+
+    /// ConfigMap key of the entry, ordered by time
+    fn key(&self) -> String {
+        format!("{}-{}", self.timestamp.timestamp_millis(), self.request)
+    }
+}
+
+/// Strings and numbers, as they would be written in the spec
+fn scalar(value: &Value) -> Option<String> {
+    match value {
+        Value::String(s) => Some(s.clone()),
+        Value::Number(n) => Some(n.to_string()),
+        _ => None,
+    }
+}
+
+/// Where accepted spec changes are recorded
+#[derive(Clone)]
+pub struct AuditLog {
+    pub client: Client,
+    /// JSON lines file to append to
+    pub file: Option<PathBuf>,
+    /// Namespace and name of the ConfigMap collecting entries
+    pub config_map: Option<(String, String)>,
+}
+
+impl AuditLog {
+    pub fn is_enabled_from_env() -> bool {
+        std::env::var("MYAPP_AUDIT").is_ok_and(|v| v == "true" || v == "1")
+    }
+
+    pub fn from_env(client: Client) -> Self {
+        let var = |name: &str| std::env::var(name).ok().filter(|v| !v.is_empty());
+        let namespace = var("POD_NAMESPACE").unwrap_or_else(|| "default".to_string());
+        Self {
+            client,
+            file: var("MYAPP_AUDIT_FILE").map(PathBuf::from),
+            config_map: var("MYAPP_AUDIT_CONFIGMAP").map(|name| (namespace, name)),
+        }
+    }
+
+    /// Write `entry` to every sink; failures are logged so admission never depends on them
+    pub async fn record(&self, myapp: &MyApp, entry: &AuditEntry) {
+        let action = match entry.operation {
+            "CREATE" => "Create",
+            _ => "Update",
+        };
+        publish_event(
+            myapp,
+            self.client.clone(),
+            EventType::Normal,
+            "SpecChanged",
+            action,
+            entry.note(),
+        )
+        .await;
+
+        let line = match serde_json::to_string(entry) {
+            Ok(line) => line,
+            Err(e) => {
+                tracing::warn!("Could not serialize audit entry: {}", e);
+                return;
+            }
+        };
+        if let Some(path) = &self.file {
+            if let Err(e) = append_line(path, &line).await {
+                tracing::warn!("Could not append to audit log {}: {}", path.display(), e);
+            }
+        }
+        if let Some((namespace, name)) = &self.config_map {
+            if let Err(e) = self.append_config_map(namespace, name, entry, line).await {
+                tracing::warn!(
+                    "Could not append to audit ConfigMap {}/{}: {}",
+                    namespace,
+                    name,
+                    e
+                );
+            }
+        }
+    }
+
+    /// Add the entry under its own key; existing keys are never rewritten
+    async fn append_config_map(
+        &self,
+        namespace: &str,
+        name: &str,
+        entry: &AuditEntry,
+        line: String,
+    ) -> Result<(), kube::Error> {
+        let api: Api<ConfigMap> = Api::namespaced(self.client.clone(), namespace);
+        let patch = serde_json::json!({ "data": { entry.key(): line.clone() } });
+        match api
+            .patch(name, &PatchParams::default(), &Patch::Merge(&patch))
+            .await
+        {
+            Err(kube::Error::Api(e)) if e.code == 404 => {
+                let config_map = ConfigMap {
+                    metadata: ObjectMeta {
+                        name: Some(name.to_string()),
+                        ..Default::default()
+                    },
+                    data: Some(BTreeMap::from([(entry.key(), line)])),
+                    ..Default::default()
+                };
+                api.create(&PostParams::default(), &config_map).await?;
+                Ok(())
+            }
+            result => result.map(|_| ()),
+        }
+    }
+}
+
+async fn append_line(path: &PathBuf, line: &str) -> std::io::Result<()> {
+    let mut file = tokio::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .await?;
+    // One write per entry keeps concurrent appends from interleaving
+    file.write_all(format!("{}\n", line).as_bytes()).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_changes_and_note() {
+        let mut old = MyApp::new("web", Default::default());
+        old.spec.image = "nginx:1.25".to_string();
+        old.spec.replicas = 2;
+        let mut new = old.clone();
+        new.spec.image = "nginx:1.26".to_string();
+        new.spec
+            .env_vars
+            .insert("MODE".to_string(), "fast".to_string());
+
+        let changes = changes(Some(&old), &new);
+        assert_eq!(changes.keys().collect::<Vec<_>>(), ["envVars", "image"]);
+        assert_eq!(changes["image"].old, "nginx:1.25");
+
+        let entry = AuditEntry {
+            timestamp: Utc::now(),
+            namespace: "shop".to_string(),
+            name: "web".to_string(),
+            operation: "UPDATE",
+            user: "alice".to_string(),
+            groups: Vec::new(),
+            request: "uid-1".to_string(),
+            changes,
+        };
+        assert_eq!(
+            entry.note(),
+            "alice changed envVars, image (nginx:1.25 -> nginx:1.26)"
+        );
+        assert!(super::changes(Some(&old), &old).is_empty());
+    }
+}
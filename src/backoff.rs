@@ -0,0 +1,191 @@
+// Error backoff for MyApp Controller
+// Per-object exponential backoff with jitter, tuned to the kind of reconcile error
+
+use crate::{MyApp, ReconcileError};
+use kube::runtime::watcher;
+use kube::ResourceExt;
+use rand::Rng;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// How a reconcile error should be retried
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorClass {
+    /// The spec is invalid; retrying will not help until it is edited
+    Validation,
+    /// API server rate limiting (429)
+    Throttled,
+    /// Optimistic concurrency conflict (409)
+    Conflict,
+    /// Connection problems, 5xx responses and children still being deleted
+    Transient,
+    Other,
+}
+
+impl ErrorClass {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ErrorClass::Validation => "validation",
+            ErrorClass::Throttled => "throttled",
+            ErrorClass::Conflict => "conflict",
+            ErrorClass::Transient => "transient",
+            ErrorClass::Other => "other",
+        }
+    }
+
+    /// First delay and upper bound
+    fn bounds(self) -> (Duration, Duration) {
+        match self {
+            ErrorClass::Validation => (Duration::from_secs(300), Duration::from_secs(1800)),
+            ErrorClass::Throttled => (Duration::from_secs(10), Duration::from_secs(300)),
+            ErrorClass::Conflict => (Duration::from_secs(2), Duration::from_secs(120)),
+            ErrorClass::Transient => (Duration::from_secs(1), Duration::from_secs(60)),
+            ErrorClass::Other => (Duration::from_secs(5), Duration::from_secs(300)),
+        }
+    }
+
+    /// Delay for the given attempt (starting at 0), scaled by `jitter` in [0.8, 1.2]
+    pub fn delay(self, attempt: u32, jitter: f64) -> Duration {
+        let (base, cap) = self.bounds();
+        base.saturating_mul(2u32.saturating_pow(attempt))
+            .min(cap)
+            .mul_f64(jitter.clamp(0.8, 1.2))
+    }
+}
+
+pub fn classify(error: &ReconcileError) -> ErrorClass {
+    match error {
+        ReconcileError::ValidationError(_) => ErrorClass::Validation,
+        ReconcileError::FinalizerError(_) => ErrorClass::Other,
+        ReconcileError::ChildrenTerminating(_) => ErrorClass::Transient,
+        ReconcileError::SecretError(_) => ErrorClass::Other,
+        ReconcileError::KubeError(kube::Error::Api(resp)) => match resp.code {
+            429 => ErrorClass::Throttled,
+            409 => ErrorClass::Conflict,
+            500..=599 => ErrorClass::Transient,
+            _ => ErrorClass::Other,
+        },
+        ReconcileError::KubeError(
+            kube::Error::HyperError(_) | kube::Error::Service(_) | kube::Error::ReadEvents(_),
+        ) => ErrorClass::Transient,
+        ReconcileError::KubeError(_) => ErrorClass::Other,
+    }
+}
+
+fn jitter() -> f64 {
+    rand::thread_rng().gen_range(0.8..=1.2)
+}
+
+/// Consecutive failures per object, keyed by namespace/name
+#[derive(Clone, Default)]
+pub struct ErrorBackoff {
+    attempts: Arc<Mutex<HashMap<String, u32>>>,
+}
+
+impl ErrorBackoff {
+    /// Count a failure and return how long to wait before the next attempt
+    pub fn next_delay(&self, key: &str, class: ErrorClass) -> Duration {
+        let mut attempts = self.attempts.lock().unwrap();
+        let attempt = attempts.entry(key.to_string()).or_insert(0);
+        let delay = class.delay(*attempt, jitter());
+        *attempt = attempt.saturating_add(1);
+        delay
+    }
+
+    /// Forget the failures of an object after a successful reconcile
+    pub fn reset(&self, key: &str) {
+        self.attempts.lock().unwrap().remove(key);
+    }
+
+    /// Forget the failures of MyApps as the watch reports them deleted
+    pub fn forget_deleted(&self) -> impl Fn(&Result<watcher::Event<MyApp>, watcher::Error>) {
+        let this = self.clone();
+        move |event| {
+            if let Ok(watcher::Event::Delete(myapp)) = event {
+                this.reset(&format!(
+                    "{}/{}",
+                    myapp.namespace().unwrap_or_default(),
+                    myapp.name_any()
+                ));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use kube::core::ErrorResponse;
+
+    fn api_error(code: u16) -> ReconcileError {
+        ReconcileError::KubeError(kube::Error::Api(ErrorResponse {
+            status: "Failure".to_string(),
+            message: String::new(),
+            reason: String::new(),
+            code,
+        }))
+    }
+
+    #[test]
+    fn test_classify() {
+        assert_eq!(classify(&api_error(429)), ErrorClass::Throttled);
+        assert_eq!(classify(&api_error(409)), ErrorClass::Conflict);
+        assert_eq!(classify(&api_error(503)), ErrorClass::Transient);
+        assert_eq!(classify(&api_error(404)), ErrorClass::Other);
+        assert_eq!(
+            classify(&ReconcileError::ValidationError("bad".to_string())),
+            ErrorClass::Validation
+        );
+        assert_eq!(
+            classify(&ReconcileError::ChildrenTerminating(2)),
+            ErrorClass::Transient
+        );
+    }
+
+    #[test]
+    fn test_backoff_grows_and_resets() {
+        assert_eq!(ErrorClass::Transient.delay(0, 1.0), Duration::from_secs(1));
+        assert_eq!(ErrorClass::Transient.delay(3, 1.0), Duration::from_secs(8));
+        assert_eq!(
+            ErrorClass::Transient.delay(30, 1.0),
+            Duration::from_secs(60)
+        );
+        assert_eq!(
+            ErrorClass::Transient.delay(30, 5.0),
+            Duration::from_secs(72)
+        );
+
+        let backoff = ErrorBackoff::default();
+        let first = backoff.next_delay("shop/web", ErrorClass::Conflict);
+        let second = backoff.next_delay("shop/web", ErrorClass::Conflict);
+        assert!(first <= Duration::from_millis(2400));
+        assert!(second >= Duration::from_millis(3200));
+
+        backoff.reset("shop/web");
+        assert!(
+            backoff.next_delay("shop/web", ErrorClass::Conflict) <= Duration::from_millis(2400)
+        );
+    }
+
+    #[test]
+    fn test_jitter_range() {
+        assert!((0..100).map(|_| jitter()).all(|j| (0.8..=1.2).contains(&j)));
+    }
+
+    #[test]
+    fn test_deleted_objects_are_forgotten() {
+        let backoff = ErrorBackoff::default();
+        backoff.next_delay("shop/web", ErrorClass::Other);
+        backoff.next_delay("shop/api", ErrorClass::Other);
+
+        let mut myapp = MyApp::new("web", Default::default());
+        myapp.metadata.namespace = Some("shop".to_string());
+        let forget = backoff.forget_deleted();
+        forget(&Ok(watcher::Event::Apply(myapp.clone())));
+        assert_eq!(backoff.attempts.lock().unwrap().len(), 2);
+        forget(&Ok(watcher::Event::Delete(myapp)));
+        let attempts = backoff.attempts.lock().unwrap();
+        assert_eq!(attempts.keys().collect::<Vec<_>>(), ["shop/api"]);
+    }
+}
@@ -0,0 +1,315 @@
+// Blue-green rollout module for MyApp Controller
+// Keeps an active and a preview Deployment and flips the Service selector once the preview is healthy.
+// A MyApp switched over from another strategy keeps serving from `<name>-deployment` until the
+// first color is available; then the Service moves to that color and the old Deployment is deleted
+
+use crate::hooks::deployment_available;
+use crate::{
+    app_labels, apply_deployment, build_deployment, child_name, create_service, publish_event,
+    MyApp,
+};
+use k8s_openapi::api::apps::v1::Deployment;
+use k8s_openapi::api::core::v1::Service;
+use kube::api::{Api, DeleteParams, Patch, PatchParams};
+use kube::runtime::events::EventType;
+use kube::{Client, ResourceExt};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+pub const COLOR_LABEL: &str = "myapps.example.com/color";
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, JsonSchema)]
+pub enum Color {
+    Blue,
+    Green,
+}
+
+impl Color {
+    pub fn other(&self) -> Self {
+        match self {
+            Color::Blue => Color::Green,
+            Color::Green => Color::Blue,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Color::Blue => "blue",
+            Color::Green => "green",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, JsonSchema)]
+pub enum BlueGreenPhase {
+    /// Service points at the active Deployment and no preview is running
+    Stable,
+    /// A preview Deployment with the new spec is rolling out
+    Previewing,
+}
+
+/// Blue-green rollout state, persisted in status so it survives controller restarts
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct BlueGreenStatus {
+    /// Color currently receiving traffic
+    pub active_color: Color,
+
+    /// MyApp generation served by the active Deployment
+    pub active_generation: i64,
+
+    pub phase: BlueGreenPhase,
+
+    /// MyApp generation being previewed
+    #[serde(default)]
+    pub preview_generation: Option<i64>,
+
+    /// When traffic was last switched
+    #[serde(default)]
+    pub last_switch_time: Option<String>,
+}
+
+/// What the controller has to do next
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Step {
+    /// First rollout: create the active Deployment
+    Initialize,
+    /// Nothing to roll out
+    Idle,
+    /// The spec changed: roll out (or refresh) the preview Deployment
+    StartPreview,
+    /// The preview is not healthy yet
+    WaitForPreview,
+    /// The preview is healthy: switch traffic to it
+    Promote,
+}
+
+pub fn next_step(status: Option<&BlueGreenStatus>, generation: i64, preview_ready: bool) -> Step {
+    match status {
+        None => Step::Initialize,
+        Some(s) if s.phase == BlueGreenPhase::Stable => {
+            if generation > s.active_generation {
+                Step::StartPreview
+            } else {
+                Step::Idle
+            }
+        }
+        Some(s) if s.preview_generation != Some(generation) => Step::StartPreview,
+        Some(_) if preview_ready => Step::Promote,
+        Some(_) => Step::WaitForPreview,
+    }
+}
+
+pub fn deployment_name(myapp: &MyApp, color: Color) -> String {
+    child_name(myapp, color.as_str())
+}
+
+pub fn build_color_deployment(myapp: &MyApp, color: Color) -> Deployment {
+    let mut labels = app_labels(myapp);
+    labels.insert(COLOR_LABEL.to_string(), color.as_str().to_string());
+    build_deployment(myapp, &deployment_name(myapp, color), labels)
+}
+
+async fn point_service_at(myapp: &MyApp, color: Color, client: Client) -> Result<(), kube::Error> {
+    let api: Api<Service> = Api::namespaced(client.clone(), &myapp.namespace().unwrap());
+    let svc_name = child_name(myapp, "service");
+    if api.get_opt(&svc_name).await?.is_none() {
+        create_service(myapp, client).await?;
+    }
+
+    let patch = serde_json::json!({
+        "spec": { "selector": { "app": myapp.name_any(), COLOR_LABEL: color.as_str() } }
+    });
+    api.patch(&svc_name, &PatchParams::default(), &Patch::Merge(&patch))
+        .await?;
+    Ok(())
+}
+
+/// Deployment used by the RollingUpdate, Recreate and Canary strategies
+fn single_deployment_name(myapp: &MyApp) -> String {
+    child_name(myapp, "deployment")
+}
+
+/// Move traffic off the Deployment of a previous strategy once `active` is available,
+/// then delete it; its pods would otherwise keep matching an app-only Service selector
+async fn retire_single_deployment(
+    myapp: &MyApp,
+    api: &Api<Deployment>,
+    active: Color,
+    client: Client,
+) -> Result<(), kube::Error> {
+    let name = single_deployment_name(myapp);
+    if api.get_opt(&name).await?.is_none() {
+        return Ok(());
+    }
+    let active_ready = api
+        .get_opt(&deployment_name(myapp, active))
+        .await?
+        .is_some_and(|d| deployment_available(&d));
+    if !active_ready {
+        return Ok(());
+    }
+
+    point_service_at(myapp, active, client.clone()).await?;
+    api.delete(&name, &DeleteParams::background()).await?;
+    record(
+        myapp,
+        client,
+        "PreviousDeploymentRemoved",
+        format!(
+            "Switched traffic to {} and deleted deployment {}",
+            active.as_str(),
+            name
+        ),
+    )
+    .await;
+    Ok(())
+}
+
+async fn record(myapp: &MyApp, client: Client, reason: &str, note: String) {
+    publish_event(
+        myapp,
+        client,
+        EventType::Normal,
+        reason,
+        "BlueGreenRollout",
+        note,
+    )
+    .await;
+}
+
+/// Advance the blue-green state machine by one step and return the new state
+pub async fn reconcile(myapp: &MyApp, client: Client) -> Result<BlueGreenStatus, kube::Error> {
+    let api: Api<Deployment> = Api::namespaced(client.clone(), &myapp.namespace().unwrap());
+    let generation = myapp.metadata.generation.unwrap_or(0);
+    let current = myapp.status.as_ref().and_then(|s| s.blue_green.clone());
+
+    let preview_ready = match &current {
+        Some(s) if s.phase == BlueGreenPhase::Previewing => api
+            .get_opt(&deployment_name(myapp, s.active_color.other()))
+            .await?
+            .is_some_and(|d| deployment_available(&d)),
+        _ => false,
+    };
+
+    let Some(status) = current else {
+        apply_deployment(&api, &build_color_deployment(myapp, Color::Blue)).await?;
+        // Switching from another strategy: the old Deployment serves until blue is available
+        if api.get_opt(&single_deployment_name(myapp)).await?.is_none() {
+            point_service_at(myapp, Color::Blue, client).await?;
+        }
+        tracing::info!("Created blue deployment for {}", myapp.name_any());
+
+        return Ok(BlueGreenStatus {
+            active_color: Color::Blue,
+            active_generation: generation,
+            phase: BlueGreenPhase::Stable,
+            preview_generation: None,
+            last_switch_time: None,
+        });
+    };
+
+    retire_single_deployment(myapp, &api, status.active_color, client.clone()).await?;
+
+    match next_step(Some(&status), generation, preview_ready) {
+        Step::StartPreview => {
+            let preview = status.active_color.other();
+            apply_deployment(&api, &build_color_deployment(myapp, preview)).await?;
+            record(
+                myapp,
+                client,
+                "PreviewStarted",
+                format!(
+                    "Rolling out generation {} to {} deployment",
+                    generation,
+                    preview.as_str()
+                ),
+            )
+            .await;
+
+            Ok(BlueGreenStatus {
+                phase: BlueGreenPhase::Previewing,
+                preview_generation: Some(generation),
+                ..status
+            })
+        }
+        Step::Promote => {
+            let preview = status.active_color.other();
+            point_service_at(myapp, preview, client.clone()).await?;
+
+            // Keep the old color around at zero replicas for a quick rollback
+            let scale_down = serde_json::json!({ "spec": { "replicas": 0 } });
+            api.patch(
+                &deployment_name(myapp, status.active_color),
+                &PatchParams::default(),
+                &Patch::Merge(&scale_down),
+            )
+            .await?;
+
+            record(
+                myapp,
+                client,
+                "TrafficSwitched",
+                format!(
+                    "Switched traffic from {} to {}",
+                    status.active_color.as_str(),
+                    preview.as_str()
+                ),
+            )
+            .await;
+
+            Ok(BlueGreenStatus {
+                active_color: preview,
+                active_generation: status.preview_generation.unwrap_or(generation),
+                phase: BlueGreenPhase::Stable,
+                preview_generation: None,
+                last_switch_time: Some(chrono::Utc::now().to_rfc3339()),
+            })
+        }
+        Step::Initialize | Step::Idle | Step::WaitForPreview => Ok(status),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn status(phase: BlueGreenPhase, preview_generation: Option<i64>) -> BlueGreenStatus {
+        BlueGreenStatus {
+            active_color: Color::Blue,
+            active_generation: 1,
+            phase,
+            preview_generation,
+            last_switch_time: None,
+        }
+    }
+
+    #[test]
+    fn test_state_machine() {
+        assert_eq!(next_step(None, 1, false), Step::Initialize);
+
+        let stable = status(BlueGreenPhase::Stable, None);
+        assert_eq!(next_step(Some(&stable), 1, false), Step::Idle);
+        assert_eq!(next_step(Some(&stable), 2, false), Step::StartPreview);
+
+        let previewing = status(BlueGreenPhase::Previewing, Some(2));
+        assert_eq!(next_step(Some(&previewing), 2, false), Step::WaitForPreview);
+        assert_eq!(next_step(Some(&previewing), 2, true), Step::Promote);
+
+        // A newer spec during preview restarts the preview
+        assert_eq!(next_step(Some(&previewing), 3, true), Step::StartPreview);
+    }
+
+    #[test]
+    fn test_color_deployment_labels() {
+        let mut myapp = MyApp::new("web", Default::default());
+        myapp.metadata.namespace = Some("default".to_string());
+        myapp.metadata.uid = Some("uid".to_string());
+
+        let deployment = build_color_deployment(&myapp, Color::Green);
+        assert_eq!(deployment.metadata.name.as_deref(), Some("web-green"));
+
+        let selector = deployment.spec.unwrap().selector.match_labels.unwrap();
+        assert_eq!(selector.get(COLOR_LABEL).map(String::as_str), Some("green"));
+    }
+}
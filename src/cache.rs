@@ -0,0 +1,141 @@
+// Informer-backed caches for the admission webhooks
+// Validations read cluster state from watches instead of issuing GETs per request
+
+use futures::StreamExt;
+use kube::runtime::reflector::{self, Store};
+use kube::runtime::{watcher, WatchStreamExt};
+use kube::{Api, Resource};
+use serde::de::DeserializeOwned;
+use std::fmt::Debug;
+use std::hash::Hash;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// How long a failing watch may keep serving its last known state
+pub fn max_staleness_from_env() -> Duration {
+    let secs = std::env::var("MYAPP_CACHE_MAX_STALENESS_SECS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(60);
+    Duration::from_secs(secs)
+}
+
+/// Sync state of a cache
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Freshness {
+    /// The initial list has completed
+    pub synced: bool,
+    /// How long the watch has been failing, if it is
+    pub failing_for: Option<Duration>,
+}
+
+impl Freshness {
+    pub fn is_fresh(&self, max_staleness: Duration) -> bool {
+        self.synced && self.failing_for.is_none_or(|d| d <= max_staleness)
+    }
+}
+
+#[derive(Default)]
+struct WatchHealth {
+    synced: bool,
+    failing_since: Option<Instant>,
+}
+
+/// Reflector store kept up to date by a background watch
+#[derive(Clone)]
+pub struct ClusterCache<K: Resource<DynamicType = ()> + 'static> {
+    store: Store<K>,
+    health: Arc<Mutex<WatchHealth>>,
+    max_staleness: Duration,
+}
+
+impl<K> ClusterCache<K>
+where
+    K: Resource<DynamicType = ()> + Clone + DeserializeOwned + Debug + Send + Sync + 'static,
+    K::DynamicType: Eq + Hash + Clone,
+{
+    /// Start watching in the background
+    pub fn spawn(api: Api<K>, config: watcher::Config, max_staleness: Duration) -> Self {
+        let (store, writer) = reflector::store();
+        let health = Arc::new(Mutex::new(WatchHealth::default()));
+
+        let tracked = health.clone();
+        let stream = reflector::reflector(writer, watcher(api, config))
+            .default_backoff()
+            .for_each(move |event| {
+                let mut health = tracked.lock().unwrap();
+                match event {
+                    Ok(event) => {
+                        if matches!(event, watcher::Event::InitDone) {
+                            health.synced = true;
+                        }
+                        health.failing_since = None;
+                    }
+                    Err(e) => {
+                        tracing::warn!("Admission cache watch failed: {}", e);
+                        health.failing_since.get_or_insert_with(Instant::now);
+                    }
+                }
+                futures::future::ready(())
+            });
+        tokio::spawn(stream);
+
+        Self {
+            store,
+            health,
+            max_staleness,
+        }
+    }
+
+    pub fn freshness(&self) -> Freshness {
+        let health = self.health.lock().unwrap();
+        Freshness {
+            synced: health.synced,
+            failing_for: health.failing_since.map(|t| t.elapsed()),
+        }
+    }
+
+    /// Cached objects, or None while the cache is not synced or too stale to trust
+    pub fn fresh_state(&self) -> Option<Vec<Arc<K>>> {
+        let freshness = self.freshness();
+        if freshness.is_fresh(self.max_staleness) {
+            Some(self.store.state())
+        } else {
+            tracing::warn!(
+                "{} cache is not fresh ({:?}), skipping checks that need it",
+                K::kind(&()),
+                freshness
+            );
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_freshness() {
+        let max = Duration::from_secs(60);
+        assert!(!Freshness::default().is_fresh(max));
+
+        let synced = Freshness {
+            synced: true,
+            failing_for: None,
+        };
+        assert!(synced.is_fresh(max));
+
+        let briefly_failing = Freshness {
+            failing_for: Some(Duration::from_secs(5)),
+            ..synced
+        };
+        assert!(briefly_failing.is_fresh(max));
+
+        let stale = Freshness {
+            failing_for: Some(Duration::from_secs(120)),
+            ..synced
+        };
+        assert!(!stale.is_fresh(max));
+    }
+}
@@ -0,0 +1,528 @@
+// Canary rollout module for MyApp Controller
+// Shifts traffic to a canary Deployment in weighted steps, then promotes or aborts based on health
+
+use crate::analysis::{self, CanaryAnalysis, MetricResult, Verdict};
+use crate::{app_labels, apply_deployment, build_deployment, child_name, publish_event, MyApp};
+use k8s_openapi::api::apps::v1::Deployment;
+use kube::api::{Api, DeleteParams, Patch, PatchParams};
+use kube::runtime::events::EventType;
+use kube::{Client, ResourceExt};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+pub const TRACK_LABEL: &str = "myapps.example.com/track";
+
+/// Canary steps; traffic follows the replica split because both Deployments back the Service
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct CanaryConfig {
+    /// Traffic steps, e.g. 10% for 5 minutes then 50% for 10 minutes
+    #[serde(default)]
+    pub steps: Vec<CanaryStep>,
+
+    /// Prometheus checks that gate each step
+    #[serde(default)]
+    pub analysis: Option<CanaryAnalysis>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CanaryStep {
+    /// Percentage of replicas running the canary
+    #[schemars(range(min = 1, max = 100))]
+    pub weight: i32,
+
+    /// Seconds the canary must stay healthy before moving to the next step
+    #[serde(default)]
+    pub bake_seconds: i64,
+}
+
+impl CanaryConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.steps.is_empty() {
+            return Err("strategy.canary.steps must not be empty".to_string());
+        }
+        if self.steps.iter().any(|s| s.weight < 1 || s.weight > 100) {
+            return Err("strategy.canary.steps weights must be between 1 and 100".to_string());
+        }
+        if self.steps.windows(2).any(|w| w[0].weight > w[1].weight) {
+            return Err("strategy.canary.steps weights must not decrease".to_string());
+        }
+        if let Some(analysis) = &self.analysis {
+            analysis.validate()?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, JsonSchema)]
+pub enum CanaryPhase {
+    /// Stable Deployment runs the latest promoted spec
+    Stable,
+    /// Canary is stepping through weights
+    Progressing,
+    /// Canary was healthy through every step and its spec now runs on the stable Deployment
+    Promoted,
+    /// Canary became unhealthy and was rolled back
+    Aborted,
+}
+
+/// Canary rollout state, persisted in status so it survives controller restarts
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CanaryStatus {
+    pub phase: CanaryPhase,
+
+    /// Generation running on the stable Deployment
+    pub stable_generation: i64,
+
+    /// Generation running on the canary Deployment
+    #[serde(default)]
+    pub canary_generation: Option<i64>,
+
+    /// Index into spec.strategy.canary.steps
+    #[serde(default)]
+    pub step_index: usize,
+
+    /// When the current step started (RFC 3339)
+    #[serde(default)]
+    pub step_started_at: Option<String>,
+
+    /// Human readable progress or failure details
+    #[serde(default)]
+    pub message: Option<String>,
+
+    /// Metric results from the most recent analysis of the current step
+    #[serde(default)]
+    pub analysis: Vec<MetricResult>,
+}
+
+/// Health of the canary Deployment
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Health {
+    Healthy,
+    Pending,
+    Unhealthy,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    /// First rollout: stable Deployment with the current spec
+    Initialize,
+    /// Nothing to do
+    Idle,
+    /// Start (or restart) the canary at the first step
+    Start,
+    /// Keep the current step running
+    Wait,
+    /// Move to the given step
+    Advance(usize),
+    /// All steps passed
+    Promote,
+    /// Canary unhealthy: roll back
+    Abort,
+}
+
+pub fn next_action(
+    status: Option<&CanaryStatus>,
+    generation: i64,
+    health: Health,
+    elapsed_seconds: i64,
+    steps: &[CanaryStep],
+) -> Action {
+    let Some(status) = status else {
+        return Action::Initialize;
+    };
+
+    match status.phase {
+        CanaryPhase::Progressing if status.canary_generation != Some(generation) => Action::Start,
+        CanaryPhase::Progressing => match health {
+            Health::Unhealthy => Action::Abort,
+            Health::Pending => Action::Wait,
+            Health::Healthy => {
+                let bake = steps.get(status.step_index).map_or(0, |s| s.bake_seconds);
+                if elapsed_seconds < bake {
+                    Action::Wait
+                } else if status.step_index + 1 < steps.len() {
+                    Action::Advance(status.step_index + 1)
+                } else {
+                    Action::Promote
+                }
+            }
+        },
+        // An aborted spec is not retried until the spec changes again
+        CanaryPhase::Aborted if status.canary_generation == Some(generation) => Action::Idle,
+        _ if generation > status.stable_generation => Action::Start,
+        _ => Action::Idle,
+    }
+}
+
+/// Hold or abort a step transition based on metric analysis
+pub fn gate(action: Action, verdict: Option<Verdict>) -> Action {
+    match (action, verdict) {
+        (Action::Advance(_) | Action::Promote, Some(Verdict::Fail)) => Action::Abort,
+        (Action::Advance(_) | Action::Promote, Some(Verdict::Inconclusive)) => Action::Wait,
+        (action, _) => action,
+    }
+}
+
+/// Split the desired replicas between stable and canary for a traffic weight
+pub fn split_replicas(replicas: i32, weight: i32) -> (i32, i32) {
+    let canary = ((replicas * weight) as f64 / 100.0).ceil().max(1.0) as i32;
+    let canary = canary.min(replicas);
+    (replicas - canary, canary)
+}
+
+pub fn canary_health(deployment: &Deployment) -> Health {
+    let conditions = deployment
+        .status
+        .as_ref()
+        .and_then(|s| s.conditions.clone())
+        .unwrap_or_default();
+
+    let failed = conditions.iter().any(|c| {
+        (c.type_ == "Progressing" && c.status == "False")
+            || (c.type_ == "ReplicaFailure" && c.status == "True")
+    });
+    if failed {
+        Health::Unhealthy
+    } else if crate::hooks::deployment_available(deployment) {
+        Health::Healthy
+    } else {
+        Health::Pending
+    }
+}
+
+pub fn stable_name(myapp: &MyApp) -> String {
+    child_name(myapp, "deployment")
+}
+
+pub fn canary_name(myapp: &MyApp) -> String {
+    child_name(myapp, "canary")
+}
+
+fn build_canary(myapp: &MyApp, replicas: i32) -> Deployment {
+    // The stable selector also matches canary pods; ReplicaSet ownership keeps them apart
+    let mut labels = app_labels(myapp);
+    labels.insert(TRACK_LABEL.to_string(), "canary".to_string());
+    let mut deployment = build_deployment(myapp, &canary_name(myapp), labels);
+    if let Some(spec) = deployment.spec.as_mut() {
+        spec.replicas = Some(replicas);
+    }
+    deployment
+}
+
+async fn scale(api: &Api<Deployment>, name: &str, replicas: i32) -> Result<(), kube::Error> {
+    let patch = serde_json::json!({ "spec": { "replicas": replicas } });
+    api.patch(name, &PatchParams::default(), &Patch::Merge(&patch))
+        .await?;
+    Ok(())
+}
+
+async fn set_weight(myapp: &MyApp, api: &Api<Deployment>, weight: i32) -> Result<(), kube::Error> {
+    let (stable, canary) = split_replicas(myapp.spec.replicas, weight);
+    apply_deployment(api, &build_canary(myapp, canary)).await?;
+    scale(api, &stable_name(myapp), stable).await
+}
+
+/// Advance the canary state machine by one step and return the new state
+pub async fn reconcile(
+    myapp: &MyApp,
+    config: &CanaryConfig,
+    client: Client,
+) -> Result<CanaryStatus, kube::Error> {
+    let api: Api<Deployment> = Api::namespaced(client.clone(), &myapp.namespace().unwrap());
+    let generation = myapp.metadata.generation.unwrap_or(0);
+    let current = myapp.status.as_ref().and_then(|s| s.canary.clone());
+    let now = chrono::Utc::now();
+
+    let health = match api.get_opt(&canary_name(myapp)).await? {
+        Some(d) => canary_health(&d),
+        None => Health::Pending,
+    };
+    let elapsed = current
+        .as_ref()
+        .and_then(|s| s.step_started_at.as_deref())
+        .and_then(|t| chrono::DateTime::parse_from_rfc3339(t).ok())
+        .map_or(0, |t| (now - t.with_timezone(&chrono::Utc)).num_seconds());
+
+    let action = next_action(current.as_ref(), generation, health, elapsed, &config.steps);
+    let Some(status) = current else {
+        apply_deployment(
+            &api,
+            &build_deployment(myapp, &stable_name(myapp), app_labels(myapp)),
+        )
+        .await?;
+        return Ok(CanaryStatus {
+            phase: CanaryPhase::Stable,
+            stable_generation: generation,
+            canary_generation: None,
+            step_index: 0,
+            step_started_at: None,
+            message: None,
+            analysis: Vec::new(),
+        });
+    };
+
+    // Metric analysis runs once the step has baked and health checks pass
+    let mut results = status.analysis.clone();
+    let mut action = action;
+    if let (Action::Advance(_) | Action::Promote, Some(config)) = (action, &config.analysis) {
+        results = analysis::run(config, &myapp.namespace().unwrap(), &canary_name(myapp)).await;
+        action = gate(action, Some(analysis::verdict(&results)));
+    }
+    let failed_metrics: Vec<String> = results
+        .iter()
+        .filter(|r| r.value.is_some() && !r.passed)
+        .map(|r| format!("{}: {}", r.name, r.message.as_deref().unwrap_or_default()))
+        .collect();
+
+    match action {
+        Action::Start => {
+            set_weight(myapp, &api, config.steps[0].weight).await?;
+            let note = format!(
+                "Started canary for generation {} at {}%",
+                generation, config.steps[0].weight
+            );
+            publish_event(
+                myapp,
+                client,
+                EventType::Normal,
+                "CanaryStarted",
+                "CanaryRollout",
+                note.clone(),
+            )
+            .await;
+
+            Ok(CanaryStatus {
+                phase: CanaryPhase::Progressing,
+                canary_generation: Some(generation),
+                step_index: 0,
+                step_started_at: Some(now.to_rfc3339()),
+                message: Some(note),
+                analysis: Vec::new(),
+                ..status
+            })
+        }
+        Action::Advance(step) => {
+            set_weight(myapp, &api, config.steps[step].weight).await?;
+            let note = format!("Canary advanced to {}%", config.steps[step].weight);
+            publish_event(
+                myapp,
+                client,
+                EventType::Normal,
+                "CanaryAdvanced",
+                "CanaryRollout",
+                note.clone(),
+            )
+            .await;
+
+            Ok(CanaryStatus {
+                step_index: step,
+                step_started_at: Some(now.to_rfc3339()),
+                message: Some(note),
+                analysis: results,
+                ..status
+            })
+        }
+        Action::Promote => {
+            apply_deployment(
+                &api,
+                &build_deployment(myapp, &stable_name(myapp), app_labels(myapp)),
+            )
+            .await?;
+            api.delete(&canary_name(myapp), &DeleteParams::background())
+                .await?;
+            let note = format!("Promoted generation {} to stable", generation);
+            publish_event(
+                myapp,
+                client,
+                EventType::Normal,
+                "CanaryPromoted",
+                "CanaryRollout",
+                note.clone(),
+            )
+            .await;
+
+            Ok(CanaryStatus {
+                phase: CanaryPhase::Promoted,
+                stable_generation: generation,
+                step_started_at: None,
+                message: Some(note),
+                analysis: results,
+                ..status
+            })
+        }
+        Action::Abort => {
+            scale(&api, &stable_name(myapp), myapp.spec.replicas).await?;
+            api.delete(&canary_name(myapp), &DeleteParams::background())
+                .await?;
+            let note = if failed_metrics.is_empty() {
+                format!(
+                    "Canary for generation {} was unhealthy and rolled back",
+                    generation
+                )
+            } else {
+                format!(
+                    "Canary for generation {} failed analysis ({}) and was rolled back",
+                    generation,
+                    failed_metrics.join(", ")
+                )
+            };
+            publish_event(
+                myapp,
+                client,
+                EventType::Warning,
+                "CanaryAborted",
+                "CanaryRollout",
+                note.clone(),
+            )
+            .await;
+
+            Ok(CanaryStatus {
+                phase: CanaryPhase::Aborted,
+                step_started_at: None,
+                message: Some(note),
+                analysis: results,
+                ..status
+            })
+        }
+        Action::Wait => Ok(CanaryStatus {
+            analysis: results,
+            ..status
+        }),
+        Action::Initialize | Action::Idle => Ok(status),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn steps() -> Vec<CanaryStep> {
+        vec![
+            CanaryStep {
+                weight: 10,
+                bake_seconds: 60,
+            },
+            CanaryStep {
+                weight: 50,
+                bake_seconds: 60,
+            },
+        ]
+    }
+
+    fn progressing(step_index: usize) -> CanaryStatus {
+        CanaryStatus {
+            phase: CanaryPhase::Progressing,
+            stable_generation: 1,
+            canary_generation: Some(2),
+            step_index,
+            step_started_at: None,
+            message: None,
+            analysis: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_split_replicas() {
+        assert_eq!(split_replicas(10, 10), (9, 1));
+        assert_eq!(split_replicas(3, 10), (2, 1));
+        assert_eq!(split_replicas(4, 50), (2, 2));
+        assert_eq!(split_replicas(4, 100), (0, 4));
+    }
+
+    #[test]
+    fn test_state_machine() {
+        let steps = steps();
+        assert_eq!(
+            next_action(None, 1, Health::Pending, 0, &steps),
+            Action::Initialize
+        );
+
+        let stable = CanaryStatus {
+            phase: CanaryPhase::Stable,
+            canary_generation: None,
+            ..progressing(0)
+        };
+        assert_eq!(
+            next_action(Some(&stable), 1, Health::Pending, 0, &steps),
+            Action::Idle
+        );
+        assert_eq!(
+            next_action(Some(&stable), 2, Health::Pending, 0, &steps),
+            Action::Start
+        );
+
+        let step0 = progressing(0);
+        assert_eq!(
+            next_action(Some(&step0), 2, Health::Healthy, 30, &steps),
+            Action::Wait
+        );
+        assert_eq!(
+            next_action(Some(&step0), 2, Health::Healthy, 60, &steps),
+            Action::Advance(1)
+        );
+        assert_eq!(
+            next_action(Some(&step0), 2, Health::Unhealthy, 0, &steps),
+            Action::Abort
+        );
+        assert_eq!(
+            next_action(Some(&step0), 3, Health::Healthy, 0, &steps),
+            Action::Start
+        );
+
+        let step1 = progressing(1);
+        assert_eq!(
+            next_action(Some(&step1), 2, Health::Healthy, 60, &steps),
+            Action::Promote
+        );
+
+        let aborted = CanaryStatus {
+            phase: CanaryPhase::Aborted,
+            ..progressing(0)
+        };
+        assert_eq!(
+            next_action(Some(&aborted), 2, Health::Healthy, 0, &steps),
+            Action::Idle
+        );
+        assert_eq!(
+            next_action(Some(&aborted), 3, Health::Healthy, 0, &steps),
+            Action::Start
+        );
+    }
+
+    #[test]
+    fn test_validate_steps() {
+        assert!(CanaryConfig::default().validate().is_err());
+        let config = CanaryConfig {
+            steps: steps(),
+            analysis: None,
+        };
+        assert!(config.validate().is_ok());
+
+        let mut decreasing = steps();
+        decreasing.reverse();
+        assert!(CanaryConfig {
+            steps: decreasing,
+            analysis: None
+        }
+        .validate()
+        .is_err());
+    }
+
+    #[test]
+    fn test_analysis_gate() {
+        assert_eq!(gate(Action::Advance(1), None), Action::Advance(1));
+        assert_eq!(
+            gate(Action::Advance(1), Some(Verdict::Pass)),
+            Action::Advance(1)
+        );
+        assert_eq!(gate(Action::Promote, Some(Verdict::Fail)), Action::Abort);
+        assert_eq!(
+            gate(Action::Promote, Some(Verdict::Inconclusive)),
+            Action::Wait
+        );
+        assert_eq!(gate(Action::Wait, Some(Verdict::Fail)), Action::Wait);
+    }
+}
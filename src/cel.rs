@@ -0,0 +1,95 @@
+// CEL validation rules for the MyApp CRD
+// `generate-crd` embeds these as x-kubernetes-validations so the API server enforces the
+// basic invariants even when the validating webhook is down or bypassed
+
+use crate::MyApp;
+use k8s_openapi::apiextensions_apiserver::pkg::apis::apiextensions::v1::{
+    CustomResourceDefinition, JSONSchemaProps, ValidationRule,
+};
+use kube::CustomResourceExt;
+
+/// (schema path below the root, CEL rule, message); rules mirror `MyApp::validate`
+const RULES: &[(&[&str], &str, &str)] = &[
+    (
+        &["spec"],
+        "self.replicas >= 1 && self.replicas <= 100",
+        "replicas must be between 1 and 100",
+    ),
+    (&["spec"], "size(self.image) > 0", "image cannot be empty"),
+    (
+        &["spec"],
+        "!self.image.endsWith(':latest')",
+        "Image tag 'latest' is not allowed",
+    ),
+    (
+        &["spec"],
+        "!has(self.workloadType) || self.workloadType != 'CronJob' || (has(self.job) && has(self.job.schedule))",
+        "job.schedule is required for CronJob workloads",
+    ),
+    (
+        &["spec", "job"],
+        "!has(self.concurrencyPolicy) || self.concurrencyPolicy in ['Allow', 'Forbid', 'Replace']",
+        "job.concurrencyPolicy must be one of Allow, Forbid, Replace",
+    ),
+    (
+        &["spec", "strategy"],
+        "has(self.canary) == (has(self.type) && self.type == 'Canary')",
+        "strategy.canary is required for Canary and only valid for Canary",
+    ),
+];
+
+fn schema_at<'a>(root: &'a mut JSONSchemaProps, path: &[&str]) -> Option<&'a mut JSONSchemaProps> {
+    path.iter().try_fold(root, |schema, field| {
+        schema.properties.as_mut()?.get_mut(*field)
+    })
+}
+
+/// The MyApp CRD with the CEL rules added to every served version
+pub fn crd() -> CustomResourceDefinition {
+    let mut crd = MyApp::crd();
+    for version in &mut crd.spec.versions {
+        let Some(root) = version
+            .schema
+            .as_mut()
+            .and_then(|s| s.open_api_v3_schema.as_mut())
+        else {
+            continue;
+        };
+        for (path, rule, message) in RULES {
+            let schema = schema_at(root, path)
+                .unwrap_or_else(|| panic!("CEL rule target {} not in schema", path.join(".")));
+            schema
+                .x_kubernetes_validations
+                .get_or_insert_with(Vec::new)
+                .push(ValidationRule {
+                    rule: rule.to_string(),
+                    message: Some(message.to_string()),
+                    ..Default::default()
+                });
+        }
+    }
+    crd
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rules_attached() {
+        let crd = crd();
+        let root = crd.spec.versions[0]
+            .schema
+            .clone()
+            .unwrap()
+            .open_api_v3_schema
+            .unwrap();
+        let spec = &root.properties.as_ref().unwrap()["spec"];
+        let rules = spec.x_kubernetes_validations.as_ref().unwrap();
+        assert_eq!(rules.len(), 4);
+        assert!(rules.iter().any(|r| r.rule.contains(":latest")));
+
+        let strategy = &spec.properties.as_ref().unwrap()["strategy"];
+        assert_eq!(strategy.x_kubernetes_validations.as_ref().unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,234 @@
+// cert-manager integration for MyApp Controller webhooks
+// Owns a cert-manager Certificate for the webhook Service, has cainjector fill the
+// webhook configurations' caBundle, and serves the issued Secret, swapping the
+// certificate in place whenever cert-manager renews it
+
+use crate::certs::WebhookObjects;
+use crate::tls::ReloadableCert;
+use futures::StreamExt;
+use k8s_openapi::api::admissionregistration::v1::{
+    MutatingWebhookConfiguration, ValidatingWebhookConfiguration,
+};
+use k8s_openapi::api::core::v1::Secret;
+use kube::api::{Api, ApiResource, DynamicObject, GroupVersionKind, Patch, PatchParams};
+use kube::runtime::{watcher, WatchStreamExt};
+use kube::{Client, ResourceExt};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use tokio::sync::oneshot;
+
+/// Annotation asking cert-manager's cainjector to fill in the CA of a Certificate
+pub const INJECT_CA_ANNOTATION: &str = "cert-manager.io/inject-ca-from";
+
+/// Webhook certificate issued by cert-manager
+#[derive(Debug, Clone)]
+pub struct CertManager {
+    pub objects: WebhookObjects,
+    /// Name of the Certificate resource
+    pub certificate: String,
+    pub issuer: String,
+    /// Issuer or ClusterIssuer
+    pub issuer_kind: String,
+}
+
+impl CertManager {
+    /// Issuer from `MYAPP_CERT_MANAGER_ISSUER` (default myapp-selfsigned) and
+    /// `MYAPP_CERT_MANAGER_ISSUER_KIND` (Issuer or ClusterIssuer)
+    pub fn from_env() -> Result<Self, String> {
+        let var = |name: &str| std::env::var(name).ok().filter(|v| !v.is_empty());
+        let issuer_kind = var("MYAPP_CERT_MANAGER_ISSUER_KIND").unwrap_or_else(|| "Issuer".into());
+        if issuer_kind != "Issuer" && issuer_kind != "ClusterIssuer" {
+            return Err(format!(
+                "MYAPP_CERT_MANAGER_ISSUER_KIND must be Issuer or ClusterIssuer, got '{}'",
+                issuer_kind
+            ));
+        }
+        let objects = WebhookObjects::from_env();
+        Ok(Self {
+            certificate: objects.service.clone(),
+            issuer: var("MYAPP_CERT_MANAGER_ISSUER").unwrap_or_else(|| "myapp-selfsigned".into()),
+            issuer_kind,
+            objects,
+        })
+    }
+
+    fn resource() -> ApiResource {
+        let gvk = GroupVersionKind::gvk("cert-manager.io", "v1", "Certificate");
+        ApiResource::from_gvk_with_plural(&gvk, "certificates")
+    }
+
+    /// Certificate for the webhook Service, written by cert-manager into the webhook Secret
+    pub fn certificate(&self) -> DynamicObject {
+        let mut certificate = DynamicObject::new(&self.certificate, &Self::resource())
+            .within(&self.objects.namespace);
+        certificate.metadata.labels = Some(BTreeMap::from([(
+            "managed-by".to_string(),
+            "myapp-controller".to_string(),
+        )]));
+        certificate.data = serde_json::json!({
+            "spec": {
+                "secretName": self.objects.secret,
+                "dnsNames": self.objects.dns_names(),
+                "issuerRef": {
+                    "group": "cert-manager.io",
+                    "kind": self.issuer_kind,
+                    "name": self.issuer,
+                },
+                "privateKey": { "rotationPolicy": "Always" },
+            }
+        });
+        certificate
+    }
+
+    /// Apply the Certificate and point cainjector at it; returns once the first
+    /// certificate is loaded into `cert`, then keeps following renewals
+    pub async fn start(&self, client: Client, cert: Arc<ReloadableCert>) -> Result<(), String> {
+        let certificates: Api<DynamicObject> =
+            Api::namespaced_with(client.clone(), &self.objects.namespace, &Self::resource());
+        let certificate = self.certificate();
+        certificates
+            .patch(
+                &self.certificate,
+                &PatchParams::apply("myapp-webhook").force(),
+                &Patch::Apply(&certificate),
+            )
+            .await
+            .map_err(|e| format!("applying Certificate {}: {}", self.certificate, e))?;
+
+        self.annotate_webhooks(client.clone())
+            .await
+            .map_err(|e| format!("annotating webhook configurations: {}", e))?;
+
+        let loaded = self.watch_secret(client, cert);
+        tracing::info!(
+            "Waiting for cert-manager to issue Secret {}/{}",
+            self.objects.namespace,
+            self.objects.secret
+        );
+        loaded
+            .await
+            .map_err(|_| "certificate Secret watch ended".to_string())
+    }
+
+    /// Add the cainjector annotation to both webhook configurations; missing ones are skipped
+    async fn annotate_webhooks(&self, client: Client) -> Result<(), kube::Error> {
+        let patch = serde_json::json!({
+            "metadata": {
+                "annotations": {
+                    INJECT_CA_ANNOTATION: format!("{}/{}", self.objects.namespace, self.certificate),
+                }
+            }
+        });
+        let params = PatchParams::default();
+
+        let validating: Api<ValidatingWebhookConfiguration> = Api::all(client.clone());
+        let name = &self.objects.validating_webhook;
+        match validating.patch(name, &params, &Patch::Merge(&patch)).await {
+            Err(kube::Error::Api(e)) if e.code == 404 => {
+                tracing::warn!("ValidatingWebhookConfiguration {} not found", name)
+            }
+            result => drop(result?),
+        }
+
+        let mutating: Api<MutatingWebhookConfiguration> = Api::all(client);
+        let name = &self.objects.mutating_webhook;
+        match mutating.patch(name, &params, &Patch::Merge(&patch)).await {
+            Err(kube::Error::Api(e)) if e.code == 404 => {
+                tracing::warn!("MutatingWebhookConfiguration {} not found", name)
+            }
+            result => drop(result?),
+        }
+        Ok(())
+    }
+
+    /// Load the webhook Secret into `cert` on every change; the receiver resolves on the
+    /// first successful load
+    fn watch_secret(&self, client: Client, cert: Arc<ReloadableCert>) -> oneshot::Receiver<()> {
+        let secrets: Api<Secret> = Api::namespaced(client, &self.objects.namespace);
+        let config =
+            watcher::Config::default().fields(&format!("metadata.name={}", self.objects.secret));
+        let (loaded_tx, loaded_rx) = oneshot::channel();
+
+        tokio::spawn(async move {
+            let mut loaded_tx = Some(loaded_tx);
+            let mut secrets = watcher(secrets, config)
+                .default_backoff()
+                .applied_objects()
+                .boxed();
+            while let Some(event) = secrets.next().await {
+                let secret = match event {
+                    Ok(secret) => secret,
+                    Err(e) => {
+                        tracing::warn!("Watching webhook certificate Secret failed: {}", e);
+                        continue;
+                    }
+                };
+                match load(&cert, &secret) {
+                    Ok(()) => {
+                        tracing::info!(
+                            "Serving webhook certificate from Secret {}",
+                            secret.name_any()
+                        );
+                        if let Some(tx) = loaded_tx.take() {
+                            let _ = tx.send(());
+                        }
+                    }
+                    Err(e) => tracing::warn!("Secret {} not usable yet: {}", secret.name_any(), e),
+                }
+            }
+        });
+        loaded_rx
+    }
+}
+
+/// Swap the certificate in `secret` (tls.crt/tls.key) into `cert`
+fn load(cert: &ReloadableCert, secret: &Secret) -> Result<(), String> {
+    let data = secret.data.as_ref().ok_or("no data")?;
+    let field = |key: &str| {
+        data.get(key)
+            .map(|v| v.0.as_slice())
+            .ok_or(format!("missing {}", key))
+    };
+    cert.load_pem(field("tls.crt")?, field("tls.key")?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use k8s_openapi::ByteString;
+
+    #[test]
+    fn test_certificate_and_load() {
+        let manager = CertManager {
+            objects: WebhookObjects::from_env(),
+            certificate: "myapp-webhook".to_string(),
+            issuer: "ca-issuer".to_string(),
+            issuer_kind: "ClusterIssuer".to_string(),
+        };
+        let certificate = manager.certificate();
+        assert_eq!(
+            certificate.data["spec"]["issuerRef"]["kind"],
+            "ClusterIssuer"
+        );
+        assert_eq!(
+            certificate.data["spec"]["secretName"],
+            manager.objects.secret.as_str()
+        );
+
+        let bundle = crate::certs::issue(&manager.objects.dns_names(), None).unwrap();
+        let mut secret = Secret::default();
+        let cert = ReloadableCert::default();
+        assert!(load(&cert, &secret).is_err());
+        secret.data = Some(BTreeMap::from([
+            (
+                "tls.crt".to_string(),
+                ByteString(bundle.tls_cert.into_bytes()),
+            ),
+            (
+                "tls.key".to_string(),
+                ByteString(bundle.tls_key.into_bytes()),
+            ),
+        ]));
+        load(&cert, &secret).unwrap();
+    }
+}
@@ -0,0 +1,400 @@
+// Webhook certificate bootstrap for MyApp Controller
+// Issues a self-signed CA and serving certificate, keeps them in a Secret shared by all
+// webhook replicas and injects the CA into the webhook configurations, so admission
+// works without cert-manager. Serving certificates are reissued before they expire.
+
+use crate::tls::TlsConfig;
+use chrono::{DateTime, Datelike, Duration, Utc};
+use k8s_openapi::api::admissionregistration::v1::{
+    MutatingWebhookConfiguration, ValidatingWebhookConfiguration,
+};
+use k8s_openapi::api::core::v1::Secret;
+use k8s_openapi::ByteString;
+use kube::api::{Api, PostParams};
+use kube::Client;
+use rcgen::{
+    date_time_ymd, BasicConstraints, CertificateParams, DnType, IsCa, KeyPair, KeyUsagePurpose,
+};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use thiserror::Error;
+use tokio::sync::watch;
+
+/// Annotation on the Secret recording when the serving certificate expires
+pub const NOT_AFTER_ANNOTATION: &str = "myapps.example.com/cert-not-after";
+
+const CA_COMMON_NAME: &str = "myapp-webhook-ca";
+const CA_VALIDITY_DAYS: i64 = 3650;
+const SERVING_VALIDITY_DAYS: i64 = 365;
+/// Serving certificates are reissued this long before they expire
+const RENEW_BEFORE_DAYS: i64 = 30;
+
+#[derive(Error, Debug)]
+pub enum CertError {
+    #[error("Kube error: {0}")]
+    Kube(#[from] kube::Error),
+
+    #[error("Certificate generation failed: {0}")]
+    Generate(#[from] rcgen::Error),
+
+    #[error("Writing certificates failed: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Certificate Secret kept changing concurrently")]
+    Contended,
+}
+
+/// CA and serving certificate as stored in the Secret (PEM)
+#[derive(Debug, Clone, PartialEq)]
+pub struct Bundle {
+    pub ca_cert: String,
+    pub ca_key: String,
+    pub tls_cert: String,
+    pub tls_key: String,
+    pub not_after: DateTime<Utc>,
+}
+
+fn validity(days: i64) -> (DateTime<Utc>, rcgen::CertificateParams) {
+    let not_after = Utc::now() + Duration::days(days);
+    let mut params = CertificateParams::default();
+    let yesterday = Utc::now() - Duration::days(1);
+    params.not_before = date_time_ymd(
+        yesterday.year(),
+        yesterday.month() as u8,
+        yesterday.day() as u8,
+    );
+    params.not_after = date_time_ymd(
+        not_after.year(),
+        not_after.month() as u8,
+        not_after.day() as u8,
+    );
+    (not_after, params)
+}
+
+/// CA parameters; re-signing them with the stored CA key rebuilds the same issuer
+fn ca_params() -> CertificateParams {
+    let (_, mut params) = validity(CA_VALIDITY_DAYS);
+    params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+    params
+        .distinguished_name
+        .push(DnType::CommonName, CA_COMMON_NAME);
+    params.key_usages = vec![KeyUsagePurpose::KeyCertSign, KeyUsagePurpose::CrlSign];
+    params
+}
+
+/// Serving certificate for `dns_names`, signed by the CA; reuses the CA when one is given
+pub fn issue(dns_names: &[String], ca: Option<(&str, &str)>) -> Result<Bundle, CertError> {
+    let (ca_cert, ca_key) = match ca {
+        Some((cert, key)) => (cert.to_string(), KeyPair::from_pem(key)?),
+        None => {
+            let key = KeyPair::generate()?;
+            (ca_params().self_signed(&key)?.pem(), key)
+        }
+    };
+    let issuer = ca_params().self_signed(&ca_key)?;
+
+    let (not_after, mut params) = validity(SERVING_VALIDITY_DAYS);
+    params.subject_alt_names = CertificateParams::new(dns_names.to_vec())?.subject_alt_names;
+    params
+        .distinguished_name
+        .push(DnType::CommonName, dns_names[0].as_str());
+    let key = KeyPair::generate()?;
+    let cert = params.signed_by(&key, &issuer, &ca_key)?;
+
+    Ok(Bundle {
+        ca_cert,
+        ca_key: ca_key.serialize_pem(),
+        tls_cert: cert.pem(),
+        tls_key: key.serialize_pem(),
+        not_after,
+    })
+}
+
+impl Bundle {
+    fn from_secret(secret: &Secret) -> Option<Self> {
+        let data = secret.data.as_ref()?;
+        let field = |key: &str| {
+            data.get(key)
+                .and_then(|v| String::from_utf8(v.0.clone()).ok())
+        };
+        let not_after = secret
+            .metadata
+            .annotations
+            .as_ref()?
+            .get(NOT_AFTER_ANNOTATION)?;
+        Some(Self {
+            ca_cert: field("ca.crt")?,
+            ca_key: field("ca.key")?,
+            tls_cert: field("tls.crt")?,
+            tls_key: field("tls.key")?,
+            not_after: DateTime::parse_from_rfc3339(not_after).ok()?.into(),
+        })
+    }
+
+    fn to_secret(&self, name: &str, namespace: &str) -> Secret {
+        let data = [
+            ("ca.crt", &self.ca_cert),
+            ("ca.key", &self.ca_key),
+            ("tls.crt", &self.tls_cert),
+            ("tls.key", &self.tls_key),
+        ]
+        .into_iter()
+        .map(|(k, v)| (k.to_string(), ByteString(v.clone().into_bytes())))
+        .collect();
+
+        let mut secret = Secret {
+            type_: Some("kubernetes.io/tls".to_string()),
+            data: Some(data),
+            ..Default::default()
+        };
+        secret.metadata.name = Some(name.to_string());
+        secret.metadata.namespace = Some(namespace.to_string());
+        secret.metadata.annotations = Some(BTreeMap::from([(
+            NOT_AFTER_ANNOTATION.to_string(),
+            self.not_after.to_rfc3339(),
+        )]));
+        secret
+    }
+
+    pub fn needs_renewal(&self, now: DateTime<Utc>) -> bool {
+        self.not_after - now < Duration::days(RENEW_BEFORE_DAYS)
+    }
+}
+
+/// Names of the objects serving and trusting the webhook certificate
+#[derive(Debug, Clone)]
+pub struct WebhookObjects {
+    pub namespace: String,
+    pub secret: String,
+    pub service: String,
+    pub validating_webhook: String,
+    pub mutating_webhook: String,
+}
+
+impl WebhookObjects {
+    /// Defaults match deploy/webhook-manifests.yaml
+    pub fn from_env() -> Self {
+        let var = |name: &str, default: &str| {
+            std::env::var(name)
+                .ok()
+                .filter(|v| !v.is_empty())
+                .unwrap_or_else(|| default.to_string())
+        };
+        Self {
+            namespace: var("POD_NAMESPACE", "default"),
+            secret: var("MYAPP_WEBHOOK_CERT_SECRET", "myapp-webhook-certs"),
+            service: var("MYAPP_WEBHOOK_SERVICE", "myapp-webhook"),
+            validating_webhook: var("MYAPP_VALIDATING_WEBHOOK", "myapp-validator"),
+            mutating_webhook: var("MYAPP_MUTATING_WEBHOOK", "myapp-mutator"),
+        }
+    }
+
+    /// Names the API server uses to reach the webhook Service
+    pub fn dns_names(&self) -> Vec<String> {
+        let svc = &self.service;
+        let ns = &self.namespace;
+        vec![
+            format!("{}.{}.svc", svc, ns),
+            format!("{}.{}.svc.cluster.local", svc, ns),
+            format!("{}.{}", svc, ns),
+            svc.clone(),
+        ]
+    }
+}
+
+/// Self-signed certificates kept in the webhook Secret
+#[derive(Debug, Clone)]
+pub struct CertBootstrap {
+    pub objects: WebhookObjects,
+    /// Local directory the serving certificate is written to for the TLS listener
+    pub dir: PathBuf,
+}
+
+impl CertBootstrap {
+    pub fn from_env() -> Self {
+        Self {
+            objects: WebhookObjects::from_env(),
+            dir: std::env::temp_dir().join("myapp-webhook-certs"),
+        }
+    }
+
+    /// Load or issue the certificates, inject the CA and return the TLS settings to serve with
+    pub async fn ensure(&self, client: Client) -> Result<(TlsConfig, Bundle), CertError> {
+        let bundle = self.ensure_secret(client.clone()).await?;
+        inject_ca_bundle(
+            client,
+            &self.objects.validating_webhook,
+            &self.objects.mutating_webhook,
+            &bundle.ca_cert,
+        )
+        .await?;
+
+        std::fs::create_dir_all(&self.dir)?;
+        let cert_path = self.dir.join("tls.crt");
+        let key_path = self.dir.join("tls.key");
+        std::fs::write(&cert_path, &bundle.tls_cert)?;
+        std::fs::write(&key_path, &bundle.tls_key)?;
+
+        let tls = TlsConfig {
+            cert_path,
+            key_path,
+            client_ca_path: None,
+            client_auth_required: false,
+        };
+        Ok((tls, bundle))
+    }
+
+    /// Current bundle from the Secret, creating or renewing it; replicas racing on the
+    /// Secret re-read it and use whatever won
+    async fn ensure_secret(&self, client: Client) -> Result<Bundle, CertError> {
+        let secrets: Api<Secret> = Api::namespaced(client, &self.objects.namespace);
+        for _ in 0..crate::retry::MAX_ATTEMPTS {
+            let existing = secrets.get_opt(&self.objects.secret).await?;
+            let current = existing.as_ref().and_then(Bundle::from_secret);
+            if let Some(bundle) = &current {
+                if !bundle.needs_renewal(Utc::now()) {
+                    return Ok(bundle.clone());
+                }
+            }
+
+            let ca = current
+                .as_ref()
+                .map(|b| (b.ca_cert.as_str(), b.ca_key.as_str()));
+            let bundle = issue(&self.objects.dns_names(), ca)?;
+            let mut secret = bundle.to_secret(&self.objects.secret, &self.objects.namespace);
+            let written = match existing {
+                Some(existing) => {
+                    secret.metadata.resource_version = existing.metadata.resource_version;
+                    secrets
+                        .replace(&self.objects.secret, &PostParams::default(), &secret)
+                        .await
+                }
+                None => secrets.create(&PostParams::default(), &secret).await,
+            };
+            match written {
+                Ok(_) => {
+                    tracing::info!(
+                        "Issued webhook serving certificate valid until {}",
+                        bundle.not_after
+                    );
+                    return Ok(bundle);
+                }
+                Err(e) if crate::retry::is_conflict(&e) => continue,
+                Err(kube::Error::Api(e)) if e.code == 409 => continue,
+                Err(e) => return Err(e.into()),
+            }
+        }
+        Err(CertError::Contended)
+    }
+
+    /// Reissue the serving certificate before it expires, then request a restart so the
+    /// TLS listener picks it up
+    pub fn spawn_rotation(self, client: Client, bundle: Bundle, restart: watch::Sender<bool>) {
+        tokio::spawn(async move {
+            let mut bundle = bundle;
+            loop {
+                let renew_at = bundle.not_after - Duration::days(RENEW_BEFORE_DAYS);
+                let wait = (renew_at - Utc::now())
+                    .to_std()
+                    .unwrap_or_default()
+                    .min(std::time::Duration::from_secs(24 * 3600));
+                tokio::time::sleep(wait).await;
+                if !bundle.needs_renewal(Utc::now()) {
+                    continue;
+                }
+                match self.ensure(client.clone()).await {
+                    Ok(_) => {
+                        tracing::info!("Webhook certificate rotated, restarting to serve it");
+                        let _ = restart.send(true);
+                        return;
+                    }
+                    Err(e) => {
+                        tracing::warn!("Webhook certificate rotation failed: {}", e);
+                        tokio::time::sleep(std::time::Duration::from_secs(300)).await;
+                    }
+                }
+                bundle.not_after = Utc::now() + Duration::days(RENEW_BEFORE_DAYS);
+            }
+        });
+    }
+}
+
+/// Set `caBundle` on every webhook of the two configurations; missing ones are skipped
+pub async fn inject_ca_bundle(
+    client: Client,
+    validating: &str,
+    mutating: &str,
+    ca_cert: &str,
+) -> Result<(), kube::Error> {
+    let ca = ByteString(ca_cert.as_bytes().to_vec());
+
+    let api: Api<ValidatingWebhookConfiguration> = Api::all(client.clone());
+    match api.get_opt(validating).await? {
+        Some(mut config) => {
+            let webhooks = config.webhooks.iter_mut().flatten();
+            if set_ca(webhooks.map(|w| &mut w.client_config.ca_bundle), &ca) {
+                api.replace(validating, &PostParams::default(), &config)
+                    .await?;
+                tracing::info!("Injected CA bundle into {}", validating);
+            }
+        }
+        None => tracing::warn!("ValidatingWebhookConfiguration {} not found", validating),
+    }
+
+    let api: Api<MutatingWebhookConfiguration> = Api::all(client);
+    match api.get_opt(mutating).await? {
+        Some(mut config) => {
+            let webhooks = config.webhooks.iter_mut().flatten();
+            if set_ca(webhooks.map(|w| &mut w.client_config.ca_bundle), &ca) {
+                api.replace(mutating, &PostParams::default(), &config)
+                    .await?;
+                tracing::info!("Injected CA bundle into {}", mutating);
+            }
+        }
+        None => tracing::warn!("MutatingWebhookConfiguration {} not found", mutating),
+    }
+    Ok(())
+}
+
+/// Point every bundle at `ca`; whether anything changed
+fn set_ca<'a>(bundles: impl Iterator<Item = &'a mut Option<ByteString>>, ca: &ByteString) -> bool {
+    let mut changed = false;
+    for bundle in bundles {
+        if bundle.as_ref() != Some(ca) {
+            *bundle = Some(ca.clone());
+            changed = true;
+        }
+    }
+    changed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_issue_and_reissue() {
+        let names = vec!["myapp-webhook.default.svc".to_string()];
+        let first = issue(&names, None).unwrap();
+        assert!(first.ca_cert.contains("BEGIN CERTIFICATE"));
+        assert!(!first.needs_renewal(Utc::now()));
+        assert!(first.needs_renewal(Utc::now() + Duration::days(340)));
+
+        // Renewal keeps the CA, so the injected caBundle stays valid
+        let second = issue(&names, Some((&first.ca_cert, &first.ca_key))).unwrap();
+        assert_eq!(second.ca_cert, first.ca_cert);
+        assert_ne!(second.tls_cert, first.tls_cert);
+
+        let secret = second.to_secret("certs", "default");
+        let loaded = Bundle::from_secret(&secret).unwrap();
+        assert_eq!(loaded.tls_key, second.tls_key);
+    }
+
+    #[test]
+    fn test_set_ca() {
+        let ca = ByteString(b"ca".to_vec());
+        let mut bundles = [None, Some(ByteString(b"old".to_vec()))];
+        assert!(set_ca(bundles.iter_mut(), &ca));
+        assert!(!set_ca(bundles.iter_mut(), &ca));
+    }
+}
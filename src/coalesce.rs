@@ -0,0 +1,122 @@
+// Reconcile coalescing for MyApp Controller
+// Watch events for the same MyApp within the debounce window collapse into one reconcile of
+// its latest state; the events that never got a reconcile of their own are counted
+
+use crate::metrics::MetricsCollector;
+use crate::MyApp;
+use kube::runtime::reflector::ObjectRef;
+use kube::runtime::watcher;
+use kube::{Resource, ResourceExt};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// `MYAPP_RECONCILE_DEBOUNCE_MS`: quiet period an object needs before it is reconciled
+pub fn debounce_from_env() -> Duration {
+    let ms = std::env::var("MYAPP_RECONCILE_DEBOUNCE_MS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(500);
+    Duration::from_millis(ms)
+}
+
+/// The MyApp a child belongs to, from its controller owner reference
+pub fn owner<K: Resource>(child: &K) -> Option<ObjectRef<MyApp>> {
+    let namespace = child.namespace()?;
+    child
+        .owner_references()
+        .iter()
+        .find(|o| o.kind == MyApp::kind(&()) && o.api_version == MyApp::api_version(&()))
+        .map(|o| ObjectRef::new(&o.name).within(&namespace))
+}
+
+/// Watch events seen per MyApp since its last reconcile
+#[derive(Clone, Default)]
+pub struct Coalescer {
+    pending: Arc<Mutex<HashMap<ObjectRef<MyApp>, u64>>>,
+    metrics: MetricsCollector,
+}
+
+impl Coalescer {
+    pub fn new(metrics: MetricsCollector) -> Self {
+        Self {
+            metrics,
+            ..Default::default()
+        }
+    }
+
+    /// A watch event asked for `myapp` to be reconciled
+    pub fn triggered(&self, myapp: ObjectRef<MyApp>) {
+        *self.pending.lock().unwrap().entry(myapp).or_default() += 1;
+    }
+
+    /// Count MyApp events passed to the controller
+    pub fn myapp_events(&self) -> impl Fn(&Result<MyApp, watcher::Error>) {
+        let this = self.clone();
+        move |event| {
+            if let Ok(myapp) = event {
+                this.triggered(ObjectRef::from_obj(myapp));
+            }
+        }
+    }
+
+    /// Count child events against the MyApp that owns the child
+    pub fn child_events<K: Resource>(&self) -> impl Fn(&Result<K, watcher::Error>) {
+        let this = self.clone();
+        move |event| {
+            if let Some(myapp) = event.as_ref().ok().and_then(owner) {
+                this.triggered(myapp);
+            }
+        }
+    }
+
+    /// Reconcile of `myapp` is starting; returns how many events it absorbed beyond the first
+    pub fn reconciling(&self, myapp: &ObjectRef<MyApp>) -> u64 {
+        let events = self.pending.lock().unwrap().remove(myapp).unwrap_or(0);
+        let coalesced = events.saturating_sub(1);
+        if coalesced > 0 {
+            self.metrics
+                .record_coalesced(myapp.namespace.as_deref().unwrap_or_default(), coalesced);
+        }
+        coalesced
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use k8s_openapi::api::apps::v1::Deployment;
+    use k8s_openapi::apimachinery::pkg::apis::meta::v1::OwnerReference;
+
+    #[test]
+    fn test_events_coalesce_until_reconcile() {
+        let coalescer = Coalescer::default();
+        let web = ObjectRef::<MyApp>::new("web").within("default");
+
+        for _ in 0..3 {
+            coalescer.triggered(web.clone());
+        }
+        assert_eq!(coalescer.reconciling(&web), 2);
+        // A requeue with no new events coalesces nothing
+        assert_eq!(coalescer.reconciling(&web), 0);
+    }
+
+    #[test]
+    fn test_owner_of_child() {
+        let mut deployment = Deployment::default();
+        deployment.metadata.namespace = Some("default".to_string());
+        deployment.metadata.owner_references = Some(vec![OwnerReference {
+            api_version: MyApp::api_version(&()).to_string(),
+            kind: "MyApp".to_string(),
+            name: "web".to_string(),
+            ..Default::default()
+        }]);
+        assert_eq!(
+            owner(&deployment),
+            Some(ObjectRef::new("web").within("default"))
+        );
+
+        deployment.metadata.owner_references = None;
+        assert_eq!(owner(&deployment), None);
+    }
+}
@@ -0,0 +1,639 @@
+// Cosign signature verification for MyApp Controller
+// MyApps with spec.imageVerification only roll out images whose cosign signature checks out:
+// the `sha256-<digest>.sig` artifact next to the image must hold a simple-signing payload for
+// the image's digest, signed either with the configured public key or, keyless, by a Fulcio
+// certificate for the configured identity that was logged in Rekor while it was valid.
+// Required in-toto attestations are read from `sha256-<digest>.att` the same way.
+
+use crate::arch::{ImageRef, RegistryClient, RegistryError};
+use crate::digest::is_pinned;
+use crate::scan::Verdict;
+use base64::Engine;
+use ring::signature::{UnparsedPublicKey, ECDSA_P256_SHA256_ASN1};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet};
+use std::time::Duration;
+use tokio_rustls::rustls::pki_types::{CertificateDer, UnixTime};
+
+const SIGNATURE_ANNOTATION: &str = "dev.cosignproject.cosign/signature";
+const CERTIFICATE_ANNOTATION: &str = "dev.sigstore.cosign/certificate";
+const CHAIN_ANNOTATION: &str = "dev.sigstore.cosign/chain";
+const BUNDLE_ANNOTATION: &str = "dev.sigstore.cosign/bundle";
+
+// DER contents of the object identifiers read from keys and certificates
+const OID_P256: &[u8] = &[0x2a, 0x86, 0x48, 0xce, 0x3d, 0x03, 0x01, 0x07];
+const OID_SUBJECT_ALT_NAME: &[u8] = &[0x55, 0x1d, 0x11];
+const OID_CODE_SIGNING: &[u8] = &[0x2b, 0x06, 0x01, 0x05, 0x05, 0x07, 0x03, 0x03];
+const OID_FULCIO_ISSUER: &[u8] = &[0x2b, 0x06, 0x01, 0x04, 0x01, 0x83, 0xbf, 0x30, 0x01, 0x01];
+const OID_FULCIO_ISSUER_V2: &[u8] = &[0x2b, 0x06, 0x01, 0x04, 0x01, 0x83, 0xbf, 0x30, 0x01, 0x08];
+
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct KeylessIdentity {
+    /// OIDC issuer recorded in the certificate, e.g. https://token.actions.githubusercontent.com
+    pub issuer: String,
+
+    /// Signer's email or URI, e.g. a GitHub workflow ref
+    pub subject: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ImageVerification {
+    /// PEM ECDSA P-256 public key the image is signed with (cosign generate-key-pair)
+    #[serde(default)]
+    pub public_key: Option<String>,
+
+    /// Signer of a keyless signature
+    #[serde(default)]
+    pub keyless: Option<KeylessIdentity>,
+
+    /// In-toto predicate types that must also be attested, e.g. https://slsa.dev/provenance/v1
+    #[serde(default)]
+    pub attestations: Vec<String>,
+}
+
+impl ImageVerification {
+    pub fn validate(&self) -> Result<(), String> {
+        match (&self.public_key, &self.keyless) {
+            (Some(pem), None) => public_key_point(pem)
+                .map(drop)
+                .map_err(|e| format!("imageVerification.publicKey: {}", e)),
+            (None, Some(identity)) if identity.issuer.is_empty() || identity.subject.is_empty() => {
+                Err("imageVerification.keyless needs issuer and subject".to_string())
+            }
+            (None, Some(_)) => Ok(()),
+            _ => Err("imageVerification needs exactly one of publicKey and keyless".to_string()),
+        }
+    }
+}
+
+/// Split the first DER element off `input` into its tag, its contents and what follows
+fn der(input: &[u8]) -> Option<(u8, &[u8], &[u8])> {
+    let (&tag, rest) = input.split_first()?;
+    let (&first, rest) = rest.split_first()?;
+    let (len, rest) = if first < 0x80 {
+        (first as usize, rest)
+    } else {
+        let n = (first & 0x7f) as usize;
+        if n == 0 || n > 4 || rest.len() < n {
+            return None;
+        }
+        let len = rest[..n].iter().fold(0, |len, b| len << 8 | *b as usize);
+        (len, &rest[n..])
+    };
+    (rest.len() >= len).then(|| (tag, &rest[..len], &rest[len..]))
+}
+
+/// Tags and contents of consecutive DER elements
+fn elements(mut input: &[u8]) -> impl Iterator<Item = (u8, &[u8])> {
+    std::iter::from_fn(move || {
+        let (tag, contents, rest) = der(input)?;
+        input = rest;
+        Some((tag, contents))
+    })
+}
+
+/// Uncompressed EC point of a P-256 SubjectPublicKeyInfo
+fn spki_point(spki: &[u8]) -> Option<&[u8]> {
+    let (0x30, spki, _) = der(spki)? else {
+        return None;
+    };
+    let mut parts = elements(spki);
+    let (0x30, algorithm) = parts.next()? else {
+        return None;
+    };
+    if !elements(algorithm).any(|(tag, oid)| tag == 0x06 && oid == OID_P256) {
+        return None;
+    }
+    let (0x03, bits) = parts.next()? else {
+        return None;
+    };
+    match bits.split_first()? {
+        (0, point) => Some(point),
+        _ => None,
+    }
+}
+
+fn public_key_point(pem: &str) -> Result<Vec<u8>, String> {
+    let spki = rustls_pemfile::public_keys(&mut pem.as_bytes())
+        .next()
+        .ok_or("no PUBLIC KEY block")?
+        .map_err(|e| e.to_string())?;
+    spki_point(spki.as_ref())
+        .map(<[u8]>::to_vec)
+        .ok_or_else(|| "not an ECDSA P-256 key".to_string())
+}
+
+/// Extension values of a DER certificate, by OID
+fn extensions(cert: &[u8]) -> BTreeMap<&[u8], &[u8]> {
+    let tbs = der(cert)
+        .and_then(|(_, certificate, _)| elements(certificate).next())
+        .map_or(&[][..], |(_, tbs)| tbs);
+    elements(tbs)
+        .filter(|(tag, _)| *tag == 0xa3)
+        .flat_map(|(_, explicit)| elements(explicit))
+        .flat_map(|(_, list)| elements(list))
+        .filter_map(|(_, extension)| {
+            let mut parts = elements(extension);
+            let (0x06, oid) = parts.next()? else {
+                return None;
+            };
+            let (_, value) = parts.find(|(tag, _)| *tag == 0x04)?;
+            Some((oid, value))
+        })
+        .collect()
+}
+
+/// Email and URI subject alternative names and the Fulcio OIDC issuer of a certificate
+fn cert_identity(cert: &[u8]) -> (Vec<String>, Option<String>) {
+    let extensions = extensions(cert);
+    let subjects = extensions
+        .get(&OID_SUBJECT_ALT_NAME)
+        .and_then(|names| der(names))
+        .map(|(_, names, _)| {
+            elements(names)
+                // rfc822Name and uniformResourceIdentifier
+                .filter(|(tag, _)| *tag == 0x81 || *tag == 0x86)
+                .filter_map(|(_, name)| String::from_utf8(name.to_vec()).ok())
+                .collect()
+        })
+        .unwrap_or_default();
+    let issuer = extensions
+        .get(&OID_FULCIO_ISSUER_V2)
+        .and_then(|value| der(value))
+        .map(|(_, issuer, _)| issuer)
+        .or_else(|| extensions.get(&OID_FULCIO_ISSUER).copied())
+        .and_then(|issuer| String::from_utf8(issuer.to_vec()).ok());
+    (subjects, issuer)
+}
+
+fn decode_base64(value: &str) -> Result<Vec<u8>, String> {
+    base64::engine::general_purpose::STANDARD
+        .decode(value)
+        .map_err(|e| format!("invalid base64: {}", e))
+}
+
+fn sha256_digest(data: &[u8]) -> String {
+    let hash = ring::digest::digest(&ring::digest::SHA256, data);
+    let hex: String = hash.as_ref().iter().map(|b| format!("{:02x}", b)).collect();
+    format!("sha256:{}", hex)
+}
+
+/// DSSE pre-authentication encoding, the bytes an envelope's signatures cover
+fn pae(payload_type: &str, payload: &[u8]) -> Vec<u8> {
+    let mut message = format!(
+        "DSSEv1 {} {} {} ",
+        payload_type.len(),
+        payload_type,
+        payload.len()
+    )
+    .into_bytes();
+    message.extend_from_slice(payload);
+    message
+}
+
+/// Time the signature entered Rekor, once the log's signed entry timestamp verifies
+fn integrated_time(bundle: &str, rekor_key: &[u8]) -> Result<u64, String> {
+    let bundle: Value =
+        serde_json::from_str(bundle).map_err(|e| format!("invalid Rekor bundle: {}", e))?;
+    let payload = &bundle["Payload"];
+    let (Some(body), Some(time), Some(log_id), Some(index)) = (
+        payload["body"].as_str(),
+        payload["integratedTime"].as_u64(),
+        payload["logID"].as_str(),
+        payload["logIndex"].as_u64(),
+    ) else {
+        return Err("incomplete Rekor bundle".to_string());
+    };
+    // Rekor signs the entry as canonical JSON: sorted keys, no whitespace
+    let canonical = format!(
+        r#"{{"body":{},"integratedTime":{},"logID":{},"logIndex":{}}}"#,
+        Value::from(body),
+        time,
+        Value::from(log_id),
+        index
+    );
+    let timestamp = decode_base64(bundle["SignedEntryTimestamp"].as_str().unwrap_or_default())?;
+    UnparsedPublicKey::new(&ECDSA_P256_SHA256_ASN1, rekor_key)
+        .verify(canonical.as_bytes(), &timestamp)
+        .map_err(|_| "Rekor entry timestamp does not verify".to_string())?;
+    Ok(time)
+}
+
+/// Registry access and keyless trust roots
+#[derive(Clone)]
+pub struct Verifier {
+    registry: RegistryClient,
+    fulcio: Vec<CertificateDer<'static>>,
+    rekor: Option<Vec<u8>>,
+}
+
+impl Verifier {
+    /// Keyless trust roots from the PEM files named by `MYAPP_FULCIO_ROOTS` (certificates)
+    /// and `MYAPP_REKOR_PUBLIC_KEY`; keyless verification fails without them
+    pub fn from_env() -> Self {
+        let read = |var: &str| {
+            let path = std::env::var(var).ok()?;
+            std::fs::read_to_string(&path)
+                .inspect_err(|e| tracing::warn!("Could not read {} ({}): {}", var, path, e))
+                .ok()
+        };
+        let fulcio = read("MYAPP_FULCIO_ROOTS")
+            .map(|pem| {
+                rustls_pemfile::certs(&mut pem.as_bytes())
+                    .filter_map(Result::ok)
+                    .collect()
+            })
+            .unwrap_or_default();
+        let rekor = read("MYAPP_REKOR_PUBLIC_KEY").and_then(|pem| {
+            public_key_point(&pem)
+                .inspect_err(|e| tracing::warn!("Invalid MYAPP_REKOR_PUBLIC_KEY: {}", e))
+                .ok()
+        });
+        Self {
+            registry: RegistryClient::new(),
+            fulcio,
+            rekor,
+        }
+    }
+
+    /// Check `signature` over `message` against the policy, keyless material coming from
+    /// the layer annotations
+    fn check_signature(
+        &self,
+        policy: &ImageVerification,
+        annotations: &Value,
+        message: &[u8],
+        signature: &[u8],
+    ) -> Result<(), String> {
+        if let Some(pem) = &policy.public_key {
+            return UnparsedPublicKey::new(&ECDSA_P256_SHA256_ASN1, public_key_point(pem)?)
+                .verify(message, signature)
+                .map_err(|_| "signature does not match the public key".to_string());
+        }
+        let Some(identity) = &policy.keyless else {
+            return Err("no public key or keyless identity".to_string());
+        };
+        let Some(rekor) = &self.rekor else {
+            return Err("keyless verification needs MYAPP_REKOR_PUBLIC_KEY".to_string());
+        };
+        if self.fulcio.is_empty() {
+            return Err("keyless verification needs MYAPP_FULCIO_ROOTS".to_string());
+        }
+
+        let certs = |key: &str| -> Vec<CertificateDer<'static>> {
+            annotations[key]
+                .as_str()
+                .map(|pem| {
+                    rustls_pemfile::certs(&mut pem.as_bytes())
+                        .filter_map(Result::ok)
+                        .collect()
+                })
+                .unwrap_or_default()
+        };
+        let leaf = certs(CERTIFICATE_ANNOTATION)
+            .into_iter()
+            .next()
+            .ok_or("no signing certificate")?;
+        let chain = certs(CHAIN_ANNOTATION);
+        let bundle = annotations[BUNDLE_ANNOTATION]
+            .as_str()
+            .ok_or("no Rekor bundle")?;
+        let logged = integrated_time(bundle, rekor)?;
+
+        // Fulcio certificates live minutes; they must have been valid when Rekor logged them
+        let anchors = self
+            .fulcio
+            .iter()
+            .map(webpki::anchor_from_trusted_cert)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("invalid Fulcio root: {}", e))?;
+        let cert = webpki::EndEntityCert::try_from(&leaf)
+            .map_err(|e| format!("invalid signing certificate: {}", e))?;
+        cert.verify_for_usage(
+            &[
+                webpki::ring::ECDSA_P256_SHA256,
+                webpki::ring::ECDSA_P256_SHA384,
+                webpki::ring::ECDSA_P384_SHA256,
+                webpki::ring::ECDSA_P384_SHA384,
+            ],
+            &anchors,
+            &chain,
+            UnixTime::since_unix_epoch(Duration::from_secs(logged)),
+            webpki::KeyUsage::required(OID_CODE_SIGNING),
+            None,
+            None,
+        )
+        .map_err(|e| format!("signing certificate not issued by Fulcio: {}", e))?;
+        cert.verify_signature(webpki::ring::ECDSA_P256_SHA256, message, signature)
+            .map_err(|_| "signature does not match the signing certificate".to_string())?;
+
+        let (subjects, issuer) = cert_identity(&leaf);
+        if issuer.as_deref() != Some(identity.issuer.as_str()) {
+            return Err(format!(
+                "signed through issuer {}, expected {}",
+                issuer.unwrap_or_default(),
+                identity.issuer
+            ));
+        }
+        if !subjects.contains(&identity.subject) {
+            return Err(format!(
+                "signed by {}, expected {}",
+                subjects.join(", "),
+                identity.subject
+            ));
+        }
+        Ok(())
+    }
+
+    /// Layers of the cosign artifact `tag`, empty when it was never pushed
+    async fn layers(&self, image: &ImageRef, tag: &str) -> Result<Vec<Value>, String> {
+        match self.registry.manifest(image, tag).await {
+            Ok(manifest) => Ok(manifest["layers"].as_array().cloned().unwrap_or_default()),
+            Err(RegistryError::Status { status, .. })
+                if status == reqwest::StatusCode::NOT_FOUND =>
+            {
+                Ok(Vec::new())
+            }
+            Err(e) => Err(e.to_string()),
+        }
+    }
+
+    /// Contents of a layer, checked against its digest
+    async fn layer_blob(&self, image: &ImageRef, layer: &Value) -> Result<Vec<u8>, String> {
+        let digest = layer["digest"].as_str().ok_or("layer without digest")?;
+        let blob = self
+            .registry
+            .blob(image, digest)
+            .await
+            .map_err(|e| e.to_string())?;
+        if sha256_digest(&blob) != digest {
+            return Err(format!("layer {} does not match its digest", digest));
+        }
+        Ok(blob)
+    }
+
+    /// A `.sig` layer: a simple-signing payload naming `digest`, signed per the policy
+    async fn check_signature_layer(
+        &self,
+        image: &ImageRef,
+        digest: &str,
+        policy: &ImageVerification,
+        layer: &Value,
+    ) -> Result<(), String> {
+        let payload = self.layer_blob(image, layer).await?;
+        let claim: Value = serde_json::from_slice(&payload)
+            .map_err(|e| format!("invalid signature payload: {}", e))?;
+        if claim["critical"]["image"]["docker-manifest-digest"] != digest {
+            return Err("signature is for another image".to_string());
+        }
+        let annotations = &layer["annotations"];
+        let signature = decode_base64(
+            annotations[SIGNATURE_ANNOTATION]
+                .as_str()
+                .unwrap_or_default(),
+        )?;
+        self.check_signature(policy, annotations, &payload, &signature)
+    }
+
+    /// A `.att` layer: a DSSE envelope signed per the policy whose in-toto statement is
+    /// about `digest`; returns its predicate type
+    async fn check_attestation_layer(
+        &self,
+        image: &ImageRef,
+        digest: &str,
+        policy: &ImageVerification,
+        layer: &Value,
+    ) -> Result<String, String> {
+        let envelope: Value = serde_json::from_slice(&self.layer_blob(image, layer).await?)
+            .map_err(|e| format!("invalid attestation envelope: {}", e))?;
+        let payload = decode_base64(envelope["payload"].as_str().unwrap_or_default())?;
+        let signature = decode_base64(
+            envelope["signatures"][0]["sig"]
+                .as_str()
+                .unwrap_or_default(),
+        )?;
+        let message = pae(
+            envelope["payloadType"].as_str().unwrap_or_default(),
+            &payload,
+        );
+        self.check_signature(policy, &layer["annotations"], &message, &signature)?;
+
+        let statement: Value = serde_json::from_slice(&payload)
+            .map_err(|e| format!("invalid in-toto statement: {}", e))?;
+        let hex = digest.trim_start_matches("sha256:");
+        let about_image = statement["subject"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .any(|subject| subject["digest"]["sha256"] == hex);
+        if !about_image {
+            return Err("attestation is for another image".to_string());
+        }
+        Ok(statement["predicateType"]
+            .as_str()
+            .unwrap_or_default()
+            .to_string())
+    }
+
+    /// Digest of `image` once its signature and required attestations verify
+    pub async fn verify(&self, image: &str, policy: &ImageVerification) -> Result<String, String> {
+        let reference = ImageRef::parse(image);
+        let digest = match is_pinned(image) {
+            true => reference.reference.clone(),
+            false => self
+                .registry
+                .resolve_digest(image)
+                .await
+                .map_err(|e| e.to_string())?,
+        };
+        let tag = digest.replace(':', "-");
+
+        let mut rejected = Vec::new();
+        let mut signed = false;
+        for layer in self.layers(&reference, &format!("{}.sig", tag)).await? {
+            match self
+                .check_signature_layer(&reference, &digest, policy, &layer)
+                .await
+            {
+                Ok(()) => {
+                    signed = true;
+                    break;
+                }
+                Err(e) => rejected.push(e),
+            }
+        }
+        if !signed {
+            return Err(format!(
+                "no valid signature for {} ({})",
+                digest,
+                match rejected.is_empty() {
+                    true => "none found".to_string(),
+                    false => rejected.join("; "),
+                }
+            ));
+        }
+
+        if policy.attestations.is_empty() {
+            return Ok(digest);
+        }
+        let mut attested = BTreeSet::new();
+        for layer in self.layers(&reference, &format!("{}.att", tag)).await? {
+            match self
+                .check_attestation_layer(&reference, &digest, policy, &layer)
+                .await
+            {
+                Ok(predicate_type) => {
+                    attested.insert(predicate_type);
+                }
+                Err(e) => tracing::warn!("Skipping attestation of {}: {}", image, e),
+            }
+        }
+        let missing: Vec<&str> = policy
+            .attestations
+            .iter()
+            .filter(|t| !attested.contains(*t))
+            .map(String::as_str)
+            .collect();
+        match missing.is_empty() {
+            true => Ok(digest),
+            false => Err(format!(
+                "no valid attestation of {} for {}",
+                digest,
+                missing.join(", ")
+            )),
+        }
+    }
+
+    /// Whether `image` may be rolled out under `policy`
+    pub async fn check(&self, image: &str, policy: &ImageVerification) -> Verdict {
+        match self.verify(image, policy).await {
+            Ok(digest) => {
+                tracing::info!("Verified signature of {} ({})", image, digest);
+                Verdict::Pass
+            }
+            Err(e) => Verdict::Blocked("SignatureVerificationFailed", format!("{}: {}", image, e)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rcgen::{
+        BasicConstraints, CertificateParams, CustomExtension, ExtendedKeyUsagePurpose, IsCa,
+        KeyPair, KeyUsagePurpose, SanType,
+    };
+    use ring::rand::SystemRandom;
+    use ring::signature::{EcdsaKeyPair, ECDSA_P256_SHA256_ASN1_SIGNING};
+
+    fn sign(key: &KeyPair, message: &[u8]) -> Vec<u8> {
+        let rng = SystemRandom::new();
+        EcdsaKeyPair::from_pkcs8(&ECDSA_P256_SHA256_ASN1_SIGNING, &key.serialize_der(), &rng)
+            .unwrap()
+            .sign(&rng, message)
+            .unwrap()
+            .as_ref()
+            .to_vec()
+    }
+
+    fn verifier(fulcio: Vec<CertificateDer<'static>>, rekor: Option<&KeyPair>) -> Verifier {
+        Verifier {
+            registry: RegistryClient::new(),
+            fulcio,
+            rekor: rekor.map(|key| public_key_point(&key.public_key_pem()).unwrap()),
+        }
+    }
+
+    #[test]
+    fn test_public_key_signature() {
+        let key = KeyPair::generate().unwrap();
+        let policy = ImageVerification {
+            public_key: Some(key.public_key_pem()),
+            ..Default::default()
+        };
+        assert!(policy.validate().is_ok());
+
+        let payload = br#"{"critical":{"image":{"docker-manifest-digest":"sha256:ab"}}}"#;
+        let signature = sign(&key, payload);
+        let verifier = verifier(Vec::new(), None);
+        assert!(verifier
+            .check_signature(&policy, &Value::Null, payload, &signature)
+            .is_ok());
+        assert!(verifier
+            .check_signature(&policy, &Value::Null, b"tampered", &signature)
+            .is_err());
+        assert!(ImageVerification::default().validate().is_err());
+    }
+
+    #[test]
+    fn test_keyless_signature() {
+        let ca_key = KeyPair::generate().unwrap();
+        let mut ca_params = CertificateParams::default();
+        ca_params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+        ca_params.key_usages = vec![KeyUsagePurpose::KeyCertSign];
+        let ca = ca_params.clone().self_signed(&ca_key).unwrap();
+
+        let subject = "https://github.com/org/app/.github/workflows/release.yml@refs/heads/main";
+        let issuer = "https://token.actions.githubusercontent.com";
+        let leaf_key = KeyPair::generate().unwrap();
+        let mut leaf_params = CertificateParams::default();
+        leaf_params.subject_alt_names = vec![SanType::URI(subject.try_into().unwrap())];
+        leaf_params.extended_key_usages = vec![ExtendedKeyUsagePurpose::CodeSigning];
+        leaf_params.custom_extensions = vec![CustomExtension::from_oid_content(
+            &[1, 3, 6, 1, 4, 1, 57264, 1, 1],
+            issuer.as_bytes().to_vec(),
+        )];
+        let leaf = leaf_params.signed_by(&leaf_key, &ca, &ca_key).unwrap();
+
+        let rekor_key = KeyPair::generate().unwrap();
+        let entry =
+            r#"{"body":"e30=","integratedTime":1760000000,"logID":"c0d23d6a","logIndex":7}"#;
+        let bundle = serde_json::json!({
+            "SignedEntryTimestamp": base64::engine::general_purpose::STANDARD
+                .encode(sign(&rekor_key, entry.as_bytes())),
+            "Payload": serde_json::from_str::<Value>(entry).unwrap(),
+        });
+        let annotations = serde_json::json!({
+            CERTIFICATE_ANNOTATION: leaf.pem(),
+            BUNDLE_ANNOTATION: bundle.to_string(),
+        });
+
+        let payload = b"payload";
+        let signature = sign(&leaf_key, payload);
+        let trusted = verifier(vec![ca.der().clone()], Some(&rekor_key));
+        let policy = |subject: &str| ImageVerification {
+            keyless: Some(KeylessIdentity {
+                issuer: issuer.to_string(),
+                subject: subject.to_string(),
+            }),
+            ..Default::default()
+        };
+        assert_eq!(
+            trusted.check_signature(&policy(subject), &annotations, payload, &signature),
+            Ok(())
+        );
+        assert!(trusted
+            .check_signature(&policy("ci@example.com"), &annotations, payload, &signature)
+            .is_err());
+
+        // A certificate from another CA is refused
+        let other_key = KeyPair::generate().unwrap();
+        let other_root = ca_params.self_signed(&other_key).unwrap();
+        let untrusted = verifier(vec![other_root.der().clone()], Some(&rekor_key));
+        assert!(untrusted
+            .check_signature(&policy(subject), &annotations, payload, &signature)
+            .is_err());
+    }
+
+    #[test]
+    fn test_pae() {
+        assert_eq!(
+            pae("application/vnd.in-toto+json", b"{}"),
+            b"DSSEv1 28 application/vnd.in-toto+json 2 {}".to_vec()
+        );
+    }
+}
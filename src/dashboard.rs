@@ -0,0 +1,179 @@
+// Grafana dashboard for MyApp Controller
+// `generate-dashboard` prints a dashboard over the controller's own metrics, ready to import
+
+use crate::metrics::MetricsConfig;
+use serde_json::{json, Value};
+
+/// One time-series panel; `targets` are (PromQL, legend) pairs
+fn panel(id: u32, title: &str, unit: &str, x: u32, y: u32, targets: &[(String, &str)]) -> Value {
+    json!({
+        "id": id,
+        "type": "timeseries",
+        "title": title,
+        "datasource": { "type": "prometheus", "uid": "${datasource}" },
+        "gridPos": { "h": 8, "w": 12, "x": x, "y": y },
+        "fieldConfig": { "defaults": { "unit": unit }, "overrides": [] },
+        "targets": targets
+            .iter()
+            .enumerate()
+            .map(|(i, (expr, legend))| json!({
+                "refId": ((b'A' + i as u8) as char).to_string(),
+                "expr": expr,
+                "legendFormat": legend,
+            }))
+            .collect::<Vec<_>>(),
+    })
+}
+
+/// Dashboard JSON for metrics named `<prefix>_*`
+pub fn dashboard(prefix: &str) -> Value {
+    let ns = "namespace=~\"$namespace\"";
+    let quantile = |q: &str| {
+        format!(
+            "histogram_quantile({}, sum by (le) (rate({}_reconcile_duration_seconds_bucket{{{}}}[5m])))",
+            q, prefix, ns
+        )
+    };
+
+    let panels = vec![
+        panel(
+            1,
+            "Reconcile rate",
+            "ops",
+            0,
+            0,
+            &[(
+                format!("sum by (result) (rate({}_reconcile_total{{{}}}[5m]))", prefix, ns),
+                "{{result}}",
+            )],
+        ),
+        panel(
+            2,
+            "Error rate",
+            "ops",
+            12,
+            0,
+            &[(
+                format!("sum by (error_type) (rate({}_errors_total{{{}}}[5m]))", prefix, ns),
+                "{{error_type}}",
+            )],
+        ),
+        panel(
+            3,
+            "Reconcile duration",
+            "s",
+            0,
+            8,
+            &[
+                (quantile("0.5"), "p50"),
+                (quantile("0.95"), "p95"),
+                (quantile("0.99"), "p99"),
+            ],
+        ),
+        panel(
+            4,
+            "Managed resources",
+            "short",
+            12,
+            8,
+            &[(
+                format!("sum by (resource_type) ({}_managed_resources_total{{{}}})", prefix, ns),
+                "{{resource_type}}",
+            )],
+        ),
+        panel(
+            5,
+            "Active reconciles and queue depth",
+            "short",
+            0,
+            16,
+            &[
+                (
+                    format!("sum({}_active_reconciles{{{}}})", prefix, ns),
+                    "active",
+                ),
+                (format!("{}_reconcile_queue_depth", prefix), "queued"),
+            ],
+        ),
+        panel(
+            6,
+            "Webhook duration (p95)",
+            "s",
+            12,
+            16,
+            &[(
+                format!(
+                    "histogram_quantile(0.95, sum by (le, webhook_type) (rate({}_webhook_duration_seconds_bucket[5m])))",
+                    prefix
+                ),
+                "{{webhook_type}}",
+            )],
+        ),
+    ];
+
+    json!({
+        "title": "MyApp Controller",
+        "uid": format!("{}-controller", prefix.replace([':', '_'], "-")),
+        "tags": ["myapp", "kubernetes"],
+        "timezone": "browser",
+        "schemaVersion": 39,
+        "refresh": "30s",
+        "time": { "from": "now-6h", "to": "now" },
+        "templating": {
+            "list": [
+                {
+                    "name": "datasource",
+                    "type": "datasource",
+                    "query": "prometheus",
+                },
+                {
+                    "name": "namespace",
+                    "type": "query",
+                    "datasource": { "type": "prometheus", "uid": "${datasource}" },
+                    "query": format!("label_values({}_reconcile_total, namespace)", prefix),
+                    "includeAll": true,
+                    "multi": true,
+                    "allValue": ".*",
+                    "refresh": 2,
+                },
+            ]
+        },
+        "panels": panels,
+    })
+}
+
+/// `generate-dashboard [--prefix <prefix>]`; the prefix defaults to `MYAPP_METRICS_PREFIX`
+pub fn run(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let mut prefix = MetricsConfig::from_env().prefix;
+
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--prefix" => {
+                prefix = iter.next().cloned().ok_or("--prefix needs a value")?;
+            }
+            other => return Err(format!("unknown generate-dashboard argument: {}", other).into()),
+        }
+    }
+
+    println!("{}", serde_json::to_string_pretty(&dashboard(&prefix))?);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dashboard_uses_prefix() {
+        let dashboard = dashboard("acme_myapp");
+        let panels = dashboard["panels"].as_array().unwrap();
+        assert_eq!(panels.len(), 6);
+        assert!(panels[0]["targets"][0]["expr"]
+            .as_str()
+            .unwrap()
+            .contains("acme_myapp_reconcile_total"));
+        assert_eq!(panels[2]["targets"][2]["refId"], "C");
+        assert_eq!(dashboard["uid"], "acme-myapp-controller");
+    }
+}
@@ -0,0 +1,284 @@
+// Deletion policy for MyApp Controller
+// Decides whether the finalizer deletes, orphans or partially retains child resources.
+// Children are found by listing every kind the controller creates and matching the owner
+// UID, so renamed or extra children are cleaned up too
+
+use crate::external_secrets::external_secret_resource;
+use crate::monitoring::{prometheus_rule_resource, service_monitor_resource};
+use crate::MyApp;
+use k8s_openapi::api::apps::v1::Deployment;
+use k8s_openapi::api::batch::v1::{CronJob, Job};
+use k8s_openapi::api::core::v1::{PersistentVolumeClaim, Secret, Service, ServiceAccount};
+use k8s_openapi::api::networking::v1::NetworkPolicy;
+use k8s_openapi::api::rbac::v1::{Role, RoleBinding};
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::OwnerReference;
+use kube::api::{Api, DeleteParams, DynamicObject, ListParams, Patch, PatchParams};
+use kube::{Client, Resource, ResourceExt};
+use schemars::JsonSchema;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::fmt::Debug;
+
+/// What happens to child resources when a MyApp is deleted
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, JsonSchema, Default)]
+pub enum DeletionPolicy {
+    /// Delete all child resources (default)
+    #[default]
+    Delete,
+    /// Leave all child resources running, detached from the MyApp
+    Orphan,
+    /// Delete workloads but keep PersistentVolumeClaims labelled app=<name>
+    Retain,
+}
+
+/// How deleting a child reaches what it owns in turn (ReplicaSets, Pods)
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, JsonSchema, Default)]
+pub enum DeletionPropagation {
+    /// Remove the finalizer once children are marked for deletion (default)
+    #[default]
+    Background,
+    /// Keep the MyApp until every child and its pods are gone, so a MyApp re-created
+    /// under the same name never meets half-deleted children
+    Foreground,
+}
+
+impl DeletionPropagation {
+    pub fn params(self) -> DeleteParams {
+        match self {
+            DeletionPropagation::Background => DeleteParams::background(),
+            DeletionPropagation::Foreground => DeleteParams::foreground(),
+        }
+    }
+}
+
+/// Annotation marking a MyApp that must not be deleted by accident
+pub const PROTECTED_ANNOTATION: &str = "myapps.example.com/protected";
+/// Annotation lifting the protection; its value must be the MyApp's name
+pub const CONFIRM_DELETE_ANNOTATION: &str = "myapps.example.com/confirm-delete";
+
+/// Refuse deletion of a protected MyApp unless the deletion was confirmed by name
+pub fn check_protection(myapp: &MyApp) -> Result<(), String> {
+    let annotations = myapp.annotations();
+    if annotations.get(PROTECTED_ANNOTATION).map(String::as_str) != Some("true") {
+        return Ok(());
+    }
+    let name = myapp.name_any();
+    if annotations.get(CONFIRM_DELETE_ANNOTATION) == Some(&name) {
+        return Ok(());
+    }
+    Err(format!(
+        "MyApp {} is protected by {}=true; annotate it with {}={} to delete it",
+        name, PROTECTED_ANNOTATION, CONFIRM_DELETE_ANNOTATION, name
+    ))
+}
+
+/// Label every child the controller creates carries
+pub const MANAGED_BY_SELECTOR: &str = "managed-by=myapp-controller";
+
+/// Owner references with the MyApp's removed
+pub fn without_owner(refs: &[OwnerReference], uid: &str) -> Vec<OwnerReference> {
+    refs.iter().filter(|r| r.uid != uid).cloned().collect()
+}
+
+/// Objects listed by `selector` that carry an owner reference to `uid`; kinds whose CRD is
+/// not installed have no children
+pub async fn owned<K>(api: &Api<K>, selector: &str, uid: &str) -> Result<Vec<K>, kube::Error>
+where
+    K: Resource + Clone + DeserializeOwned + Debug,
+{
+    match api.list(&ListParams::default().labels(selector)).await {
+        Ok(list) => Ok(list
+            .items
+            .into_iter()
+            .filter(|o| o.owner_references().iter().any(|r| r.uid == uid))
+            .collect()),
+        Err(kube::Error::Api(e)) if e.code == 404 => Ok(Vec::new()),
+        Err(e) => Err(e),
+    }
+}
+
+/// Drop the MyApp's owner reference so garbage collection leaves the object alone
+async fn release<K>(api: &Api<K>, obj: &K, uid: &str) -> Result<(), kube::Error>
+where
+    K: Resource + Clone + DeserializeOwned + Debug,
+{
+    let patch = serde_json::json!({
+        "metadata": { "ownerReferences": without_owner(obj.owner_references(), uid) }
+    });
+    api.patch(
+        &obj.name_any(),
+        &PatchParams::default(),
+        &Patch::Merge(&patch),
+    )
+    .await?;
+    tracing::info!("Released {} from its MyApp owner", obj.name_any());
+    Ok(())
+}
+
+async fn delete<K>(api: &Api<K>, obj: &K, params: &DeleteParams) -> Result<(), kube::Error>
+where
+    K: Resource + Clone + DeserializeOwned + Debug,
+{
+    // Already terminating; a second delete would not change how it propagates
+    if obj.meta().deletion_timestamp.is_some() {
+        return Ok(());
+    }
+    match api.delete(&obj.name_any(), params).await {
+        Ok(_) => {
+            tracing::info!("Deleted {}", obj.name_any());
+            Ok(())
+        }
+        Err(kube::Error::Api(e)) if e.code == 404 => Ok(()),
+        Err(e) => Err(e),
+    }
+}
+
+/// Delete (with `params`) or release every child of one kind; returns how many were found
+async fn each_child<K>(
+    api: Api<K>,
+    uid: &str,
+    remove: Option<&DeleteParams>,
+) -> Result<usize, kube::Error>
+where
+    K: Resource + Clone + DeserializeOwned + Debug,
+{
+    let children = owned(&api, MANAGED_BY_SELECTOR, uid).await?;
+    for obj in &children {
+        match remove {
+            Some(params) => delete(&api, obj, params).await?,
+            None => release(&api, obj, uid).await?,
+        }
+    }
+    Ok(children.len())
+}
+
+/// Delete or detach every child the MyApp owns, whatever its name
+async fn children(
+    myapp: &MyApp,
+    client: Client,
+    remove: Option<&DeleteParams>,
+) -> Result<usize, kube::Error> {
+    let ns = myapp.namespace().unwrap();
+    let uid = myapp.uid().unwrap_or_default();
+
+    let mut found = 0;
+    found += each_child(
+        Api::<Deployment>::namespaced(client.clone(), &ns),
+        &uid,
+        remove,
+    )
+    .await?;
+    found += each_child(
+        Api::<Service>::namespaced(client.clone(), &ns),
+        &uid,
+        remove,
+    )
+    .await?;
+    found += each_child(Api::<Job>::namespaced(client.clone(), &ns), &uid, remove).await?;
+    found += each_child(Api::<Secret>::namespaced(client.clone(), &ns), &uid, remove).await?;
+    found += each_child(
+        Api::<CronJob>::namespaced(client.clone(), &ns),
+        &uid,
+        remove,
+    )
+    .await?;
+    found += each_child(
+        Api::<NetworkPolicy>::namespaced(client.clone(), &ns),
+        &uid,
+        remove,
+    )
+    .await?;
+    found += each_child(
+        Api::<RoleBinding>::namespaced(client.clone(), &ns),
+        &uid,
+        remove,
+    )
+    .await?;
+    found += each_child(Api::<Role>::namespaced(client.clone(), &ns), &uid, remove).await?;
+    found += each_child(
+        Api::<ServiceAccount>::namespaced(client.clone(), &ns),
+        &uid,
+        remove,
+    )
+    .await?;
+    for resource in [
+        service_monitor_resource(),
+        prometheus_rule_resource(),
+        external_secret_resource(),
+    ] {
+        let api = Api::<DynamicObject>::namespaced_with(client.clone(), &ns, &resource);
+        found += each_child(api, &uid, remove).await?;
+    }
+    Ok(found)
+}
+
+/// Delete every child resource of the MyApp. Returns how many children must still go
+/// before the finalizer may be removed, which is always 0 for background propagation
+pub async fn delete_children(myapp: &MyApp, client: Client) -> Result<usize, kube::Error> {
+    let propagation = myapp.spec.deletion_propagation;
+    let found = children(myapp, client, Some(&propagation.params())).await?;
+    Ok(match propagation {
+        DeletionPropagation::Background => 0,
+        DeletionPropagation::Foreground => found,
+    })
+}
+
+/// Detach every child resource from the MyApp
+pub async fn orphan_children(myapp: &MyApp, client: Client) -> Result<(), kube::Error> {
+    children(myapp, client.clone(), None).await?;
+    retain_volumes(myapp, client).await
+}
+
+/// Detach the app's PersistentVolumeClaims so they outlive it
+pub async fn retain_volumes(myapp: &MyApp, client: Client) -> Result<(), kube::Error> {
+    let ns = myapp.namespace().unwrap();
+    let uid = myapp.uid().unwrap_or_default();
+    let pvcs: Api<PersistentVolumeClaim> = Api::namespaced(client, &ns);
+
+    let selector = format!("app={}", myapp.name_any());
+    for pvc in owned(&pvcs, &selector, &uid).await? {
+        release(&pvcs, &pvc, &uid).await?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn owner(uid: &str) -> OwnerReference {
+        OwnerReference {
+            api_version: "example.com/v1".to_string(),
+            kind: "MyApp".to_string(),
+            name: "web".to_string(),
+            uid: uid.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_without_owner() {
+        let refs = vec![owner("a"), owner("b")];
+        let remaining = without_owner(&refs, "a");
+        assert_eq!(remaining.len(), 1);
+        assert_eq!(remaining[0].uid, "b");
+    }
+
+    #[test]
+    fn test_check_protection() {
+        let mut myapp = MyApp::new("web", Default::default());
+        assert!(check_protection(&myapp).is_ok());
+
+        let annotations = myapp.annotations_mut();
+        annotations.insert(PROTECTED_ANNOTATION.to_string(), "true".to_string());
+        annotations.insert(CONFIRM_DELETE_ANNOTATION.to_string(), "api".to_string());
+        assert!(check_protection(&myapp)
+            .unwrap_err()
+            .contains("confirm-delete=web"));
+
+        myapp
+            .annotations_mut()
+            .insert(CONFIRM_DELETE_ANNOTATION.to_string(), "web".to_string());
+        assert!(check_protection(&myapp).is_ok());
+    }
+}
@@ -0,0 +1,138 @@
+// Developer mode for MyApp Controller
+// Runs the controller from a laptop against a shared cluster without touching anyone else's objects
+
+use crate::MyApp;
+use kube::ResourceExt;
+use std::sync::OnceLock;
+
+/// Label marking MyApps (and their children) that belong to a dev session
+pub const SESSION_LABEL: &str = "myapps.example.com/dev-session";
+
+/// Sandbox settings for a `--dev` run
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DevMode {
+    /// The only namespace watched
+    pub namespace: String,
+    /// Session name; only MyApps labelled with it are reconciled
+    pub session: String,
+}
+
+static SANDBOX: OnceLock<DevMode> = OnceLock::new();
+
+impl DevMode {
+    /// `--dev` with a single `--namespace`; the session defaults to `$USER`
+    pub fn from_args(args: &[String]) -> Result<Option<Self>, String> {
+        if !args.iter().any(|a| a == "--dev") {
+            return Ok(None);
+        }
+        let value = |name: &str| {
+            args.iter()
+                .position(|a| a == name)
+                .and_then(|i| args.get(i + 1))
+        };
+
+        let namespace = value("--namespace")
+            .or_else(|| value("-n"))
+            .filter(|ns| !ns.is_empty() && !ns.contains(','))
+            .ok_or("--dev needs exactly one namespace via --namespace")?;
+        let session = value("--dev-session")
+            .cloned()
+            .or_else(|| std::env::var("USER").ok())
+            .unwrap_or_else(|| "dev".to_string())
+            .to_lowercase();
+        if session.is_empty()
+            || !session
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-')
+        {
+            return Err(format!("invalid dev session name '{}'", session));
+        }
+
+        Ok(Some(Self {
+            namespace: namespace.clone(),
+            session,
+        }))
+    }
+
+    /// Prefix for every child resource created during the session
+    pub fn prefix(&self) -> String {
+        format!("dev-{}-", self.session)
+    }
+
+    /// Label selector limiting the watch to this session's MyApps
+    pub fn label_selector(&self, extra: Option<&str>) -> String {
+        let own = format!("{}={}", SESSION_LABEL, self.session);
+        match extra {
+            Some(extra) => format!("{},{}", own, extra),
+            None => own,
+        }
+    }
+
+    pub fn owns(&self, myapp: &MyApp) -> bool {
+        myapp.namespace().as_deref() == Some(self.namespace.as_str())
+            && myapp.labels().get(SESSION_LABEL) == Some(&self.session)
+    }
+}
+
+/// Turn on the sandbox for the rest of the process
+pub fn enable(mode: DevMode) {
+    let _ = SANDBOX.set(mode);
+}
+
+pub fn sandbox() -> Option<&'static DevMode> {
+    SANDBOX.get()
+}
+
+/// Child name prefix in effect ("" outside developer mode)
+pub fn prefix() -> String {
+    sandbox().map(DevMode::prefix).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_from_args() {
+        assert_eq!(DevMode::from_args(&args(&["ctl"])), Ok(None));
+        assert!(DevMode::from_args(&args(&["ctl", "--dev"])).is_err());
+        assert!(DevMode::from_args(&args(&["ctl", "--dev", "-n", "a,b"])).is_err());
+
+        let mode = DevMode::from_args(&args(&[
+            "ctl",
+            "--dev",
+            "--namespace",
+            "sandbox",
+            "--dev-session",
+            "Alex",
+        ]))
+        .unwrap()
+        .unwrap();
+        assert_eq!(mode.namespace, "sandbox");
+        assert_eq!(mode.prefix(), "dev-alex-");
+        assert_eq!(
+            mode.label_selector(Some("team=web")),
+            "myapps.example.com/dev-session=alex,team=web"
+        );
+    }
+
+    #[test]
+    fn test_owns() {
+        let mode = DevMode {
+            namespace: "sandbox".to_string(),
+            session: "alex".to_string(),
+        };
+        let mut myapp = MyApp::new("web", Default::default());
+        myapp.metadata.namespace = Some("sandbox".to_string());
+        assert!(!mode.owns(&myapp));
+
+        myapp
+            .labels_mut()
+            .insert(SESSION_LABEL.to_string(), "alex".to_string());
+        assert!(mode.owns(&myapp));
+    }
+}
@@ -0,0 +1,84 @@
+// Runtime diagnostics for MyApp Controller (`console` feature)
+// Summarizes tokio tasks and controller watch streams to track down stuck reconciles
+
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use warp::{Filter, Reply};
+
+lazy_static::lazy_static! {
+    /// Last time each controller stream produced a reconcile result
+    static ref WATCHERS: Mutex<BTreeMap<String, Instant>> = Mutex::new(BTreeMap::new());
+}
+
+/// Streams without output for this long are reported as long-polling
+const LONG_POLL: Duration = Duration::from_secs(600);
+
+/// Record progress of a named controller stream
+pub fn touch(watcher: &str) {
+    WATCHERS
+        .lock()
+        .unwrap()
+        .insert(watcher.to_string(), Instant::now());
+}
+
+fn watcher_report(watchers: &BTreeMap<String, Instant>, now: Instant) -> serde_json::Value {
+    watchers
+        .iter()
+        .map(|(name, last)| {
+            let idle = now.saturating_duration_since(*last);
+            (
+                name.clone(),
+                serde_json::json!({
+                    "idleSeconds": idle.as_secs(),
+                    "longPolling": idle >= LONG_POLL,
+                }),
+            )
+        })
+        .collect::<serde_json::Map<_, _>>()
+        .into()
+}
+
+fn tasks_report() -> serde_json::Value {
+    let metrics = tokio::runtime::Handle::current().metrics();
+    let workers: Vec<_> = (0..metrics.num_workers())
+        .map(|w| {
+            serde_json::json!({
+                "busySeconds": metrics.worker_total_busy_duration(w).as_secs_f64(),
+                "parkCount": metrics.worker_park_count(w),
+            })
+        })
+        .collect();
+
+    serde_json::json!({
+        "aliveTasks": metrics.num_alive_tasks(),
+        "globalQueueDepth": metrics.global_queue_depth(),
+        "workers": workers,
+        "watchers": watcher_report(&WATCHERS.lock().unwrap(), Instant::now()),
+    })
+}
+
+/// `GET /debug/tasks`
+pub fn tasks_handler() -> impl Filter<Extract = (impl Reply,), Error = warp::Rejection> + Clone {
+    warp::path!("debug" / "tasks")
+        .and(warp::get())
+        .map(|| warp::reply::json(&tasks_report()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_watcher_report() {
+        let now = Instant::now();
+        let watchers = BTreeMap::from([
+            ("shop".to_string(), now),
+            ("billing".to_string(), now - Duration::from_secs(900)),
+        ]);
+        let report = watcher_report(&watchers, now);
+        assert_eq!(report["shop"]["longPolling"], false);
+        assert_eq!(report["billing"]["longPolling"], true);
+        assert_eq!(report["billing"]["idleSeconds"], 900);
+    }
+}
@@ -0,0 +1,70 @@
+// Image digest pinning for MyApp Controller
+// With `MYAPP_PIN_DIGESTS=true` the mutating webhook resolves the tag in spec.image against
+// its registry and rewrites the image to `repo:tag@sha256:...`, so every rollout of the MyApp
+// runs the exact bytes admitted. The tag stays in the reference for readability and image
+// policies; the image as written is kept in an annotation.
+
+use crate::arch::RegistryClient;
+
+/// Image reference as submitted, before it was pinned
+pub const TAG_ANNOTATION: &str = "myapps.example.com/image-tag";
+
+pub fn enabled_from_env() -> bool {
+    std::env::var("MYAPP_PIN_DIGESTS").is_ok_and(|v| v == "true" || v == "1")
+}
+
+pub fn is_pinned(image: &str) -> bool {
+    image.contains('@')
+}
+
+/// `image` with `digest` appended; an untagged image is made explicit as `latest`
+pub fn pinned(image: &str, digest: &str) -> String {
+    let name = image.rsplit('/').next().unwrap_or_default();
+    if name.contains(':') {
+        format!("{}@{}", image, digest)
+    } else {
+        format!("{}:latest@{}", image, digest)
+    }
+}
+
+/// Resolves tags for the mutating webhook
+#[derive(Clone)]
+pub struct DigestResolver {
+    pub registry: RegistryClient,
+}
+
+impl DigestResolver {
+    /// Pinned form of `image`, or None if it is already pinned or the registry cannot tell us
+    pub async fn pin(&self, image: &str) -> Option<String> {
+        if is_pinned(image) {
+            return None;
+        }
+        match self.registry.resolve_digest(image).await {
+            Ok(digest) => Some(pinned(image, &digest)),
+            Err(e) => {
+                tracing::warn!("Could not resolve digest of {}: {}", image, e);
+                None
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pinned_keeps_tag() {
+        let digest = format!("sha256:{}", "a".repeat(64));
+        assert_eq!(
+            pinned("nginx:1.25", &digest),
+            format!("nginx:1.25@{}", digest)
+        );
+        assert_eq!(
+            pinned("localhost:5000/app", &digest),
+            format!("localhost:5000/app:latest@{}", digest)
+        );
+        assert!(is_pinned(&pinned("nginx:1.25", &digest)));
+        assert!(!is_pinned("localhost:5000/app:v1"));
+    }
+}
@@ -0,0 +1,190 @@
+// Pod DNS settings for MyApp Controller
+// Passes dnsPolicy, dnsConfig and hostAliases through to the generated PodSpec
+
+use k8s_openapi::api::core::v1::{HostAlias as K8sHostAlias, PodDNSConfig, PodDNSConfigOption};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::net::IpAddr;
+
+const DNS_POLICIES: [&str; 4] = ["ClusterFirst", "ClusterFirstWithHostNet", "Default", "None"];
+
+/// Kubernetes limits on resolv.conf entries
+const MAX_NAMESERVERS: usize = 3;
+const MAX_SEARCHES: usize = 32;
+
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct DnsConfig {
+    /// Extra nameservers (at most 3)
+    #[serde(default)]
+    pub nameservers: Vec<String>,
+
+    /// Extra search domains
+    #[serde(default)]
+    pub searches: Vec<String>,
+
+    /// resolv.conf options, e.g. ndots: "2"
+    #[serde(default)]
+    pub options: Vec<DnsOption>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct DnsOption {
+    pub name: String,
+
+    #[serde(default)]
+    pub value: Option<String>,
+}
+
+/// Static /etc/hosts entry
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct HostAlias {
+    /// IPv4 or IPv6 address
+    pub ip: String,
+
+    /// Hostnames resolving to the address
+    #[serde(default)]
+    pub hostnames: Vec<String>,
+}
+
+impl HostAlias {
+    pub fn to_k8s(&self) -> K8sHostAlias {
+        K8sHostAlias {
+            ip: self.ip.clone(),
+            hostnames: Some(self.hostnames.clone()),
+        }
+    }
+}
+
+pub fn validate_host_aliases(aliases: &[HostAlias]) -> Result<(), String> {
+    for alias in aliases {
+        if alias.ip.parse::<IpAddr>().is_err() {
+            return Err(format!(
+                "hostAliases: {} is not a valid IP address",
+                alias.ip
+            ));
+        }
+        if alias.hostnames.is_empty() || alias.hostnames.iter().any(|h| h.trim().is_empty()) {
+            return Err(format!(
+                "hostAliases: {} needs at least one non-empty hostname",
+                alias.ip
+            ));
+        }
+    }
+    Ok(())
+}
+
+pub fn validate(policy: Option<&str>, config: Option<&DnsConfig>) -> Result<(), String> {
+    if let Some(policy) = policy {
+        if !DNS_POLICIES.contains(&policy) {
+            return Err(format!(
+                "dnsPolicy must be one of {}",
+                DNS_POLICIES.join(", ")
+            ));
+        }
+        if policy == "None" && config.is_none_or(|c| c.nameservers.is_empty()) {
+            return Err("dnsPolicy None requires dnsConfig.nameservers".to_string());
+        }
+    }
+
+    let Some(config) = config else {
+        return Ok(());
+    };
+    if config.nameservers.len() > MAX_NAMESERVERS {
+        return Err(format!(
+            "dnsConfig.nameservers allows at most {} entries",
+            MAX_NAMESERVERS
+        ));
+    }
+    if config.searches.len() > MAX_SEARCHES {
+        return Err(format!(
+            "dnsConfig.searches allows at most {} entries",
+            MAX_SEARCHES
+        ));
+    }
+    for option in &config.options {
+        if option.name == "ndots"
+            && option
+                .value
+                .as_deref()
+                .is_none_or(|v| v.parse::<u8>().is_err())
+        {
+            return Err("dnsConfig option ndots needs a numeric value".to_string());
+        }
+    }
+    Ok(())
+}
+
+impl DnsConfig {
+    pub fn to_pod_dns_config(&self) -> PodDNSConfig {
+        let non_empty = |v: &Vec<String>| (!v.is_empty()).then(|| v.clone());
+        PodDNSConfig {
+            nameservers: non_empty(&self.nameservers),
+            searches: non_empty(&self.searches),
+            options: (!self.options.is_empty()).then(|| {
+                self.options
+                    .iter()
+                    .map(|o| PodDNSConfigOption {
+                        name: Some(o.name.clone()),
+                        value: o.value.clone(),
+                    })
+                    .collect()
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ndots(value: &str) -> DnsConfig {
+        DnsConfig {
+            options: vec![DnsOption {
+                name: "ndots".to_string(),
+                value: Some(value.to_string()),
+            }],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(validate(Some("ClusterFirst"), Some(&ndots("2"))).is_ok());
+        assert!(validate(None, Some(&ndots("two"))).is_err());
+        assert!(validate(Some("Cluster"), None).is_err());
+        assert!(validate(Some("None"), Some(&ndots("2"))).is_err());
+
+        let custom = DnsConfig {
+            nameservers: vec!["1.1.1.1".to_string()],
+            ..Default::default()
+        };
+        assert!(validate(Some("None"), Some(&custom)).is_ok());
+    }
+
+    #[test]
+    fn test_validate_host_aliases() {
+        let alias = |ip: &str| HostAlias {
+            ip: ip.to_string(),
+            hostnames: vec!["legacy.internal".to_string()],
+        };
+        assert!(validate_host_aliases(&[alias("10.0.0.5"), alias("fd00::1")]).is_ok());
+        assert!(validate_host_aliases(&[alias("10.0.0.500")]).is_err());
+        assert!(validate_host_aliases(&[HostAlias {
+            ip: "10.0.0.5".to_string(),
+            hostnames: vec![],
+        }])
+        .is_err());
+    }
+
+    #[test]
+    fn test_to_pod_dns_config() {
+        let config = ndots("1").to_pod_dns_config();
+        assert!(config.nameservers.is_none());
+        let options = config.options.unwrap();
+        assert_eq!(options[0].name.as_deref(), Some("ndots"));
+        assert_eq!(options[0].value.as_deref(), Some("1"));
+    }
+}
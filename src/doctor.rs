@@ -0,0 +1,460 @@
+// Installation health checks for MyApp Controller
+// `doctor` inspects the CRD, RBAC, webhooks and metrics of an installation and prints
+// a remediation for every problem it finds
+
+use crate::certs::{WebhookObjects, NOT_AFTER_ANNOTATION};
+use crate::migrate::storage_version;
+use crate::rbac::{self, RULES};
+use crate::{webhooks, MyApp};
+use chrono::{DateTime, Duration, Utc};
+use k8s_openapi::api::admissionregistration::v1::{
+    MutatingWebhookConfiguration, ValidatingWebhookConfiguration,
+};
+use k8s_openapi::api::authorization::v1::{
+    ResourceAttributes, SelfSubjectAccessReview, SelfSubjectAccessReviewSpec,
+};
+use k8s_openapi::api::core::v1::{Pod, Secret};
+use k8s_openapi::apiextensions_apiserver::pkg::apis::apiextensions::v1::CustomResourceDefinition;
+use k8s_openapi::ByteString;
+use kube::api::{Api, ListParams, PostParams};
+use kube::{Client, CustomResourceExt, Resource, ResourceExt};
+use std::fmt;
+
+/// Certificates expiring sooner than this are reported as a warning
+const EXPIRY_WARNING_DAYS: i64 = 14;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    Pass,
+    Warn,
+    Fail,
+}
+
+/// Result of one check, with how to fix it when it did not pass
+#[derive(Debug, Clone, PartialEq)]
+pub struct Check {
+    pub name: &'static str,
+    pub outcome: Outcome,
+    pub detail: String,
+    pub remedy: Option<String>,
+}
+
+impl Check {
+    fn pass(name: &'static str, detail: impl Into<String>) -> Self {
+        Self {
+            name,
+            outcome: Outcome::Pass,
+            detail: detail.into(),
+            remedy: None,
+        }
+    }
+
+    fn warn(name: &'static str, detail: impl Into<String>, remedy: impl Into<String>) -> Self {
+        Self {
+            name,
+            outcome: Outcome::Warn,
+            detail: detail.into(),
+            remedy: Some(remedy.into()),
+        }
+    }
+
+    fn fail(name: &'static str, detail: impl Into<String>, remedy: impl Into<String>) -> Self {
+        Self {
+            name,
+            outcome: Outcome::Fail,
+            detail: detail.into(),
+            remedy: Some(remedy.into()),
+        }
+    }
+}
+
+impl fmt::Display for Check {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let tag = match self.outcome {
+            Outcome::Pass => "ok",
+            Outcome::Warn => "WARN",
+            Outcome::Fail => "FAIL",
+        };
+        write!(f, "[{}] {}: {}", tag, self.name, self.detail)?;
+        if let Some(remedy) = &self.remedy {
+            write!(f, "\n       fix: {}", remedy)?;
+        }
+        Ok(())
+    }
+}
+
+/// The CRD exists, is established and serves the version this binary was built for
+pub fn check_crd(crd: Option<&CustomResourceDefinition>) -> Check {
+    const NAME: &str = "CRD";
+    let expected = MyApp::crd();
+    let crd_name = expected.name_any();
+    let version = MyApp::version(&()).to_string();
+    let reinstall = "myapp-controller generate-crd -o - | kubectl apply -f -";
+
+    let Some(crd) = crd else {
+        return Check::fail(NAME, format!("{} is not installed", crd_name), reinstall);
+    };
+
+    let established = crd
+        .status
+        .as_ref()
+        .and_then(|s| s.conditions.as_ref())
+        .into_iter()
+        .flatten()
+        .any(|c| c.type_ == "Established" && c.status == "True");
+    if !established {
+        return Check::fail(
+            NAME,
+            format!("{} is not Established", crd_name),
+            format!("kubectl describe crd {} and check its conditions", crd_name),
+        );
+    }
+
+    let served = crd
+        .spec
+        .versions
+        .iter()
+        .any(|v| v.name == version && v.served);
+    if !served {
+        return Check::fail(
+            NAME,
+            format!("{} does not serve {}", crd_name, version),
+            format!("the installed CRD is from another release; {}", reinstall),
+        );
+    }
+
+    let storage = storage_version(crd).unwrap_or_default();
+    Check::pass(
+        NAME,
+        format!("{} serves {} (storage {})", crd_name, version, storage),
+    )
+}
+
+/// Every verb in rbac::RULES, checked with a SelfSubjectAccessReview
+async fn check_rbac(client: Client, namespace: &str) -> Result<Check, kube::Error> {
+    const NAME: &str = "RBAC";
+    let api: Api<SelfSubjectAccessReview> = Api::all(client);
+
+    let mut denied = Vec::new();
+    for rule in RULES {
+        for resource in rule.resources {
+            let (resource, subresource) = match resource.split_once('/') {
+                Some((resource, sub)) => (resource, Some(sub.to_string())),
+                None => (*resource, None),
+            };
+            for verb in rule.verbs {
+                let review = SelfSubjectAccessReview {
+                    spec: SelfSubjectAccessReviewSpec {
+                        resource_attributes: Some(ResourceAttributes {
+                            group: Some(rule.api_group.to_string()),
+                            resource: Some(resource.to_string()),
+                            subresource: subresource.clone(),
+                            verb: Some(verb.to_string()),
+                            ..Default::default()
+                        }),
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                };
+                let result = api.create(&PostParams::default(), &review).await?;
+                if !result.status.is_some_and(|s| s.allowed) {
+                    let group = if rule.api_group.is_empty() {
+                        "core"
+                    } else {
+                        rule.api_group
+                    };
+                    let sub = subresource
+                        .as_deref()
+                        .map(|s| format!("/{}", s))
+                        .unwrap_or_default();
+                    denied.push(format!("{} {}/{}{}", verb, group, resource, sub));
+                }
+            }
+        }
+    }
+
+    if denied.is_empty() {
+        return Ok(Check::pass(
+            NAME,
+            "all permissions the controller uses are granted",
+        ));
+    }
+    Ok(Check::fail(
+        NAME,
+        format!("denied: {}", denied.join(", ")),
+        format!(
+            "myapp-controller generate-rbac --namespace {} | kubectl apply -f -",
+            namespace
+        ),
+    ))
+}
+
+/// The webhook certificate has not expired and is the CA the API server trusts
+pub fn check_cert(
+    secret: Option<&Secret>,
+    ca_bundles: &[Option<ByteString>],
+    now: DateTime<Utc>,
+) -> Check {
+    const NAME: &str = "webhook certificate";
+    let restart = "kubectl rollout restart deployment/myapp-webhook to reissue it";
+
+    let Some(secret) = secret else {
+        return Check::fail(NAME, "certificate Secret not found", restart);
+    };
+    let ca = secret.data.as_ref().and_then(|d| d.get("ca.crt"));
+    if ca_bundles.iter().any(|bundle| bundle.as_ref() != ca) {
+        return Check::fail(
+            NAME,
+            "caBundle of the webhook configurations does not match the Secret's ca.crt",
+            "restart the webhook with --cert-source self-signed, or check cert-manager's cainjector",
+        );
+    }
+
+    // cert-manager renews its own certificates and does not set the annotation
+    let not_after = secret
+        .annotations()
+        .get(NOT_AFTER_ANNOTATION)
+        .and_then(|v| DateTime::parse_from_rfc3339(v).ok())
+        .map(DateTime::<Utc>::from);
+    match not_after {
+        Some(not_after) if not_after <= now => {
+            Check::fail(NAME, format!("expired at {}", not_after), restart)
+        }
+        Some(not_after) if not_after - now < Duration::days(EXPIRY_WARNING_DAYS) => Check::warn(
+            NAME,
+            format!("expires at {}", not_after),
+            "the webhook renews it while running; make sure it is not crash-looping",
+        ),
+        Some(not_after) => Check::pass(NAME, format!("valid until {}", not_after)),
+        None => Check::pass(NAME, "CA bundle matches the Secret"),
+    }
+}
+
+/// The status code of a failed proxy request, if it came from the backend
+fn backend_status(result: &Result<String, kube::Error>) -> Option<u16> {
+    match result {
+        Ok(_) => Some(200),
+        // 502-504 come from the API server when it cannot reach the pod
+        Err(kube::Error::Api(e)) if !(502..=504).contains(&e.code) => Some(e.code),
+        _ => None,
+    }
+}
+
+async fn proxy_get(client: &Client, path: String) -> Result<String, kube::Error> {
+    let request = http::Request::get(path)
+        .body(Vec::new())
+        .map_err(kube::Error::HttpError)?;
+    client.request_text(request).await
+}
+
+async fn check_webhooks(
+    client: Client,
+    objects: &WebhookObjects,
+) -> Result<Vec<Check>, kube::Error> {
+    const NAME: &str = "webhooks";
+    let validating = Api::<ValidatingWebhookConfiguration>::all(client.clone())
+        .get_opt(&objects.validating_webhook)
+        .await?;
+    let mutating = Api::<MutatingWebhookConfiguration>::all(client.clone())
+        .get_opt(&objects.mutating_webhook)
+        .await?;
+    if validating.is_none() && mutating.is_none() {
+        return Ok(vec![Check::warn(
+            NAME,
+            "no webhook configurations installed; manifests are only checked by the CRD schema",
+            "myapp-controller generate-webhooks | kubectl apply -f -",
+        )]);
+    }
+
+    let mut checks = Vec::new();
+    let mut ca_bundles: Vec<Option<ByteString>> = Vec::new();
+    ca_bundles.extend(
+        validating
+            .iter()
+            .flat_map(|c| c.webhooks.iter().flatten())
+            .map(|w| w.client_config.ca_bundle.clone()),
+    );
+    ca_bundles.extend(
+        mutating
+            .iter()
+            .flat_map(|c| c.webhooks.iter().flatten())
+            .map(|w| w.client_config.ca_bundle.clone()),
+    );
+
+    // Reached through the API server's service proxy, the same route admission takes
+    for def in [webhooks::VALIDATE, webhooks::MUTATE] {
+        let path = format!(
+            "/api/v1/namespaces/{}/services/https:{}:443/proxy/{}",
+            objects.namespace, objects.service, def.path
+        );
+        let check = match backend_status(&proxy_get(&client, path).await) {
+            Some(_) => Check::pass(NAME, format!("{} is reachable", def.name)),
+            None => Check::fail(
+                NAME,
+                format!(
+                    "{} is unreachable through service {}/{}",
+                    def.name, objects.namespace, objects.service
+                ),
+                format!(
+                    "kubectl -n {} get endpoints {} and check the webhook pods are ready",
+                    objects.namespace, objects.service
+                ),
+            ),
+        };
+        checks.push(check);
+    }
+
+    let secret = Api::<Secret>::namespaced(client, &objects.namespace)
+        .get_opt(&objects.secret)
+        .await?;
+    checks.push(check_cert(secret.as_ref(), &ca_bundles, Utc::now()));
+    Ok(checks)
+}
+
+/// A running controller pod answers on its metrics port
+async fn check_metrics(client: Client, namespace: &str) -> Result<Check, kube::Error> {
+    const NAME: &str = "metrics";
+    let selector = format!("app.kubernetes.io/name={}", rbac::NAME);
+    let pods = Api::<Pod>::namespaced(client.clone(), namespace)
+        .list(&ListParams::default().labels(&selector))
+        .await?;
+    let running = pods
+        .items
+        .iter()
+        .find(|p| p.status.as_ref().and_then(|s| s.phase.as_deref()) == Some("Running"));
+    let Some(pod) = running else {
+        return Ok(Check::fail(
+            NAME,
+            format!("no running controller pod ({}) in {}", selector, namespace),
+            format!(
+                "kubectl -n {} get pods -l {} and check why it is not running",
+                namespace, selector
+            ),
+        ));
+    };
+
+    let port = pod
+        .spec
+        .iter()
+        .flat_map(|s| &s.containers)
+        .flat_map(|c| c.ports.iter().flatten())
+        .find(|p| p.name.as_deref() == Some("metrics"))
+        .map(|p| p.container_port)
+        .unwrap_or(8080);
+    let path = format!(
+        "/api/v1/namespaces/{}/pods/{}:{}/proxy/metrics",
+        namespace,
+        pod.name_any(),
+        port
+    );
+    let result = proxy_get(&client, path).await;
+    Ok(match (backend_status(&result), result) {
+        (_, Ok(body)) if body.contains("myapp_") => Check::pass(
+            NAME,
+            format!("served by {} on port {}", pod.name_any(), port),
+        ),
+        (Some(401), _) => Check::pass(
+            NAME,
+            format!(
+                "served by {} on port {} (auth required)",
+                pod.name_any(),
+                port
+            ),
+        ),
+        _ => Check::fail(
+            NAME,
+            format!(
+                "{} does not serve /metrics on port {}",
+                pod.name_any(),
+                port
+            ),
+            "check MYAPP_METRICS_ADDR matches the pod's metrics port",
+        ),
+    })
+}
+
+/// `doctor [--namespace <controller namespace>]`
+///
+/// RBAC is checked for the caller; run it as the controller's ServiceAccount
+/// (e.g. `kubectl myapp --user`, or from the controller pod) to check its grants.
+pub async fn run(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let mut objects = WebhookObjects::from_env();
+
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "-n" | "--namespace" => {
+                objects.namespace = iter.next().cloned().ok_or("--namespace needs a value")?;
+            }
+            other => return Err(format!("unknown doctor argument: {}", other).into()),
+        }
+    }
+
+    let client = crate::kubectl::client().await?;
+    let crd = Api::<CustomResourceDefinition>::all(client.clone())
+        .get_opt(&MyApp::crd().name_any())
+        .await?;
+
+    let mut checks = vec![check_crd(crd.as_ref())];
+    checks.push(check_rbac(client.clone(), &objects.namespace).await?);
+    checks.extend(check_webhooks(client.clone(), &objects).await?);
+    checks.push(check_metrics(client, &objects.namespace).await?);
+
+    for check in &checks {
+        println!("{}", check);
+    }
+    let failed = checks.iter().filter(|c| c.outcome == Outcome::Fail).count();
+    if failed > 0 {
+        return Err(format!("{} check(s) failed", failed).into());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use k8s_openapi::apiextensions_apiserver::pkg::apis::apiextensions::v1::{
+        CustomResourceDefinitionCondition, CustomResourceDefinitionStatus,
+    };
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn test_check_crd() {
+        assert_eq!(check_crd(None).outcome, Outcome::Fail);
+
+        let mut crd = MyApp::crd();
+        assert_eq!(check_crd(Some(&crd)).outcome, Outcome::Fail);
+
+        crd.status = Some(CustomResourceDefinitionStatus {
+            conditions: Some(vec![CustomResourceDefinitionCondition {
+                type_: "Established".to_string(),
+                status: "True".to_string(),
+                ..Default::default()
+            }]),
+            ..Default::default()
+        });
+        assert_eq!(check_crd(Some(&crd)).outcome, Outcome::Pass);
+    }
+
+    #[test]
+    fn test_check_cert() {
+        let now = Utc::now();
+        let ca = ByteString(b"ca".to_vec());
+        let mut secret = Secret {
+            data: Some(BTreeMap::from([("ca.crt".to_string(), ca.clone())])),
+            ..Default::default()
+        };
+        secret.metadata.annotations = Some(BTreeMap::from([(
+            NOT_AFTER_ANNOTATION.to_string(),
+            (now + Duration::days(3)).to_rfc3339(),
+        )]));
+
+        let check = check_cert(Some(&secret), &[Some(ca.clone())], now);
+        assert_eq!(check.outcome, Outcome::Warn);
+
+        let stale = ByteString(b"old".to_vec());
+        let check = check_cert(Some(&secret), &[Some(ca), Some(stale)], now);
+        assert_eq!(check.outcome, Outcome::Fail);
+        assert!(check.to_string().contains("fix:"));
+    }
+}
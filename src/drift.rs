@@ -0,0 +1,289 @@
+// Drift detection for MyApp Controller
+// Reconcile creates Deployments but leaves existing ones alone, so out-of-band edits persist.
+// A periodic sweep compares each Deployment against what the MyApp renders, records the
+// drifted fields in a DriftDetected condition and, in correct mode, re-applies the desired spec
+
+use crate::metrics::MetricsCollector;
+use crate::quantity;
+use crate::shard::Shard;
+use crate::shared_store::SharedStores;
+use crate::strategy::StrategyType;
+use crate::workload::WorkloadType;
+use crate::{app_labels, apply_deployment, build_deployment, child_name, status, Condition, MyApp};
+use k8s_openapi::api::apps::v1::Deployment;
+use kube::api::Api;
+use kube::{Client, ResourceExt};
+use serde_json::Value;
+use std::time::Duration;
+
+pub const DRIFT_CONDITION: &str = "DriftDetected";
+
+/// Fields listed in the condition message before the rest are summarized
+const MAX_LISTED: usize = 5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DriftMode {
+    /// Record drift in status and metrics only
+    Report,
+    /// Also re-apply the desired Deployment
+    Correct,
+}
+
+/// `MYAPP_DRIFT_INTERVAL_SECS` (default 300, 0 disables) and `MYAPP_DRIFT_MODE`
+pub fn from_env() -> Result<Option<(Duration, DriftMode)>, String> {
+    let secs = std::env::var("MYAPP_DRIFT_INTERVAL_SECS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(300);
+    let mode = match std::env::var("MYAPP_DRIFT_MODE").as_deref() {
+        Err(_) | Ok("report") => DriftMode::Report,
+        Ok("correct") => DriftMode::Correct,
+        Ok(other) => return Err(format!("MYAPP_DRIFT_MODE: unknown mode '{}'", other)),
+    };
+    Ok((secs > 0).then(|| (Duration::from_secs(secs), mode)))
+}
+
+/// Fields set in `desired` whose live value differs; fields only the server sets are ignored
+pub fn drifted_fields(desired: &Value, live: &Value) -> Vec<String> {
+    let mut out = Vec::new();
+    for root in ["metadata.labels", "spec"] {
+        let pick = |v: &Value| {
+            root.split('.').fold(v.clone(), |v, key| {
+                v.get(key).cloned().unwrap_or(Value::Null)
+            })
+        };
+        compare(&pick(desired), &pick(live), root, &mut out);
+    }
+    out
+}
+
+/// Resource quantities the server may rewrite in canonical form ("0.5" becomes "500m")
+fn same_quantity(path: &str, want: &str, have: &str) -> bool {
+    let parse = |v: &str| quantity::parse_cpu(v).or_else(|| quantity::parse_memory(v));
+    let is_quantity = path.contains(".resources.limits.") || path.contains(".resources.requests.");
+    match (is_quantity, parse(want), parse(have)) {
+        (true, Some(a), Some(b)) => (a - b).abs() <= f64::EPSILON * a.abs().max(b.abs()),
+        _ => false,
+    }
+}
+
+fn compare(desired: &Value, live: &Value, path: &str, out: &mut Vec<String>) {
+    match (desired, live) {
+        (Value::Null, _) => {}
+        // The server drops empty lists and maps
+        (Value::Array(want), Value::Null) if want.is_empty() => {}
+        (Value::Object(want), Value::Null) if want.is_empty() => {}
+        (Value::String(want), Value::String(have)) if same_quantity(path, want, have) => {}
+        (Value::Object(want), Value::Object(have)) => {
+            for (key, value) in want {
+                let child = format!("{}.{}", path, key);
+                compare(value, have.get(key).unwrap_or(&Value::Null), &child, out);
+            }
+        }
+        (Value::Array(want), Value::Array(have)) if want.len() == have.len() => {
+            for (i, (x, y)) in want.iter().zip(have).enumerate() {
+                compare(x, y, &format!("{}[{}]", path, i), out);
+            }
+        }
+        (want, have) if want != have => out.push(path.to_string()),
+        _ => {}
+    }
+}
+
+fn message(fields: &[String]) -> String {
+    let listed = fields[..fields.len().min(MAX_LISTED)].join(", ");
+    match fields.len().saturating_sub(MAX_LISTED) {
+        0 => listed,
+        more => format!("{} and {} more", listed, more),
+    }
+}
+
+/// Condition for the sweep's findings; None when there is nothing to record
+fn condition(current: Option<&Condition>, fields: &[String], mode: DriftMode) -> Option<Condition> {
+    if fields.is_empty() {
+        // Clear a previous finding, but do not write status for apps that never drifted
+        return current
+            .filter(|c| c.status == "True")
+            .map(|_| Condition::new(DRIFT_CONDITION, false, "NoDrift", "Children match the spec"));
+    }
+    Some(match mode {
+        DriftMode::Report => Condition::new(
+            DRIFT_CONDITION,
+            true,
+            "OutOfBandChange",
+            &format!("Changed outside the controller: {}", message(fields)),
+        ),
+        DriftMode::Correct => Condition::new(
+            DRIFT_CONDITION,
+            false,
+            "DriftCorrected",
+            &format!("Reverted out-of-band changes to {}", message(fields)),
+        ),
+    })
+}
+
+/// Deployment children the sweep can judge; blue-green and canary manage their own
+fn checked(myapp: &MyApp, shard: Shard) -> bool {
+    myapp.spec.workload_type == WorkloadType::Deployment
+        && myapp.spec.strategy.as_ref().is_none_or(|s| {
+            matches!(
+                s.type_,
+                StrategyType::RollingUpdate | StrategyType::Recreate
+            )
+        })
+        && !myapp.is_paused()
+        && !crate::plan::is_plan_only(myapp)
+        && myapp.metadata.deletion_timestamp.is_none()
+        && shard.owns_myapp(myapp)
+}
+
+async fn check(
+    myapp: &MyApp,
+    live: &Deployment,
+    client: Client,
+    mode: DriftMode,
+    metrics: &MetricsCollector,
+) -> Result<bool, kube::Error> {
+    let ns = myapp.namespace().unwrap_or_default();
+    let desired = build_deployment(myapp, &child_name(myapp, "deployment"), app_labels(myapp));
+    let fields = drifted_fields(
+        &serde_json::to_value(&desired).map_err(kube::Error::SerdeError)?,
+        &serde_json::to_value(live).map_err(kube::Error::SerdeError)?,
+    );
+    if !fields.is_empty() {
+        tracing::info!(
+            "Drift in Deployment {}/{}: {}",
+            ns,
+            live.name_any(),
+            fields.join(", ")
+        );
+        metrics.record_drift(&ns);
+        if mode == DriftMode::Correct {
+            apply_deployment(&Api::namespaced(client.clone(), &ns), &desired).await?;
+        }
+    }
+
+    let current = myapp
+        .status
+        .as_ref()
+        .and_then(|s| s.conditions.iter().find(|c| c.r#type == DRIFT_CONDITION));
+    if let Some(condition) = condition(current, &fields, mode) {
+        let mut new_status = myapp.status.clone().unwrap_or_default();
+        new_status
+            .conditions
+            .retain(|c| c.r#type != DRIFT_CONDITION);
+        new_status.conditions.push(condition);
+        status::patch_status(&Api::namespaced(client, &ns), myapp, &new_status, metrics).await?;
+    }
+    Ok(!fields.is_empty())
+}
+
+/// Sweep every cached MyApp each `interval`
+pub async fn run_sweep(
+    client: Client,
+    stores: SharedStores,
+    shard: Shard,
+    metrics: MetricsCollector,
+    interval: Duration,
+    mode: DriftMode,
+) {
+    let mut ticker = tokio::time::interval(interval);
+    ticker.tick().await;
+    loop {
+        ticker.tick().await;
+        let Some(myapps) = stores.myapps(None) else {
+            continue;
+        };
+        let mut drifted = 0;
+        for myapp in myapps.iter().filter(|m| checked(m, shard)) {
+            let ns = myapp.namespace().unwrap_or_default();
+            let name = child_name(myapp, "deployment");
+            let Some(Some(live)) = stores.get::<Deployment>(&ns, &name) else {
+                continue;
+            };
+            match check(myapp, &live, client.clone(), mode, &metrics).await {
+                Ok(true) => drifted += 1,
+                Ok(false) => {}
+                Err(e) => tracing::warn!(
+                    "Drift check of MyApp {}/{} failed: {}",
+                    ns,
+                    myapp.name_any(),
+                    e
+                ),
+            }
+        }
+        metrics.set_drifted(drifted);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_drift_ignores_server_defaults() {
+        let desired = json!({
+            "metadata": { "labels": { "app": "web" } },
+            "spec": { "replicas": 3, "template": { "spec": { "containers": [
+                { "name": "app", "image": "nginx:1.25" }
+            ] } } }
+        });
+        let live = json!({
+            "metadata": { "labels": { "app": "web" }, "resourceVersion": "7" },
+            "spec": { "replicas": 5, "revisionHistoryLimit": 10, "template": { "spec": {
+                "containers": [
+                    { "name": "app", "image": "nginx:1.25", "imagePullPolicy": "IfNotPresent" }
+                ],
+                "dnsPolicy": "ClusterFirst"
+            } } }
+        });
+        assert_eq!(drifted_fields(&desired, &live), vec!["spec.replicas"]);
+    }
+
+    #[test]
+    fn test_drift_ignores_server_normalization() {
+        let desired = json!({
+            "spec": { "template": { "spec": { "containers": [{
+                "env": [],
+                "resources": { "limits": { "cpu": "0.5", "memory": "1Gi" }, "requests": {} }
+            }] } } }
+        });
+        let live = json!({
+            "spec": { "template": { "spec": { "containers": [{
+                "resources": { "limits": { "cpu": "500m", "memory": "1Gi" } }
+            }] } } }
+        });
+        assert!(drifted_fields(&desired, &live).is_empty());
+
+        let live = json!({
+            "spec": { "template": { "spec": { "containers": [{
+                "env": [{ "name": "DEBUG", "value": "1" }],
+                "resources": { "limits": { "cpu": "250m", "memory": "1Gi" } }
+            }] } } }
+        });
+        assert_eq!(
+            drifted_fields(&desired, &live),
+            vec![
+                "spec.template.spec.containers[0].env",
+                "spec.template.spec.containers[0].resources.limits.cpu"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_condition_only_written_when_meaningful() {
+        assert!(condition(None, &[], DriftMode::Report).is_none());
+
+        let fields: Vec<String> = (0..7).map(|i| format!("spec.f{}", i)).collect();
+        let found = condition(None, &fields, DriftMode::Report).unwrap();
+        assert_eq!(found.status, "True");
+        assert!(found.message.ends_with("spec.f4 and 2 more"));
+
+        let cleared = condition(Some(&found), &[], DriftMode::Report).unwrap();
+        assert_eq!(
+            (cleared.status.as_str(), cleared.reason.as_str()),
+            ("False", "NoDrift")
+        );
+    }
+}
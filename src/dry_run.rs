@@ -0,0 +1,84 @@
+// Server-side dry-run of MyApp children
+// `kubectl apply --dry-run=server` never reaches the controller, so the validating webhook
+// dry-runs the children reconcile would write and reports what the API server would reject
+// (quota, pod security, other admission policies) before the MyApp is accepted
+
+use crate::workload::{self, WorkloadType};
+use crate::{app_labels, build_deployment, build_pod_spec, build_service, child_name, MyApp};
+use k8s_openapi::api::apps::v1::Deployment;
+use k8s_openapi::api::batch::v1::{CronJob, Job};
+use k8s_openapi::api::core::v1::Service;
+use kube::api::{Api, Patch, PatchParams};
+use kube::{Client, Resource, ResourceExt};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::fmt::Debug;
+
+/// Whether dry-run admission requests also dry-run the children
+/// (`MYAPP_DRY_RUN_CHILDREN`, default true)
+pub fn enabled_from_env() -> bool {
+    std::env::var("MYAPP_DRY_RUN_CHILDREN").map_or(true, |v| v != "false")
+}
+
+/// Dry-run the children of `myapp` with server-side apply; the error names the first
+/// child the API server would reject
+pub async fn check_children(myapp: &MyApp, client: Client) -> Result<(), String> {
+    let ns = myapp.namespace().unwrap_or_default();
+    match myapp.spec.workload_type {
+        WorkloadType::Deployment => {
+            let name = child_name(myapp, "deployment");
+            let deployment = build_deployment(myapp, &name, app_labels(myapp));
+            apply(
+                Api::<Deployment>::namespaced(client.clone(), &ns),
+                deployment,
+            )
+            .await?;
+            apply(
+                Api::<Service>::namespaced(client, &ns),
+                build_service(myapp),
+            )
+            .await
+        }
+        WorkloadType::Job => {
+            let job = workload::build_job(myapp, build_pod_spec(myapp));
+            apply(Api::<Job>::namespaced(client, &ns), job).await
+        }
+        WorkloadType::CronJob => {
+            let cronjob = workload::build_cronjob(myapp, build_pod_spec(myapp));
+            apply(Api::<CronJob>::namespaced(client, &ns), cronjob).await
+        }
+    }
+}
+
+async fn apply<K>(api: Api<K>, mut object: K) -> Result<(), String>
+where
+    K: Resource<DynamicType = ()> + Clone + Debug + Serialize + DeserializeOwned,
+{
+    // The MyApp does not exist yet on create, so there is no UID to own the child by
+    object.meta_mut().owner_references = None;
+    let name = object.name_any();
+    let params = PatchParams::apply("myapp-controller").force().dry_run();
+    match api.patch(&name, &params, &Patch::Apply(&object)).await {
+        Ok(_) => Ok(()),
+        // Missing webhook permissions or an unreachable API say nothing about the MyApp
+        Err(kube::Error::Api(e)) if e.code == 403 || e.code >= 500 => {
+            tracing::warn!(
+                "Skipping dry-run of {} {}: {}",
+                K::kind(&()),
+                name,
+                e.message
+            );
+            Ok(())
+        }
+        Err(kube::Error::Api(e)) => Err(format!(
+            "{} {} would be rejected: {}",
+            K::kind(&()),
+            name,
+            e.message
+        )),
+        Err(e) => {
+            tracing::warn!("Skipping dry-run of {} {}: {}", K::kind(&()), name, e);
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,231 @@
+// Container environment for MyApp Controller
+// Builds the app container's env from spec.envVars (literal values) and spec.podEnv, which
+// injects pod metadata through the downward API so apps can learn their own name, IP or node.
+// spec.envFrom pulls whole ConfigMaps and Secrets in as envFrom sources
+
+use crate::{external_secrets, vault, MyApp, MyAppSpec};
+use k8s_openapi::api::core::v1::{
+    ConfigMapEnvSource, EnvFromSource, EnvVar, EnvVarSource, ObjectFieldSelector, SecretEnvSource,
+};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// Pod metadata the downward API exposes as an environment variable
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, JsonSchema)]
+pub enum PodField {
+    PodName,
+    PodNamespace,
+    PodUid,
+    PodIp,
+    HostIp,
+    NodeName,
+    ServiceAccountName,
+}
+
+impl PodField {
+    pub fn field_path(self) -> &'static str {
+        match self {
+            PodField::PodName => "metadata.name",
+            PodField::PodNamespace => "metadata.namespace",
+            PodField::PodUid => "metadata.uid",
+            PodField::PodIp => "status.podIP",
+            PodField::HostIp => "status.hostIP",
+            PodField::NodeName => "spec.nodeName",
+            PodField::ServiceAccountName => "spec.serviceAccountName",
+        }
+    }
+}
+
+/// Existing objects whose keys all become environment variables
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct EnvFromConfig {
+    /// ConfigMaps in the MyApp's namespace; on duplicate keys the later one wins, and
+    /// envVars/podEnv override them all
+    #[serde(default)]
+    pub config_map_refs: Vec<String>,
+
+    /// Secrets in the MyApp's namespace, e.g. `<myapp>-<name>` from generatedSecrets;
+    /// their keys override those of the ConfigMaps
+    #[serde(default)]
+    pub secret_refs: Vec<String>,
+}
+
+impl EnvFromConfig {
+    fn validate(&self) -> Result<(), String> {
+        for (field, refs) in [
+            ("configMapRefs", &self.config_map_refs),
+            ("secretRefs", &self.secret_refs),
+        ] {
+            for (i, name) in refs.iter().enumerate() {
+                if name.trim().is_empty() {
+                    return Err(format!("envFrom.{} entries must not be empty", field));
+                }
+                if refs[..i].contains(name) {
+                    return Err(format!("envFrom.{} lists {} twice", field, name));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn sources(&self) -> Vec<EnvFromSource> {
+        let config_maps = self.config_map_refs.iter().map(|name| EnvFromSource {
+            config_map_ref: Some(ConfigMapEnvSource {
+                name: name.clone(),
+                ..Default::default()
+            }),
+            ..Default::default()
+        });
+        let secrets = self.secret_refs.iter().cloned().map(secret_source);
+        config_maps.chain(secrets).collect()
+    }
+}
+
+fn secret_source(name: String) -> EnvFromSource {
+    EnvFromSource {
+        secret_ref: Some(SecretEnvSource {
+            name,
+            ..Default::default()
+        }),
+        ..Default::default()
+    }
+}
+
+/// Kubernetes' rule for environment variable names
+fn valid_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || "-._".contains(c))
+        && chars.all(|c| c.is_ascii_alphanumeric() || "-._".contains(c))
+}
+
+pub fn validate(spec: &MyAppSpec) -> Result<(), String> {
+    for name in spec.env_vars.keys().chain(spec.pod_env.keys()) {
+        if !valid_name(name) {
+            return Err(format!("{} is not a valid environment variable name", name));
+        }
+    }
+    if let Some(name) = spec.pod_env.keys().find(|n| spec.env_vars.contains_key(*n)) {
+        return Err(format!("{} is set in both envVars and podEnv", name));
+    }
+    if let Some(env_from) = &spec.env_from {
+        env_from.validate()?;
+    }
+    Ok(())
+}
+
+/// envFrom for the app container, followed by the Secrets synced from Vault and the External
+/// Secrets Operator; None when nothing is referenced
+pub fn container_env_from(myapp: &MyApp) -> Option<Vec<EnvFromSource>> {
+    let mut sources = myapp
+        .spec
+        .env_from
+        .as_ref()
+        .map(EnvFromConfig::sources)
+        .unwrap_or_default();
+    sources.extend(vault::secret_names(myapp).into_iter().map(secret_source));
+    sources.extend(
+        external_secrets::secret_names(myapp)
+            .into_iter()
+            .map(secret_source),
+    );
+    (!sources.is_empty()).then_some(sources)
+}
+
+/// Env for the app container: literal values first, then downward API fields
+pub fn container_env(spec: &MyAppSpec) -> Vec<EnvVar> {
+    let literal = spec.env_vars.iter().map(|(name, value)| EnvVar {
+        name: name.clone(),
+        value: Some(value.clone()),
+        ..Default::default()
+    });
+    let pod = spec.pod_env.iter().map(|(name, field)| EnvVar {
+        name: name.clone(),
+        value_from: Some(EnvVarSource {
+            field_ref: Some(ObjectFieldSelector {
+                field_path: field.field_path().to_string(),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }),
+        ..Default::default()
+    });
+    literal.chain(pod).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    fn spec(pod_env: &[(&str, PodField)]) -> MyAppSpec {
+        MyAppSpec {
+            env_vars: BTreeMap::from([("LOG_LEVEL".to_string(), "info".to_string())]),
+            pod_env: pod_env
+                .iter()
+                .map(|(name, field)| (name.to_string(), *field))
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_pod_fields_use_field_ref() {
+        let env = container_env(&spec(&[
+            ("POD_NAME", PodField::PodName),
+            ("POD_IP", PodField::PodIp),
+        ]));
+        assert_eq!(env.len(), 3);
+        assert_eq!(env[0].value.as_deref(), Some("info"));
+        let path = |var: &EnvVar| {
+            var.value_from
+                .as_ref()
+                .and_then(|v| v.field_ref.as_ref())
+                .map(|f| f.field_path.clone())
+        };
+        assert_eq!(path(&env[1]).as_deref(), Some("status.podIP"));
+        assert_eq!(path(&env[2]).as_deref(), Some("metadata.name"));
+    }
+
+    #[test]
+    fn test_validate_rejects_clashes_and_bad_names() {
+        assert!(validate(&spec(&[("NODE_NAME", PodField::NodeName)])).is_ok());
+        assert!(validate(&spec(&[("LOG_LEVEL", PodField::PodName)])).is_err());
+        assert!(validate(&spec(&[("1POD", PodField::PodName)])).is_err());
+    }
+
+    #[test]
+    fn test_env_from_lists_config_maps_then_secrets() {
+        let mut spec = spec(&[]);
+        assert!(container_env_from(&MyApp::new("web", spec.clone())).is_none());
+
+        spec.env_from = Some(EnvFromConfig {
+            config_map_refs: vec!["shared".to_string(), "web".to_string()],
+            secret_refs: vec!["web-db".to_string()],
+        });
+        spec.vault_secrets = vec![crate::vault::VaultSecret {
+            name: "api".to_string(),
+            path: "secret/data/web".to_string(),
+            role: "web".to_string(),
+            keys: Vec::new(),
+        }];
+        let sources = container_env_from(&MyApp::new("web", spec.clone())).unwrap();
+        let names: Vec<&str> = sources
+            .iter()
+            .map(|s| match (&s.config_map_ref, &s.secret_ref) {
+                (Some(c), _) => c.name.as_str(),
+                (_, Some(s)) => s.name.as_str(),
+                _ => "",
+            })
+            .collect();
+        assert_eq!(names, ["shared", "web", "web-db", "web-api"]);
+
+        spec.env_from = Some(EnvFromConfig {
+            config_map_refs: vec!["web".to_string(), "web".to_string()],
+            ..Default::default()
+        });
+        assert!(validate(&spec).is_err());
+    }
+}
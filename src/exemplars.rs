@@ -0,0 +1,294 @@
+// Trace-ID exemplars for MyApp Controller
+// Remembers the trace of recent histogram observations and serves them in the OpenMetrics
+// exposition format, which the prometheus crate cannot write itself
+
+use prometheus::proto::{Metric, MetricFamily, MetricType};
+use std::collections::{HashMap, VecDeque};
+use std::fmt::Write;
+use std::sync::Mutex;
+
+pub const CONTENT_TYPE: &str = "application/openmetrics-text; version=1.0.0; charset=utf-8";
+
+/// Recent observations kept per histogram series, enough to cover every bucket
+const PER_SERIES: usize = 16;
+
+/// Histogram name and its label pairs sorted by name, as the encoder sees them
+type SeriesKey = (String, Vec<(String, String)>);
+
+#[derive(Debug, Clone, PartialEq)]
+struct Exemplar {
+    trace_id: String,
+    value: f64,
+    timestamp: f64,
+}
+
+lazy_static::lazy_static! {
+    static ref EXEMPLARS: Mutex<HashMap<SeriesKey, VecDeque<Exemplar>>> =
+        Mutex::new(HashMap::new());
+}
+
+fn key(histogram: &str, labels: &[(&str, &str)]) -> SeriesKey {
+    let mut labels: Vec<_> = labels
+        .iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+    labels.sort();
+    (histogram.to_string(), labels)
+}
+
+/// Remember that the observation `value` of a histogram series belongs to `trace_id`
+pub fn record(histogram: &str, labels: &[(&str, &str)], value: f64, trace_id: String) {
+    let timestamp = chrono::Utc::now().timestamp_millis() as f64 / 1000.0;
+    let mut exemplars = EXEMPLARS.lock().unwrap();
+    let series = exemplars.entry(key(histogram, labels)).or_default();
+    if series.len() == PER_SERIES {
+        series.pop_front();
+    }
+    series.push_back(Exemplar {
+        trace_id,
+        value,
+        timestamp,
+    });
+}
+
+/// Drop the exemplars of a removed histogram series
+pub fn forget(histogram: &str, labels: &[(&str, &str)]) {
+    EXEMPLARS.lock().unwrap().remove(&key(histogram, labels));
+}
+
+/// Accept header asking for OpenMetrics, as Prometheus does with exemplar storage enabled
+pub fn wants_openmetrics(accept: Option<&str>) -> bool {
+    accept.is_some_and(|a| a.contains("application/openmetrics-text"))
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', r"\\")
+        .replace('"', "\\\"")
+        .replace('\n', r"\n")
+}
+
+fn format_float(value: f64) -> String {
+    match value {
+        v if v == f64::INFINITY => "+Inf".to_string(),
+        v if v == f64::NEG_INFINITY => "-Inf".to_string(),
+        v => v.to_string(),
+    }
+}
+
+fn sorted_labels(metric: &Metric) -> Vec<(String, String)> {
+    let mut labels: Vec<_> = metric
+        .get_label()
+        .iter()
+        .map(|l| (l.name().to_string(), l.value().to_string()))
+        .collect();
+    labels.sort();
+    labels
+}
+
+fn sample(
+    out: &mut String,
+    name: &str,
+    labels: &[(String, String)],
+    extra: Option<(&str, &str)>,
+    value: f64,
+) {
+    let pairs: Vec<String> = labels
+        .iter()
+        .map(|(k, v)| (k.as_str(), v.as_str()))
+        .chain(extra)
+        .map(|(k, v)| format!("{}=\"{}\"", k, escape(v)))
+        .collect();
+    let _ = match pairs.is_empty() {
+        true => write!(out, "{} {}", name, format_float(value)),
+        false => write!(
+            out,
+            "{}{{{}}} {}",
+            name,
+            pairs.join(","),
+            format_float(value)
+        ),
+    };
+}
+
+fn histogram(
+    out: &mut String,
+    name: &str,
+    metric: &Metric,
+    exemplars: Option<&VecDeque<Exemplar>>,
+) {
+    let labels = sorted_labels(metric);
+    let h = metric.get_histogram();
+    let mut lower = f64::NEG_INFINITY;
+    let mut bounds: Vec<(f64, u64)> = h
+        .get_bucket()
+        .iter()
+        .map(|b| (b.upper_bound(), b.cumulative_count()))
+        .collect();
+    if bounds.last().is_none_or(|(le, _)| *le != f64::INFINITY) {
+        bounds.push((f64::INFINITY, h.sample_count()));
+    }
+    for (le, count) in bounds {
+        let bucket = format!("{}_bucket", name);
+        sample(
+            out,
+            &bucket,
+            &labels,
+            Some(("le", &format_float(le))),
+            count as f64,
+        );
+        // The latest observation that fell into this bucket
+        let exemplar =
+            exemplars.and_then(|e| e.iter().rev().find(|x| x.value > lower && x.value <= le));
+        if let Some(x) = exemplar {
+            let _ = write!(
+                out,
+                " # {{trace_id=\"{}\"}} {} {}",
+                escape(&x.trace_id),
+                format_float(x.value),
+                x.timestamp
+            );
+        }
+        out.push('\n');
+        lower = le;
+    }
+    sample(out, &format!("{}_sum", name), &labels, None, h.sample_sum());
+    out.push('\n');
+    sample(
+        out,
+        &format!("{}_count", name),
+        &labels,
+        None,
+        h.sample_count() as f64,
+    );
+    out.push('\n');
+}
+
+/// OpenMetrics text of `families`, with exemplars on the histogram buckets
+pub fn encode(families: &[MetricFamily]) -> String {
+    let exemplars = EXEMPLARS.lock().unwrap();
+    let mut out = String::new();
+    for family in families {
+        let name = family.name();
+        // OpenMetrics names a counter family without the _total of its sample
+        let (family_name, type_) = match family.get_field_type() {
+            MetricType::COUNTER => match name.strip_suffix("_total") {
+                Some(base) => (base, "counter"),
+                None => (name, "unknown"),
+            },
+            MetricType::GAUGE => (name, "gauge"),
+            MetricType::HISTOGRAM => (name, "histogram"),
+            MetricType::SUMMARY => (name, "summary"),
+            MetricType::UNTYPED => (name, "unknown"),
+        };
+        let _ = writeln!(out, "# TYPE {} {}", family_name, type_);
+        if !family.help().is_empty() {
+            let _ = writeln!(out, "# HELP {} {}", family_name, escape(family.help()));
+        }
+        for metric in family.get_metric() {
+            let labels = sorted_labels(metric);
+            match family.get_field_type() {
+                MetricType::COUNTER => {
+                    sample(&mut out, name, &labels, None, metric.get_counter().value())
+                }
+                MetricType::GAUGE => {
+                    sample(&mut out, name, &labels, None, metric.get_gauge().value())
+                }
+                MetricType::UNTYPED => {
+                    sample(&mut out, name, &labels, None, metric.untyped.value())
+                }
+                MetricType::HISTOGRAM => {
+                    let series = exemplars.get(&(name.to_string(), labels));
+                    histogram(&mut out, name, metric, series);
+                    continue;
+                }
+                MetricType::SUMMARY => {
+                    let s = metric.get_summary();
+                    for q in s.get_quantile() {
+                        let quantile = format_float(q.quantile());
+                        sample(
+                            &mut out,
+                            name,
+                            &labels,
+                            Some(("quantile", &quantile)),
+                            q.value(),
+                        );
+                        out.push('\n');
+                    }
+                    sample(
+                        &mut out,
+                        &format!("{}_sum", name),
+                        &labels,
+                        None,
+                        s.sample_sum(),
+                    );
+                    out.push('\n');
+                    sample(
+                        &mut out,
+                        &format!("{}_count", name),
+                        &labels,
+                        None,
+                        s.sample_count() as f64,
+                    );
+                }
+            }
+            out.push('\n');
+        }
+    }
+    out.push_str("# EOF\n");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use prometheus::{HistogramOpts, HistogramVec, IntCounter, Registry};
+
+    #[test]
+    fn test_encode_with_exemplars() {
+        let registry = Registry::new();
+        let histogram = HistogramVec::new(
+            HistogramOpts::new("test_exemplar_duration_seconds", "Duration")
+                .buckets(vec![0.1, 1.0]),
+            &["namespace", "name"],
+        )
+        .unwrap();
+        let counter = IntCounter::new("test_exemplar_total", "Count").unwrap();
+        registry.register(Box::new(histogram.clone())).unwrap();
+        registry.register(Box::new(counter.clone())).unwrap();
+
+        let labels = [("namespace", "shop"), ("name", "web")];
+        histogram.with_label_values(&["shop", "web"]).observe(0.5);
+        record(
+            "test_exemplar_duration_seconds",
+            &labels,
+            0.5,
+            "abc123".to_string(),
+        );
+        counter.inc();
+
+        let text = encode(&registry.gather());
+        assert!(text.contains("# TYPE test_exemplar counter\n"));
+        assert!(text.contains("test_exemplar_total 1\n"));
+        assert!(text.contains(
+            "test_exemplar_duration_seconds_bucket{name=\"web\",namespace=\"shop\",le=\"0.1\"} 0\n"
+        ));
+        assert!(text.contains(
+            "test_exemplar_duration_seconds_bucket{name=\"web\",namespace=\"shop\",le=\"1\"} 1 # {trace_id=\"abc123\"} 0.5 "
+        ));
+        assert!(text.contains("le=\"+Inf\"} 1\n"));
+        assert!(text.ends_with("# EOF\n"));
+
+        forget("test_exemplar_duration_seconds", &labels);
+        assert!(!encode(&registry.gather()).contains("trace_id"));
+    }
+
+    #[test]
+    fn test_wants_openmetrics() {
+        assert!(wants_openmetrics(Some(
+            "application/openmetrics-text;version=1.0.0,text/plain;version=0.0.4;q=0.5"
+        )));
+        assert!(!wants_openmetrics(Some("text/plain")));
+        assert!(!wants_openmetrics(None));
+    }
+}
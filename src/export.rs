@@ -0,0 +1,254 @@
+// Export of a MyApp and its children for MyApp Controller
+// Writes the MyApp and every object it owns as re-appliable YAML, with server-managed
+// fields removed, for backups, support bundles and cluster migrations
+
+use crate::monitoring;
+use crate::MyApp;
+use k8s_openapi::api::apps::v1::Deployment;
+use k8s_openapi::api::batch::v1::{CronJob, Job};
+use k8s_openapi::api::core::v1::{PersistentVolumeClaim, Service};
+use kube::api::{Api, ApiResource, DynamicObject, ListParams};
+use kube::{Client, Resource, ResourceExt};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+use std::fmt::Debug;
+
+const SERVER_METADATA: &[&str] = &[
+    "uid",
+    "resourceVersion",
+    "generation",
+    "creationTimestamp",
+    "deletionTimestamp",
+    "deletionGracePeriodSeconds",
+    "managedFields",
+    "selfLink",
+    // The owner's UID changes when it is re-created; adoption re-attaches children
+    "ownerReferences",
+];
+
+const SERVER_ANNOTATIONS: &[&str] = &[
+    "kubectl.kubernetes.io/last-applied-configuration",
+    "deployment.kubernetes.io/revision",
+    "pv.kubernetes.io/bind-completed",
+    "pv.kubernetes.io/bound-by-controller",
+];
+
+/// Labels the Job controller derives from the Job's UID
+const JOB_LABELS: &[&str] = &[
+    "controller-uid",
+    "batch.kubernetes.io/controller-uid",
+    "job-name",
+    "batch.kubernetes.io/job-name",
+];
+
+fn remove_keys(value: &mut Value, keys: &[&str]) {
+    if let Some(map) = value.as_object_mut() {
+        for key in keys {
+            map.remove(*key);
+        }
+    }
+}
+
+/// Strip fields the API server sets so the object can be applied to another cluster
+pub fn clean(mut object: Value) -> Value {
+    remove_keys(&mut object, &["status"]);
+    remove_keys(&mut object["metadata"], SERVER_METADATA);
+    remove_keys(&mut object["metadata"]["annotations"], SERVER_ANNOTATIONS);
+    if object["metadata"]["annotations"]
+        .as_object()
+        .is_some_and(|a| a.is_empty())
+    {
+        remove_keys(&mut object["metadata"], &["annotations"]);
+    }
+
+    match object["kind"].as_str() {
+        Some("Service") => remove_keys(&mut object["spec"], &["clusterIP", "clusterIPs"]),
+        Some("Job") => {
+            remove_keys(&mut object["spec"], &["selector"]);
+            remove_keys(&mut object["metadata"]["labels"], JOB_LABELS);
+            remove_keys(
+                &mut object["spec"]["template"]["metadata"]["labels"],
+                JOB_LABELS,
+            );
+        }
+        Some("PersistentVolumeClaim") => remove_keys(&mut object["spec"], &["volumeName"]),
+        _ => {}
+    }
+    object
+}
+
+fn owned_by<K: Resource + Serialize>(
+    items: Vec<K>,
+    uid: &str,
+) -> Result<Vec<Value>, serde_json::Error> {
+    items
+        .into_iter()
+        .filter(|item| item.owner_references().iter().any(|r| r.uid == uid))
+        .map(|item| serde_json::to_value(&item).map(clean))
+        .collect()
+}
+
+async fn list_owned<K>(
+    client: &Client,
+    ns: &str,
+    uid: &str,
+) -> Result<Vec<Value>, Box<dyn std::error::Error>>
+where
+    K: Resource<DynamicType = (), Scope = k8s_openapi::NamespaceResourceScope>
+        + Clone
+        + Debug
+        + Serialize
+        + DeserializeOwned,
+{
+    let api: Api<K> = Api::namespaced(client.clone(), ns);
+    let items = api.list(&ListParams::default()).await?.items;
+    Ok(owned_by(items, uid)?)
+}
+
+async fn list_owned_dynamic(
+    client: &Client,
+    ns: &str,
+    uid: &str,
+    resource: &ApiResource,
+) -> Result<Vec<Value>, Box<dyn std::error::Error>> {
+    let api: Api<DynamicObject> = Api::namespaced_with(client.clone(), ns, resource);
+    let items: Vec<DynamicObject> = api
+        .list(&ListParams::default())
+        .await?
+        .items
+        .into_iter()
+        .map(|mut item| {
+            item.types = Some(kube::api::TypeMeta {
+                api_version: resource.api_version.clone(),
+                kind: resource.kind.clone(),
+            });
+            item
+        })
+        .collect();
+    Ok(owned_by(items, uid)?)
+}
+
+/// The MyApp followed by every object carrying its owner reference
+pub async fn export(
+    client: Client,
+    ns: &str,
+    name: &str,
+) -> Result<Vec<Value>, Box<dyn std::error::Error>> {
+    let myapp = Api::<MyApp>::namespaced(client.clone(), ns)
+        .get(name)
+        .await?;
+    let uid = myapp.uid().unwrap_or_default();
+
+    let mut docs = vec![clean(serde_json::to_value(&myapp)?)];
+    docs.extend(list_owned::<Deployment>(&client, ns, &uid).await?);
+    docs.extend(list_owned::<Service>(&client, ns, &uid).await?);
+    docs.extend(list_owned::<Job>(&client, ns, &uid).await?);
+    docs.extend(list_owned::<CronJob>(&client, ns, &uid).await?);
+    docs.extend(list_owned::<PersistentVolumeClaim>(&client, ns, &uid).await?);
+
+    let crds = monitoring::detect(client.clone()).await;
+    if crds.service_monitors {
+        docs.extend(
+            list_owned_dynamic(&client, ns, &uid, &monitoring::service_monitor_resource()).await?,
+        );
+    }
+    if crds.prometheus_rules {
+        docs.extend(
+            list_owned_dynamic(&client, ns, &uid, &monitoring::prometheus_rule_resource()).await?,
+        );
+    }
+    Ok(docs)
+}
+
+/// `export <namespace>/<name> [-o <file>]`
+pub async fn run(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let mut target = None;
+    let mut output = None;
+
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "-o" | "--output" => output = iter.next().cloned(),
+            other if !other.starts_with('-') && target.is_none() => {
+                target = Some(other.to_string())
+            }
+            other => return Err(format!("unknown export argument: {}", other).into()),
+        }
+    }
+
+    let target = target.ok_or("usage: export <namespace>/<name> [-o <file>]")?;
+    let (ns, name) = target
+        .split_once('/')
+        .ok_or("export target must be <namespace>/<name>")?;
+
+    let client = crate::kubectl::client().await?;
+    let mut yaml = String::new();
+    for doc in export(client, ns, name).await? {
+        yaml.push_str("---\n");
+        yaml.push_str(&serde_yaml::to_string(&doc)?);
+    }
+
+    match output {
+        Some(path) => {
+            std::fs::write(&path, yaml)?;
+            eprintln!("Exported {} to {}", target, path);
+        }
+        None => print!("{}", yaml),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_clean_strips_server_fields() {
+        let service = json!({
+            "apiVersion": "v1",
+            "kind": "Service",
+            "metadata": {
+                "name": "web-service",
+                "uid": "1234",
+                "resourceVersion": "42",
+                "managedFields": [],
+                "ownerReferences": [{ "uid": "abcd" }],
+                "annotations": { "kubectl.kubernetes.io/last-applied-configuration": "{}" }
+            },
+            "spec": { "clusterIP": "10.0.0.1", "ports": [{ "port": 80 }] },
+            "status": { "loadBalancer": {} }
+        });
+
+        assert_eq!(
+            clean(service),
+            json!({
+                "apiVersion": "v1",
+                "kind": "Service",
+                "metadata": { "name": "web-service" },
+                "spec": { "ports": [{ "port": 80 }] }
+            })
+        );
+    }
+
+    #[test]
+    fn test_clean_job_selector() {
+        let job = json!({
+            "kind": "Job",
+            "metadata": { "name": "run", "labels": { "app": "web", "controller-uid": "x" } },
+            "spec": {
+                "selector": { "matchLabels": { "controller-uid": "x" } },
+                "template": { "metadata": { "labels": { "app": "web", "job-name": "run" } } }
+            }
+        });
+
+        let cleaned = clean(job);
+        assert!(cleaned["spec"].get("selector").is_none());
+        assert_eq!(cleaned["metadata"]["labels"], json!({ "app": "web" }));
+        assert_eq!(
+            cleaned["spec"]["template"]["metadata"]["labels"],
+            json!({ "app": "web" })
+        );
+    }
+}
@@ -0,0 +1,257 @@
+// External Secrets Operator integration for MyApp Controller
+// Generates an ExternalSecret per spec.externalSecrets entry; ESO fetches the remote keys
+// from the referenced store into a Secret `<myapp>-<name>`, which the app container reads
+// through envFrom. The controller never handles the secret material itself.
+
+use crate::deletion::{owned, MANAGED_BY_SELECTOR};
+use crate::{app_labels, child_name, create_owner_reference, MyApp, ReconcileError};
+use kube::api::{
+    Api, ApiResource, DeleteParams, DynamicObject, GroupVersionKind, Patch, PatchParams,
+};
+use kube::{Client, ResourceExt};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+const GROUP_VERSION: &str = "external-secrets.io/v1beta1";
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, JsonSchema, Default)]
+pub enum StoreKind {
+    /// Store in the MyApp's namespace (default)
+    #[default]
+    SecretStore,
+    ClusterSecretStore,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct StoreRef {
+    pub name: String,
+
+    #[serde(default)]
+    pub kind: StoreKind,
+}
+
+/// One key of the target Secret and where it comes from
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct RemoteKey {
+    /// Key in the generated Secret, which becomes the variable name
+    pub secret_key: String,
+
+    /// Key in the external store
+    pub remote_key: String,
+
+    /// Property of a structured remote value
+    #[serde(default)]
+    pub property: Option<String>,
+}
+
+/// An ExternalSecret filling a Secret named `<myapp>-<name>`
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ExternalSecretConfig {
+    pub name: String,
+
+    pub secret_store_ref: StoreRef,
+
+    pub data: Vec<RemoteKey>,
+
+    /// How often ESO re-reads the store, e.g. 1h (ESO's default when unset)
+    #[serde(default)]
+    pub refresh_interval: Option<String>,
+}
+
+pub fn validate(secrets: &[ExternalSecretConfig], taken: &[String]) -> Result<(), String> {
+    for (i, secret) in secrets.iter().enumerate() {
+        if secret.name.is_empty()
+            || !secret
+                .name
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+        {
+            return Err(format!(
+                "externalSecrets: '{}' must be lowercase letters, digits and '-'",
+                secret.name
+            ));
+        }
+        if secrets[..i].iter().any(|s| s.name == secret.name) || taken.contains(&secret.name) {
+            return Err(format!(
+                "externalSecrets: {} is already used by another Secret of this MyApp",
+                secret.name
+            ));
+        }
+        if secret.secret_store_ref.name.is_empty() {
+            return Err(format!(
+                "externalSecrets: {} needs a secretStoreRef name",
+                secret.name
+            ));
+        }
+        if secret.data.is_empty() {
+            return Err(format!(
+                "externalSecrets: {} needs at least one data entry",
+                secret.name
+            ));
+        }
+        for (j, key) in secret.data.iter().enumerate() {
+            if key.secret_key.is_empty() || key.remote_key.is_empty() {
+                return Err(format!(
+                    "externalSecrets: {} entries need secretKey and remoteKey",
+                    secret.name
+                ));
+            }
+            if secret.data[..j]
+                .iter()
+                .any(|k| k.secret_key == key.secret_key)
+            {
+                return Err(format!(
+                    "externalSecrets: {} writes {} twice",
+                    secret.name, key.secret_key
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Whether the cluster serves ExternalSecrets
+pub async fn detect(client: Client) -> bool {
+    client
+        .list_api_group_resources(GROUP_VERSION)
+        .await
+        .is_ok_and(|list| list.resources.iter().any(|r| r.name == "externalsecrets"))
+}
+
+pub fn external_secret_resource() -> ApiResource {
+    let gvk = GroupVersionKind::gvk("external-secrets.io", "v1beta1", "ExternalSecret");
+    ApiResource::from_gvk_with_plural(&gvk, "externalsecrets")
+}
+
+/// Names of the Secrets ESO writes for the MyApp, in spec order
+pub fn secret_names(myapp: &MyApp) -> Vec<String> {
+    myapp
+        .spec
+        .external_secrets
+        .iter()
+        .map(|s| child_name(myapp, &s.name))
+        .collect()
+}
+
+/// ExternalSecret whose target Secret is owned by it, so it goes when the ExternalSecret does
+pub fn external_secret(myapp: &MyApp, config: &ExternalSecretConfig) -> DynamicObject {
+    let name = child_name(myapp, &config.name);
+    let mut object =
+        DynamicObject::new(&name, &external_secret_resource()).within(&myapp.namespace().unwrap());
+    object.metadata.labels = Some(app_labels(myapp));
+    object.metadata.owner_references = Some(vec![create_owner_reference(myapp)]);
+
+    let data: Vec<serde_json::Value> = config
+        .data
+        .iter()
+        .map(|key| {
+            let mut remote = serde_json::json!({ "key": key.remote_key });
+            if let Some(property) = &key.property {
+                remote["property"] = property.clone().into();
+            }
+            serde_json::json!({ "secretKey": key.secret_key, "remoteRef": remote })
+        })
+        .collect();
+    let mut spec = serde_json::json!({
+        "secretStoreRef": {
+            "name": config.secret_store_ref.name,
+            "kind": config.secret_store_ref.kind,
+        },
+        "target": { "name": name, "creationPolicy": "Owner" },
+        "data": data,
+    });
+    if let Some(interval) = &config.refresh_interval {
+        spec["refreshInterval"] = interval.clone().into();
+    }
+    object.data = serde_json::json!({ "spec": spec });
+    object
+}
+
+/// Apply the MyApp's ExternalSecrets and delete the ones dropped from its spec
+pub async fn reconcile(
+    myapp: &MyApp,
+    installed: bool,
+    client: Client,
+) -> Result<(), ReconcileError> {
+    let ns = myapp.namespace().unwrap();
+    let api: Api<DynamicObject> = Api::namespaced_with(client, &ns, &external_secret_resource());
+
+    if !installed {
+        if myapp.spec.external_secrets.is_empty() {
+            return Ok(());
+        }
+        return Err(ReconcileError::SecretError(
+            "spec.externalSecrets is set but the ExternalSecret CRD is not installed".to_string(),
+        ));
+    }
+
+    let params = PatchParams::apply("myapp-controller").force();
+    for config in &myapp.spec.external_secrets {
+        let object = external_secret(myapp, config);
+        api.patch(&object.name_any(), &params, &Patch::Apply(&object))
+            .await?;
+    }
+
+    let wanted = secret_names(myapp);
+    let uid = myapp.uid().unwrap_or_default();
+    for stale in owned(&api, MANAGED_BY_SELECTOR, &uid).await? {
+        if !wanted.contains(&stale.name_any()) {
+            match api
+                .delete(&stale.name_any(), &DeleteParams::default())
+                .await
+            {
+                Ok(_) => tracing::info!("Deleted ExternalSecret {}/{}", ns, stale.name_any()),
+                Err(kube::Error::Api(e)) if e.code == 404 => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> ExternalSecretConfig {
+        ExternalSecretConfig {
+            name: "db".to_string(),
+            secret_store_ref: StoreRef {
+                name: "aws".to_string(),
+                kind: StoreKind::ClusterSecretStore,
+            },
+            data: vec![RemoteKey {
+                secret_key: "DB_PASSWORD".to_string(),
+                remote_key: "prod/db".to_string(),
+                property: Some("password".to_string()),
+            }],
+            refresh_interval: Some("1h".to_string()),
+        }
+    }
+
+    #[test]
+    fn test_external_secret_targets_child_secret() {
+        let mut myapp = MyApp::new("web", Default::default());
+        myapp.metadata.namespace = Some("shop".to_string());
+        myapp.metadata.uid = Some("uid-1".to_string());
+
+        let object = external_secret(&myapp, &config());
+        let spec = &object.data["spec"];
+        assert_eq!(spec["target"]["name"], "web-db");
+        assert_eq!(spec["secretStoreRef"]["kind"], "ClusterSecretStore");
+        assert_eq!(spec["data"][0]["remoteRef"]["property"], "password");
+        assert_eq!(spec["refreshInterval"], "1h");
+    }
+
+    #[test]
+    fn test_validate_rejects_taken_names() {
+        assert!(validate(&[config()], &[]).is_ok());
+        assert!(validate(&[config()], &["db".to_string()]).is_err());
+        let mut twice = config();
+        twice.data.push(twice.data[0].clone());
+        assert!(validate(&[twice], &[]).is_err());
+    }
+}
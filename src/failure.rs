@@ -0,0 +1,352 @@
+// Failure handling for one-shot MyApp workloads
+// Controller-managed retries, alerting and schedule suspension on top of the Job backoffLimit
+
+use crate::workload::{self, LastRunStatus, WorkloadType};
+use crate::{publish_event, MyApp};
+use k8s_openapi::api::batch::v1::{CronJob, Job};
+use kube::api::{Api, DeleteParams, Patch, PatchParams};
+use kube::runtime::events::EventType;
+use kube::{Client, ResourceExt};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// Longest delay between controller retries
+const MAX_RETRY_BACKOFF: Duration = Duration::from_secs(3600);
+
+/// What the controller does when a Job or CronJob run fails
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct FailurePolicy {
+    /// Times a failed Job is re-created by the controller once its backoffLimit is spent
+    #[serde(default)]
+    pub max_retries: u32,
+
+    /// Delay before the first re-run, doubled after each failure (default 30)
+    #[serde(default)]
+    #[schemars(range(min = 1))]
+    pub retry_backoff_seconds: Option<u64>,
+
+    /// Emit a warning Event and notify after this many consecutive failures
+    #[serde(default)]
+    #[schemars(range(min = 1))]
+    pub alert_after: Option<u32>,
+
+    /// Suspend the CronJob schedule after this many consecutive failures; resume it by
+    /// setting spec.suspend=false on the CronJob
+    #[serde(default)]
+    #[schemars(range(min = 1))]
+    pub suspend_after: Option<u32>,
+
+    /// URL receiving a JSON POST on alerts and when retries are exhausted
+    #[serde(default)]
+    pub notify_url: Option<String>,
+}
+
+/// Consecutive failure tracking kept in the MyApp status
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct FailureStatus {
+    /// Failed runs since the last success
+    #[serde(default)]
+    pub consecutive_failures: u32,
+
+    /// Run (job name and start time) that was last counted as failed
+    #[serde(default)]
+    pub last_failed_run: Option<String>,
+
+    /// When the last failure was observed
+    #[serde(default)]
+    pub last_failure_time: Option<String>,
+
+    /// Retries already performed by the controller
+    #[serde(default)]
+    pub retries: u32,
+
+    /// Failed run the last retry replaced
+    #[serde(default)]
+    pub last_retried_run: Option<String>,
+
+    /// An alert was sent for the current failure streak
+    #[serde(default)]
+    pub alerted: bool,
+
+    /// Retries are used up, or the schedule was suspended
+    #[serde(default)]
+    pub exhausted: bool,
+}
+
+/// Next step for a failed workload
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Decision {
+    /// Nothing to do
+    None,
+    /// Re-run after the remaining backoff
+    Wait(Duration),
+    /// Delete the failed Job so it is re-created
+    Retry,
+    /// Retries are used up (or the CronJob should be suspended)
+    Exhausted,
+}
+
+fn run_key(run: &LastRunStatus) -> String {
+    format!(
+        "{}@{}",
+        run.job_name.as_deref().unwrap_or_default(),
+        run.start_time.as_deref().unwrap_or_default()
+    )
+}
+
+impl FailurePolicy {
+    pub fn backoff(&self, retries: u32) -> Duration {
+        let base = Duration::from_secs(self.retry_backoff_seconds.unwrap_or(30));
+        base.saturating_mul(2u32.saturating_pow(retries))
+            .min(MAX_RETRY_BACKOFF)
+    }
+
+    pub fn should_alert(&self, status: &FailureStatus) -> bool {
+        !status.alerted
+            && self
+                .alert_after
+                .is_some_and(|n| status.consecutive_failures >= n)
+    }
+
+    /// Decide what to do for a Job (`cron = false`) or CronJob run
+    pub fn decide(
+        &self,
+        status: &FailureStatus,
+        cron: bool,
+        now: chrono::DateTime<chrono::Utc>,
+    ) -> Decision {
+        if status.consecutive_failures == 0 || status.exhausted {
+            return Decision::None;
+        }
+        if cron {
+            // The schedule itself retries; only suspend once the streak is long enough
+            return match self.suspend_after {
+                Some(n) if status.consecutive_failures >= n => Decision::Exhausted,
+                _ => Decision::None,
+            };
+        }
+        if status.last_retried_run.is_some() && status.last_retried_run == status.last_failed_run {
+            // Already retried; waiting for the replacement Job
+            return Decision::None;
+        }
+        if status.retries >= self.max_retries {
+            return Decision::Exhausted;
+        }
+
+        let failed_at = status
+            .last_failure_time
+            .as_deref()
+            .and_then(|t| chrono::DateTime::parse_from_rfc3339(t).ok())
+            .map(|t| t.with_timezone(&chrono::Utc))
+            .unwrap_or(now);
+        let elapsed = (now - failed_at).to_std().unwrap_or_default();
+        match self.backoff(status.retries).checked_sub(elapsed) {
+            Some(remaining) if !remaining.is_zero() => Decision::Wait(remaining),
+            _ => Decision::Retry,
+        }
+    }
+}
+
+/// Fold the latest run into the failure streak; each failed run is counted once
+pub fn observe(
+    previous: Option<&FailureStatus>,
+    run: Option<&LastRunStatus>,
+    now: chrono::DateTime<chrono::Utc>,
+) -> FailureStatus {
+    let mut status = previous.cloned().unwrap_or_default();
+    match run.map(|r| r.result.as_str()) {
+        Some("Succeeded") => FailureStatus::default(),
+        Some("Failed") => {
+            let key = run_key(run.unwrap());
+            if status.last_failed_run.as_deref() != Some(key.as_str()) {
+                status.consecutive_failures += 1;
+                status.last_failed_run = Some(key);
+                status.last_failure_time = Some(now.to_rfc3339());
+            }
+            status
+        }
+        _ => status,
+    }
+}
+
+/// Warning Event plus an optional POST to the policy's notify URL
+pub async fn notify(
+    myapp: &MyApp,
+    policy: &FailurePolicy,
+    client: Client,
+    reason: &str,
+    message: String,
+) {
+    publish_event(
+        myapp,
+        client,
+        EventType::Warning,
+        reason,
+        "FailurePolicy",
+        message.clone(),
+    )
+    .await;
+
+    let Some(url) = &policy.notify_url else {
+        return;
+    };
+    let body = serde_json::json!({
+        "namespace": myapp.namespace(),
+        "name": myapp.name_any(),
+        "reason": reason,
+        "message": message,
+    });
+    let result = reqwest::Client::builder()
+        .timeout(Duration::from_secs(5))
+        .build()
+        .expect("failed to build notification HTTP client")
+        .post(url)
+        .json(&body)
+        .send()
+        .await;
+    match result {
+        Ok(resp) if !resp.status().is_success() => {
+            tracing::warn!("Failure notification to {} returned {}", url, resp.status())
+        }
+        Err(e) => tracing::warn!("Failure notification to {} failed: {}", url, e),
+        Ok(_) => {}
+    }
+}
+
+/// Track the latest run and act on the policy; returns the new status and when to look again
+pub async fn reconcile(
+    myapp: &MyApp,
+    policy: &FailurePolicy,
+    run: Option<&LastRunStatus>,
+    client: Client,
+) -> Result<(FailureStatus, Option<Duration>), kube::Error> {
+    let ns = myapp.namespace().unwrap();
+    let now = chrono::Utc::now();
+    let previous = myapp.status.as_ref().and_then(|s| s.failures.as_ref());
+    let mut status = observe(previous, run, now);
+
+    if policy.should_alert(&status) {
+        notify(
+            myapp,
+            policy,
+            client.clone(),
+            "RepeatedFailures",
+            format!("{} consecutive failed runs", status.consecutive_failures),
+        )
+        .await;
+        status.alerted = true;
+    }
+
+    let cron = myapp.spec.workload_type == WorkloadType::CronJob;
+    let requeue = match policy.decide(&status, cron, now) {
+        Decision::None => None,
+        Decision::Wait(remaining) => Some(remaining),
+        Decision::Retry => {
+            let jobs: Api<Job> = Api::namespaced(client, &ns);
+            jobs.delete(&workload::job_name(myapp), &DeleteParams::background())
+                .await?;
+            status.retries += 1;
+            status.last_retried_run = status.last_failed_run.clone();
+            tracing::info!(
+                "Retrying failed job for MyApp {}/{} (retry {} of {})",
+                ns,
+                myapp.name_any(),
+                status.retries,
+                policy.max_retries
+            );
+            Some(Duration::from_secs(5))
+        }
+        Decision::Exhausted => {
+            let message = if cron {
+                let cronjobs: Api<CronJob> = Api::namespaced(client.clone(), &ns);
+                let patch = serde_json::json!({ "spec": { "suspend": true } });
+                cronjobs
+                    .patch(
+                        &workload::cronjob_name(myapp),
+                        &PatchParams::default(),
+                        &Patch::Merge(&patch),
+                    )
+                    .await?;
+                format!(
+                    "Suspended schedule after {} consecutive failed runs",
+                    status.consecutive_failures
+                )
+            } else {
+                format!("Job failed after {} controller retries", status.retries)
+            };
+            notify(myapp, policy, client, "RetriesExhausted", message).await;
+            status.exhausted = true;
+            None
+        }
+    };
+
+    Ok((status, requeue))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(result: &str, start: &str) -> LastRunStatus {
+        LastRunStatus {
+            job_name: Some("batch-job".to_string()),
+            start_time: Some(start.to_string()),
+            completion_time: None,
+            result: result.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_observe_counts_each_run_once() {
+        let now = chrono::Utc::now();
+        let first = observe(None, Some(&run("Failed", "t1")), now);
+        assert_eq!(first.consecutive_failures, 1);
+
+        let same = observe(Some(&first), Some(&run("Failed", "t1")), now);
+        assert_eq!(same.consecutive_failures, 1);
+
+        let second = observe(Some(&same), Some(&run("Failed", "t2")), now);
+        assert_eq!(second.consecutive_failures, 2);
+
+        let reset = observe(Some(&second), Some(&run("Succeeded", "t3")), now);
+        assert_eq!(reset, FailureStatus::default());
+    }
+
+    #[test]
+    fn test_decide() {
+        let policy = FailurePolicy {
+            max_retries: 2,
+            retry_backoff_seconds: Some(60),
+            suspend_after: Some(3),
+            ..Default::default()
+        };
+        let now = chrono::Utc::now();
+        let mut status = FailureStatus {
+            consecutive_failures: 1,
+            last_failure_time: Some((now - chrono::Duration::seconds(20)).to_rfc3339()),
+            ..Default::default()
+        };
+
+        assert!(
+            matches!(policy.decide(&status, false, now), Decision::Wait(d) if d.as_secs() <= 40)
+        );
+        status.last_failure_time = Some((now - chrono::Duration::seconds(90)).to_rfc3339());
+        assert_eq!(policy.decide(&status, false, now), Decision::Retry);
+
+        status.last_retried_run = Some("batch-job@t1".to_string());
+        status.last_failed_run = status.last_retried_run.clone();
+        assert_eq!(policy.decide(&status, false, now), Decision::None);
+
+        status.last_failed_run = Some("batch-job@t2".to_string());
+        status.retries = 2;
+        assert_eq!(policy.decide(&status, false, now), Decision::Exhausted);
+
+        assert_eq!(policy.decide(&status, true, now), Decision::None);
+        status.consecutive_failures = 3;
+        assert_eq!(policy.decide(&status, true, now), Decision::Exhausted);
+        assert_eq!(policy.backoff(10), MAX_RETRY_BACKOFF);
+    }
+}
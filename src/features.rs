@@ -0,0 +1,115 @@
+// Feature flags for MyApp Controller
+// Optional subsystems can be switched off so the controller runs with only core behavior
+// (Deployment, Service, Job, CronJob). `MYAPP_FEATURES` sets the baseline, e.g.
+// `all=false,service-monitor=true`; the operator config's `features` map overrides it live
+
+use crate::operator_config::OperatorConfig;
+use std::collections::BTreeMap;
+
+/// An optional subsystem reconcile consults before acting
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Feature {
+    /// ServiceMonitor and PrometheusRule children for spec.monitoring
+    ServiceMonitor,
+    /// Canary rollouts; MyApps asking for one are rolled out as RollingUpdate when off
+    Canary,
+}
+
+impl Feature {
+    pub const ALL: &'static [Feature] = &[Feature::ServiceMonitor, Feature::Canary];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Feature::ServiceMonitor => "service-monitor",
+            Feature::Canary => "canary",
+        }
+    }
+}
+
+/// Parse `name=bool` pairs; a bare name means true and `all` sets the default
+pub fn parse_flags(value: &str) -> Result<BTreeMap<String, bool>, String> {
+    let mut flags = BTreeMap::new();
+    for entry in value.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+        let (name, enabled) = match entry.split_once('=') {
+            Some((name, value)) => {
+                let enabled = value
+                    .trim()
+                    .parse()
+                    .map_err(|_| format!("invalid value for feature {}: {}", name, value))?;
+                (name.trim(), enabled)
+            }
+            None => (entry, true),
+        };
+        if name != "all" && !Feature::ALL.iter().any(|f| f.name() == name) {
+            return Err(format!("unknown feature: {}", name));
+        }
+        flags.insert(name.to_string(), enabled);
+    }
+    Ok(flags)
+}
+
+/// Whether `feature` is on: config overrides env, a named flag overrides `all`
+pub fn resolve(
+    feature: Feature,
+    env: &BTreeMap<String, bool>,
+    config: &BTreeMap<String, bool>,
+) -> bool {
+    let lookup = |name: &str| config.get(name).or_else(|| env.get(name)).copied();
+    lookup(feature.name())
+        .or_else(|| lookup("all"))
+        .unwrap_or(true)
+}
+
+/// Feature flags in effect
+#[derive(Clone, Default)]
+pub struct Features {
+    env: BTreeMap<String, bool>,
+    config: OperatorConfig,
+}
+
+impl Features {
+    /// Read `MYAPP_FEATURES`, layered under the operator config
+    pub fn from_env(config: OperatorConfig) -> Result<Self, String> {
+        let env = parse_flags(&std::env::var("MYAPP_FEATURES").unwrap_or_default())
+            .map_err(|e| format!("MYAPP_FEATURES: {}", e))?;
+        Ok(Self { env, config })
+    }
+
+    pub fn enabled(&self, feature: Feature) -> bool {
+        resolve(feature, &self.env, &self.config.get().features)
+    }
+
+    /// Flags as they currently resolve, for logging
+    pub fn summary(&self) -> Vec<(&'static str, bool)> {
+        Feature::ALL
+            .iter()
+            .map(|f| (f.name(), self.enabled(*f)))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_flags() {
+        let flags = parse_flags("all=false, canary").unwrap();
+        assert_eq!(flags.get("all"), Some(&false));
+        assert_eq!(flags.get("canary"), Some(&true));
+        assert!(parse_flags("hpa=false").is_err());
+        assert!(parse_flags("canary=maybe").is_err());
+    }
+
+    #[test]
+    fn test_resolve_precedence() {
+        let env = parse_flags("all=false,canary=true").unwrap();
+        let none = BTreeMap::new();
+        assert!(!resolve(Feature::ServiceMonitor, &env, &none));
+        assert!(resolve(Feature::Canary, &env, &none));
+
+        let config = BTreeMap::from([("canary".to_string(), false)]);
+        assert!(!resolve(Feature::Canary, &env, &config));
+        assert!(resolve(Feature::ServiceMonitor, &none, &none));
+    }
+}
@@ -0,0 +1,113 @@
+// Container hardening defaults for MyApp Controller
+// The mutating webhook fills the unset fields of spec.containerSecurity with runAsNonRoot=true,
+// readOnlyRootFilesystem=true and dropCapabilities=[ALL]. A MyApp opts out of one default by
+// setting the field itself (false, or an empty list), or out of all of them with the skip
+// annotation. The result is rendered as the app container's securityContext.
+
+use crate::MyApp;
+use k8s_openapi::api::core::v1::{Capabilities, SecurityContext};
+use kube::ResourceExt;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// Set to "true" to leave the MyApp's containerSecurity as written
+pub const SKIP_ANNOTATION: &str = "myapps.example.com/skip-hardening";
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, JsonSchema, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ContainerSecurity {
+    /// Refuse to start images that run as root (defaulted to true)
+    #[serde(default)]
+    pub run_as_non_root: Option<bool>,
+
+    #[serde(default)]
+    pub run_as_user: Option<i64>,
+
+    /// Mount the image read-only; writable paths need volumes (defaulted to true)
+    #[serde(default)]
+    pub read_only_root_filesystem: Option<bool>,
+
+    #[serde(default)]
+    pub allow_privilege_escalation: Option<bool>,
+
+    /// Capabilities to drop (defaulted to ALL)
+    #[serde(default)]
+    pub drop_capabilities: Option<Vec<String>>,
+
+    #[serde(default)]
+    pub add_capabilities: Vec<String>,
+}
+
+impl ContainerSecurity {
+    pub fn to_security_context(&self) -> SecurityContext {
+        let drop = self.drop_capabilities.clone().filter(|d| !d.is_empty());
+        let add = (!self.add_capabilities.is_empty()).then(|| self.add_capabilities.clone());
+        let capabilities = (drop.is_some() || add.is_some()).then_some(Capabilities { drop, add });
+        SecurityContext {
+            run_as_non_root: self.run_as_non_root,
+            run_as_user: self.run_as_user,
+            read_only_root_filesystem: self.read_only_root_filesystem,
+            allow_privilege_escalation: self.allow_privilege_escalation,
+            capabilities,
+            ..Default::default()
+        }
+    }
+}
+
+pub fn is_skipped(myapp: &MyApp) -> bool {
+    myapp
+        .annotations()
+        .get(SKIP_ANNOTATION)
+        .is_some_and(|v| v == "true")
+}
+
+/// `security` with the hardening defaults filled in, and the names of the fields defaulted
+pub fn with_defaults(
+    security: Option<&ContainerSecurity>,
+) -> (ContainerSecurity, Vec<&'static str>) {
+    let mut security = security.cloned().unwrap_or_default();
+    let mut applied = Vec::new();
+    if security.run_as_non_root.is_none() {
+        security.run_as_non_root = Some(true);
+        applied.push("runAsNonRoot");
+    }
+    if security.read_only_root_filesystem.is_none() {
+        security.read_only_root_filesystem = Some(true);
+        applied.push("readOnlyRootFilesystem");
+    }
+    if security.drop_capabilities.is_none() {
+        security.drop_capabilities = Some(vec!["ALL".to_string()]);
+        applied.push("dropCapabilities");
+    }
+    (security, applied)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_defaults_respect_opt_outs() {
+        let (hardened, applied) = with_defaults(None);
+        assert_eq!(
+            applied,
+            ["runAsNonRoot", "readOnlyRootFilesystem", "dropCapabilities"]
+        );
+        let context = hardened.to_security_context();
+        assert_eq!(context.run_as_non_root, Some(true));
+        assert_eq!(
+            context.capabilities.unwrap().drop,
+            Some(vec!["ALL".to_string()])
+        );
+
+        let opted_out = ContainerSecurity {
+            read_only_root_filesystem: Some(false),
+            drop_capabilities: Some(Vec::new()),
+            ..Default::default()
+        };
+        let (hardened, applied) = with_defaults(Some(&opted_out));
+        assert_eq!(applied, ["runAsNonRoot"]);
+        assert_eq!(hardened.read_only_root_filesystem, Some(false));
+        assert_eq!(hardened.to_security_context().capabilities, None);
+    }
+}
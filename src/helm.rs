@@ -0,0 +1,272 @@
+// Helm chart for MyApp Controller
+// `generate-helm` writes a chart whose CRD, RBAC and webhook configurations come from
+// the same Rust definitions as `generate-crd`, `generate-rbac` and `generate-webhooks`
+
+use crate::certs::WebhookObjects;
+use crate::install::{self, Manifest, Output};
+use crate::scope::WatchScope;
+use crate::{rbac, webhooks};
+use std::path::PathBuf;
+
+const RELEASE_NAMESPACE: &str = "{{ .Release.Namespace }}";
+const IMAGE: &str =
+    r#""{{ .Values.image.repository }}:{{ .Values.image.tag | default .Chart.AppVersion }}""#;
+
+const WEBHOOK_MANIFESTS: &str = include_str!("../deploy/webhook-manifests.yaml");
+const MONITORING: &str = include_str!("../k8s/monitoring.yaml");
+
+const VALUES: &str = r#"# Controller image; the tag defaults to the chart appVersion
+image:
+  repository: ghcr.io/jyasuu/kubernetes-resource-app
+  tag: ""
+  pullPolicy: IfNotPresent
+
+replicas: 1
+logLevel: info
+
+resources:
+  requests:
+    cpu: 100m
+    memory: 128Mi
+  limits:
+    cpu: 500m
+    memory: 512Mi
+
+# Comma-separated namespaces to watch; empty watches the whole cluster
+watchNamespace: ""
+
+# Optional behaviours, passed to the controller as environment variables,
+# e.g. MYAPP_METRICS_AUTH: tokenreview
+features: {}
+
+rbac:
+  create: true
+
+webhook:
+  enabled: false
+  # Use a cert-manager Certificate and CA injection instead of a mounted Secret
+  certManager: false
+
+serviceMonitor:
+  enabled: false
+"#;
+
+const DEPLOYMENT: &str = r#"apiVersion: apps/v1
+kind: Deployment
+metadata:
+  name: myapp-controller
+  namespace: {{ .Release.Namespace }}
+  labels:
+    app.kubernetes.io/name: myapp-controller
+    app.kubernetes.io/component: controller
+    app.kubernetes.io/version: {{ .Chart.AppVersion | quote }}
+spec:
+  replicas: {{ .Values.replicas }}
+  selector:
+    matchLabels:
+      app.kubernetes.io/name: myapp-controller
+      app.kubernetes.io/component: controller
+  template:
+    metadata:
+      labels:
+        app.kubernetes.io/name: myapp-controller
+        app.kubernetes.io/component: controller
+    spec:
+      serviceAccountName: myapp-controller
+      securityContext:
+        runAsNonRoot: true
+        runAsUser: 1000
+        fsGroup: 1000
+      containers:
+      - name: controller
+        image: IMAGE
+        imagePullPolicy: {{ .Values.image.pullPolicy }}
+        ports:
+        - name: metrics
+          containerPort: 8080
+        - name: health
+          containerPort: 8081
+        env:
+        - name: RUST_LOG
+          value: {{ .Values.logLevel | quote }}
+        {{- with .Values.watchNamespace }}
+        - name: WATCH_NAMESPACE
+          value: {{ . | quote }}
+        {{- end }}
+        {{- range $name, $value := .Values.features }}
+        - name: {{ $name }}
+          value: {{ $value | quote }}
+        {{- end }}
+        resources:
+          {{- toYaml .Values.resources | nindent 10 }}
+        livenessProbe:
+          httpGet:
+            path: /health
+            port: health
+        readinessProbe:
+          httpGet:
+            path: /ready
+            port: health
+        securityContext:
+          allowPrivilegeEscalation: false
+          capabilities:
+            drop:
+            - ALL
+          readOnlyRootFilesystem: true
+---
+apiVersion: v1
+kind: Service
+metadata:
+  name: myapp-controller-metrics
+  namespace: {{ .Release.Namespace }}
+  labels:
+    app.kubernetes.io/name: myapp-controller
+    app.kubernetes.io/component: controller
+spec:
+  ports:
+  - name: metrics
+    port: 8080
+    targetPort: metrics
+  selector:
+    app.kubernetes.io/name: myapp-controller
+    app.kubernetes.io/component: controller
+"#;
+
+fn chart_yaml() -> String {
+    format!(
+        "apiVersion: v2\nname: myapp-controller\ndescription: Operator for MyApp resources\ntype: application\nversion: {version}\nappVersion: \"{version}\"\n",
+        version = env!("CARGO_PKG_VERSION")
+    )
+}
+
+fn to_yaml_stream<T: serde::Serialize>(docs: &[T]) -> Result<String, serde_yaml::Error> {
+    docs.iter()
+        .map(|doc| Ok(format!("---\n{}", serde_yaml::to_string(doc)?)))
+        .collect()
+}
+
+/// Documents of a checked-in manifest, in the release namespace and using the chart image
+fn release_docs(manifest: &str, keep: impl Fn(&str) -> bool) -> String {
+    install::rehome_docs(manifest, RELEASE_NAMESPACE, keep).replace(
+        "image: myapp-controller:latest",
+        &format!("image: {}", IMAGE),
+    )
+}
+
+fn webhooks_template() -> Result<String, serde_yaml::Error> {
+    let objects = WebhookObjects {
+        namespace: RELEASE_NAMESPACE.to_string(),
+        ..WebhookObjects::from_env()
+    };
+    let configurations = |cert_manager: bool| {
+        to_yaml_stream(&[
+            serde_yaml::to_value(webhooks::validating_configuration(&objects, cert_manager))?,
+            serde_yaml::to_value(webhooks::mutating_configuration(&objects, cert_manager))?,
+        ])
+    };
+    let server = release_docs(WEBHOOK_MANIFESTS, |doc| {
+        !doc.contains("kind: ValidatingWebhookConfiguration")
+            && !doc.contains("kind: MutatingWebhookConfiguration")
+    })
+    .replace(
+        r#""webhook"]"#,
+        r#""webhook"{{ if .Values.webhook.certManager }}, "--cert-source=cert-manager"{{ end }}]"#,
+    );
+
+    Ok(format!(
+        "{{{{- if .Values.webhook.enabled }}}}\n{{{{- if .Values.webhook.certManager }}}}\n{}{{{{- else }}}}\n{}{{{{- end }}}}\n{}{{{{- end }}}}\n",
+        configurations(true)?,
+        configurations(false)?,
+        server
+    ))
+}
+
+/// Every file of the chart, relative to the chart directory
+pub fn chart() -> Result<Vec<Manifest>, serde_yaml::Error> {
+    let rbac = rbac::manifests(RELEASE_NAMESPACE, &WatchScope::All)?;
+    let service_monitor = release_docs(MONITORING, |doc| doc.contains("kind: ServiceMonitor"));
+
+    Ok(vec![
+        Manifest {
+            file_name: "Chart.yaml",
+            yaml: chart_yaml(),
+        },
+        Manifest {
+            file_name: "values.yaml",
+            yaml: VALUES.to_string(),
+        },
+        Manifest {
+            file_name: "crds/myapps.example.com.yaml",
+            yaml: install::crd()?.yaml,
+        },
+        Manifest {
+            file_name: "templates/deployment.yaml",
+            yaml: DEPLOYMENT.replace("IMAGE", IMAGE),
+        },
+        Manifest {
+            file_name: "templates/rbac.yaml",
+            yaml: format!(
+                "{{{{- if .Values.rbac.create }}}}\n{}{{{{- end }}}}\n",
+                to_yaml_stream(&rbac)?
+            ),
+        },
+        Manifest {
+            file_name: "templates/webhooks.yaml",
+            yaml: webhooks_template()?,
+        },
+        Manifest {
+            file_name: "templates/servicemonitor.yaml",
+            yaml: format!(
+                "{{{{- if .Values.serviceMonitor.enabled }}}}\n{}{{{{- end }}}}\n",
+                service_monitor
+            ),
+        },
+    ])
+}
+
+/// `generate-helm [--out-dir <dir>]`
+pub fn run(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let mut dir = PathBuf::from("myapp-controller");
+
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--out-dir" => dir = iter.next().ok_or("--out-dir needs a value")?.into(),
+            other => return Err(format!("unknown generate-helm argument: {}", other).into()),
+        }
+    }
+
+    install::write(&chart()?, &Output::Dir(dir))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chart_layout() {
+        let files = chart().unwrap();
+        let names: Vec<&str> = files.iter().map(|f| f.file_name).collect();
+        assert!(names.contains(&"Chart.yaml"));
+        assert!(names.contains(&"crds/myapps.example.com.yaml"));
+
+        let values: serde_yaml::Value = serde_yaml::from_str(VALUES).unwrap();
+        assert_eq!(values["webhook"]["enabled"], false);
+        assert!(values["features"].is_mapping());
+    }
+
+    #[test]
+    fn test_webhook_template_is_release_namespaced() {
+        let template = webhooks_template().unwrap();
+        assert!(!template.contains("namespace: default"));
+        assert!(template.contains("kind: Deployment"));
+        assert!(template.contains(".Values.image.repository"));
+        assert_eq!(
+            template
+                .matches("kind: ValidatingWebhookConfiguration")
+                .count(),
+            2
+        );
+    }
+}
@@ -0,0 +1,316 @@
+// Deploy hooks module for MyApp Controller
+// Runs pre-deploy and post-deploy Jobs (Helm-hook style) around the Deployment rollout.
+// A hook runs once per pod template: the Job name carries a hash of it, so edits that leave
+// the pods alone (replicas, labels) do not run the hook again
+
+use crate::spec_hash::fnv1a;
+use crate::workload::{job_run_status, LastRunStatus};
+use crate::{build_pod_spec, child_name, create_owner_reference, Condition, MyApp};
+use k8s_openapi::api::apps::v1::Deployment;
+use k8s_openapi::api::batch::v1::{Job, JobSpec};
+use k8s_openapi::api::core::v1::{PodSpec, PodTemplateSpec};
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta;
+use kube::api::{Api, DeleteParams, ListParams, PostParams};
+use kube::{Client, ResourceExt};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// Jobs run around the Deployment rollout
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct HooksConfig {
+    /// Job that must succeed before the Deployment is rolled out (e.g. migrations)
+    #[serde(default)]
+    pub pre_deploy: Option<HookTemplate>,
+
+    /// Job run once the Deployment is available (e.g. smoke tests)
+    #[serde(default)]
+    pub post_deploy: Option<HookTemplate>,
+}
+
+/// Template for a hook Job
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct HookTemplate {
+    /// Image to run (defaults to the app image)
+    #[serde(default)]
+    pub image: Option<String>,
+
+    /// Command to run in the hook container
+    #[serde(default)]
+    pub command: Vec<String>,
+
+    /// Arguments passed to the command
+    #[serde(default)]
+    pub args: Vec<String>,
+
+    /// Number of retries before the hook is marked failed
+    #[serde(default)]
+    pub backoff_limit: Option<i32>,
+
+    /// Maximum hook run time in seconds
+    #[serde(default)]
+    pub active_deadline_seconds: Option<i64>,
+}
+
+/// Seconds a finished hook Job is kept for inspection; its result stays in status.hooks
+pub const HOOK_TTL_SECONDS: i32 = 3600;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HookPhase {
+    PreDeploy,
+    PostDeploy,
+}
+
+impl HookPhase {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            HookPhase::PreDeploy => "preDeploy",
+            HookPhase::PostDeploy => "postDeploy",
+        }
+    }
+
+    fn suffix(&self) -> &'static str {
+        match self {
+            HookPhase::PreDeploy => "pre-deploy",
+            HookPhase::PostDeploy => "post-deploy",
+        }
+    }
+}
+
+/// Labels of a MyApp's hook Jobs for one phase
+fn hook_labels(myapp: &MyApp, phase: HookPhase) -> std::collections::BTreeMap<String, String> {
+    let mut labels = crate::app_labels(myapp);
+    labels.insert(
+        "myapps.example.com/hook".to_string(),
+        phase.suffix().to_string(),
+    );
+
+    // The hook pods must not match the Deployment selector
+    labels.remove("app");
+    labels.insert("myapps.example.com/app".to_string(), myapp.name_any());
+    labels
+}
+
+/// Hook Jobs are named after a hash of their pod spec, so only changes to what the hook
+/// pod runs start a new hook Job
+pub fn hook_job_name(myapp: &MyApp, phase: HookPhase, pod_spec: &PodSpec) -> String {
+    let hash = fnv1a(&serde_json::to_vec(pod_spec).unwrap_or_default());
+    child_name(myapp, &format!("{}-{:08x}", phase.suffix(), hash as u32))
+}
+
+pub fn build_hook_job(myapp: &MyApp, phase: HookPhase, hook: &HookTemplate) -> Job {
+    let labels = hook_labels(myapp, phase);
+
+    let base = build_pod_spec(myapp);
+    let mut container = base.containers[0].clone();
+    container.name = "hook".to_string();
+    if let Some(image) = &hook.image {
+        container.image = Some(image.clone());
+    }
+    if !hook.command.is_empty() {
+        container.command = Some(hook.command.clone());
+    }
+    if !hook.args.is_empty() {
+        container.args = Some(hook.args.clone());
+    }
+
+    let pod_spec = PodSpec {
+        containers: vec![container],
+        restart_policy: Some("Never".to_string()),
+        ..base
+    };
+
+    Job {
+        metadata: ObjectMeta {
+            name: Some(hook_job_name(myapp, phase, &pod_spec)),
+            namespace: myapp.namespace(),
+            labels: Some(labels.clone()),
+            owner_references: Some(vec![create_owner_reference(myapp)]),
+            ..Default::default()
+        },
+        spec: Some(JobSpec {
+            backoff_limit: hook.backoff_limit,
+            active_deadline_seconds: hook.active_deadline_seconds,
+            ttl_seconds_after_finished: Some(HOOK_TTL_SECONDS),
+            template: PodTemplateSpec {
+                metadata: Some(ObjectMeta {
+                    labels: Some(labels),
+                    annotations: crate::security_profiles::template_annotations(myapp, "hook"),
+                    ..Default::default()
+                }),
+                spec: Some(pod_spec),
+            },
+            ..Default::default()
+        }),
+        ..Default::default()
+    }
+}
+
+/// Ensure the hook Job for the current pod spec has run, deleting Jobs of earlier ones.
+/// `previous` is the phase's run from status.hooks, which outlives the Job's TTL
+pub async fn run_hook(
+    myapp: &MyApp,
+    phase: HookPhase,
+    hook: &HookTemplate,
+    previous: Option<&LastRunStatus>,
+    client: Client,
+) -> Result<LastRunStatus, kube::Error> {
+    let api: Api<Job> = Api::namespaced(client, &myapp.namespace().unwrap());
+    let desired = build_hook_job(myapp, phase, hook);
+    let name = desired.name_any();
+
+    let run = match api.get_opt(&name).await? {
+        Some(job) => job_run_status(&job),
+        // Finished and removed after its TTL
+        None if previous.is_some_and(|run| {
+            run.job_name.as_deref() == Some(name.as_str()) && !run.is_active()
+        }) =>
+        {
+            previous.cloned().unwrap()
+        }
+        None => {
+            let job = api.create(&PostParams::default(), &desired).await?;
+            tracing::info!("Created {} hook job {}", phase.as_str(), name);
+            job_run_status(&job)
+        }
+    };
+
+    // Jobs of superseded pod specs
+    let selector = hook_labels(myapp, phase)
+        .iter()
+        .map(|(k, v)| format!("{}={}", k, v))
+        .collect::<Vec<_>>()
+        .join(",");
+    for job in api.list(&ListParams::default().labels(&selector)).await? {
+        if job.name_any() != name && job.metadata.deletion_timestamp.is_none() {
+            api.delete(&job.name_any(), &DeleteParams::background())
+                .await?;
+            tracing::info!(
+                "Deleted superseded {} hook job {}",
+                phase.as_str(),
+                job.name_any()
+            );
+        }
+    }
+
+    Ok(run)
+}
+
+/// Whether the Deployment has rolled out and all desired replicas are available
+pub fn deployment_available(deployment: &Deployment) -> bool {
+    let desired = deployment
+        .spec
+        .as_ref()
+        .and_then(|s| s.replicas)
+        .unwrap_or(1);
+    let status = deployment.status.clone().unwrap_or_default();
+
+    status.observed_generation >= deployment.metadata.generation
+        && status.updated_replicas.unwrap_or(0) >= desired
+        && status.available_replicas.unwrap_or(0) >= desired
+}
+
+/// Degraded condition for a failed hook run
+pub fn degraded_condition(phase: HookPhase, run: &LastRunStatus) -> Option<Condition> {
+    (run.result == "Failed").then(|| {
+        let reason = match phase {
+            HookPhase::PreDeploy => "PreDeployHookFailed",
+            HookPhase::PostDeploy => "PostDeployHookFailed",
+        };
+        Condition::new(
+            "Degraded",
+            true,
+            reason,
+            &format!(
+                "{} hook job {} failed",
+                phase.as_str(),
+                run.job_name.as_deref().unwrap_or_default()
+            ),
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MyAppSpec;
+
+    #[test]
+    fn test_hook_job_does_not_match_deployment_selector() {
+        let mut myapp = MyApp::new(
+            "web",
+            MyAppSpec {
+                replicas: 2,
+                image: "nginx:1.25".to_string(),
+                ..Default::default()
+            },
+        );
+        myapp.metadata.namespace = Some("default".to_string());
+        myapp.metadata.generation = Some(4);
+        myapp.metadata.uid = Some("uid".to_string());
+
+        let hook = HookTemplate {
+            command: vec!["./migrate".to_string()],
+            ..Default::default()
+        };
+        let job = build_hook_job(&myapp, HookPhase::PreDeploy, &hook);
+
+        let name = job.metadata.name.clone().unwrap();
+        assert!(name.starts_with("web-pre-deploy-"));
+        let labels = job.metadata.labels.unwrap();
+        assert!(!labels.contains_key("app"));
+
+        let spec = job.spec.unwrap();
+        assert_eq!(spec.ttl_seconds_after_finished, Some(HOOK_TTL_SECONDS));
+        let pod = spec.template.spec.unwrap();
+        assert_eq!(pod.containers[0].image.as_deref(), Some("nginx:1.25"));
+        assert_eq!(
+            pod.containers[0].command,
+            Some(vec!["./migrate".to_string()])
+        );
+    }
+
+    #[test]
+    fn test_hook_runs_once_per_pod_spec() {
+        let mut myapp = MyApp::new(
+            "web",
+            MyAppSpec {
+                replicas: 2,
+                image: "nginx:1.25".to_string(),
+                ..Default::default()
+            },
+        );
+        myapp.metadata.namespace = Some("default".to_string());
+        myapp.metadata.uid = Some("uid".to_string());
+        let hook = HookTemplate {
+            command: vec!["./migrate".to_string()],
+            ..Default::default()
+        };
+        let name = |myapp: &MyApp| build_hook_job(myapp, HookPhase::PreDeploy, &hook).name_any();
+        let first = name(&myapp);
+
+        // Scaling and relabelling leave the hook pods alone
+        myapp.metadata.generation = Some(2);
+        myapp.spec.replicas = 5;
+        assert_eq!(name(&myapp), first);
+
+        myapp.spec.image = "nginx:1.26".to_string();
+        assert_ne!(name(&myapp), first);
+    }
+
+    #[test]
+    fn test_degraded_condition_only_on_failure() {
+        let mut run = LastRunStatus {
+            job_name: Some("web-post-deploy-1".to_string()),
+            start_time: None,
+            completion_time: None,
+            result: "Succeeded".to_string(),
+        };
+        assert!(degraded_condition(HookPhase::PostDeploy, &run).is_none());
+
+        run.result = "Failed".to_string();
+        let condition = degraded_condition(HookPhase::PostDeploy, &run).unwrap();
+        assert_eq!(condition.reason, "PostDeployHookFailed");
+    }
+}
@@ -0,0 +1,240 @@
+// Image freshness policy for MyApp Controller
+// Flags apps running image tags that are no longer allowed or have reached end-of-life
+
+use crate::metrics::MetricsCollector;
+use crate::retry;
+use crate::scope::WatchScope;
+use crate::{Condition, MyApp};
+use kube::api::{Api, ListParams, Patch, PatchParams};
+use kube::{Client, ResourceExt};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+pub const OUTDATED_IMAGE: &str = "OutdatedImage";
+
+/// Tag policy per image repository, loaded from `MYAPP_IMAGE_POLICY_FILE`
+///
+/// ```yaml
+/// images:
+///   nginx:
+///     allowedTags: ["1.25", "1.26"]
+///     endOfLife: ["1.21", "1.22"]
+/// ```
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ImagePolicy {
+    #[serde(default)]
+    pub images: BTreeMap<String, TagPolicy>,
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct TagPolicy {
+    /// Tag prefixes that are considered current (empty allows any tag)
+    #[serde(default)]
+    pub allowed_tags: Vec<String>,
+
+    /// Tag prefixes that have reached end-of-life
+    #[serde(default)]
+    pub end_of_life: Vec<String>,
+}
+
+/// Split "registry/repo:tag" into repository and tag
+pub fn split_image(image: &str) -> (&str, &str) {
+    // Pinned images keep their tag before the digest
+    let image = image.split_once('@').map_or(image, |(name, _)| name);
+    let name_start = image.rfind('/').map(|i| i + 1).unwrap_or(0);
+    match image[name_start..].rfind(':') {
+        Some(i) => (&image[..name_start + i], &image[name_start + i + 1..]),
+        None => (image, "latest"),
+    }
+}
+
+fn tag_matches(tag: &str, prefix: &str) -> bool {
+    tag == prefix
+        || tag
+            .strip_prefix(prefix)
+            .is_some_and(|rest| rest.starts_with(['.', '-']))
+}
+
+impl ImagePolicy {
+    pub fn from_env() -> Result<Self, Box<dyn std::error::Error>> {
+        match std::env::var("MYAPP_IMAGE_POLICY_FILE") {
+            Ok(path) => Ok(serde_yaml::from_str(&std::fs::read_to_string(path)?)?),
+            Err(_) => Ok(Self::default()),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        !self.images.is_empty()
+    }
+
+    /// Return (reason, message) if the image violates the policy
+    pub fn evaluate(&self, image: &str) -> Option<(&'static str, String)> {
+        let (repo, tag) = split_image(image);
+        let policy = self.images.get(repo)?;
+
+        if policy.end_of_life.iter().any(|p| tag_matches(tag, p)) {
+            return Some((
+                "EndOfLife",
+                format!("image tag {} of {} has reached end-of-life", tag, repo),
+            ));
+        }
+
+        if !policy.allowed_tags.is_empty()
+            && !policy.allowed_tags.iter().any(|p| tag_matches(tag, p))
+        {
+            return Some((
+                "TagNotAllowed",
+                format!(
+                    "image tag {} of {} is not in the allowed list ({})",
+                    tag,
+                    repo,
+                    policy.allowed_tags.join(", ")
+                ),
+            ));
+        }
+
+        None
+    }
+
+    /// OutdatedImage condition for the app's image
+    pub fn condition(&self, image: &str) -> Condition {
+        match self.evaluate(image) {
+            Some((reason, message)) => Condition::new(OUTDATED_IMAGE, true, reason, &message),
+            None => Condition::new(
+                OUTDATED_IMAGE,
+                false,
+                "ImageCurrent",
+                "Image tag is current",
+            ),
+        }
+    }
+}
+
+/// Periodically re-evaluate the MyApps this replica `owns` so policy changes surface without a
+/// spec change
+pub async fn run_background_check(
+    client: Client,
+    scope: WatchScope,
+    params: ListParams,
+    owns: impl Fn(&MyApp) -> bool,
+    policy: ImagePolicy,
+    metrics: MetricsCollector,
+    interval: Duration,
+) {
+    let apis = scope.apis::<MyApp>(client.clone());
+
+    loop {
+        for api in &apis {
+            match api.list(&params).await {
+                Ok(list) => {
+                    for myapp in list.items.iter().filter(|m| owns(m)) {
+                        if let Err(e) = check_app(myapp, &policy, &metrics, client.clone()).await {
+                            tracing::warn!(
+                                "Image policy check failed for {}/{}: {}",
+                                myapp.namespace().unwrap_or_default(),
+                                myapp.name_any(),
+                                e
+                            );
+                        }
+                    }
+                }
+                Err(e) => tracing::warn!("Image policy check could not list MyApps: {}", e),
+            }
+        }
+
+        tokio::time::sleep(interval).await;
+    }
+}
+
+async fn check_app(
+    myapp: &MyApp,
+    policy: &ImagePolicy,
+    metrics: &MetricsCollector,
+    client: Client,
+) -> Result<(), kube::Error> {
+    let ns = myapp.namespace().unwrap_or_default();
+    let name = myapp.name_any();
+    let condition = policy.condition(&myapp.spec.image);
+    metrics.set_outdated_image(&ns, &name, &myapp.spec.image, condition.status == "True");
+
+    let api: Api<MyApp> = Api::namespaced(client, &ns);
+    let mut current = myapp.clone();
+
+    let mut attempt = 0;
+    loop {
+        attempt += 1;
+        // Only patch when the condition actually changed
+        let mut conditions = current
+            .status
+            .as_ref()
+            .map(|s| s.conditions.clone())
+            .unwrap_or_default();
+        let existing = conditions.iter().find(|c| c.r#type == OUTDATED_IMAGE);
+        if existing.is_some_and(|c| c.status == condition.status && c.reason == condition.reason) {
+            return Ok(());
+        }
+
+        conditions.retain(|c| c.r#type != OUTDATED_IMAGE);
+        conditions.push(condition.clone());
+
+        // resourceVersion makes the write fail instead of dropping conditions written meanwhile
+        let patch = serde_json::json!({
+            "metadata": { "resourceVersion": current.resource_version() },
+            "status": { "conditions": conditions }
+        });
+        match api
+            .patch_status(&name, &PatchParams::default(), &Patch::Merge(&patch))
+            .await
+        {
+            Ok(_) => {
+                metrics.set_conditions(&ns, &name, &conditions);
+                return Ok(());
+            }
+            Err(e) if retry::retry_conflict(&e, attempt, "status", &name, metrics) => {
+                current = api.get_status(&name).await?;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy() -> ImagePolicy {
+        serde_yaml::from_str(
+            r#"
+images:
+  nginx:
+    allowedTags: ["1.25", "1.26"]
+    endOfLife: ["1.21"]
+"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_split_image() {
+        assert_eq!(split_image("nginx:1.25.3"), ("nginx", "1.25.3"));
+        assert_eq!(
+            split_image("localhost:5000/team/app:v2"),
+            ("localhost:5000/team/app", "v2")
+        );
+        assert_eq!(split_image("nginx"), ("nginx", "latest"));
+    }
+
+    #[test]
+    fn test_evaluate() {
+        let policy = policy();
+
+        assert!(policy.evaluate("nginx:1.25.3").is_none());
+        assert_eq!(policy.evaluate("nginx:1.21.0").unwrap().0, "EndOfLife");
+        assert_eq!(policy.evaluate("nginx:1.23").unwrap().0, "TagNotAllowed");
+        assert_eq!(policy.evaluate("nginx:1.250").unwrap().0, "TagNotAllowed");
+        assert!(policy.evaluate("redis:7").is_none());
+    }
+}
@@ -0,0 +1,320 @@
+// Image update tracking for MyApp Controller
+// MyApps with spec.imagePolicy are checked every MYAPP_IMAGE_UPDATE_INTERVAL_SECS: the
+// registry's tags are filtered by the policy and the newest one compared with the running
+// tag. Report mode records an UpdateAvailable condition; apply mode rewrites spec.image so the
+// next reconcile rolls the Deployment. Each newly found version is announced with an event.
+
+use crate::arch::{ImageRef, RegistryClient};
+use crate::image_policy::split_image;
+use crate::metrics::MetricsCollector;
+use crate::shard::Shard;
+use crate::shared_store::SharedStores;
+use crate::{publish_event, status, Condition, MyApp};
+use kube::api::{Api, Patch, PatchParams};
+use kube::runtime::events::EventType;
+use kube::{Client, ResourceExt};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::time::Duration;
+
+pub const UPDATE_CONDITION: &str = "UpdateAvailable";
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, JsonSchema, Default)]
+pub enum UpdateStrategy {
+    /// Newest patch of the running major.minor (default)
+    #[default]
+    LatestPatch,
+    /// Newest minor or patch of the running major
+    LatestMinor,
+    /// Newest version satisfying `range`
+    Range,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, JsonSchema, Default)]
+pub enum UpdateMode {
+    /// Only record the UpdateAvailable condition (default)
+    #[default]
+    Report,
+    /// Rewrite spec.image to the newer tag
+    Apply,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ImageUpdatePolicy {
+    #[serde(default)]
+    pub strategy: UpdateStrategy,
+
+    /// Comparators for the Range strategy, e.g. ">=1.4.0 <2.0.0"
+    #[serde(default)]
+    pub range: Option<String>,
+
+    #[serde(default)]
+    pub mode: UpdateMode,
+}
+
+/// A `major.minor.patch` tag, optionally prefixed with `v`; pre-releases are not versions
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Version(u64, u64, u64);
+
+impl Version {
+    pub fn parse(tag: &str) -> Option<Self> {
+        let mut parts = tag.strip_prefix('v').unwrap_or(tag).split('.');
+        let mut next = || parts.next()?.parse().ok();
+        let version = Version(next()?, next()?, next()?);
+        parts.next().is_none().then_some(version)
+    }
+}
+
+/// One `<op><version>` term of a range
+fn comparator(term: &str) -> Result<(&'static [Ordering], Version), String> {
+    let (ops, rest): (&'static [Ordering], &str) = if let Some(v) = term.strip_prefix(">=") {
+        (&[Ordering::Greater, Ordering::Equal], v)
+    } else if let Some(v) = term.strip_prefix("<=") {
+        (&[Ordering::Less, Ordering::Equal], v)
+    } else if let Some(v) = term.strip_prefix('>') {
+        (&[Ordering::Greater], v)
+    } else if let Some(v) = term.strip_prefix('<') {
+        (&[Ordering::Less], v)
+    } else {
+        (&[Ordering::Equal], term.strip_prefix('=').unwrap_or(term))
+    };
+    let version = Version::parse(rest).ok_or_else(|| format!("invalid version in '{}'", term))?;
+    Ok((ops, version))
+}
+
+fn parse_range(range: &str) -> Result<Vec<(&'static [Ordering], Version)>, String> {
+    range
+        .split([' ', ','])
+        .filter(|t| !t.is_empty())
+        .map(comparator)
+        .collect()
+}
+
+impl ImageUpdatePolicy {
+    pub fn validate(&self) -> Result<(), String> {
+        match (self.strategy, &self.range) {
+            (UpdateStrategy::Range, None) => {
+                Err("imagePolicy.range is required for the Range strategy".to_string())
+            }
+            (UpdateStrategy::Range, Some(range)) => parse_range(range)
+                .and_then(|terms| match terms.is_empty() {
+                    true => Err("empty range".to_string()),
+                    false => Ok(()),
+                })
+                .map_err(|e| format!("imagePolicy.range: {}", e)),
+            (_, Some(_)) => Err("imagePolicy.range only applies to the Range strategy".to_string()),
+            _ => Ok(()),
+        }
+    }
+
+    fn allows(&self, current: Version, candidate: Version) -> bool {
+        match self.strategy {
+            UpdateStrategy::LatestPatch => (candidate.0, candidate.1) == (current.0, current.1),
+            UpdateStrategy::LatestMinor => candidate.0 == current.0,
+            UpdateStrategy::Range => self
+                .range
+                .as_deref()
+                .and_then(|r| parse_range(r).ok())
+                .is_some_and(|terms| terms.iter().all(|(ops, v)| ops.contains(&candidate.cmp(v)))),
+        }
+    }
+
+    /// Newest tag the policy allows that is newer than `current`
+    pub fn newer<'a>(&self, current: &str, tags: &'a [String]) -> Option<&'a str> {
+        let running = Version::parse(current)?;
+        tags.iter()
+            .filter_map(|tag| Some((Version::parse(tag)?, tag.as_str())))
+            .filter(|(v, _)| *v > running && self.allows(running, *v))
+            .max_by_key(|(v, _)| *v)
+            .map(|(_, tag)| tag)
+    }
+}
+
+/// `MYAPP_IMAGE_UPDATE_INTERVAL_SECS` (default 3600, 0 disables)
+pub fn interval_from_env() -> Option<Duration> {
+    let secs = std::env::var("MYAPP_IMAGE_UPDATE_INTERVAL_SECS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(3600);
+    (secs > 0).then(|| Duration::from_secs(secs))
+}
+
+fn current_condition(myapp: &MyApp) -> Option<&Condition> {
+    myapp
+        .status
+        .as_ref()
+        .and_then(|s| s.conditions.iter().find(|c| c.r#type == UPDATE_CONDITION))
+}
+
+async fn check(
+    myapp: &MyApp,
+    policy: &ImageUpdatePolicy,
+    registry: &RegistryClient,
+    client: Client,
+    metrics: &MetricsCollector,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let ns = myapp.namespace().unwrap_or_default();
+    let (repo, tag) = split_image(&myapp.spec.image);
+    let tags = registry
+        .list_tags(&ImageRef::parse(&myapp.spec.image))
+        .await?;
+    let newer = policy.newer(tag, &tags);
+    let api: Api<MyApp> = Api::namespaced(client.clone(), &ns);
+
+    let condition = match newer {
+        Some(newer) => {
+            let message = format!("{}:{} is available (running {})", repo, newer, tag);
+            let announced = current_condition(myapp).is_some_and(|c| c.message == message);
+            if !announced {
+                metrics.record_image_update(&ns, policy.mode == UpdateMode::Apply);
+                publish_event(
+                    myapp,
+                    client.clone(),
+                    EventType::Normal,
+                    UPDATE_CONDITION,
+                    "CheckImage",
+                    message.clone(),
+                )
+                .await;
+            }
+            if policy.mode == UpdateMode::Apply {
+                // Digest-pinned images are re-pinned by the mutating webhook on update
+                let image = format!("{}:{}", repo, newer);
+                let patch = serde_json::json!({ "spec": { "image": image } });
+                api.patch(
+                    &myapp.name_any(),
+                    &PatchParams::default(),
+                    &Patch::Merge(&patch),
+                )
+                .await?;
+                tracing::info!("Updated MyApp {}/{} to {}", ns, myapp.name_any(), image);
+                Condition::new(UPDATE_CONDITION, false, "Updated", &message)
+            } else {
+                Condition::new(UPDATE_CONDITION, true, "NewerTag", &message)
+            }
+        }
+        None => Condition::new(
+            UPDATE_CONDITION,
+            false,
+            "UpToDate",
+            &format!("{} is the newest tag the policy allows", tag),
+        ),
+    };
+
+    let unchanged = current_condition(myapp).is_some_and(|c| {
+        c.status == condition.status
+            && c.reason == condition.reason
+            && c.message == condition.message
+    });
+    if !unchanged {
+        let mut new_status = myapp.status.clone().unwrap_or_default();
+        new_status
+            .conditions
+            .retain(|c| c.r#type != UPDATE_CONDITION);
+        new_status.conditions.push(condition);
+        status::patch_status(&api, myapp, &new_status, metrics).await?;
+    }
+    Ok(())
+}
+
+/// Check every cached MyApp with an image policy each `interval`
+pub async fn run_sweep(
+    client: Client,
+    stores: SharedStores,
+    shard: Shard,
+    metrics: MetricsCollector,
+    interval: Duration,
+) {
+    let registry = RegistryClient::new();
+    let mut ticker = tokio::time::interval(interval);
+    ticker.tick().await;
+    loop {
+        ticker.tick().await;
+        let Some(myapps) = stores.myapps(None) else {
+            continue;
+        };
+        for myapp in myapps.iter().filter(|m| shard.owns_myapp(m)) {
+            let Some(policy) = &myapp.spec.image_policy else {
+                continue;
+            };
+            if myapp.is_paused() || myapp.metadata.deletion_timestamp.is_some() {
+                continue;
+            }
+            if let Err(e) = check(myapp, policy, &registry, client.clone(), &metrics).await {
+                tracing::warn!(
+                    "Image update check of MyApp {}/{} failed: {}",
+                    myapp.namespace().unwrap_or_default(),
+                    myapp.name_any(),
+                    e
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tags(list: &[&str]) -> Vec<String> {
+        list.iter().map(|t| t.to_string()).collect()
+    }
+
+    #[test]
+    fn test_newer_follows_strategy() {
+        let tags = tags(&[
+            "1.24.0",
+            "1.25.3",
+            "1.25.4",
+            "1.26.1",
+            "2.0.0",
+            "1.25.5-rc1",
+            "latest",
+        ]);
+        let policy = |strategy, range: Option<&str>| ImageUpdatePolicy {
+            strategy,
+            range: range.map(str::to_string),
+            ..Default::default()
+        };
+        assert_eq!(
+            policy(UpdateStrategy::LatestPatch, None).newer("1.25.3", &tags),
+            Some("1.25.4")
+        );
+        assert_eq!(
+            policy(UpdateStrategy::LatestMinor, None).newer("1.25.3", &tags),
+            Some("1.26.1")
+        );
+        assert_eq!(
+            policy(UpdateStrategy::Range, Some(">=1.25.0 <2.0.0")).newer("1.24.0", &tags),
+            Some("1.26.1")
+        );
+        assert_eq!(
+            policy(UpdateStrategy::LatestPatch, None).newer("latest", &tags),
+            None
+        );
+        assert_eq!(
+            policy(UpdateStrategy::LatestPatch, None).newer("1.25.4", &tags),
+            None
+        );
+    }
+
+    #[test]
+    fn test_validate() {
+        let range = |range: &str| ImageUpdatePolicy {
+            strategy: UpdateStrategy::Range,
+            range: Some(range.to_string()),
+            ..Default::default()
+        };
+        assert!(range(">=1.2.0, <2.0.0").validate().is_ok());
+        assert!(range(">=1.2").validate().is_err());
+        assert!(ImageUpdatePolicy {
+            strategy: UpdateStrategy::Range,
+            ..Default::default()
+        }
+        .validate()
+        .is_err());
+        assert_eq!(Version::parse("v1.2.3"), Some(Version(1, 2, 3)));
+    }
+}
@@ -0,0 +1,103 @@
+// Immutable MyApp fields
+// Changing these after creation would strand the generated children (a Deployment left
+// behind by a switch to CronJob, a Service that cannot change its primary IP family), so
+// the validating webhook rejects UPDATEs that touch them
+
+use crate::MyAppSpec;
+use kube::core::admission::AdmissionResponse;
+use kube::core::response::{StatusCause, StatusDetails};
+
+/// Reason reported for each offending field, as the API server does for immutable fields
+const FIELD_IMMUTABLE: &str = "FieldValueForbidden";
+
+fn cause(field: &str, old: impl std::fmt::Debug, new: impl std::fmt::Debug) -> StatusCause {
+    StatusCause {
+        reason: FIELD_IMMUTABLE.to_string(),
+        message: format!("field is immutable (was {:?}, now {:?})", old, new),
+        field: field.to_string(),
+    }
+}
+
+/// One cause per immutable field that differs between `old` and `new`
+pub fn check(old: &MyAppSpec, new: &MyAppSpec) -> Vec<StatusCause> {
+    let mut causes = Vec::new();
+    if old.workload_type != new.workload_type {
+        causes.push(cause(
+            "spec.workloadType",
+            old.workload_type,
+            new.workload_type,
+        ));
+    }
+
+    // The primary IP family of a Service is fixed; adding a secondary family is allowed
+    let primary = |spec: &MyAppSpec| {
+        spec.service
+            .as_ref()
+            .and_then(|s| s.ip_families.first().cloned())
+    };
+    if let (Some(old_family), new_family) = (primary(old), primary(new)) {
+        if new_family.as_ref() != Some(&old_family) {
+            causes.push(cause("spec.service.ipFamilies[0]", old_family, new_family));
+        }
+    }
+    causes
+}
+
+/// Deny `res` listing every offending field in the status details
+pub fn deny(res: AdmissionResponse, causes: Vec<StatusCause>) -> AdmissionResponse {
+    let fields: Vec<&str> = causes.iter().map(|c| c.field.as_str()).collect();
+    let mut res = res.deny(format!("immutable fields changed: {}", fields.join(", ")));
+    res.result.reason = "Invalid".to_string();
+    res.result.code = 422;
+    res.result.details = Some(StatusDetails {
+        name: String::new(),
+        group: String::new(),
+        kind: "MyApp".to_string(),
+        uid: String::new(),
+        causes,
+        retry_after_seconds: 0,
+    });
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::network::ServiceConfig;
+    use crate::workload::WorkloadType;
+
+    #[test]
+    fn test_check() {
+        let old = MyAppSpec {
+            service: Some(ServiceConfig {
+                ip_families: vec!["IPv4".to_string()],
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        assert!(check(&old, &old).is_empty());
+
+        // Adding a secondary family keeps the primary
+        let mut dual = old.clone();
+        dual.service
+            .as_mut()
+            .unwrap()
+            .ip_families
+            .push("IPv6".to_string());
+        assert!(check(&old, &dual).is_empty());
+
+        let mut new = old.clone();
+        new.workload_type = WorkloadType::CronJob;
+        new.service = None;
+        let fields: Vec<String> = check(&old, &new).into_iter().map(|c| c.field).collect();
+        assert_eq!(
+            fields,
+            vec!["spec.workloadType", "spec.service.ipFamilies[0]"]
+        );
+
+        let res = deny(AdmissionResponse::invalid(""), check(&old, &new));
+        assert!(!res.allowed);
+        assert!(res.result.message.contains("spec.workloadType"));
+        assert_eq!(res.result.details.unwrap().causes.len(), 2);
+    }
+}
@@ -0,0 +1,184 @@
+// Install manifests for MyApp Controller
+// `generate-crd` writes the CRD, or a full install bundle, to stdout, a file or a directory
+
+use crate::certs::WebhookObjects;
+use crate::operator_config::MyAppOperatorConfig;
+use crate::scope::WatchScope;
+use crate::{cel, rbac, webhooks};
+use kube::CustomResourceExt;
+use std::path::PathBuf;
+
+const DEPLOYMENT: &str = include_str!("../k8s/deployment.yaml");
+
+/// Where the generated manifests go
+#[derive(Debug, Clone, PartialEq)]
+pub enum Output {
+    Stdout,
+    /// All documents concatenated into one file
+    File(PathBuf),
+    /// One file per manifest
+    Dir(PathBuf),
+}
+
+/// One generated manifest file, possibly holding several documents
+#[derive(Debug, Clone)]
+pub struct Manifest {
+    pub file_name: &'static str,
+    pub yaml: String,
+}
+
+/// The MyApp CRD followed by the MyAppOperatorConfig CRD
+pub fn crd() -> Result<Manifest, serde_yaml::Error> {
+    Ok(Manifest {
+        file_name: "crd.yaml",
+        yaml: format!(
+            "{}---\n{}",
+            serde_yaml::to_string(&cel::crd())?,
+            serde_yaml::to_string(&MyAppOperatorConfig::crd())?
+        ),
+    })
+}
+
+/// CRD, controller RBAC and Deployment, and webhook configurations for `objects.namespace`
+pub fn bundle(
+    objects: &WebhookObjects,
+    cert_manager: bool,
+) -> Result<Vec<Manifest>, serde_yaml::Error> {
+    let webhooks = format!(
+        "{}---\n{}",
+        serde_yaml::to_string(&webhooks::validating_configuration(objects, cert_manager))?,
+        serde_yaml::to_string(&webhooks::mutating_configuration(objects, cert_manager))?
+    );
+    let rbac = rbac::manifests(&objects.namespace, &WatchScope::All)?;
+
+    Ok(vec![
+        crd()?,
+        Manifest {
+            file_name: "rbac.yaml",
+            yaml: rbac
+                .iter()
+                .map(|doc| Ok(format!("---\n{}", serde_yaml::to_string(doc)?)))
+                .collect::<Result<_, serde_yaml::Error>>()?,
+        },
+        Manifest {
+            file_name: "deployment.yaml",
+            yaml: DEPLOYMENT.replace("${NAMESPACE}", &objects.namespace),
+        },
+        Manifest {
+            file_name: "webhooks.yaml",
+            yaml: webhooks,
+        },
+    ])
+}
+
+/// Join manifests into a single multi-document stream
+pub fn concat(manifests: &[Manifest]) -> String {
+    manifests
+        .iter()
+        .map(|m| format!("---\n{}\n", m.yaml.trim_start_matches("---\n").trim_end()))
+        .collect()
+}
+
+/// Documents of a checked-in multi-document manifest, moved into `namespace`
+pub fn rehome_docs(manifest: &str, namespace: &str, keep: impl Fn(&str) -> bool) -> String {
+    let target = format!("namespace: {}", namespace);
+    manifest
+        .split("\n---\n")
+        .filter(|doc| keep(doc))
+        .map(|doc| {
+            let doc = doc
+                .trim_start_matches("---\n")
+                .replace("namespace: default", &target)
+                .replace("namespace: ${NAMESPACE}", &target);
+            format!("---\n{}\n", doc.trim_end())
+        })
+        .collect()
+}
+
+pub fn write(manifests: &[Manifest], output: &Output) -> std::io::Result<()> {
+    match output {
+        Output::Stdout => print!("{}", concat(manifests)),
+        Output::File(path) => {
+            std::fs::write(path, concat(manifests))?;
+            eprintln!("Manifests written to {}", path.display());
+        }
+        Output::Dir(dir) => {
+            for manifest in manifests {
+                let path = dir.join(manifest.file_name);
+                std::fs::create_dir_all(path.parent().unwrap_or(dir))?;
+                std::fs::write(&path, &manifest.yaml)?;
+                eprintln!("{} written", path.display());
+            }
+        }
+    }
+    Ok(())
+}
+
+/// `generate-crd [--output <file>|-] [--out-dir <dir>] [--bundle [--namespace <ns>] [--cert-manager]]`
+pub fn run(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let mut output = Output::Dir(PathBuf::from("."));
+    let mut include_bundle = false;
+    let mut objects = WebhookObjects::from_env();
+    let mut cert_manager = false;
+
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "-o" | "--output" => {
+                output = match iter.next().map(String::as_str) {
+                    Some("-") => Output::Stdout,
+                    Some(path) => Output::File(PathBuf::from(path)),
+                    None => return Err("--output needs a value".into()),
+                }
+            }
+            "--out-dir" => {
+                output = Output::Dir(iter.next().ok_or("--out-dir needs a value")?.into());
+            }
+            "--bundle" => include_bundle = true,
+            "--namespace" => {
+                objects.namespace = iter.next().cloned().ok_or("--namespace needs a value")?;
+            }
+            "--cert-manager" => cert_manager = true,
+            other => return Err(format!("unknown generate-crd argument: {}", other).into()),
+        }
+    }
+
+    let manifests = if include_bundle {
+        bundle(&objects, cert_manager)?
+    } else {
+        vec![crd()?]
+    };
+    write(&manifests, &output)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+
+    #[test]
+    fn test_bundle_substitutes_namespace() {
+        let objects = WebhookObjects {
+            namespace: "myapp-system".to_string(),
+            ..WebhookObjects::from_env()
+        };
+        let manifests = bundle(&objects, false).unwrap();
+        let stream = concat(&manifests);
+        assert!(!stream.contains("${NAMESPACE}"));
+
+        let kinds: Vec<String> = serde_yaml::Deserializer::from_str(&stream)
+            .map(serde_yaml::Value::deserialize)
+            .filter_map(|doc| doc.ok()?["kind"].as_str().map(str::to_string))
+            .collect();
+        for kind in [
+            "CustomResourceDefinition",
+            "ClusterRole",
+            "Deployment",
+            "ValidatingWebhookConfiguration",
+            "MutatingWebhookConfiguration",
+        ] {
+            assert!(kinds.iter().any(|k| k == kind), "missing {}", kind);
+        }
+    }
+}
@@ -0,0 +1,144 @@
+// kubectl plugin entry point for MyApp Controller
+// Invoked as `kubectl-myapp` (a copy or symlink of the controller binary on PATH), the
+// binary runs the client-side subcommands with kubectl's connection flags
+
+use kube::config::{KubeConfigOptions, Kubeconfig};
+use kube::{Client, Config};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+pub const PLUGIN_NAME: &str = "kubectl-myapp";
+
+const USAGE: &str = "Usage: kubectl myapp <command> [flags]
+
+Commands:
+  status     Summarize state, readiness and last error of MyApps
+  plan       Show what reconcile would change for a MyApp
+  export     Dump a MyApp and the objects it owns as YAML
+  validate   Check MyApp manifests offline
+  doctor     Check the operator installation and suggest fixes
+
+Connection flags: --kubeconfig, --context, --cluster, --user (KUBECONFIG is honored)
+";
+
+/// kubectl connection flags given to the plugin
+#[derive(Clone, Default)]
+pub struct ConnectionFlags {
+    pub kubeconfig: Option<PathBuf>,
+    pub options: KubeConfigOptions,
+}
+
+static FLAGS: OnceLock<ConnectionFlags> = OnceLock::new();
+
+/// Whether the binary was started under the plugin name
+pub fn invoked_as_plugin(argv0: &str) -> bool {
+    Path::new(argv0)
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .is_some_and(|name| name == PLUGIN_NAME)
+}
+
+/// Split kubectl's connection flags (`--context x` or `--context=x`) from the rest
+pub fn take_flags(args: &[String]) -> Result<(ConnectionFlags, Vec<String>), String> {
+    let mut flags = ConnectionFlags::default();
+    let mut rest = Vec::new();
+
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        let (key, inline) = match arg.split_once('=') {
+            Some((key, value)) => (key, Some(value.to_string())),
+            None => (arg.as_str(), None),
+        };
+        if !matches!(key, "--kubeconfig" | "--context" | "--cluster" | "--user") {
+            rest.push(arg.clone());
+            continue;
+        }
+
+        let value = match inline {
+            Some(value) => value,
+            None => iter
+                .next()
+                .cloned()
+                .ok_or_else(|| format!("{} needs a value", key))?,
+        };
+        match key {
+            "--kubeconfig" => flags.kubeconfig = Some(value.into()),
+            "--context" => flags.options.context = Some(value),
+            "--cluster" => flags.options.cluster = Some(value),
+            _ => flags.options.user = Some(value),
+        }
+    }
+
+    Ok((flags, rest))
+}
+
+/// Client for the CLI subcommands: the plugin's connection flags if given, else the
+/// usual in-cluster config, KUBECONFIG or ~/.kube/config
+pub async fn client() -> Result<Client, Box<dyn std::error::Error>> {
+    let Some(flags) = FLAGS.get() else {
+        return Ok(Client::try_default().await?);
+    };
+
+    let config = match &flags.kubeconfig {
+        Some(path) => {
+            Config::from_custom_kubeconfig(Kubeconfig::read_from(path)?, &flags.options).await?
+        }
+        None => Config::from_kubeconfig(&flags.options).await?,
+    };
+    Ok(Client::try_from(config)?)
+}
+
+/// `kubectl myapp <command> ...`
+pub async fn run(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let (flags, rest) = take_flags(args)?;
+    let _ = FLAGS.set(flags);
+
+    let Some((command, args)) = rest.split_first() else {
+        print!("{}", USAGE);
+        return Ok(());
+    };
+    match command.as_str() {
+        "status" => crate::summary::run(args).await,
+        "plan" => crate::plan::run(args).await,
+        "export" => crate::export::run(args).await,
+        "validate" => crate::offline::run(args),
+        "doctor" => crate::doctor::run(args).await,
+        "help" | "-h" | "--help" => {
+            print!("{}", USAGE);
+            Ok(())
+        }
+        other => Err(format!("unknown command {:?}\n\n{}", other, USAGE).into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_invoked_as_plugin() {
+        assert!(invoked_as_plugin("/usr/local/bin/kubectl-myapp"));
+        assert!(invoked_as_plugin("kubectl-myapp.exe"));
+        assert!(!invoked_as_plugin("/app/myapp-controller"));
+    }
+
+    #[test]
+    fn test_take_flags() {
+        let args: Vec<String> = [
+            "--context=staging",
+            "status",
+            "--kubeconfig",
+            "/tmp/kc",
+            "-n",
+            "shop",
+        ]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+
+        let (flags, rest) = take_flags(&args).unwrap();
+        assert_eq!(flags.options.context.as_deref(), Some("staging"));
+        assert_eq!(flags.kubeconfig, Some(PathBuf::from("/tmp/kc")));
+        assert_eq!(rest, vec!["status", "-n", "shop"]);
+    }
+}
@@ -0,0 +1,372 @@
+// Kustomize layout for MyApp Controller
+// `generate-kustomize` writes a base and example overlays built from the same
+// definitions as `generate-crd`, `generate-rbac` and `generate-webhooks`
+
+use crate::certs::WebhookObjects;
+use crate::install::{self, Manifest, Output};
+use crate::rbac::{self, Rule, RULES};
+use crate::scope::WatchScope;
+use crate::webhooks;
+use serde::Deserialize;
+use std::path::PathBuf;
+
+const DEPLOYMENT: &str = include_str!("../k8s/deployment.yaml");
+const WEBHOOK_MANIFESTS: &str = include_str!("../deploy/webhook-manifests.yaml");
+
+const BASE: &str = "apiVersion: kustomize.config.k8s.io/v1beta1
+kind: Kustomization
+namespace: ${NAMESPACE}
+resources:
+- crd.yaml
+- rbac.yaml
+- deployment.yaml
+images:
+- name: myapp-controller
+  newName: ghcr.io/jyasuu/kubernetes-resource-app
+  newTag: ${VERSION}
+";
+
+const NAMESPACE_SCOPED: &str =
+    "# Watch only the controller's namespace; namespaced grants move to a Role
+apiVersion: kustomize.config.k8s.io/v1beta1
+kind: Kustomization
+resources:
+- ../../base
+- role.yaml
+patches:
+- path: cluster-role.yaml
+- target:
+    kind: Deployment
+    name: myapp-controller
+  patch: |-
+    - op: add
+      path: /spec/template/spec/containers/0/env/-
+      value:
+        name: WATCH_NAMESPACE
+        value: ${NAMESPACE}
+";
+
+const WEBHOOK: &str = "# Admission webhooks and the webhook server, with a self-issued certificate
+apiVersion: kustomize.config.k8s.io/v1beta1
+kind: Kustomization
+resources:
+- ../../base
+- webhook.yaml
+patches:
+- target:
+    kind: Deployment
+    name: myapp-webhook
+  patch: |-
+    - op: replace
+      path: /spec/template/spec/containers/0/env/2/value
+      value: \"true\"
+    # The Secret is created by the webhook itself on first start
+    - op: add
+      path: /spec/template/spec/volumes/0/secret/optional
+      value: true
+";
+
+/// Controller replicas in the HA overlay, each reconciling one shard of the MyApps
+const HA_SHARDS: u64 = 3;
+
+const HA: &str =
+    "# Webhook server spread over three nodes, and the controller split into three shards
+# instead of using leader election: a StatefulSet with MYAPP_SHARDS=3 replaces the base
+# Deployment and each pod reconciles the MyApps hashing to its ordinal. Change replicas and
+# MYAPP_SHARDS together. Both get a disruption budget.
+apiVersion: kustomize.config.k8s.io/v1beta1
+kind: Kustomization
+resources:
+- ../webhook
+- controller.yaml
+- pdb.yaml
+patches:
+- target:
+    kind: Deployment
+    name: myapp-controller
+  patch: |-
+    $patch: delete
+    apiVersion: apps/v1
+    kind: Deployment
+    metadata:
+      name: myapp-controller
+- target:
+    kind: Deployment
+    name: myapp-webhook
+  patch: |-
+    - op: replace
+      path: /spec/replicas
+      value: 3
+    - op: add
+      path: /spec/template/spec/topologySpreadConstraints
+      value:
+      - maxSkew: 1
+        topologyKey: kubernetes.io/hostname
+        whenUnsatisfiable: ScheduleAnyway
+        labelSelector:
+          matchLabels:
+            app: myapp-webhook
+";
+
+const PDB: &str = "apiVersion: policy/v1
+kind: PodDisruptionBudget
+metadata:
+  name: myapp-webhook
+  namespace: ${NAMESPACE}
+spec:
+  minAvailable: 2
+  selector:
+    matchLabels:
+      app: myapp-webhook
+---
+# One shard at a time may be down; its MyApps wait for it
+apiVersion: policy/v1
+kind: PodDisruptionBudget
+metadata:
+  name: myapp-controller
+  namespace: ${NAMESPACE}
+spec:
+  maxUnavailable: 1
+  selector:
+    matchLabels:
+      app.kubernetes.io/name: myapp-controller
+      app.kubernetes.io/component: controller
+";
+
+/// Headless Service giving the controller StatefulSet pods their stable names
+const SHARDS_SERVICE: &str = "apiVersion: v1
+kind: Service
+metadata:
+  name: myapp-controller
+  namespace: ${NAMESPACE}
+spec:
+  clusterIP: None
+  selector:
+    app.kubernetes.io/name: myapp-controller
+    app.kubernetes.io/component: controller
+";
+
+fn yaml_stream(docs: &[serde_yaml::Value]) -> Result<String, serde_yaml::Error> {
+    docs.iter()
+        .map(|doc| Ok(format!("---\n{}", serde_yaml::to_string(doc)?)))
+        .collect()
+}
+
+/// Controller Deployment and metrics Service with the deploy-script placeholders filled in
+fn deployment(namespace: &str) -> String {
+    install::rehome_docs(DEPLOYMENT, namespace, |_| true)
+        .replace(
+            "ghcr.io/${GITHUB_REPOSITORY}:${IMAGE_TAG}",
+            "myapp-controller",
+        )
+        .replace("${IMAGE_TAG}", env!("CARGO_PKG_VERSION"))
+        .replace("${REPLICAS}", "1")
+}
+
+/// The base controller Deployment as a StatefulSet of `shards` replicas, one shard each;
+/// the shard index is the pod's ordinal
+fn sharded_controller(namespace: &str, shards: u64) -> Result<String, serde_yaml::Error> {
+    let deployment = deployment(namespace);
+    let Some(doc) = serde_yaml::Deserializer::from_str(&deployment).next() else {
+        return Ok(String::new());
+    };
+    let mut controller = serde_yaml::Value::deserialize(doc)?;
+    controller["kind"] = "StatefulSet".into();
+    controller["spec"]["replicas"] = shards.into();
+    controller["spec"]["serviceName"] = "myapp-controller".into();
+    // All shards start together; none waits for a lower ordinal to become ready
+    controller["spec"]["podManagementPolicy"] = "Parallel".into();
+    if let Some(env) =
+        controller["spec"]["template"]["spec"]["containers"][0]["env"].as_sequence_mut()
+    {
+        env.push(serde_yaml::to_value(serde_json::json!({
+            "name": "MYAPP_SHARDS",
+            "value": shards.to_string(),
+        }))?);
+    }
+    let service: serde_yaml::Value =
+        serde_yaml::from_str(&SHARDS_SERVICE.replace("${NAMESPACE}", namespace))?;
+    yaml_stream(&[controller, service])
+}
+
+/// Strategic merge patch leaving only cluster-scoped rules in the ClusterRole
+fn cluster_role_patch() -> Result<String, serde_yaml::Error> {
+    let rules: Vec<serde_yaml::Value> = RULES
+        .iter()
+        .filter(|r| r.cluster_scoped)
+        .map(|r: &Rule| {
+            serde_yaml::to_value(serde_json::json!({
+                "apiGroups": [r.api_group],
+                "resources": r.resources,
+                "verbs": r.verbs,
+            }))
+        })
+        .collect::<Result<_, _>>()?;
+
+    serde_yaml::to_string(&serde_json::json!({
+        "apiVersion": "rbac.authorization.k8s.io/v1",
+        "kind": "ClusterRole",
+        "metadata": { "name": rbac::NAME },
+        "rules": rules,
+    }))
+}
+
+fn webhook(objects: &WebhookObjects) -> Result<String, serde_yaml::Error> {
+    let configurations = yaml_stream(&[
+        serde_yaml::to_value(webhooks::validating_configuration(objects, false))?,
+        serde_yaml::to_value(webhooks::mutating_configuration(objects, false))?,
+    ])?;
+    let server = install::rehome_docs(WEBHOOK_MANIFESTS, &objects.namespace, |doc| {
+        !doc.contains("kind: ValidatingWebhookConfiguration")
+            && !doc.contains("kind: MutatingWebhookConfiguration")
+    });
+    Ok(configurations + &server)
+}
+
+/// Every file of the layout, relative to the output directory
+pub fn layout(namespace: &str) -> Result<Vec<Manifest>, serde_yaml::Error> {
+    let objects = WebhookObjects {
+        namespace: namespace.to_string(),
+        ..WebhookObjects::from_env()
+    };
+    let own_namespace = WatchScope::Namespaces(vec![namespace.to_string()]);
+    let role: Vec<serde_yaml::Value> = rbac::manifests(namespace, &own_namespace)?
+        .into_iter()
+        .filter(|doc| doc["kind"] == "Role" || doc["kind"] == "RoleBinding")
+        .collect();
+    let fill = |template: &str| {
+        template
+            .replace("${NAMESPACE}", namespace)
+            .replace("${VERSION}", env!("CARGO_PKG_VERSION"))
+    };
+
+    Ok(vec![
+        Manifest {
+            file_name: "base/kustomization.yaml",
+            yaml: fill(BASE),
+        },
+        Manifest {
+            file_name: "base/crd.yaml",
+            yaml: install::crd()?.yaml,
+        },
+        Manifest {
+            file_name: "base/rbac.yaml",
+            yaml: yaml_stream(&rbac::manifests(namespace, &WatchScope::All)?)?,
+        },
+        Manifest {
+            file_name: "base/deployment.yaml",
+            yaml: deployment(namespace),
+        },
+        Manifest {
+            file_name: "overlays/namespace-scoped/kustomization.yaml",
+            yaml: fill(NAMESPACE_SCOPED),
+        },
+        Manifest {
+            file_name: "overlays/namespace-scoped/role.yaml",
+            yaml: yaml_stream(&role)?,
+        },
+        Manifest {
+            file_name: "overlays/namespace-scoped/cluster-role.yaml",
+            yaml: cluster_role_patch()?,
+        },
+        Manifest {
+            file_name: "overlays/webhook/kustomization.yaml",
+            yaml: WEBHOOK.to_string(),
+        },
+        Manifest {
+            file_name: "overlays/webhook/webhook.yaml",
+            yaml: webhook(&objects)?,
+        },
+        Manifest {
+            file_name: "overlays/ha/kustomization.yaml",
+            yaml: HA.to_string(),
+        },
+        Manifest {
+            file_name: "overlays/ha/controller.yaml",
+            yaml: sharded_controller(namespace, HA_SHARDS)?,
+        },
+        Manifest {
+            file_name: "overlays/ha/pdb.yaml",
+            yaml: fill(PDB),
+        },
+    ])
+}
+
+/// `generate-kustomize [--out-dir <dir>] [--namespace <ns>]`
+pub fn run(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let mut dir = PathBuf::from("kustomize");
+    let mut namespace = "myapp-system".to_string();
+
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--out-dir" => dir = iter.next().ok_or("--out-dir needs a value")?.into(),
+            "--namespace" => {
+                namespace = iter.next().cloned().ok_or("--namespace needs a value")?;
+            }
+            other => return Err(format!("unknown generate-kustomize argument: {}", other).into()),
+        }
+    }
+
+    install::write(&layout(&namespace)?, &Output::Dir(dir))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_base_has_no_placeholders() {
+        let files = layout("myapp-system").unwrap();
+        let base = files
+            .iter()
+            .find(|f| f.file_name == "base/deployment.yaml")
+            .unwrap();
+        assert!(!base.yaml.contains("${"));
+        assert!(base.yaml.contains("namespace: myapp-system"));
+
+        let scoped = files
+            .iter()
+            .find(|f| f.file_name == "overlays/namespace-scoped/kustomization.yaml")
+            .unwrap();
+        assert!(scoped
+            .yaml
+            .contains("name: WATCH_NAMESPACE\n        value: myapp-system"));
+    }
+
+    #[test]
+    fn test_ha_controller_is_sharded() {
+        let docs = sharded_controller("myapp-system", 3).unwrap();
+        let docs: Vec<serde_yaml::Value> = serde_yaml::Deserializer::from_str(&docs)
+            .map(|doc| serde_yaml::Value::deserialize(doc).unwrap())
+            .collect();
+        let controller = &docs[0];
+        assert_eq!(controller["kind"], "StatefulSet");
+        assert_eq!(controller["spec"]["replicas"], 3);
+        assert_eq!(
+            controller["spec"]["serviceName"],
+            docs[1]["metadata"]["name"]
+        );
+        assert_eq!(docs[1]["spec"]["clusterIP"], "None");
+        let env = controller["spec"]["template"]["spec"]["containers"][0]["env"]
+            .as_sequence()
+            .unwrap();
+        let shards = env.iter().find(|e| e["name"] == "MYAPP_SHARDS").unwrap();
+        assert_eq!(shards["value"], "3");
+        assert!(HA.contains("name: myapp-controller"));
+    }
+
+    /// The webhook overlay patches MYAPP_WEBHOOK_CERT_BOOTSTRAP by index
+    #[test]
+    fn test_webhook_bootstrap_env_index() {
+        let docs = webhook(&WebhookObjects::from_env()).unwrap();
+        let deployment: serde_yaml::Value = docs
+            .split("\n---\n")
+            .filter_map(|doc| serde_yaml::from_str(doc.trim_start_matches("---\n")).ok())
+            .find(|doc: &serde_yaml::Value| doc["kind"] == "Deployment")
+            .unwrap();
+        let env = &deployment["spec"]["template"]["spec"]["containers"][0]["env"][2];
+        assert_eq!(env["name"], "MYAPP_WEBHOOK_CERT_BOOTSTRAP");
+    }
+}
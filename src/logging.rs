@@ -0,0 +1,104 @@
+// Log level control for MyApp Controller
+// Installs the tracing subscriber (with OpenTelemetry export when configured) and lets the
+// admin server swap its filter at runtime
+
+use std::sync::{Arc, Mutex};
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{reload, EnvFilter, Layer, Registry};
+use warp::http::StatusCode;
+use warp::{Filter, Reply};
+
+/// Handle to the active log filter
+#[derive(Clone)]
+pub struct LogLevel {
+    handle: reload::Handle<EnvFilter, Registry>,
+    current: Arc<Mutex<String>>,
+}
+
+/// Parse filter directives such as `info,kube_runtime=debug`
+pub fn parse_filter(directives: &str) -> Result<EnvFilter, String> {
+    let directives = directives.trim();
+    if directives.is_empty() {
+        return Err("empty log filter".to_string());
+    }
+    EnvFilter::try_new(directives).map_err(|e| e.to_string())
+}
+
+impl LogLevel {
+    /// Install the global subscriber, starting from `RUST_LOG` (default `info`)
+    pub fn init() -> Self {
+        let initial = std::env::var("RUST_LOG").unwrap_or_else(|_| "info".to_string());
+        let filter = parse_filter(&initial).unwrap_or_else(|e| {
+            eprintln!("Ignoring invalid RUST_LOG '{}': {}", initial, e);
+            EnvFilter::new("info")
+        });
+        // The filter only applies to log output so tokio-console still sees every task
+        let (filter, handle) = reload::Layer::new(filter);
+        let registry = tracing_subscriber::registry()
+            .with(tracing_subscriber::fmt::layer().with_filter(filter))
+            .with(crate::telemetry::layer());
+        #[cfg(feature = "console")]
+        let registry = registry.with(console_subscriber::spawn());
+        registry.init();
+
+        Self {
+            handle,
+            current: Arc::new(Mutex::new(initial)),
+        }
+    }
+
+    pub fn current(&self) -> String {
+        self.current.lock().unwrap().clone()
+    }
+
+    pub fn set(&self, directives: &str) -> Result<(), String> {
+        let filter = parse_filter(directives)?;
+        self.handle.reload(filter).map_err(|e| e.to_string())?;
+        *self.current.lock().unwrap() = directives.trim().to_string();
+        tracing::info!("Log filter changed to '{}'", directives.trim());
+        Ok(())
+    }
+}
+
+/// `GET /debug/loglevel` shows the filter, `PUT /debug/loglevel` replaces it with the request body
+pub fn loglevel_handler(
+    level: LogLevel,
+) -> impl Filter<Extract = (impl Reply,), Error = warp::Rejection> + Clone {
+    let path = warp::path!("debug" / "loglevel");
+    let get = {
+        let level = level.clone();
+        path.and(warp::get())
+            .map(move || warp::reply::json(&serde_json::json!({ "filter": level.current() })))
+    };
+    let put = path
+        .and(warp::put())
+        .and(warp::body::content_length_limit(4096))
+        .and(warp::body::bytes())
+        .map(move |body: warp::hyper::body::Bytes| {
+            let directives = String::from_utf8_lossy(&body).to_string();
+            match level.set(&directives) {
+                Ok(()) => warp::reply::with_status(
+                    warp::reply::json(&serde_json::json!({ "filter": level.current() })),
+                    StatusCode::OK,
+                ),
+                Err(e) => warp::reply::with_status(
+                    warp::reply::json(&serde_json::json!({ "error": e })),
+                    StatusCode::BAD_REQUEST,
+                ),
+            }
+        });
+    get.or(put)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_filter() {
+        assert!(parse_filter("info,kube_runtime=debug").is_ok());
+        assert!(parse_filter("  ").is_err());
+        assert!(parse_filter("kube=loud").is_err());
+    }
+}
@@ -1,18 +1,156 @@
 use futures_util::StreamExt;
-use json_patch::{AddOperation, Patch as JsonPatch, PatchOperation};
-use kube::{CustomResource, CustomResourceExt, Resource};
+use json_patch::{AddOperation, Patch as JsonPatch, PatchOperation, ReplaceOperation};
+use kube::{CustomResource, Resource};
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use std::collections::BTreeMap;
-
+use std::collections::{BTreeMap, BTreeSet};
+
+mod adoption;
+mod analysis;
+mod app_rbac;
+mod arch;
+mod audit;
+mod backoff;
+mod bluegreen;
+mod cache;
+mod canary;
+mod cel;
+mod cert_manager;
+mod certs;
+mod coalesce;
+mod cosign;
+mod dashboard;
+mod deletion;
+mod dev;
+#[cfg(feature = "console")]
+mod diagnostics;
+mod digest;
+mod dns;
+mod doctor;
+mod drift;
+mod dry_run;
+mod env;
+mod exemplars;
+mod export;
+mod external_secrets;
+mod failure;
+mod features;
+mod hardening;
+mod helm;
+mod hooks;
+mod image_policy;
+mod image_update;
+mod immutable;
+mod install;
+mod kubectl;
+mod kustomize;
+mod logging;
 mod metrics;
+mod metrics_auth;
+mod migrate;
+mod monitoring;
+mod namespace_rate;
+mod network;
+mod network_policy;
+mod offline;
+mod operator_config;
+mod plan;
+mod pressure;
+mod priority;
+mod pss;
+mod pull_secrets;
+mod qos;
+mod quantity;
+mod rbac;
+mod readiness;
+mod report;
+mod retry;
+mod rollout;
+mod sa_token;
+mod scan;
 mod scheduling;
-
-use metrics::{health_handler, metrics_handler, ready_handler, MetricsCollector};
-use scheduling::SchedulingConfig;
+mod scope;
+mod secrets;
+mod security_profiles;
+mod server;
+mod shard;
+mod shared_store;
+mod shutdown;
+mod spec_hash;
+mod status;
+mod strategy;
+mod summary;
+mod telemetry;
+mod throttle;
+mod tls;
+mod uniqueness;
+mod vault;
+mod warnings;
+mod watchdog;
+mod webhooks;
+mod workload;
+
+use app_rbac::AppRbac;
+use arch::{ArchDetector, RegistryClient};
+use audit::AuditLog;
+use backoff::ErrorBackoff;
+use bluegreen::{BlueGreenPhase, BlueGreenStatus};
+use cache::ClusterCache;
+use canary::{CanaryPhase, CanaryStatus};
+use cert_manager::CertManager;
+use certs::CertBootstrap;
+use coalesce::Coalescer;
+use cosign::{ImageVerification, Verifier};
+use deletion::{DeletionPolicy, DeletionPropagation};
+use dev::DevMode;
+use digest::DigestResolver;
+use dns::{DnsConfig, HostAlias};
+use env::{EnvFromConfig, PodField};
+use external_secrets::ExternalSecretConfig;
+use failure::FailureStatus;
+use features::{Feature, Features};
+use hardening::ContainerSecurity;
+use hooks::{HookPhase, HooksConfig};
+use image_policy::ImagePolicy;
+use image_update::ImageUpdatePolicy;
+use logging::LogLevel;
+use metrics::{
+    health_handler, metrics_handler, ready_handler, Cardinality, MetricsCollector, MetricsConfig,
+    ReconcileTimer,
+};
+use metrics_auth::MetricsAuth;
+use monitoring::{MonitoringConfig, MonitoringCrds};
+use namespace_rate::NamespaceRateLimiter;
+use network::ServiceConfig;
+use network_policy::NetworkPolicyConfig;
+use operator_config::{ConfigStatus, OperatorConfig};
+use pressure::ReconcileLimiter;
+use priority::PriorityLimiter;
+use pss::PssChecker;
+use pull_secrets::PullSecretChecker;
+use qos::RatioPolicy;
+use readiness::Readiness;
+use rollout::Rollout;
+use sa_token::ServiceAccountTokenConfig;
+use scan::{ScanGate, Verdict};
+use scheduling::{AdvancedScheduler, SchedulingConfig, TopologySpreadConfig};
+use scope::{NamespaceFilter, WatchScope};
+use secrets::GeneratedSecret;
+use security_profiles::{Profile, SecurityProfiles};
+use server::{HttpServer, ServerConfig};
+use shard::Shard;
+use shared_store::{ControllerStores, SharedStores};
+use shutdown::Shutdown;
+use strategy::{StrategyConfig, StrategyType};
+use throttle::{ApiThrottle, ClientTuning};
+use tls::{CertSource, ReloadableCert, TlsConfig};
+use uniqueness::UniquenessPolicy;
+use vault::{VaultClient, VaultSecret};
+use watchdog::{StreamProbe, Watchdog};
+use workload::{JobConfig, LastRunStatus, WorkloadType};
 
 // Define your Custom Resource with proper derive macros
-#[derive(CustomResource, Serialize, Deserialize, Debug, Clone, JsonSchema)]
+#[derive(CustomResource, Serialize, Deserialize, Debug, Clone, JsonSchema, Default)]
 #[kube(
     group = "example.com",
     version = "v1",
@@ -20,7 +158,12 @@ use scheduling::SchedulingConfig;
     namespaced,
     status = "MyAppStatus",
     shortname = "ma",
-    printcolumn = r#"{"name":"State", "type":"string", "jsonPath":".status.state"}"#,
+    category = "apps",
+    category = "all",
+    printcolumn = r#"{"name":"Phase", "type":"string", "jsonPath":".status.state"}"#,
+    printcolumn = r#"{"name":"Ready", "type":"string", "jsonPath":".status.ready"}"#,
+    printcolumn = r#"{"name":"Replicas", "type":"integer", "jsonPath":".spec.replicas", "priority":1}"#,
+    printcolumn = r#"{"name":"Image", "type":"string", "jsonPath":".spec.image", "priority":1}"#,
     printcolumn = r#"{"name":"Age", "type":"date", "jsonPath":".metadata.creationTimestamp"}"#
 )]
 #[serde(rename_all = "camelCase")]
@@ -30,31 +173,149 @@ pub struct MyAppSpec {
     pub replicas: i32,
 
     /// Image to deploy
-    #[schemars(regex(pattern = r"^[a-z0-9-./]+:[a-z0-9.-]+$"))]
+    #[schemars(regex(pattern = r"^[a-z0-9-./]+:[a-z0-9.-]+(@sha256:[a-f0-9]{64})?$"))]
     pub image: String,
 
+    /// Secrets in the MyApp's namespace holding registry credentials for the image
+    #[serde(default)]
+    pub image_pull_secrets: Vec<String>,
+
     /// Optional environment variables
     #[serde(default)]
     pub env_vars: BTreeMap<String, String>,
 
+    /// Pod metadata injected through the downward API, e.g. POD_IP: PodIp
+    #[serde(default)]
+    pub pod_env: BTreeMap<String, PodField>,
+
+    /// Existing ConfigMaps whose keys are all exposed as environment variables
+    #[serde(default)]
+    pub env_from: Option<EnvFromConfig>,
+
+    /// Secrets the controller creates with random passwords or keypairs and never regenerates
+    /// unless the MyApp's rotate-secrets annotation changes
+    #[serde(default)]
+    pub generated_secrets: Vec<GeneratedSecret>,
+
+    /// Vault paths synced into Secrets the app container reads (needs MYAPP_VAULT_ADDR)
+    #[serde(default)]
+    pub vault_secrets: Vec<VaultSecret>,
+
+    /// ExternalSecrets the External Secrets Operator turns into Secrets the app container reads
+    #[serde(default)]
+    pub external_secrets: Vec<ExternalSecretConfig>,
+
     /// Resource requirements
     #[serde(default)]
     pub resources: Option<ResourceRequirements>,
 
+    /// Service tier (e.g. dev, staging, prod)
+    #[serde(default)]
+    pub tier: Option<String>,
+
     /// Advanced scheduling configuration
     #[serde(default)]
     pub scheduling: Option<SchedulingConfig>,
+
+    /// Kind of workload to run (Deployment, Job or CronJob)
+    #[serde(default)]
+    pub workload_type: WorkloadType,
+
+    /// Job and CronJob settings
+    #[serde(default)]
+    pub job: Option<JobConfig>,
+
+    /// Pre- and post-deploy hook Jobs for Deployment workloads
+    #[serde(default)]
+    pub hooks: Option<HooksConfig>,
+
+    /// Deployment update strategy (RollingUpdate or Recreate)
+    #[serde(default)]
+    pub strategy: Option<StrategyConfig>,
+
+    /// Projected service account tokens for external identity providers
+    #[serde(default)]
+    pub service_account_token: Option<ServiceAccountTokenConfig>,
+
+    /// Stop converging child resources; status is still updated
+    #[serde(default)]
+    pub paused: bool,
+
+    /// What the finalizer does with child resources (Delete, Orphan or Retain)
+    #[serde(default)]
+    pub deletion_policy: DeletionPolicy,
+
+    /// Whether deletion waits until children and their pods are gone (Background or Foreground)
+    #[serde(default)]
+    pub deletion_propagation: DeletionPropagation,
+
+    /// Service networking (IP families for IPv6 and dual-stack)
+    #[serde(default)]
+    pub service: Option<ServiceConfig>,
+
+    /// Pod DNS policy (ClusterFirst, ClusterFirstWithHostNet, Default or None)
+    #[serde(default)]
+    pub dns_policy: Option<String>,
+
+    /// Extra resolv.conf settings for the pods
+    #[serde(default)]
+    pub dns_config: Option<DnsConfig>,
+
+    /// Static /etc/hosts entries for the pods
+    #[serde(default)]
+    pub host_aliases: Vec<HostAlias>,
+
+    /// ServiceMonitor and PrometheusRule generation (needs the Prometheus Operator CRDs)
+    #[serde(default)]
+    pub monitoring: Option<MonitoringConfig>,
+
+    /// Track newer tags of the image in its registry and report or apply them
+    #[serde(default)]
+    pub image_policy: Option<ImageUpdatePolicy>,
+
+    /// Only roll out images with a valid cosign signature
+    #[serde(default)]
+    pub image_verification: Option<ImageVerification>,
+
+    /// Seccomp and AppArmor profiles of the pods
+    #[serde(default)]
+    pub security_profiles: Option<SecurityProfiles>,
+
+    /// Security context of the app container; unset fields are hardened by the mutating webhook
+    #[serde(default)]
+    pub container_security: Option<ContainerSecurity>,
+
+    /// Default-deny NetworkPolicies with allow rules for the declared ports and peer apps
+    #[serde(default)]
+    pub network_policy: Option<NetworkPolicyConfig>,
+
+    /// API permissions of the pods, granted through a Role bound to their own ServiceAccount
+    #[serde(default)]
+    pub rbac: Option<AppRbac>,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub struct ResourceRequirements {
     pub cpu: String,
     pub memory: String,
+
+    /// Optional limits; requests are taken from cpu and memory above
+    #[serde(default)]
+    pub limits: Option<ResourceLimits>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ResourceLimits {
+    #[serde(default)]
+    pub cpu: Option<String>,
+    #[serde(default)]
+    pub memory: Option<String>,
 }
 
 // Status subresource - best practice for tracking reconciliation state
-#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema, Default)]
 #[serde(rename_all = "camelCase")]
 pub struct MyAppStatus {
     /// Current state of the application
@@ -71,6 +332,50 @@ pub struct MyAppStatus {
     /// Last update timestamp
     #[serde(default)]
     pub last_updated: Option<String>,
+
+    /// Most recent run of a Job or CronJob workload
+    #[serde(default)]
+    pub last_run: Option<LastRunStatus>,
+
+    /// Hook runs for the current generation, keyed by phase
+    #[serde(default)]
+    pub hooks: BTreeMap<String, LastRunStatus>,
+
+    /// Blue-green rollout state
+    #[serde(default)]
+    pub blue_green: Option<BlueGreenStatus>,
+
+    /// Canary rollout state
+    #[serde(default)]
+    pub canary: Option<CanaryStatus>,
+
+    /// QoS class the pods are scheduled with (Guaranteed, Burstable or BestEffort)
+    #[serde(default)]
+    pub qos_class: Option<String>,
+
+    /// Failure streak of a Job or CronJob workload with a failurePolicy
+    #[serde(default)]
+    pub failures: Option<FailureStatus>,
+
+    /// Desired replicas of the Deployment serving traffic
+    #[serde(default)]
+    pub replicas: Option<i32>,
+
+    /// Ready replicas of the Deployment serving traffic
+    #[serde(default)]
+    pub ready_replicas: Option<i32>,
+
+    /// Ready out of desired replicas, e.g. 2/3
+    #[serde(default)]
+    pub ready: Option<String>,
+
+    /// Image that last passed the vulnerability scan gate
+    #[serde(default)]
+    pub scanned_image: Option<String>,
+
+    /// Image whose cosign signature last verified under spec.imageVerification
+    #[serde(default)]
+    pub verified_image: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
@@ -85,6 +390,14 @@ pub struct Condition {
 
 // Validation methods
 impl MyApp {
+    /// Whether reconciliation of child resources is suspended
+    pub fn is_paused(&self) -> bool {
+        self.annotations()
+            .get(PAUSED_ANNOTATION)
+            .and_then(|v| v.parse::<bool>().ok())
+            .unwrap_or(self.spec.paused)
+    }
+
     /// Validate the spec before processing
     pub fn validate(&self) -> Result<(), String> {
         if self.spec.replicas < 1 || self.spec.replicas > 100 {
@@ -95,6 +408,66 @@ impl MyApp {
             return Err("image cannot be empty".to_string());
         }
 
+        workload::validate(self.spec.workload_type, self.spec.job.as_ref())?;
+        env::validate(&self.spec)?;
+        pull_secrets::validate(&self.spec.image_pull_secrets)?;
+        secrets::validate(&self.spec.generated_secrets)?;
+        let mut taken: Vec<String> = self
+            .spec
+            .generated_secrets
+            .iter()
+            .map(|s| s.name.clone())
+            .collect();
+        vault::validate(&self.spec.vault_secrets, &taken)?;
+        taken.extend(self.spec.vault_secrets.iter().map(|s| s.name.clone()));
+        external_secrets::validate(&self.spec.external_secrets, &taken)?;
+
+        if let Some(strategy) = &self.spec.strategy {
+            strategy.validate(self.spec.replicas)?;
+        }
+
+        if let Some(token) = &self.spec.service_account_token {
+            token.validate()?;
+        }
+
+        if let Some(service) = &self.spec.service {
+            service.validate()?;
+        }
+
+        if let Some(scheduling) = &self.spec.scheduling {
+            scheduling.validate()?;
+        }
+
+        if let Some(monitoring) = &self.spec.monitoring {
+            monitoring.validate()?;
+        }
+
+        if let Some(policy) = &self.spec.image_policy {
+            policy.validate()?;
+        }
+
+        if let Some(verification) = &self.spec.image_verification {
+            verification.validate()?;
+        }
+
+        if let Some(profiles) = &self.spec.security_profiles {
+            profiles.validate()?;
+        }
+
+        if let Some(policy) = &self.spec.network_policy {
+            policy.validate()?;
+        }
+
+        if let Some(rbac) = &self.spec.rbac {
+            rbac.validate()?;
+        }
+
+        dns::validate(
+            self.spec.dns_policy.as_deref(),
+            self.spec.dns_config.as_ref(),
+        )?;
+        dns::validate_host_aliases(&self.spec.host_aliases)?;
+
         Ok(())
     }
 
@@ -111,8 +484,12 @@ impl MyApp {
 // Helper to create conditions
 impl Condition {
     pub fn ready(status: bool, reason: &str, message: &str) -> Self {
+        Self::new("Ready", status, reason, message)
+    }
+
+    pub fn new(type_: &str, status: bool, reason: &str, message: &str) -> Self {
         Self {
-            r#type: "Ready".to_string(),
+            r#type: type_.to_string(),
             status: if status { "True" } else { "False" }.to_string(),
             reason: reason.to_string(),
             message: message.to_string(),
@@ -125,88 +502,60 @@ impl Condition {
 // FINALIZERS - Ensure cleanup before deletion
 // ============================================================================
 
+use k8s_openapi::api::batch::v1::{CronJob, Job};
 use kube::api::{Api, Patch, PatchParams, PostParams};
 use kube::{Client, ResourceExt};
 
 const FINALIZER: &str = "myapps.example.com/finalizer";
 
-pub async fn add_finalizer(myapp: &MyApp, client: Client) -> Result<MyApp, kube::Error> {
-    let api: Api<MyApp> = Api::namespaced(client, &myapp.namespace().unwrap());
-
-    let mut finalizers = myapp.finalizers().to_vec();
-    if !finalizers.contains(&FINALIZER.to_string()) {
-        finalizers.push(FINALIZER.to_string());
-
-        let patch = serde_json::json!({
-            "metadata": {
-                "finalizers": finalizers
-            }
-        });
-
-        api.patch(
-            &myapp.name_any(),
-            &PatchParams::default(),
-            &Patch::Merge(&patch),
-        )
-        .await
-    } else {
-        Ok(myapp.clone())
-    }
-}
-
-pub async fn remove_finalizer(myapp: &MyApp, client: Client) -> Result<MyApp, kube::Error> {
-    let api: Api<MyApp> = Api::namespaced(client, &myapp.namespace().unwrap());
-
-    let mut finalizers = myapp.finalizers().to_vec();
-    finalizers.retain(|f| f != FINALIZER);
-
-    let patch = serde_json::json!({
-        "metadata": {
-            "finalizers": finalizers
+/// Annotation that overrides spec.paused ("true" or "false")
+pub const PAUSED_ANNOTATION: &str = "myapps.example.com/paused";
+
+/// Errors from the finalizer helper, unwrapped to the reconcile error that caused them
+fn finalizer_error(
+    error: finalizer::Error<ReconcileError>,
+    metrics: &MetricsCollector,
+    namespace: &str,
+) -> ReconcileError {
+    match error {
+        finalizer::Error::ApplyFailed(e) => e,
+        finalizer::Error::CleanupFailed(e) => {
+            metrics.record_error("finalizer_cleanup_error", namespace);
+            e
         }
-    });
-
-    api.patch(
-        &myapp.name_any(),
-        &PatchParams::default(),
-        &Patch::Merge(&patch),
-    )
-    .await
+        finalizer::Error::RemoveFinalizer(e) => {
+            metrics.record_error("finalizer_removal_error", namespace);
+            ReconcileError::KubeError(e)
+        }
+        finalizer::Error::AddFinalizer(e) => ReconcileError::KubeError(e),
+        other => ReconcileError::FinalizerError(other.to_string()),
+    }
 }
 
+/// Delete or release the children per the deletion policy; returns how many are still terminating
 async fn cleanup_resources(
     myapp: &MyApp,
     client: Client,
-) -> Result<(), Box<dyn std::error::Error>> {
+) -> Result<usize, Box<dyn std::error::Error>> {
     let ns = myapp.namespace().unwrap();
-    println!(
+    tracing::info!(
         "Cleaning up resources for MyApp {}/{}",
         ns,
         myapp.name_any()
     );
 
-    // Delete owned Deployments
-    let deployments: Api<k8s_openapi::api::apps::v1::Deployment> =
-        Api::namespaced(client.clone(), &ns);
-
-    let deploy_name = format!("{}-deployment", myapp.name_any());
-    if deployments.get_opt(&deploy_name).await?.is_some() {
-        deployments
-            .delete(&deploy_name, &Default::default())
-            .await?;
-        println!("Deleted deployment: {}", deploy_name);
-    }
-
-    // Delete owned Services
-    let services: Api<k8s_openapi::api::core::v1::Service> = Api::namespaced(client.clone(), &ns);
-
-    let svc_name = format!("{}-service", myapp.name_any());
-    if services.get_opt(&svc_name).await?.is_some() {
-        services.delete(&svc_name, &Default::default()).await?;
-        println!("Deleted service: {}", svc_name);
+    match myapp.spec.deletion_policy {
+        DeletionPolicy::Orphan => {
+            deletion::orphan_children(myapp, client).await?;
+            tracing::info!("Orphaned child resources of MyApp {}", myapp.name_any());
+            return Ok(0);
+        }
+        DeletionPolicy::Retain => deletion::retain_volumes(myapp, client.clone()).await?,
+        DeletionPolicy::Delete => {}
     }
 
-    Ok(())
+    // Every child owned by the MyApp, found by owner UID rather than by name
+    Ok(deletion::delete_children(myapp, client).await?)
 }
 
 // ============================================================================
@@ -215,7 +564,8 @@ async fn cleanup_resources(
 
 use k8s_openapi::api::apps::v1::{Deployment, DeploymentSpec};
 use k8s_openapi::api::core::v1::{
-    Container, PodSpec, PodTemplateSpec, Service, ServicePort, ServiceSpec,
+    Container, LocalObjectReference, PodSecurityContext, PodSpec, PodTemplateSpec, Service,
+    ServicePort, ServiceSpec,
 };
 use k8s_openapi::apimachinery::pkg::apis::meta::v1::OwnerReference;
 use std::collections::BTreeMap as StdBTreeMap;
@@ -231,19 +581,141 @@ pub fn create_owner_reference(myapp: &MyApp) -> OwnerReference {
     }
 }
 
-pub async fn create_deployment(myapp: &MyApp, client: Client) -> Result<Deployment, kube::Error> {
-    let ns = myapp.namespace().unwrap();
-    let name = format!("{}-deployment", myapp.name_any());
-    let owner_ref = create_owner_reference(myapp);
+/// Desired and ready replicas of a Deployment
+pub fn deployment_replicas(deployment: &Deployment) -> (i32, i32) {
+    let desired = deployment
+        .spec
+        .as_ref()
+        .and_then(|s| s.replicas)
+        .unwrap_or(1);
+    let ready = deployment
+        .status
+        .as_ref()
+        .and_then(|s| s.ready_replicas)
+        .unwrap_or(0);
+    (desired, ready)
+}
 
+/// Labels shared by every workload generated for a MyApp
+pub fn app_labels(myapp: &MyApp) -> StdBTreeMap<String, String> {
     let mut labels = StdBTreeMap::new();
     labels.insert("app".to_string(), myapp.name_any());
     labels.insert("managed-by".to_string(), "myapp-controller".to_string());
+    if let Some(dev) = dev::sandbox() {
+        labels.insert(dev::SESSION_LABEL.to_string(), dev.session.clone());
+    }
+    labels
+}
+
+/// Name of a child resource, e.g. `web-deployment` (prefixed in developer mode)
+pub fn child_name(myapp: &MyApp, suffix: &str) -> String {
+    format!("{}{}-{}", dev::prefix(), myapp.name_any(), suffix)
+}
+
+/// Pod spec shared by Deployment, Job and CronJob workloads
+pub fn build_pod_spec(myapp: &MyApp) -> PodSpec {
+    let token = myapp.spec.service_account_token.as_ref();
+
+    PodSpec {
+        containers: vec![Container {
+            name: "app".to_string(),
+            image: Some(myapp.spec.image.clone()),
+            env: Some(env::container_env(&myapp.spec)),
+            env_from: env::container_env_from(myapp),
+            resources: myapp.spec.resources.as_ref().map(container_resources),
+            volume_mounts: token.map(|t| vec![t.volume_mount()]),
+            security_context: myapp
+                .spec
+                .container_security
+                .as_ref()
+                .map(ContainerSecurity::to_security_context),
+            ..Default::default()
+        }],
+        volumes: token.map(|t| vec![t.volume()]),
+        service_account_name: app_rbac::service_account_name(myapp),
+        security_context: security_profiles::seccomp_profile(myapp).map(|profile| {
+            PodSecurityContext {
+                seccomp_profile: Some(profile),
+                ..Default::default()
+            }
+        }),
+        image_pull_secrets: (!myapp.spec.image_pull_secrets.is_empty()).then(|| {
+            myapp
+                .spec
+                .image_pull_secrets
+                .iter()
+                .map(|name| LocalObjectReference { name: name.clone() })
+                .collect()
+        }),
+        dns_policy: myapp.spec.dns_policy.clone(),
+        dns_config: myapp
+            .spec
+            .dns_config
+            .as_ref()
+            .map(DnsConfig::to_pod_dns_config),
+        host_aliases: (!myapp.spec.host_aliases.is_empty()).then(|| {
+            myapp
+                .spec
+                .host_aliases
+                .iter()
+                .map(HostAlias::to_k8s)
+                .collect()
+        }),
+        affinity: myapp
+            .spec
+            .scheduling
+            .as_ref()
+            .and_then(SchedulingConfig::build_affinity),
+        topology_spread_constraints: myapp
+            .spec
+            .scheduling
+            .as_ref()
+            .filter(|s| !s.topology_spread.is_empty())
+            .map(|s| {
+                s.topology_spread
+                    .iter()
+                    .map(TopologySpreadConfig::to_constraint)
+                    .collect()
+            }),
+        ..Default::default()
+    }
+}
+
+fn container_resources(
+    resources: &ResourceRequirements,
+) -> k8s_openapi::api::core::v1::ResourceRequirements {
+    use k8s_openapi::apimachinery::pkg::api::resource::Quantity;
+
+    let requests = StdBTreeMap::from([
+        ("cpu".to_string(), Quantity(resources.cpu.clone())),
+        ("memory".to_string(), Quantity(resources.memory.clone())),
+    ]);
+    let limits = resources.limits.as_ref().map(|l| {
+        [("cpu", &l.cpu), ("memory", &l.memory)]
+            .into_iter()
+            .filter_map(|(k, v)| v.clone().map(|v| (k.to_string(), Quantity(v))))
+            .collect()
+    });
+
+    k8s_openapi::api::core::v1::ResourceRequirements {
+        requests: Some(requests),
+        limits,
+        ..Default::default()
+    }
+}
 
-    let deployment = Deployment {
+/// Deployment manifest for a MyApp with the given name and pod labels, carrying its spec hash
+pub fn build_deployment(
+    myapp: &MyApp,
+    name: &str,
+    labels: StdBTreeMap<String, String>,
+) -> Deployment {
+    let owner_ref = create_owner_reference(myapp);
+
+    spec_hash::annotate(Deployment {
         metadata: k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta {
-            name: Some(name.clone()),
-            namespace: Some(ns.clone()),
+            name: Some(name.to_string()),
+            namespace: myapp.namespace(),
             labels: Some(labels.clone()),
             owner_references: Some(vec![owner_ref]), // Set owner reference
             ..Default::default()
@@ -257,51 +729,63 @@ pub async fn create_deployment(myapp: &MyApp, client: Client) -> Result<Deployme
             template: PodTemplateSpec {
                 metadata: Some(k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta {
                     labels: Some(labels.clone()),
+                    annotations: security_profiles::template_annotations(myapp, "app"),
                     ..Default::default()
                 }),
-                spec: Some(PodSpec {
-                    containers: vec![Container {
-                        name: "app".to_string(),
-                        image: Some(myapp.spec.image.clone()),
-                        env: Some(
-                            myapp
-                                .spec
-                                .env_vars
-                                .iter()
-                                .map(|(k, v)| k8s_openapi::api::core::v1::EnvVar {
-                                    name: k.clone(),
-                                    value: Some(v.clone()),
-                                    ..Default::default()
-                                })
-                                .collect(),
-                        ),
-                        ..Default::default()
-                    }],
-                    ..Default::default()
-                }),
+                spec: Some(build_pod_spec(myapp)),
             },
+            strategy: myapp
+                .spec
+                .strategy
+                .as_ref()
+                .map(StrategyConfig::to_deployment_strategy),
             ..Default::default()
         }),
         ..Default::default()
-    };
+    })
+}
+
+pub async fn create_deployment(myapp: &MyApp, client: Client) -> Result<Deployment, kube::Error> {
+    let ns = myapp.namespace().unwrap();
+    let name = child_name(myapp, "deployment");
+    let deployment = build_deployment(myapp, &name, app_labels(myapp));
 
     let api: Api<Deployment> = Api::namespaced(client, &ns);
     api.create(&PostParams::default(), &deployment).await
 }
 
-pub async fn create_service(myapp: &MyApp, client: Client) -> Result<Service, kube::Error> {
+/// Create or update a Deployment with server-side apply
+pub async fn apply_deployment(
+    api: &Api<Deployment>,
+    deployment: &Deployment,
+) -> Result<Deployment, kube::Error> {
+    api.patch(
+        deployment.metadata.name.as_deref().unwrap(),
+        &PatchParams::apply("myapp-controller").force(),
+        &Patch::Apply(deployment),
+    )
+    .await
+}
+
+/// Service manifest for a MyApp, carrying its spec hash
+pub fn build_service(myapp: &MyApp) -> Service {
     let ns = myapp.namespace().unwrap();
-    let name = format!("{}-service", myapp.name_any());
+    let name = child_name(myapp, "service");
     let owner_ref = create_owner_reference(myapp);
 
+    let network = myapp.spec.service.as_ref();
+
     let mut labels = StdBTreeMap::new();
     labels.insert("app".to_string(), myapp.name_any());
+    // Selector stays app-only; managed-by lets cleanup find the Service
+    let mut metadata_labels = labels.clone();
+    metadata_labels.insert("managed-by".to_string(), "myapp-controller".to_string());
 
-    let service = Service {
+    spec_hash::annotate(Service {
         metadata: k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta {
             name: Some(name.clone()),
             namespace: Some(ns.clone()),
-            labels: Some(labels.clone()),
+            labels: Some(metadata_labels),
             owner_references: Some(vec![owner_ref]), // Set owner reference
             ..Default::default()
         },
@@ -314,82 +798,391 @@ pub async fn create_service(myapp: &MyApp, client: Client) -> Result<Service, ku
                 ),
                 ..Default::default()
             }]),
+            ip_families: network
+                .map(|n| n.ip_families.clone())
+                .filter(|f| !f.is_empty()),
+            ip_family_policy: network.and_then(|n| n.ip_family_policy.clone()),
             ..Default::default()
         }),
         ..Default::default()
-    };
+    })
+}
+
+/// Create or update a Service with server-side apply
+pub async fn apply_service(api: &Api<Service>, service: &Service) -> Result<Service, kube::Error> {
+    api.patch(
+        service.metadata.name.as_deref().unwrap(),
+        &PatchParams::apply("myapp-controller").force(),
+        &Patch::Apply(service),
+    )
+    .await
+}
+
+/// Server-side apply `desired` over `live` unless both came from the same rendering;
+/// children the MyApp does not own are left to adoption
+async fn apply_if_changed<K>(
+    myapp: &MyApp,
+    live: &K,
+    desired: &K,
+    apply: impl std::future::Future<Output = Result<K, kube::Error>>,
+    metrics: &MetricsCollector,
+) -> Result<(), kube::Error>
+where
+    K: Resource<DynamicType = ()>,
+{
+    let owned = live
+        .owner_references()
+        .iter()
+        .any(|o| Some(&o.uid) == myapp.metadata.uid.as_ref());
+    let kind = K::kind(&());
+    if !owned || spec_hash::is_current(live, desired) {
+        metrics.record_child_apply(&kind, false);
+        return Ok(());
+    }
+    apply.await?;
+    metrics.record_child_apply(&kind, true);
+    tracing::info!("Updated {} {} to the current spec", kind, live.name_any());
+    Ok(())
+}
+
+pub async fn create_service(myapp: &MyApp, client: Client) -> Result<Service, kube::Error> {
+    let api: Api<Service> = Api::namespaced(client, &myapp.namespace().unwrap());
+    api.create(&PostParams::default(), &build_service(myapp))
+        .await
+}
+
+// ============================================================================
+// EVENTS - Surface controller activity in `kubectl describe`
+// ============================================================================
+
+use kube::runtime::events::{Event, EventType, Recorder, Reporter};
 
-    let api: Api<Service> = Api::namespaced(client, &ns);
-    api.create(&PostParams::default(), &service).await
+/// Publish an Event on the MyApp; failures are logged rather than failing reconcile
+pub async fn publish_event(
+    myapp: &MyApp,
+    client: Client,
+    type_: EventType,
+    reason: &str,
+    action: &str,
+    note: String,
+) {
+    let reporter = Reporter {
+        controller: "myapp-controller".to_string(),
+        instance: std::env::var("CONTROLLER_NAME").ok(),
+    };
+    let recorder = Recorder::new(client, reporter, myapp.object_ref(&()));
+    let event = Event {
+        type_,
+        reason: reason.to_string(),
+        note: Some(note),
+        action: action.to_string(),
+        secondary: None,
+    };
+    if let Err(e) = recorder.publish(event).await {
+        tracing::warn!("Failed to publish event for {}: {}", myapp.name_any(), e);
+    }
 }
 
 // ============================================================================
 // ADMISSION WEBHOOKS - Validation and Mutation
 // ============================================================================
 
-use kube::core::admission::{AdmissionRequest, AdmissionResponse, AdmissionReview};
+use kube::core::admission::{AdmissionRequest, AdmissionResponse, AdmissionReview, Operation};
 use warp::{Filter, Rejection, Reply};
 
-// Validating Webhook
-pub async fn validate_webhook(body: AdmissionReview<MyApp>) -> Result<impl Reply, Rejection> {
-    let req: AdmissionRequest<MyApp> = match body.try_into() {
-        Ok(req) => req,
+/// Shared state for admission handlers that need cluster context
+#[derive(Clone, Default)]
+pub struct AdmissionState {
+    pub uniqueness: UniquenessPolicy,
+    pub ratio_policy: RatioPolicy,
+    /// Cached lister of all MyApps, only populated when a policy needs it
+    pub lister: Option<ClusterCache<MyApp>>,
+    /// Image architecture detection, only populated when enabled
+    pub arch: Option<ArchDetector>,
+    /// Client for dry-running children on dry-run requests, only populated when enabled
+    pub dry_run_client: Option<Client>,
+    /// Tag-to-digest resolution, only populated when enabled
+    pub digests: Option<DigestResolver>,
+    /// imagePullSecrets lookups, only populated when enabled
+    pub pull_secrets: Option<PullSecretChecker>,
+    /// Pod Security Standards levels of namespaces, only populated when enabled
+    pub pss: Option<PssChecker>,
+    /// Records accepted spec changes, only populated when enabled
+    pub audit: Option<AuditLog>,
+    /// Defaults and webhook toggles from the operator config
+    pub config: OperatorConfig,
+}
+
+impl AdmissionState {
+    pub async fn from_env() -> Result<Self, kube::Error> {
+        let uniqueness = UniquenessPolicy::from_env();
+        let ratio_policy = RatioPolicy::from_env();
+        let arch_detection = ArchDetector::is_enabled_from_env();
+        let dry_run_children = dry_run::enabled_from_env();
+        let digests = digest::enabled_from_env().then(|| DigestResolver {
+            registry: RegistryClient::new(),
+        });
+        let check_pull_secrets = pull_secrets::enabled_from_env();
+        let enforce_pss = PssChecker::is_enabled_from_env();
+        let audit = AuditLog::is_enabled_from_env();
+        if !uniqueness.is_enabled()
+            && !arch_detection
+            && !dry_run_children
+            && !check_pull_secrets
+            && !enforce_pss
+            && !audit
+        {
+            return Ok(Self {
+                ratio_policy,
+                digests,
+                ..Default::default()
+            });
+        }
+
+        // Cluster state comes from watches so admission never waits on an API round trip
+        let client = Client::try_default().await?;
+        let max_staleness = cache::max_staleness_from_env();
+        let lister = uniqueness.is_enabled().then(|| {
+            ClusterCache::spawn(
+                Api::<MyApp>::all(client.clone()),
+                Default::default(),
+                max_staleness,
+            )
+        });
+        let dry_run_client = dry_run_children.then(|| client.clone());
+        let pull_secrets = check_pull_secrets.then(|| PullSecretChecker {
+            client: client.clone(),
+            registry: RegistryClient::new(),
+        });
+        let pss = enforce_pss.then(|| PssChecker {
+            namespaces: ClusterCache::spawn(
+                Api::all(client.clone()),
+                Default::default(),
+                max_staleness,
+            ),
+            default_level: PssChecker::default_level_from_env(),
+        });
+        let audit = audit.then(|| AuditLog::from_env(client.clone()));
+        let arch = arch_detection.then(|| ArchDetector {
+            registry: RegistryClient::new(),
+            nodes: arch::node_cache(client, max_staleness),
+        });
+
+        Ok(Self {
+            uniqueness,
+            ratio_policy,
+            lister,
+            arch,
+            dry_run_client,
+            digests,
+            pull_secrets,
+            pss,
+            audit,
+            config: OperatorConfig::default(),
+        })
+    }
+}
+
+/// Decode an AdmissionReview body, counting undecodable requests as invalid
+fn admission_review(
+    webhook: &'static str,
+    metrics: MetricsCollector,
+) -> impl Filter<Extract = (AdmissionReview<MyApp>,), Error = Rejection> + Clone {
+    warp::body::json().or_else(move |rejection: Rejection| {
+        tracing::warn!("Undecodable {} admission review", webhook);
+        metrics.start_webhook(webhook).finish("invalid");
+        async move { Err::<(AdmissionReview<MyApp>,), _>(rejection) }
+    })
+}
+
+/// Run a webhook handler over the request in `body`, recording its duration and outcome
+async fn review<F, Fut>(
+    webhook: &'static str,
+    body: AdmissionReview<MyApp>,
+    metrics: MetricsCollector,
+    handler: F,
+) -> Result<impl Reply, Rejection>
+where
+    F: FnOnce(AdmissionRequest<MyApp>) -> Fut,
+    Fut: std::future::Future<Output = AdmissionResponse>,
+{
+    let timer = metrics.start_webhook(webhook);
+    let req: Result<AdmissionRequest<MyApp>, _> = body.try_into();
+    let res = match req {
+        Ok(req) => handler(req).await,
         Err(err) => {
-            eprintln!("Invalid admission request: {}", err);
-            return Ok(warp::reply::json(
-                &AdmissionResponse::invalid(format!("Invalid request: {}", err)).into_review(),
-            ));
+            tracing::warn!("Invalid admission request: {}", err);
+            timer.finish("invalid");
+            let res = AdmissionResponse::invalid(format!("Invalid request: {}", err));
+            return Ok(warp::reply::json(&res.into_review()));
         }
     };
+    timer.finish(if res.allowed { "allowed" } else { "denied" });
+    Ok(warp::reply::json(&res.into_review()))
+}
+
+// Validating Webhook
+#[tracing::instrument(skip_all)]
+pub async fn validate_webhook(
+    body: AdmissionReview<MyApp>,
+    state: AdmissionState,
+    metrics: MetricsCollector,
+) -> Result<impl Reply, Rejection> {
+    let audit = state.audit.clone();
+    let recorder = metrics.clone();
+    review(webhooks::VALIDATE.path, body, metrics, |req| async move {
+        let pending = audit.and_then(|log| Some((log, audit::prepare(&req)?)));
+        let res = validate(req, state, recorder).await;
+        // Only changes the API server will persist are recorded
+        if let (Some((log, (myapp, entry))), true) = (pending, res.allowed) {
+            tokio::spawn(async move { log.record(&myapp, &entry).await });
+        }
+        res
+    })
+    .await
+}
+
+async fn validate(
+    req: AdmissionRequest<MyApp>,
+    state: AdmissionState,
+    metrics: MetricsCollector,
+) -> AdmissionResponse {
+    // Deletions only carry the old object
+    if req.operation == Operation::Delete {
+        return match req.old_object.as_ref().map(deletion::check_protection) {
+            Some(Err(msg)) => AdmissionResponse::from(&req).deny(msg),
+            _ => AdmissionResponse::from(&req),
+        };
+    }
+
+    // Switched off in the operator config
+    if !state.config.get().validate {
+        return AdmissionResponse::from(&req);
+    }
 
     let myapp = match &req.object {
         Some(obj) => obj,
         None => {
-            return Ok(warp::reply::json(
-                &AdmissionResponse::invalid("No object in request".to_string()).into_review(),
-            ));
+            return AdmissionResponse::invalid("No object in request".to_string());
         }
     };
 
-    // Validate the MyApp resource
-    match myapp.validate() {
-        Ok(_) => {
-            // Additional custom validation
-            if myapp.spec.image.contains("latest") {
-                return Ok(warp::reply::json(
-                    &AdmissionResponse::invalid("Image tag 'latest' is not allowed".to_string())
-                        .into_review(),
-                ));
+    // Rules that need no cluster state, shared with the offline `validate` and `lint`
+    let violations = offline::admission_violations(myapp, &state.ratio_policy);
+    if !violations.is_empty() {
+        return AdmissionResponse::from(&req).deny(violations.join("; "));
+    }
+
+    // Refuse images no node in the cluster can run
+    if let (Some(arch), Some(scheduling)) = (&state.arch, &myapp.spec.scheduling) {
+        if !scheduling.architectures.is_empty() {
+            if let Err(msg) = arch.check_nodes(&scheduling.architectures).await {
+                return AdmissionResponse::from(&req).deny(msg);
             }
+        }
+    }
+
+    // Pull credentials, unless an update leaves the image and its secrets alone
+    let pull_unchanged = req.old_object.as_ref().is_some_and(|old| {
+        old.spec.image == myapp.spec.image
+            && old.spec.image_pull_secrets == myapp.spec.image_pull_secrets
+    });
+    if let (Some(checker), false) = (&state.pull_secrets, pull_unchanged) {
+        let ns = myapp
+            .namespace()
+            .or_else(|| req.namespace.clone())
+            .unwrap_or_default();
+        if let Err(msg) = checker
+            .check(&myapp.spec.image, &myapp.spec.image_pull_secrets, &ns)
+            .await
+        {
+            return AdmissionResponse::from(&req).deny(msg);
+        }
+    }
 
-            // Validation passed
-            Ok(warp::reply::json(
-                &AdmissionResponse::from(&req).into_review(),
-            ))
+    // Pods the namespace's Pod Security level would reject
+    if let Some(pss) = &state.pss {
+        let ns = myapp
+            .namespace()
+            .or_else(|| req.namespace.clone())
+            .unwrap_or_default();
+        if let Err(msg) = pss.check(myapp, &ns) {
+            return AdmissionResponse::from(&req).deny(msg);
         }
-        Err(e) => Ok(warp::reply::json(
-            &AdmissionResponse::invalid(e).into_review(),
-        )),
     }
+
+    // Permissions beyond what the operator config lets apps request
+    if let Some(rbac) = &myapp.spec.rbac {
+        if let Err(msg) = app_rbac::check(rbac, &state.config.get().app_rbac_denied) {
+            return AdmissionResponse::from(&req).deny(msg);
+        }
+    }
+
+    // Fields fixed at creation
+    if let (Operation::Update, Some(old)) = (&req.operation, &req.old_object) {
+        let causes = immutable::check(&old.spec, &myapp.spec);
+        if !causes.is_empty() {
+            return immutable::deny(AdmissionResponse::from(&req), causes);
+        }
+    }
+
+    // Cluster-wide uniqueness is only enforced on creation
+    if let (Operation::Create, Some(lister)) = (&req.operation, &state.lister) {
+        match lister.fresh_state() {
+            Some(existing) => {
+                if let Some(msg) = state.uniqueness.find_collision(myapp, &existing) {
+                    return AdmissionResponse::from(&req).deny(msg);
+                }
+            }
+            // Admitted rather than blocking creates while the watch catches up
+            None => metrics.record_webhook_check_skipped("uniqueness"),
+        }
+    }
+
+    // Dry-run requests never reach the controller; dry-run the children instead.
+    // Everything above is free of side effects, so it ran as usual.
+    if let (true, Some(client)) = (req.dry_run, &state.dry_run_client) {
+        let mut myapp = myapp.clone();
+        if myapp.metadata.namespace.is_none() {
+            myapp.metadata.namespace = req.namespace.clone();
+        }
+        if let Err(msg) = dry_run::check_children(&myapp, client.clone()).await {
+            return AdmissionResponse::from(&req).deny(msg);
+        }
+    }
+
+    // Validation passed; risky settings are admitted with warnings
+    warnings::attach(AdmissionResponse::from(&req), &myapp.spec)
 }
 
 // Mutating Webhook
-pub async fn mutate_webhook(body: AdmissionReview<MyApp>) -> Result<impl Reply, Rejection> {
-    let req: AdmissionRequest<MyApp> = match body.try_into() {
-        Ok(req) => req,
-        Err(err) => {
-            return Ok(warp::reply::json(
-                &AdmissionResponse::invalid(format!("Invalid request: {}", err)).into_review(),
-            ));
-        }
-    };
+#[tracing::instrument(skip_all)]
+pub async fn mutate_webhook(
+    body: AdmissionReview<MyApp>,
+    state: AdmissionState,
+    metrics: MetricsCollector,
+) -> Result<impl Reply, Rejection> {
+    let recorder = metrics.clone();
+    review(webhooks::MUTATE.path, body, metrics, |req| {
+        mutate(req, state, recorder)
+    })
+    .await
+}
+
+async fn mutate(
+    req: AdmissionRequest<MyApp>,
+    state: AdmissionState,
+    metrics: MetricsCollector,
+) -> AdmissionResponse {
+    let config = state.config.get();
+    if !config.mutate {
+        return AdmissionResponse::from(&req);
+    }
 
     let myapp = match &req.object {
         Some(obj) => obj,
         None => {
-            return Ok(warp::reply::json(
-                &AdmissionResponse::invalid("No object in request".to_string()).into_review(),
-            ));
+            return AdmissionResponse::invalid("No object in request".to_string());
         }
     };
 
@@ -408,43 +1201,158 @@ pub async fn mutate_webhook(body: AdmissionReview<MyApp>) -> Result<impl Reply,
     if myapp.spec.resources.is_none() {
         patches.push(PatchOperation::Add(AddOperation {
             path: "/spec/resources".parse().unwrap(),
-            value: serde_json::json!({
-                "cpu": "100m",
-                "memory": "128Mi"
-            }),
+            value: serde_json::to_value(&config.default_resources).unwrap(),
         }));
     }
 
-    let patch = JsonPatch(patches);
-    let mut res = AdmissionResponse::from(&req);
-    res = res.with_patch(patch).unwrap();
+    // Add configured default labels the MyApp does not set itself
+    let labels = myapp.labels();
+    for (key, value) in &config.default_labels {
+        if !labels.contains_key(key) {
+            let key = key.replace('~', "~0").replace('/', "~1");
+            patches.push(PatchOperation::Add(AddOperation {
+                path: format!("/metadata/labels/{}", key).parse().unwrap(),
+                value: serde_json::Value::String(value.clone()),
+            }));
+        }
+    }
 
-    Ok(warp::reply::json(&res.into_review()))
+    // Pin the image to the digest its tag resolves to now, keeping the original for reference
+    if let Some(digests) = &state.digests {
+        if let Some(image) = digests.pin(&myapp.spec.image).await {
+            let annotation = (
+                digest::TAG_ANNOTATION.to_string(),
+                serde_json::Value::String(myapp.spec.image.clone()),
+            );
+            patches.push(match myapp.metadata.annotations {
+                Some(_) => PatchOperation::Add(AddOperation {
+                    path: format!("/metadata/annotations/{}", annotation.0.replace('/', "~1"))
+                        .parse()
+                        .unwrap(),
+                    value: annotation.1,
+                }),
+                None => PatchOperation::Add(AddOperation {
+                    path: "/metadata/annotations".parse().unwrap(),
+                    value: serde_json::Value::Object([annotation].into_iter().collect()),
+                }),
+            });
+            patches.push(PatchOperation::Replace(ReplaceOperation {
+                path: "/spec/image".parse().unwrap(),
+                value: serde_json::Value::String(image),
+            }));
+        }
+    }
+
+    // Spread replicated Deployments across nodes and zones unless scheduling is set
+    let mut added_scheduling = match (&myapp.spec.scheduling, myapp.spec.workload_type) {
+        (None, WorkloadType::Deployment) => {
+            AdvancedScheduler::recommend_placement(&myapp.name_any(), myapp.spec.replicas)
+        }
+        _ => None,
+    };
+
+    // Pin pods to the architectures the image is published for
+    let architectures_set = myapp
+        .spec
+        .scheduling
+        .as_ref()
+        .is_some_and(|s| !s.architectures.is_empty());
+    if let (Some(arch), false) = (&state.arch, architectures_set) {
+        if let Some(architectures) = arch.detect(&myapp.spec.image).await {
+            if myapp.spec.scheduling.is_some() {
+                patches.push(PatchOperation::Add(AddOperation {
+                    path: "/spec/scheduling/architectures".parse().unwrap(),
+                    value: serde_json::json!(architectures),
+                }));
+            } else {
+                added_scheduling
+                    .get_or_insert_with(Default::default)
+                    .architectures = architectures;
+            }
+        }
+    }
+
+    if let Some(scheduling) = added_scheduling {
+        patches.push(PatchOperation::Add(AddOperation {
+            path: "/spec/scheduling".parse().unwrap(),
+            value: serde_json::to_value(&scheduling).unwrap(),
+        }));
+    }
+
+    // Confine pods with the runtime's default seccomp profile unless one is chosen
+    match &myapp.spec.security_profiles {
+        None => patches.push(PatchOperation::Add(AddOperation {
+            path: "/spec/securityProfiles".parse().unwrap(),
+            value: serde_json::to_value(SecurityProfiles {
+                seccomp: Some(Profile::runtime_default()),
+                ..Default::default()
+            })
+            .unwrap(),
+        })),
+        Some(profiles) if profiles.seccomp.is_none() => {
+            patches.push(PatchOperation::Add(AddOperation {
+                path: "/spec/securityProfiles/seccomp".parse().unwrap(),
+                value: serde_json::to_value(Profile::runtime_default()).unwrap(),
+            }))
+        }
+        Some(_) => {}
+    }
+
+    // Run as non-root on a read-only root filesystem without capabilities unless the spec
+    // sets those fields itself or the MyApp opts out of hardening
+    if !hardening::is_skipped(myapp) {
+        let (hardened, applied) = hardening::with_defaults(myapp.spec.container_security.as_ref());
+        if !applied.is_empty() {
+            for field in &applied {
+                metrics.record_hardening_default(field);
+            }
+            patches.push(PatchOperation::Add(AddOperation {
+                path: "/spec/containerSecurity".parse().unwrap(),
+                value: serde_json::to_value(&hardened).unwrap(),
+            }));
+        }
+    }
+
+    let patch = JsonPatch(patches);
+    AdmissionResponse::from(&req).with_patch(patch).unwrap()
 }
 
 // Webhook server
-pub async fn run_webhook_server() {
+pub fn webhook_routes(
+    state: AdmissionState,
+    metrics: MetricsCollector,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    let state = warp::any().map(move || state.clone());
+    let with_metrics = {
+        let metrics = metrics.clone();
+        warp::any().map(move || metrics.clone())
+    };
+
     let validate = warp::post()
-        .and(warp::path("validate"))
-        .and(warp::body::json())
+        .and(warp::path(webhooks::VALIDATE.path))
+        .and(admission_review(webhooks::VALIDATE.path, metrics.clone()))
+        .and(state.clone())
+        .and(with_metrics.clone())
         .and_then(validate_webhook);
 
     let mutate = warp::post()
-        .and(warp::path("mutate"))
-        .and(warp::body::json())
+        .and(warp::path(webhooks::MUTATE.path))
+        .and(admission_review(webhooks::MUTATE.path, metrics))
+        .and(state)
+        .and(with_metrics)
         .and_then(mutate_webhook);
 
-    let routes = validate.or(mutate);
-
-    println!("Starting webhook server on :8443");
-    warp::serve(routes).run(([0, 0, 0, 0], 8443)).await;
+    validate.or(mutate)
 }
 
 // ============================================================================
 // CONTROLLER with Finalizers and Owner References
 // ============================================================================
 
-use kube::runtime::controller::{Action, Controller};
+use kube::runtime::controller::{self, Action, Controller};
+use kube::runtime::finalizer::{self, finalizer, Event as FinalizerEvent};
+use kube::runtime::reflector::{self, ObjectRef};
+use kube::runtime::{predicates, watcher, Predicate, WatchStreamExt};
 use std::sync::Arc;
 use thiserror::Error;
 
@@ -458,112 +1366,649 @@ pub enum ReconcileError {
 
     #[error("Finalizer error: {0}")]
     FinalizerError(String),
+
+    #[error("Waiting for {0} child resources to finish deleting")]
+    ChildrenTerminating(usize),
+
+    #[error("Secret error: {0}")]
+    SecretError(String),
 }
 
 pub struct Context {
     pub client: Client,
     pub metrics: MetricsCollector,
+    pub image_policy: ImagePolicy,
+    pub limiter: PriorityLimiter,
+    /// IP families the cluster supports, detected at startup (empty if unknown)
+    pub ip_families: BTreeSet<String>,
+    /// Prometheus Operator CRDs found at startup
+    pub monitoring: MonitoringCrds,
+    pub namespace_filter: NamespaceFilter,
+    /// Per-object retry state for error_policy
+    pub backoff: ErrorBackoff,
+    /// Pause advised by the API server through 429 responses
+    pub throttle: ApiThrottle,
+    /// Requeue intervals and namespace filters from the MyAppOperatorConfig
+    pub config: OperatorConfig,
+    /// Optional subsystems switched on or off
+    pub features: Features,
+    /// Per-namespace reconcile budgets
+    pub namespace_rate: NamespaceRateLimiter,
+    /// Watch events awaiting each MyApp's next reconcile
+    pub coalescer: Coalescer,
+    /// Slice of MyApps this replica reconciles
+    pub shard: Shard,
+    /// Vault connection for spec.vaultSecrets, if MYAPP_VAULT_ADDR is set
+    pub vault: Option<VaultClient>,
+    /// External Secrets Operator CRD found at startup
+    pub external_secrets: bool,
+    /// Vulnerability scan gate for new images, if MYAPP_SCAN_URL is set
+    pub scan_gate: Option<ScanGate>,
+    /// Cosign verification for spec.imageVerification
+    pub cosign: Verifier,
 }
 
+impl Context {
+    /// Whether reconcile keeps requeueing `myapp` on this replica; skipped, paused and
+    /// plan-only MyApps wait for a change instead
+    pub fn requeues(&self, myapp: &MyApp) -> bool {
+        let ns = myapp.namespace().unwrap_or_default();
+        self.namespace_filter.allows(&ns)
+            && self.config.get().namespace_filter.allows(&ns)
+            && self.shard.owns_myapp(myapp)
+            && dev::sandbox().is_none_or(|dev| dev.owns(myapp))
+            && !myapp.is_paused()
+            && !plan::is_plan_only(myapp)
+    }
+}
+
+#[tracing::instrument(
+    skip_all,
+    fields(namespace = %myapp.namespace().unwrap_or_default(), name = %myapp.name_any())
+)]
 pub async fn reconcile(myapp: Arc<MyApp>, ctx: Arc<Context>) -> Result<Action, ReconcileError> {
     let ns = myapp.namespace().unwrap();
     let name = myapp.name_any();
     let api: Api<MyApp> = Api::namespaced(ctx.client.clone(), &ns);
+    ctx.coalescer.reconciling(&ObjectRef::from_obj(&*myapp));
+
+    // Defensive check in case the watch selector let an excluded namespace through
+    if !ctx.namespace_filter.allows(&ns) || !ctx.config.get().namespace_filter.allows(&ns) {
+        ctx.metrics.record_skipped("namespace_filtered", &ns);
+        return Ok(Action::await_change());
+    }
+
+    // Another replica reconciles MyApps outside this shard; children still trigger them here
+    if !ctx.shard.owns(&ns, &name) {
+        ctx.metrics.record_skipped("other_shard", &ns);
+        return Ok(Action::await_change());
+    }
+
+    // Developer mode never touches MyApps from outside its session
+    if dev::sandbox().is_some_and(|dev| !dev.owns(&myapp)) {
+        ctx.metrics.record_skipped("dev_session", &ns);
+        return Ok(Action::await_change());
+    }
+
+    // Defer over-budget namespaces without holding a worker slot
+    if let Err(delay) = ctx
+        .namespace_rate
+        .acquire(&ns, ctx.config.get().namespace_rate(&ns))
+    {
+        ctx.metrics.record_namespace_rate_limited(&ns, delay);
+        return Ok(Action::requeue(delay));
+    }
+
+    // Honor API server pushback before taking a slot
+    let queued = ctx.metrics.enter_queue();
+    let waited = ctx.throttle.wait().await;
+    if !waited.is_zero() {
+        ctx.metrics.record_throttle_wait(waited);
+    }
+
+    // Wait for a slot, behind higher-priority MyApps; the cap shrinks when the controller
+    // is short on memory or CPU
+    let _permit = ctx.limiter.acquire(priority::priority(&myapp)).await;
+    drop(queued);
 
     // Start metrics timer
     let timer = ctx.metrics.start_reconcile(&ns, &name);
 
-    // Handle deletion with finalizer
-    if myapp.metadata.deletion_timestamp.is_some() {
-        if myapp.finalizers().contains(&FINALIZER.to_string()) {
-            // Perform cleanup
-            cleanup_resources(&myapp, ctx.client.clone())
-                .await
-                .map_err(|e| {
-                    ctx.metrics.record_error("finalizer_cleanup_error", &ns);
-                    ReconcileError::FinalizerError(e.to_string())
-                })?;
-
-            // Remove finalizer
-            remove_finalizer(&myapp, ctx.client.clone())
-                .await
-                .inspect_err(|_e| {
-                    ctx.metrics.record_error("finalizer_removal_error", &ns);
-                })?;
-            println!("Finalizer removed for MyApp {}/{}", ns, name);
+    // The helper adds the finalizer first and only releases it once cleanup has succeeded
+    let context: &Context = &ctx;
+    finalizer(&api, FINALIZER, myapp, |event| async move {
+        match event {
+            FinalizerEvent::Apply(myapp) => apply(myapp, context, timer).await,
+            FinalizerEvent::Cleanup(myapp) => cleanup(myapp, context, timer).await,
         }
+    })
+    .await
+    .map_err(|e| finalizer_error(e, &ctx.metrics, &ns))
+}
+
+/// Release a deleted MyApp's children according to its deletion policy
+async fn cleanup(
+    myapp: Arc<MyApp>,
+    ctx: &Context,
+    timer: ReconcileTimer,
+) -> Result<Action, ReconcileError> {
+    let ns = myapp.namespace().unwrap();
+    let name = myapp.name_any();
+    let remaining = cleanup_resources(&myapp, ctx.client.clone())
+        .await
+        .map_err(|e| ReconcileError::FinalizerError(e.to_string()))?;
+    if remaining > 0 {
+        // Keep the finalizer; children's delete events bring the MyApp back
+        return Err(ReconcileError::ChildrenTerminating(remaining));
+    }
+    tracing::info!("Cleaned up MyApp {}/{}, removing finalizer", ns, name);
+    timer.success();
+    ctx.metrics.forget_object(&ns, &name);
+    Ok(Action::await_change())
+}
+
+/// Mark the MyApp Degraded because its image may not be rolled out; the children are left as
+/// they are, so the running pods keep their current image
+async fn hold_rollout(
+    api: &Api<MyApp>,
+    myapp: &MyApp,
+    ctx: &Context,
+    reason: &str,
+    action: &str,
+    message: String,
+) -> Result<(), ReconcileError> {
+    let mut new_status = myapp.status.clone().unwrap_or_default();
+    new_status.state = "Degraded".to_string();
+    new_status.last_updated = Some(chrono::Utc::now().to_rfc3339());
+    new_status
+        .conditions
+        .retain(|c| !(c.r#type == "Degraded" && c.reason == reason));
+    new_status
+        .conditions
+        .push(Condition::new("Degraded", true, reason, &message));
+    status::patch_status(api, myapp, &new_status, &ctx.metrics).await?;
+    publish_event(
+        myapp,
+        ctx.client.clone(),
+        EventType::Warning,
+        reason,
+        action,
+        message,
+    )
+    .await;
+    Ok(())
+}
+
+/// Bring the children of a live MyApp in line with its spec
+async fn apply(
+    myapp: Arc<MyApp>,
+    ctx: &Context,
+    timer: ReconcileTimer,
+) -> Result<Action, ReconcileError> {
+    let ns = myapp.namespace().unwrap();
+    let name = myapp.name_any();
+    let api: Api<MyApp> = Api::namespaced(ctx.client.clone(), &ns);
+
+    // Validate the resource
+    myapp
+        .validate()
+        .and_then(|_| match &myapp.spec.service {
+            Some(service) => service.check_supported(&ctx.ip_families),
+            None => Ok(()),
+        })
+        .map_err(|e| {
+            ctx.metrics.record_error("validation_error", &ns);
+            ReconcileError::ValidationError(e)
+        })?;
+
+    // Paused apps keep their children as they are but still acknowledge the generation
+    if myapp.is_paused() {
+        tracing::info!("MyApp {}/{} is paused, skipping reconciliation", ns, name);
+        let mut new_status = myapp.status.clone().unwrap_or_default();
+        new_status.state = "Paused".to_string();
+        new_status.observed_generation = myapp.metadata.generation;
+        new_status.last_updated = Some(chrono::Utc::now().to_rfc3339());
+        new_status.conditions.retain(|c| c.r#type != "Paused");
+        new_status.conditions.push(Condition::new(
+            "Paused",
+            true,
+            "ReconciliationPaused",
+            "Child resources are not reconciled while the MyApp is paused",
+        ));
+        status::patch_status(&api, &myapp, &new_status, &ctx.metrics).await?;
         timer.success();
         return Ok(Action::await_change());
     }
 
-    // Add finalizer if not present
-    if !myapp.finalizers().contains(&FINALIZER.to_string()) {
-        add_finalizer(&myapp, ctx.client.clone()).await?;
-        println!("Finalizer added for MyApp {}/{}", ns, name);
-        return Ok(Action::requeue(std::time::Duration::from_secs(1)));
+    // Plan-only apps get the plan as an event and condition; nothing is applied
+    if plan::is_plan_only(&myapp) {
+        let plan = plan::plan(&myapp, ctx.client.clone()).await?;
+        tracing::info!("Plan for MyApp {}/{}:\n{}", ns, name, plan);
+        publish_event(
+            &myapp,
+            ctx.client.clone(),
+            EventType::Normal,
+            "Planned",
+            "Plan",
+            plan.to_string(),
+        )
+        .await;
+
+        let mut new_status = myapp.status.clone().unwrap_or_default();
+        new_status.state = "Planned".to_string();
+        new_status.observed_generation = myapp.metadata.generation;
+        new_status.last_updated = Some(chrono::Utc::now().to_rfc3339());
+        new_status.conditions.retain(|c| c.r#type != "Planned");
+        new_status
+            .conditions
+            .push(Condition::new("Planned", true, "PlanOnly", &plan.summary()));
+        status::patch_status(&api, &myapp, &new_status, &ctx.metrics).await?;
+        timer.success();
+        return Ok(Action::await_change());
     }
 
-    // Validate the resource
-    myapp.validate().map_err(|e| {
-        ctx.metrics.record_error("validation_error", &ns);
-        ReconcileError::ValidationError(e)
-    })?;
+    tracing::info!("Reconciling MyApp {}/{}", ns, name);
+
+    // Hold back images the scanner has not cleared; the running Deployment is left alone
+    let scanned_image = myapp.status.as_ref().and_then(|s| s.scanned_image.clone());
+    let scanned_image = match &ctx.scan_gate {
+        Some(gate) if scanned_image.as_deref() != Some(myapp.spec.image.as_str()) => {
+            match gate.check(&myapp, &ctx.metrics).await {
+                Verdict::Pass => Some(myapp.spec.image.clone()),
+                Verdict::Blocked(reason, message) => {
+                    hold_rollout(&api, &myapp, ctx, reason, "ScanImage", message).await?;
+                    timer.success();
+                    return Ok(Action::requeue(gate.retry));
+                }
+            }
+        }
+        _ => scanned_image,
+    };
+
+    // Likewise images without a valid cosign signature, when the spec asks for one
+    let verified_image = myapp.status.as_ref().and_then(|s| s.verified_image.clone());
+    let verified_image = match &myapp.spec.image_verification {
+        Some(policy) if verified_image.as_deref() != Some(myapp.spec.image.as_str()) => {
+            let verdict = ctx.cosign.check(&myapp.spec.image, policy).await;
+            ctx.metrics
+                .record_signature_check(&ns, verdict == Verdict::Pass);
+            match verdict {
+                Verdict::Pass => Some(myapp.spec.image.clone()),
+                Verdict::Blocked(reason, message) => {
+                    hold_rollout(&api, &myapp, ctx, reason, "VerifyImage", message).await?;
+                    timer.success();
+                    return Ok(Action::requeue(ctx.config.get().requeue.steady));
+                }
+            }
+        }
+        Some(_) => verified_image,
+        None => None,
+    };
 
-    println!("Reconciling MyApp {}/{}", ns, name);
+    // Generated Secrets first, so the pods referencing them can start
+    secrets::reconcile(&myapp, ctx.client.clone()).await?;
+    vault::reconcile(&myapp, ctx.vault.as_ref(), ctx.client.clone()).await?;
+    external_secrets::reconcile(&myapp, ctx.external_secrets, ctx.client.clone()).await?;
+    // Isolate the pods before they start
+    network_policy::reconcile(&myapp, ctx.client.clone()).await?;
+    app_rbac::reconcile(
+        &myapp,
+        &ctx.config.get().app_rbac_denied,
+        ctx.client.clone(),
+    )
+    .await?;
+
+    let mut hook_runs: Vec<(HookPhase, LastRunStatus)> = Vec::new();
+    let mut awaiting_rollout = false;
+    let mut blue_green_status: Option<BlueGreenStatus> = None;
+    let mut canary_status: Option<CanaryStatus> = None;
+    let mut replicas: Option<(i32, i32)> = None;
+    let mut rollout: Option<(Rollout, String)> = None;
+
+    let last_run = match myapp.spec.workload_type {
+        WorkloadType::Deployment => {
+            let hooks = myapp.spec.hooks.clone().unwrap_or_default();
+
+            let previous_hook_run = |phase: HookPhase| {
+                myapp
+                    .status
+                    .as_ref()
+                    .and_then(|s| s.hooks.get(phase.as_str()))
+            };
+
+            // Pre-deploy hook gates the rollout of a new pod spec
+            if let Some(hook) = &hooks.pre_deploy {
+                let run = hooks::run_hook(
+                    &myapp,
+                    HookPhase::PreDeploy,
+                    hook,
+                    previous_hook_run(HookPhase::PreDeploy),
+                    ctx.client.clone(),
+                )
+                .await?;
+                hook_runs.push((HookPhase::PreDeploy, run));
+            }
 
-    // Create or update Deployment with owner reference
-    let deployments: Api<Deployment> = Api::namespaced(ctx.client.clone(), &ns);
-    let deploy_name = format!("{}-deployment", name);
+            let pre_deploy_done = hook_runs.iter().all(|(_, run)| run.result == "Succeeded");
+            let mut strategy = myapp.spec.strategy.clone().unwrap_or_default();
+            if strategy.type_ == StrategyType::Canary && !ctx.features.enabled(Feature::Canary) {
+                tracing::info!(
+                    "Canary rollouts are disabled; rolling out MyApp {}/{} as RollingUpdate",
+                    ns,
+                    name
+                );
+                strategy.type_ = StrategyType::RollingUpdate;
+            }
+
+            if pre_deploy_done {
+                let deployments: Api<Deployment> = Api::namespaced(ctx.client.clone(), &ns);
+                let deploy_name = match strategy.type_ {
+                    StrategyType::BlueGreen => {
+                        // Blue-green manages its own Deployments and the Service selector
+                        let status = bluegreen::reconcile(&myapp, ctx.client.clone()).await?;
+                        let active = bluegreen::deployment_name(&myapp, status.active_color);
+                        blue_green_status = Some(status);
+                        active
+                    }
+                    StrategyType::Canary => {
+                        let config = strategy.canary.clone().unwrap_or_default();
+                        let status = canary::reconcile(&myapp, &config, ctx.client.clone()).await?;
+                        canary_status = Some(status);
+                        canary::stable_name(&myapp)
+                    }
+                    StrategyType::RollingUpdate | StrategyType::Recreate => {
+                        // Create or update Deployment with owner reference
+                        let deploy_name = child_name(&myapp, "deployment");
+
+                        match deployments.get_opt(&deploy_name).await? {
+                            Some(existing) => {
+                                adoption::ensure_owned(
+                                    &myapp,
+                                    &deployments,
+                                    &existing,
+                                    ctx.client.clone(),
+                                )
+                                .await?;
+                                let desired =
+                                    build_deployment(&myapp, &deploy_name, app_labels(&myapp));
+                                apply_if_changed(
+                                    &myapp,
+                                    &existing,
+                                    &desired,
+                                    apply_deployment(&deployments, &desired),
+                                    &ctx.metrics,
+                                )
+                                .await?;
+                            }
+                            None => {
+                                create_deployment(&myapp, ctx.client.clone()).await?;
+                                tracing::info!(
+                                    "Created deployment {} with owner reference",
+                                    deploy_name
+                                );
+                            }
+                        }
+
+                        deploy_name
+                    }
+                };
+
+                // Create or update Service with owner reference
+                if strategy.type_ != StrategyType::BlueGreen {
+                    let services: Api<Service> = Api::namespaced(ctx.client.clone(), &ns);
+                    let svc_name = child_name(&myapp, "service");
+
+                    match services.get_opt(&svc_name).await? {
+                        Some(existing) => {
+                            adoption::ensure_owned(
+                                &myapp,
+                                &services,
+                                &existing,
+                                ctx.client.clone(),
+                            )
+                            .await?;
+                            let desired = build_service(&myapp);
+                            apply_if_changed(
+                                &myapp,
+                                &existing,
+                                &desired,
+                                apply_service(&services, &desired),
+                                &ctx.metrics,
+                            )
+                            .await?;
+                        }
+                        None => {
+                            create_service(&myapp, ctx.client.clone()).await?;
+                            tracing::info!("Created service {} with owner reference", svc_name);
+                        }
+                    }
+                }
 
-    match deployments.get_opt(&deploy_name).await? {
-        Some(_) => {
-            println!("Deployment {} already exists", deploy_name);
+                if ctx.features.enabled(Feature::ServiceMonitor) {
+                    monitoring::reconcile(&myapp, ctx.monitoring, &deploy_name, ctx.client.clone())
+                        .await?;
+                } else if myapp.spec.monitoring.is_some() {
+                    tracing::info!(
+                        "MyApp {}/{} requests monitoring but the service-monitor feature is disabled",
+                        ns, name
+                    );
+                }
+
+                // Replica counts of the Deployment serving traffic, for kubectl get
+                let deployment = deployments.get(&deploy_name).await?;
+                replicas = Some(deployment_replicas(&deployment));
+                let progress = Rollout::of(&deployment);
+                awaiting_rollout |= progress == Rollout::Progressing;
+                rollout = Some((progress, deploy_name.clone()));
+
+                // Post-deploy hook runs once the Deployment has stabilized
+                if let Some(hook) = &hooks.post_deploy {
+                    let rolling_out = blue_green_status
+                        .as_ref()
+                        .is_some_and(|s| s.phase == BlueGreenPhase::Previewing)
+                        || canary_status
+                            .as_ref()
+                            .is_some_and(|s| s.phase == CanaryPhase::Progressing);
+                    if !rolling_out && hooks::deployment_available(&deployment) {
+                        let run = hooks::run_hook(
+                            &myapp,
+                            HookPhase::PostDeploy,
+                            hook,
+                            previous_hook_run(HookPhase::PostDeploy),
+                            ctx.client.clone(),
+                        )
+                        .await?;
+                        hook_runs.push((HookPhase::PostDeploy, run));
+                    } else {
+                        awaiting_rollout = true;
+                    }
+                }
+            }
+
+            None
         }
-        None => {
-            create_deployment(&myapp, ctx.client.clone()).await?;
-            println!("Created deployment {} with owner reference", deploy_name);
+        WorkloadType::Job => {
+            let job =
+                workload::sync_job(&myapp, build_pod_spec(&myapp), ctx.client.clone()).await?;
+            // Requeued until the replacement of an outdated Job exists
+            awaiting_rollout |= job.is_none();
+            job.as_ref().map(workload::job_run_status)
         }
-    }
+        WorkloadType::CronJob => {
+            // Applied every reconcile so schedule and template changes reach the CronJob
+            let cronjob =
+                workload::apply_cronjob(&myapp, build_pod_spec(&myapp), ctx.client.clone()).await?;
+            workload::cronjob_run_status(&cronjob)
+        }
+    };
 
-    // Create or update Service with owner reference
-    let services: Api<Service> = Api::namespaced(ctx.client.clone(), &ns);
-    let svc_name = format!("{}-service", name);
+    // Controller-managed retries and alerting for failed runs
+    let failure_policy = myapp
+        .spec
+        .job
+        .as_ref()
+        .and_then(|j| j.failure_policy.as_ref())
+        .filter(|_| myapp.spec.workload_type != WorkloadType::Deployment);
+    let (failures, retry_after) = match failure_policy {
+        Some(policy) => {
+            let (status, retry_after) =
+                failure::reconcile(&myapp, policy, last_run.as_ref(), ctx.client.clone()).await?;
+            (Some(status), retry_after)
+        }
+        None => (None, None),
+    };
 
-    match services.get_opt(&svc_name).await? {
-        Some(_) => {
-            println!("Service {} already exists", svc_name);
+    // Update status subresource
+    let hook_failed = hook_runs.iter().any(|(_, run)| run.result == "Failed");
+    let pre_deploy_pending = hook_runs
+        .iter()
+        .any(|(phase, run)| *phase == HookPhase::PreDeploy && run.is_active());
+
+    let state = match (myapp.spec.workload_type, last_run.as_ref()) {
+        (WorkloadType::Deployment, _) if hook_failed => "Degraded",
+        (WorkloadType::Deployment, _) if pre_deploy_pending => "PreDeploy",
+        (WorkloadType::Deployment, _)
+            if blue_green_status
+                .as_ref()
+                .is_some_and(|s| s.phase == BlueGreenPhase::Previewing) =>
+        {
+            "Previewing"
         }
-        None => {
-            create_service(&myapp, ctx.client.clone()).await?;
-            println!("Created service {} with owner reference", svc_name);
+        (WorkloadType::Deployment, _)
+            if canary_status
+                .as_ref()
+                .is_some_and(|s| s.phase == CanaryPhase::Progressing) =>
+        {
+            "Canary"
+        }
+        (WorkloadType::Deployment, _) => rollout.as_ref().map_or("Running", |(r, _)| r.state()),
+        (WorkloadType::CronJob, None) => "Scheduled",
+        (_, Some(run)) if run.result == "Succeeded" => "Completed",
+        (_, Some(run)) if run.result == "Failed" => "Failed",
+        _ => "Running",
+    };
+
+    let mut conditions = vec![match &rollout {
+        _ if hook_failed => Condition::ready(false, "HookFailed", "A deploy hook job failed"),
+        _ if pre_deploy_pending => {
+            Condition::ready(false, "PreDeploy", "Waiting for the pre-deploy hook job")
         }
+        Some((progress, deployment_name)) => progress.ready_condition(deployment_name),
+        None => Condition::ready(true, "ReconcileSuccess", "Resource reconciled successfully"),
+    }];
+    conditions.extend(last_run.as_ref().map(LastRunStatus::condition));
+    if let Some((progress, deployment_name)) = &rollout {
+        conditions.extend(progress.conditions(deployment_name));
+    }
+    // Drift and image update findings belong to their sweeps
+    conditions.extend(
+        myapp
+            .status
+            .iter()
+            .flat_map(|s| &s.conditions)
+            .filter(|c| {
+                c.r#type == drift::DRIFT_CONDITION || c.r#type == image_update::UPDATE_CONDITION
+            })
+            .cloned(),
+    );
+    if ctx.image_policy.is_enabled() {
+        let condition = ctx.image_policy.condition(&myapp.spec.image);
+        ctx.metrics
+            .set_outdated_image(&ns, &name, &myapp.spec.image, condition.status == "True");
+        conditions.push(condition);
+    }
+    conditions.extend(
+        hook_runs
+            .iter()
+            .filter_map(|(phase, run)| hooks::degraded_condition(*phase, run)),
+    );
+    if failures.as_ref().is_some_and(|f| f.exhausted) {
+        conditions.push(Condition::new(
+            "Degraded",
+            true,
+            "RetriesExhausted",
+            "Failure policy exhausted; fix the workload or resume it manually",
+        ));
+    }
+    if let Some(canary) = canary_status
+        .as_ref()
+        .filter(|s| s.phase == CanaryPhase::Aborted)
+    {
+        conditions.push(Condition::new(
+            "Degraded",
+            true,
+            "CanaryAborted",
+            canary.message.as_deref().unwrap_or_default(),
+        ));
     }
 
-    // Update status subresource
     let new_status = MyAppStatus {
-        state: "Running".to_string(),
+        state: state.to_string(),
         observed_generation: myapp.metadata.generation,
-        conditions: vec![Condition::ready(
-            true,
-            "ReconcileSuccess",
-            "Resource reconciled successfully",
-        )],
+        conditions,
         last_updated: Some(chrono::Utc::now().to_rfc3339()),
+        last_run: last_run.clone(),
+        hooks: hook_runs
+            .iter()
+            .map(|(phase, run)| (phase.as_str().to_string(), run.clone()))
+            .collect(),
+        blue_green: blue_green_status.clone(),
+        canary: canary_status.clone(),
+        qos_class: Some(qos::qos_class(myapp.spec.resources.as_ref()).to_string()),
+        failures,
+        replicas: replicas.map(|(desired, _)| desired),
+        ready_replicas: replicas.map(|(_, ready)| ready),
+        ready: replicas.map(|(desired, ready)| format!("{}/{}", ready, desired)),
+        scanned_image,
+        verified_image,
     };
 
-    let status_patch = serde_json::json!({
-        "status": new_status
-    });
-
-    api.patch_status(&name, &PatchParams::default(), &Patch::Merge(&status_patch))
-        .await?;
+    // Guarded by observedGeneration so a stale cache cannot clobber newer status
+    status::patch_status(&api, &myapp, &new_status, &ctx.metrics).await?;
 
     // Update metrics
-    ctx.metrics.set_managed_resources("deployment", &ns, 1);
-    ctx.metrics.set_managed_resources("service", &ns, 1);
+    match myapp.spec.workload_type {
+        WorkloadType::Deployment => {
+            ctx.metrics.set_managed_resources("deployment", &ns, 1);
+            ctx.metrics.set_managed_resources("service", &ns, 1);
+        }
+        WorkloadType::Job => ctx.metrics.set_managed_resources("job", &ns, 1),
+        WorkloadType::CronJob => ctx.metrics.set_managed_resources("cronjob", &ns, 1),
+    }
 
     timer.success();
-    Ok(Action::requeue(std::time::Duration::from_secs(300)))
+
+    if let Some(delay) = retry_after {
+        return Ok(Action::requeue(delay));
+    }
+
+    // Poll running jobs and hooks more often so their results are reported promptly
+    if last_run.as_ref().is_some_and(LastRunStatus::is_active)
+        || hook_runs.iter().any(|(_, run)| run.is_active())
+    {
+        return Ok(Action::requeue(ctx.config.get().requeue.active_run));
+    }
+
+    // Wait for the Deployment (or blue-green preview / canary step) to stabilize
+    if awaiting_rollout
+        || blue_green_status
+            .as_ref()
+            .is_some_and(|s| s.phase == BlueGreenPhase::Previewing)
+        || canary_status
+            .as_ref()
+            .is_some_and(|s| s.phase == CanaryPhase::Progressing)
+    {
+        return Ok(Action::requeue(ctx.config.get().requeue.rollout));
+    }
+
+    // Re-read Vault values at least every resync interval
+    let steady = ctx.config.get().requeue.steady;
+    match ctx.vault.as_ref() {
+        Some(vault) if !myapp.spec.vault_secrets.is_empty() => {
+            Ok(Action::requeue(steady.min(vault.resync)))
+        }
+        _ => Ok(Action::requeue(steady)),
+    }
 }
 
 pub fn error_policy(myapp: Arc<MyApp>, error: &ReconcileError, ctx: Arc<Context>) -> Action {
@@ -574,11 +2019,103 @@ pub fn error_policy(myapp: Arc<MyApp>, error: &ReconcileError, ctx: Arc<Context>
         ReconcileError::KubeError(_) => "kube_error",
         ReconcileError::ValidationError(_) => "validation_error",
         ReconcileError::FinalizerError(_) => "finalizer_error",
+        ReconcileError::ChildrenTerminating(_) => "children_terminating",
+        ReconcileError::SecretError(_) => "secret_error",
     };
     ctx.metrics.record_error(error_type, &ns);
 
-    eprintln!("Reconciliation error: {:?}", error);
-    Action::requeue(std::time::Duration::from_secs(60))
+    // Back off per object, faster for transient errors than for invalid specs
+    let class = backoff::classify(error);
+    let key = format!("{}/{}", ns, myapp.name_any());
+    let mut delay = ctx.backoff.next_delay(&key, class);
+    if let Some(pause) = ctx.throttle.remaining() {
+        delay = delay.max(pause);
+    }
+    tracing::warn!(
+        "Reconciliation error ({}), retrying in {:?}: {:?}",
+        class.as_str(),
+        delay,
+        error
+    );
+    Action::requeue(delay)
+}
+
+/// Controller for one namespace (or the whole cluster), with the stores its watches feed
+///
+/// MyApp events only pass when generation, labels, annotations or finalizers change, so the
+/// controller's own status writes do not re-trigger it. Owned workloads still trigger a reconcile.
+/// Events for one MyApp within the debounce window collapse into a single reconcile, and only
+/// MyApps in `shard` are queued. Deleted MyApps drop out of `backoff`.
+pub fn build_controller(
+    client: Client,
+    namespace: Option<&str>,
+    config: watcher::Config,
+    probe: &StreamProbe,
+    coalescer: &Coalescer,
+    backoff: &ErrorBackoff,
+    shard: Shard,
+) -> (Controller<MyApp>, ControllerStores) {
+    let (reader, writer) = reflector::store();
+    let watch_probe = probe.clone();
+    let myapps = watcher(scope::api::<MyApp>(client.clone(), namespace), config)
+        .default_backoff()
+        .inspect(move |event| watch_probe.watch_event(event.is_ok()))
+        .modify(pressure::slim)
+        .reflect(writer)
+        .inspect(backoff.forget_deleted())
+        .applied_objects()
+        .predicate_filter(
+            predicates::generation
+                .combine(predicates::labels)
+                .combine(predicates::annotations)
+                .combine(predicates::finalizers),
+        )
+        .filter(move |event| {
+            futures::future::ready(event.as_ref().map_or(true, |m| shard.owns_myapp(m)))
+        })
+        .inspect(coalescer.myapp_events());
+
+    let children = || watcher::Config::default().labels("managed-by=myapp-controller");
+    let (deployments, deployment_writer) = reflector::store();
+    let (jobs, job_writer) = reflector::store();
+    let (cronjobs, cronjob_writer) = reflector::store();
+    let stores = ControllerStores {
+        namespace: namespace.map(str::to_string),
+        myapps: reader.clone(),
+        deployments,
+        jobs,
+        cronjobs,
+    };
+    let controller = Controller::for_stream(myapps, reader)
+        .owns_stream(
+            watcher(
+                scope::api::<Deployment>(client.clone(), namespace),
+                children(),
+            )
+            .default_backoff()
+            .modify(pressure::slim)
+            .reflect(deployment_writer)
+            .touched_objects()
+            .inspect(coalescer.child_events()),
+        )
+        .owns_stream(
+            watcher(scope::api::<Job>(client.clone(), namespace), children())
+                .default_backoff()
+                .modify(pressure::slim)
+                .reflect(job_writer)
+                .touched_objects()
+                .inspect(coalescer.child_events()),
+        )
+        .owns_stream(
+            watcher(scope::api::<CronJob>(client, namespace), children())
+                .default_backoff()
+                .modify(pressure::slim)
+                .reflect(cronjob_writer)
+                .touched_objects()
+                .inspect(coalescer.child_events()),
+        )
+        .with_config(controller::Config::default().debounce(coalesce::debounce_from_env()));
+    (controller, stores)
 }
 
 // ============================================================================
@@ -589,53 +2126,372 @@ pub fn error_policy(myapp: Arc<MyApp>, error: &ReconcileError, ctx: Arc<Context>
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let args: Vec<String> = std::env::args().collect();
 
+    // Installed as kubectl-myapp: only the client-side subcommands
+    if kubectl::invoked_as_plugin(&args[0]) {
+        return kubectl::run(&args[1..]).await;
+    }
+
     if args.len() > 1 && args[1] == "webhook" {
+        if args.iter().any(|a| a == "--dev") {
+            return Err("admission webhooks are disabled in developer mode".into());
+        }
         // Run webhook server
-        run_webhook_server().await;
+        let log_level = LogLevel::init();
+        metrics::configure(MetricsConfig::from_env());
+        let mut state = AdmissionState::from_env().await?;
+        state.config = OperatorConfig::from_env(Client::try_default().await?);
+        state.config.apply_log_level(log_level);
+        let mut readiness = Readiness::new(Client::try_default().await?);
+        if let Some(lister) = &state.lister {
+            readiness = readiness.cache("admission cache", lister.clone());
+        }
+        let http = ServerConfig::from_env()?;
+        let (restart, shutdown) = Shutdown::install_with_trigger();
+        let metrics = MetricsCollector::new();
+        let routes = server::routes(webhook_routes(state, metrics.clone()));
+        let servers = match CertSource::from_args(&args)? {
+            CertSource::Files => HttpServer::default().mount_tls(
+                "webhook",
+                http.webhook_addr,
+                routes,
+                TlsConfig::from_env()?,
+            ),
+            CertSource::SelfSigned => {
+                let bootstrap = CertBootstrap::from_env();
+                let client = Client::try_default().await?;
+                let (tls, bundle) = bootstrap.ensure(client.clone()).await?;
+                bootstrap.spawn_rotation(client, bundle, restart);
+                HttpServer::default().mount_tls("webhook", http.webhook_addr, routes, Some(tls))
+            }
+            CertSource::CertManager => {
+                let cert = Arc::new(ReloadableCert::default());
+                CertManager::from_env()?
+                    .start(Client::try_default().await?, cert.clone())
+                    .await?;
+                HttpServer::default().mount_reloadable_tls(
+                    "webhook",
+                    http.webhook_addr,
+                    routes,
+                    cert,
+                )
+            }
+        };
+        // Webhook counters and durations, behind the same auth as the controller's /metrics
+        let metrics_auth = MetricsAuth::from_env(Client::try_default().await?)?;
+        let metrics_routes = metrics_auth
+            .protect(metrics_handler())
+            .recover(metrics_auth::handle_rejection);
+        let servers = servers
+            .mount("metrics", http.metrics_addr, server::routes(metrics_routes))
+            .mount(
+                "health",
+                http.health_addr,
+                server::routes(health_handler(Watchdog::default()).or(ready_handler(readiness))),
+            )
+            .start(metrics, shutdown)?;
+        futures::future::join_all(servers).await;
     } else if args.len() > 1 && args[1] == "generate-crd" {
-        // Generate CRD YAML
-        let crd = MyApp::crd();
-        let yaml = serde_yaml::to_string(&crd)?;
-
-        std::fs::write("crd.yaml", yaml)?;
-        println!("CRD written to crd.yaml");
+        // Generate CRD YAML (with CEL rules), optionally as a full install bundle
+        install::run(&args[2..])?;
+    } else if args.len() > 1 && args[1] == "generate-webhooks" {
+        // Print webhook configurations matching the served handlers
+        webhooks::run(&args[2..])?;
+    } else if args.len() > 1 && args[1] == "generate-rbac" {
+        // Print the controller's ServiceAccount, roles and bindings
+        rbac::run(&args[2..])?;
+    } else if args.len() > 1 && args[1] == "generate-helm" {
+        // Write a Helm chart for the operator
+        helm::run(&args[2..])?;
+    } else if args.len() > 1 && args[1] == "generate-kustomize" {
+        // Write a kustomize base and example overlays
+        kustomize::run(&args[2..])?;
+    } else if args.len() > 1 && args[1] == "generate-dashboard" {
+        // Print a Grafana dashboard for the controller metrics
+        dashboard::run(&args[2..])?;
+    } else if args.len() > 1 && args[1] == "plan" {
+        // Show what reconcile would change, without applying it
+        plan::run(&args[2..]).await?;
+    } else if args.len() > 1 && args[1] == "export" {
+        // Dump a MyApp and the objects it owns as re-appliable YAML
+        export::run(&args[2..]).await?;
+    } else if args.len() > 1 && args[1] == "status" {
+        // Summarize state, readiness and last error of every MyApp
+        summary::run(&args[2..]).await?;
+    } else if args.len() > 1 && args[1] == "doctor" {
+        // Check the installation's CRD, RBAC, webhooks and metrics
+        doctor::run(&args[2..]).await?;
+    } else if args.len() > 1 && args[1] == "report" {
+        // Print a fleet-wide report of all MyApps
+        report::run(&args[2..]).await?;
+    } else if args.len() > 1 && args[1] == "migrate-storage" {
+        // Rewrite MyApps in the storage version and prune old storedVersions
+        migrate::run(&args[2..]).await?;
+    } else if args.len() > 1 && args[1] == "validate" {
+        // Check MyApp manifests against the CRD schema and webhook rules, offline
+        offline::run(&args[2..])?;
+    } else if args.len() > 1 && args[1] == "lint" {
+        // `validate` with the advisory limit:request ratio
+        offline::lint(&args[2..])?;
     } else {
-        // Run controller
-        let client = Client::try_default().await?;
-        let metrics = MetricsCollector::new();
-        let context = Arc::new(Context {
-            client: client.clone(),
-            metrics,
-        });
-
-        let myapps = Api::<MyApp>::all(client);
-
-        // Start metrics server
-        let metrics_routes = metrics_handler().or(health_handler()).or(ready_handler());
+        // Run controller; the client reports 429s so every reconcile backs off together
+        let log_level = LogLevel::init();
+        metrics::configure(MetricsConfig::from_env());
+        let metrics = MetricsCollector::with_cardinality(Cardinality::from_env());
+        let throttle = ApiThrottle::default();
+        let client = throttle
+            .client(ClientTuning::from_env()?, metrics.clone())
+            .await?;
+        let namespace_filter = NamespaceFilter::from_env();
+        let scope = WatchScope::from_args_env(&args).restrict(&namespace_filter);
+        let mut label_selector = scope::label_selector_from_args_env(&args);
+        if let Some(dev) = DevMode::from_args(&args)? {
+            tracing::info!(
+                "Developer mode: session {} in namespace {}, children prefixed {}",
+                dev.session,
+                dev.namespace,
+                dev.prefix()
+            );
+            label_selector = Some(dev.label_selector(label_selector.as_deref()));
+            dev::enable(dev);
+        }
+        // Nothing to run; exit non-zero so the misconfiguration does not pass as a clean run
+        if scope == WatchScope::Namespaces(Vec::new()) {
+            return Err("no namespaces left to watch after applying the namespace filter".into());
+        }
+        tracing::info!(
+            "Watching MyApps in {:?} matching {}",
+            scope,
+            label_selector.as_deref().unwrap_or("any labels")
+        );
+        let shard = Shard::from_env()?;
+        if shard.count > 1 {
+            tracing::info!("Reconciling shard {} of the MyApps", shard);
+        }
+        let image_policy = ImagePolicy::from_env()?;
+
+        // Follow the MyAppOperatorConfig (or config file) for tuning applied without a restart,
+        // reporting what was applied on the resource's status
+        let config = OperatorConfig::from_env(client.clone());
+        let config_status = ConfigStatus::from_env(client.clone());
+        config.report_status(config_status.clone());
+        tokio::spawn(
+            throttle
+                .clone()
+                .report(config_status.clone(), std::time::Duration::from_secs(5)),
+        );
+
+        // Start background image freshness check, limited to the MyApps reconcile would handle
+        if image_policy.is_enabled() {
+            let interval = std::env::var("MYAPP_IMAGE_POLICY_INTERVAL_SECS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(600);
+            let (policy_filter, policy_config) = (namespace_filter.clone(), config.clone());
+            let owns = move |myapp: &MyApp| {
+                let ns = myapp.namespace().unwrap_or_default();
+                policy_filter.allows(&ns)
+                    && policy_config.get().namespace_filter.allows(&ns)
+                    && shard.owns_myapp(myapp)
+            };
+            tokio::spawn(image_policy::run_background_check(
+                client.clone(),
+                scope.clone(),
+                scope::list_params(label_selector.as_deref(), &namespace_filter),
+                owns,
+                image_policy.clone(),
+                metrics.clone(),
+                std::time::Duration::from_secs(interval),
+            ));
+        }
 
-        tokio::spawn(async {
-            println!("Starting metrics server on :8080");
-            warp::serve(metrics_routes).run(([0, 0, 0, 0], 8080)).await;
-        });
+        config.apply_log_level(log_level.clone());
+        let features = Features::from_env(config.clone())?;
+        tracing::info!("Features: {:?}", features.summary());
+
+        // Start self-monitoring of the controller's cgroup usage
+        let limiter = ReconcileLimiter::from_env();
+        tokio::spawn(pressure::run_monitor(
+            limiter.clone(),
+            config.clone(),
+            config_status.clone(),
+            metrics.clone(),
+            std::time::Duration::from_secs(15),
+        ));
+
+        // Detect IPv4/IPv6 support for Service validation
+        let ip_families = network::detect_ip_families(client.clone())
+            .await
+            .unwrap_or_else(|e| {
+                tracing::warn!("Could not detect cluster IP families: {}", e);
+                BTreeSet::new()
+            });
+        tracing::info!("Cluster IP families: {:?}", ip_families);
+        let monitoring = monitoring::detect(client.clone()).await;
+        tracing::info!("Prometheus Operator CRDs: {:?}", monitoring);
+        let external_secrets = external_secrets::detect(client.clone()).await;
+        tracing::info!("External Secrets Operator CRD: {}", external_secrets);
+        let vault = VaultClient::from_env();
+        if vault.is_some() {
+            tracing::info!("Syncing spec.vaultSecrets from Vault");
+        }
+        let scan_gate = ScanGate::from_env();
+        if scan_gate.is_some() {
+            tracing::info!("Gating new images on vulnerability scans");
+        }
 
-        // Start health server
-        tokio::spawn(async {
-            let health_routes = health_handler().or(ready_handler());
-            println!("Starting health server on :8081");
-            warp::serve(health_routes).run(([0, 0, 0, 0], 8081)).await;
+        let context = Arc::new(Context {
+            client: client.clone(),
+            metrics: metrics.clone(),
+            image_policy,
+            limiter: PriorityLimiter::new(limiter),
+            ip_families,
+            monitoring,
+            namespace_filter: namespace_filter.clone(),
+            backoff: ErrorBackoff::default(),
+            throttle,
+            config: config.clone(),
+            features,
+            namespace_rate: NamespaceRateLimiter::from_env(),
+            coalescer: Coalescer::new(metrics.clone()),
+            shard,
+            vault,
+            external_secrets,
+            scan_gate,
+            cosign: Verifier::from_env(),
         });
 
-        println!("Starting MyApp controller...");
-        Controller::new(myapps, Default::default())
-            .run(reconcile, error_policy, context)
-            .for_each(|res| async move {
-                match res {
-                    Ok(o) => println!("Reconciled: {:?}", o),
-                    Err(e) => eprintln!("Reconcile error: {:?}", e),
-                }
+        let shutdown = Shutdown::install();
+
+        // One controller per watched namespace so namespace-scoped RBAC is enough
+        let watchdog = Watchdog::new(watchdog::staleness_from_env());
+        let controllers: Vec<_> = scope
+            .namespaces()
+            .into_iter()
+            .map(|namespace| {
+                let probe = watchdog.stream(namespace.unwrap_or("all-namespaces"));
+                let (controller, stores) = build_controller(
+                    client.clone(),
+                    namespace,
+                    scope::watcher_config(label_selector.as_deref(), &namespace_filter),
+                    &probe,
+                    &context.coalescer,
+                    &context.backoff,
+                    context.shard,
+                );
+                let ctx = context.clone();
+                probe.track(controller.store(), move |myapp| ctx.requeues(myapp));
+                // Namespace filters may have widened; revisit everything on a config change
+                let controller = controller.reconcile_all_on(config.changes());
+                (namespace, controller, probe, stores)
             })
-            .await;
+            .collect();
+        let readiness = controllers.iter().fold(
+            Readiness::new(client.clone()),
+            |readiness, (ns, controller, _, _)| {
+                readiness.store(ns.unwrap_or("all-namespaces"), controller.store())
+            },
+        );
+
+        // Metrics/debug and health routes, on one listener if both use the same address
+        let http = ServerConfig::from_env()?;
+        let metrics_auth = MetricsAuth::from_env(client.clone())?;
+        // Status and plans are served from the controllers' caches instead of API GETs
+        let stores =
+            controllers
+                .iter()
+                .fold(SharedStores::default(), |mut shared, (_, _, _, stores)| {
+                    shared.push(stores.clone());
+                    shared
+                });
+        if let Some((interval, mode)) = drift::from_env()? {
+            tokio::spawn(drift::run_sweep(
+                client.clone(),
+                stores.clone(),
+                context.shard,
+                context.metrics.clone(),
+                interval,
+                mode,
+            ));
+        }
+        if let Some(interval) = image_update::interval_from_env() {
+            tokio::spawn(image_update::run_sweep(
+                client.clone(),
+                stores.clone(),
+                context.shard,
+                context.metrics.clone(),
+                interval,
+            ));
+        }
+        // Every admin route (metrics, log level, watch caches, tasks) shares the /metrics auth
+        let metrics_routes = metrics_handler()
+            .or(logging::loglevel_handler(log_level))
+            .or(shared_store::handler(stores, client.clone()));
+        #[cfg(feature = "console")]
+        let metrics_routes = metrics_routes.or(diagnostics::tasks_handler());
+        let metrics_routes = metrics_auth
+            .protect(metrics_routes)
+            .recover(metrics_auth::handle_rejection);
+        HttpServer::default()
+            .mount("metrics", http.metrics_addr, server::routes(metrics_routes))
+            .mount(
+                "health",
+                http.health_addr,
+                server::routes(health_handler(watchdog).or(ready_handler(readiness))),
+            )
+            .start(context.metrics.clone(), shutdown.clone())?;
+
+        tracing::info!("Starting MyApp controller...");
+        let controllers = controllers
+            .into_iter()
+            .map(|(namespace, controller, probe, _)| {
+                let context = context.clone();
+                controller
+                    // Stop picking up new work on shutdown and let in-flight reconciles finish
+                    .graceful_shutdown_on(shutdown.clone().wait())
+                    .run(reconcile, error_policy, context.clone())
+                    .for_each(move |res| {
+                        let context = context.clone();
+                        if !matches!(res, Err(controller::Error::QueueError(_))) {
+                            probe.reconciled();
+                        }
+                        #[cfg(feature = "console")]
+                        diagnostics::touch(namespace.unwrap_or("all-namespaces"));
+                        async move {
+                            match res {
+                                Ok((o, _)) => {
+                                    tracing::info!("Reconciled: {:?}", o);
+                                    let key = format!(
+                                        "{}/{}",
+                                        o.namespace.as_deref().unwrap_or_default(),
+                                        o.name
+                                    );
+                                    context.backoff.reset(&key);
+                                }
+                                Err(controller::Error::QueueError(e)) => {
+                                    tracing::warn!(
+                                        "Watch failed, restarting with backoff: {:?}",
+                                        e
+                                    );
+                                    context.metrics.record_watcher_restart(
+                                        namespace.unwrap_or("all-namespaces"),
+                                    );
+                                }
+                                Err(e) => tracing::error!("Reconcile error: {:?}", e),
+                            }
+                        }
+                    })
+            });
+
+        let timeout = shutdown::timeout_from_env();
+        tokio::select! {
+            _ = futures::future::join_all(controllers) => tracing::info!("Controller stopped"),
+            _ = shutdown.deadline(timeout) => tracing::warn!(
+                "In-flight reconciles did not finish within {:?}, exiting",
+                timeout
+            ),
+        }
     }
 
+    telemetry::shutdown();
     Ok(())
 }
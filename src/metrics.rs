@@ -1,74 +1,405 @@
 // Metrics module for MyApp Controller
 // Provides Prometheus metrics for monitoring controller performance
 
+use crate::readiness::Readiness;
+use crate::watchdog::Watchdog;
+use crate::Condition;
+use crate::{exemplars, telemetry};
 use prometheus::{
-    register_counter_vec, register_gauge_vec, register_histogram_vec, CounterVec, Encoder,
-    GaugeVec, HistogramVec, TextEncoder,
+    register_counter, register_counter_vec, register_gauge, register_gauge_vec,
+    register_histogram_vec, Counter, CounterVec, Encoder, Gauge, GaugeVec, HistogramVec,
+    TextEncoder,
 };
-use std::time::Instant;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use warp::http::StatusCode;
 use warp::{Filter, Reply};
 
+/// Metric naming and histogram layout, fixed before the first metric is registered
+#[derive(Debug, Clone, PartialEq)]
+pub struct MetricsConfig {
+    /// Prefix for every metric name, without the trailing underscore
+    pub prefix: String,
+    pub reconcile_buckets: Vec<f64>,
+    pub webhook_buckets: Vec<f64>,
+}
+
+impl Default for MetricsConfig {
+    fn default() -> Self {
+        Self {
+            prefix: "myapp".to_string(),
+            reconcile_buckets: vec![0.01, 0.1, 0.5, 1.0, 5.0, 10.0, 30.0],
+            webhook_buckets: vec![0.001, 0.01, 0.1, 0.5, 1.0],
+        }
+    }
+}
+
+impl MetricsConfig {
+    /// Read `MYAPP_METRICS_PREFIX`, `MYAPP_RECONCILE_BUCKETS` and `MYAPP_WEBHOOK_BUCKETS`
+    /// (comma-separated seconds); invalid values keep the defaults
+    pub fn from_env() -> Self {
+        let default = Self::default();
+        let prefix = match std::env::var("MYAPP_METRICS_PREFIX") {
+            Ok(prefix) if valid_prefix(&prefix) => prefix,
+            Ok(prefix) => {
+                tracing::warn!("Ignoring invalid MYAPP_METRICS_PREFIX '{}'", prefix);
+                default.prefix
+            }
+            Err(_) => default.prefix,
+        };
+        let buckets = |var: &str, default: Vec<f64>| match std::env::var(var) {
+            Ok(value) => parse_buckets(&value).unwrap_or_else(|e| {
+                tracing::warn!("Ignoring invalid {} '{}': {}", var, value, e);
+                default
+            }),
+            Err(_) => default,
+        };
+
+        Self {
+            prefix,
+            reconcile_buckets: buckets("MYAPP_RECONCILE_BUCKETS", default.reconcile_buckets),
+            webhook_buckets: buckets("MYAPP_WEBHOOK_BUCKETS", default.webhook_buckets),
+        }
+    }
+}
+
+fn valid_prefix(prefix: &str) -> bool {
+    let mut chars = prefix.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_' || c == ':')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == ':')
+}
+
+/// Parse histogram bucket bounds such as `0.05,0.25,1,5`
+pub fn parse_buckets(value: &str) -> Result<Vec<f64>, String> {
+    let buckets = value
+        .split(',')
+        .map(|b| {
+            b.trim()
+                .parse::<f64>()
+                .map_err(|_| format!("'{}' is not a number", b.trim()))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    if !buckets.windows(2).all(|w| w[0] < w[1]) {
+        return Err("buckets must be strictly increasing".to_string());
+    }
+    if buckets.iter().any(|b| !b.is_finite() || *b <= 0.0) {
+        return Err("buckets must be positive".to_string());
+    }
+    Ok(buckets)
+}
+
+static CONFIG: OnceLock<MetricsConfig> = OnceLock::new();
+
+/// Apply the naming and bucket layout; must run before any metric is touched
+pub fn configure(config: MetricsConfig) {
+    if CONFIG.set(config).is_err() {
+        tracing::warn!("Metrics already configured, keeping the first configuration");
+    }
+}
+
+fn config() -> &'static MetricsConfig {
+    CONFIG.get_or_init(MetricsConfig::default)
+}
+
+fn metric_name(name: &str) -> String {
+    format!("{}_{}", config().prefix, name)
+}
+
 // Metric definitions
 lazy_static::lazy_static! {
     // Reconciliation metrics
     static ref RECONCILE_COUNTER: CounterVec = register_counter_vec!(
-        "myapp_reconcile_total",
+        metric_name("reconcile_total"),
         "Total number of reconciliation attempts",
         &["namespace", "name", "result"]
     ).unwrap();
 
     static ref RECONCILE_DURATION: HistogramVec = register_histogram_vec!(
-        "myapp_reconcile_duration_seconds",
+        metric_name("reconcile_duration_seconds"),
         "Time spent in reconciliation",
         &["namespace", "name"],
-        vec![0.01, 0.1, 0.5, 1.0, 5.0, 10.0, 30.0]
+        config().reconcile_buckets.clone()
     ).unwrap();
 
     // Resource metrics
     static ref MANAGED_RESOURCES: GaugeVec = register_gauge_vec!(
-        "myapp_managed_resources_total",
+        metric_name("managed_resources_total"),
         "Number of resources managed by controller",
         &["resource_type", "namespace"]
     ).unwrap();
 
     // Error metrics
     static ref ERROR_COUNTER: CounterVec = register_counter_vec!(
-        "myapp_errors_total",
+        metric_name("errors_total"),
         "Total number of errors by type",
         &["error_type", "namespace"]
     ).unwrap();
 
+    static ref SKIPPED_COUNTER: CounterVec = register_counter_vec!(
+        metric_name("skipped_objects_total"),
+        "MyApps seen by the controller but not reconciled",
+        &["reason", "namespace"]
+    ).unwrap();
+
+    static ref API_THROTTLED: Counter = register_counter!(
+        metric_name("api_throttled_total"),
+        "API server responses with 429 Too Many Requests"
+    ).unwrap();
+
+    static ref API_THROTTLE_ADVISED: Counter = register_counter!(
+        metric_name("api_throttle_advised_seconds_total"),
+        "Retry-After seconds advised by the API server"
+    ).unwrap();
+
+    static ref API_THROTTLE_WAIT: Counter = register_counter!(
+        metric_name("api_throttle_wait_seconds_total"),
+        "Time reconciles spent waiting out API server throttling"
+    ).unwrap();
+
+    static ref CHILD_APPLIES: CounterVec = register_counter_vec!(
+        metric_name("child_applies_total"),
+        "Existing children checked by reconcile, by whether their spec hash required an update",
+        &["kind", "outcome"]
+    ).unwrap();
+
+    static ref DRIFT_DETECTED: CounterVec = register_counter_vec!(
+        metric_name("drift_detected_total"),
+        "Drift sweeps that found a child changed outside the controller",
+        &["namespace"]
+    ).unwrap();
+
+    static ref DRIFTED_MYAPPS: Gauge = register_gauge!(
+        metric_name("drifted_myapps"),
+        "MyApps whose children had drifted in the last sweep"
+    ).unwrap();
+
+    static ref IMAGE_UPDATES: CounterVec = register_counter_vec!(
+        metric_name("image_updates_detected_total"),
+        "Newer image tags found by the image update sweep, by whether they were applied",
+        &["namespace", "outcome"]
+    ).unwrap();
+
+    static ref IMAGE_SCANS: CounterVec = register_counter_vec!(
+        metric_name("image_scans_total"),
+        "Vulnerability gate decisions for new images (passed, blocked, overridden, error)",
+        &["outcome"]
+    ).unwrap();
+
+    static ref IMAGE_VERIFICATIONS: CounterVec = register_counter_vec!(
+        metric_name("image_signature_checks_total"),
+        "Cosign verifications of new images under spec.imageVerification, by outcome",
+        &["namespace", "outcome"]
+    ).unwrap();
+
+    static ref WEBHOOK_CHECKS_SKIPPED: CounterVec = register_counter_vec!(
+        metric_name("webhook_checks_skipped_total"),
+        "Admission checks admitted without running because their cluster cache was stale",
+        &["check"]
+    ).unwrap();
+
+    static ref HARDENING_DEFAULTS: CounterVec = register_counter_vec!(
+        metric_name("hardening_defaults_applied_total"),
+        "Container hardening defaults added by the mutating webhook, by field",
+        &["field"]
+    ).unwrap();
+
+    static ref RECONCILES_COALESCED: CounterVec = register_counter_vec!(
+        metric_name("reconcile_events_coalesced_total"),
+        "Watch events folded into a reconcile triggered by an earlier event",
+        &["namespace"]
+    ).unwrap();
+
+    static ref NAMESPACE_RATE_LIMITED: CounterVec = register_counter_vec!(
+        metric_name("namespace_rate_limited_total"),
+        "Reconciles deferred by the per-namespace rate limit",
+        &["namespace"]
+    ).unwrap();
+
+    static ref NAMESPACE_RATE_DELAY: CounterVec = register_counter_vec!(
+        metric_name("namespace_rate_limit_delay_seconds_total"),
+        "Time reconciles were deferred by the per-namespace rate limit",
+        &["namespace"]
+    ).unwrap();
+
+    static ref CLIENT_RATE_LIMITED: Counter = register_counter!(
+        metric_name("client_rate_limited_total"),
+        "API requests held back by the client-side QPS limit"
+    ).unwrap();
+
+    static ref CLIENT_RATE_LIMIT_WAIT: Counter = register_counter!(
+        metric_name("client_rate_limit_wait_seconds_total"),
+        "Time API requests waited for the client-side QPS limit"
+    ).unwrap();
+
+    static ref CLIENT_TIMEOUTS: Counter = register_counter!(
+        metric_name("client_request_timeouts_total"),
+        "API requests that hit the client's connect, read or write timeout"
+    ).unwrap();
+
+    static ref CONFLICT_RETRIES: CounterVec = register_counter_vec!(
+        metric_name("conflict_retries_total"),
+        "Writes retried after an optimistic-concurrency conflict",
+        &["operation"]
+    ).unwrap();
+
     // Webhook metrics
     static ref WEBHOOK_COUNTER: CounterVec = register_counter_vec!(
-        "myapp_webhook_requests_total",
+        metric_name("webhook_requests_total"),
         "Total webhook requests",
         &["webhook_type", "result"]
     ).unwrap();
 
     static ref WEBHOOK_DURATION: HistogramVec = register_histogram_vec!(
-        "myapp_webhook_duration_seconds",
+        metric_name("webhook_duration_seconds"),
         "Webhook request duration",
         &["webhook_type"],
-        vec![0.001, 0.01, 0.1, 0.5, 1.0]
+        config().webhook_buckets.clone()
     ).unwrap();
 
     // Controller health metrics
     static ref CONTROLLER_INFO: GaugeVec = register_gauge_vec!(
-        "myapp_controller_info",
+        metric_name("controller_info"),
         "Controller version and build info",
         &["version", "build_date", "git_commit"]
     ).unwrap();
 
     static ref ACTIVE_RECONCILES: GaugeVec = register_gauge_vec!(
-        "myapp_active_reconciles",
+        metric_name("active_reconciles"),
         "Number of active reconciliation loops",
         &["namespace"]
     ).unwrap();
+
+    // Work-queue metrics
+    static ref RECONCILE_QUEUE_DEPTH: Gauge = register_gauge!(
+        metric_name("reconcile_queue_depth"),
+        "Reconciles waiting for a concurrency slot or for API server throttling to clear"
+    ).unwrap();
+
+    static ref RECONCILE_LAG: GaugeVec = register_gauge_vec!(
+        metric_name("reconcile_lag_seconds"),
+        "Seconds since the last successful reconcile of each MyApp",
+        &["namespace", "name"]
+    ).unwrap();
+
+    static ref HTTP_REQUESTS: CounterVec = register_counter_vec!(
+        metric_name("http_requests_total"),
+        "Requests served by the controller's HTTP listeners",
+        &["server", "code"]
+    ).unwrap();
+
+    static ref WATCHER_RESTARTS: CounterVec = register_counter_vec!(
+        metric_name("watcher_restarts_total"),
+        "Watch streams that failed and were restarted with backoff",
+        &["scope"]
+    ).unwrap();
+
+    static ref RESOURCE_CONDITION: GaugeVec = register_gauge_vec!(
+        metric_name("resource_condition"),
+        "Status conditions of each MyApp; 1 for the current status of each type",
+        &["namespace", "name", "type", "status"]
+    ).unwrap();
+
+    /// Condition types exported per MyApp, so vanished conditions can be removed
+    static ref CONDITION_TYPES: Mutex<HashMap<(String, String), BTreeSet<String>>> =
+        Mutex::new(HashMap::new());
+
+    /// Last successful reconcile per MyApp, turned into RECONCILE_LAG at scrape time
+    static ref LAST_SUCCESS: Mutex<HashMap<(String, String), Instant>> = Mutex::new(HashMap::new());
+
+    // Image policy metrics
+    static ref OUTDATED_IMAGES: GaugeVec = register_gauge_vec!(
+        metric_name("outdated_image"),
+        "Whether a MyApp runs an outdated or end-of-life image (1 = outdated)",
+        &["namespace", "name", "image"]
+    ).unwrap();
+
+    // Self-monitoring metrics
+    static ref SELF_PRESSURE: Gauge = register_gauge!(
+        metric_name("controller_self_pressure"),
+        "Controller resource pressure (0 = normal, 1 = elevated, 2 = critical)"
+    ).unwrap();
+
+    static ref RESOURCE_USAGE: GaugeVec = register_gauge_vec!(
+        metric_name("controller_resource_usage_ratio"),
+        "Controller usage of its own cgroup limit",
+        &["resource"]
+    ).unwrap();
+
+    static ref RECONCILE_CONCURRENCY: Gauge = register_gauge!(
+        metric_name("controller_reconcile_concurrency"),
+        "Current cap on concurrent reconciles"
+    ).unwrap();
+}
+
+/// Label value used once the series cap is reached
+pub const OVERFLOW_LABEL: &str = "_overflow";
+
+/// How reconcile metrics identify the MyApp they belong to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NameLabels {
+    /// `namespace` and `name` labels per MyApp
+    PerName,
+    /// Empty `name` label, aggregated per namespace
+    PerNamespace,
+    /// Empty `namespace` and `name` labels, aggregated cluster-wide
+    None,
+}
+
+/// Cardinality limits for the reconcile counter and duration histogram
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cardinality {
+    pub name_labels: NameLabels,
+    /// Distinct namespace/name pairs before new ones go to the overflow bucket (0 = unlimited)
+    pub max_series: usize,
+}
+
+impl Default for Cardinality {
+    fn default() -> Self {
+        Self {
+            name_labels: NameLabels::PerName,
+            max_series: 1000,
+        }
+    }
+}
+
+impl Cardinality {
+    /// Read `MYAPP_METRICS_NAME_LABELS` (name, namespace or none) and `MYAPP_METRICS_MAX_SERIES`
+    pub fn from_env() -> Self {
+        let default = Self::default();
+        let name_labels = match std::env::var("MYAPP_METRICS_NAME_LABELS").as_deref() {
+            Ok("namespace") => NameLabels::PerNamespace,
+            Ok("none") => NameLabels::None,
+            Ok("name") | Err(_) => default.name_labels,
+            Ok(other) => {
+                tracing::warn!(
+                    "Ignoring unknown MYAPP_METRICS_NAME_LABELS '{}', labelling by name",
+                    other
+                );
+                default.name_labels
+            }
+        };
+        let max_series = std::env::var("MYAPP_METRICS_MAX_SERIES")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(default.max_series);
+
+        Self {
+            name_labels,
+            max_series,
+        }
+    }
 }
 
 /// Metrics collector for tracking controller performance
+#[derive(Clone)]
 pub struct MetricsCollector {
     start_time: Instant,
+    cardinality: Cardinality,
+    /// Namespace/name label pairs handed out so far
+    series: Arc<Mutex<HashSet<(String, String)>>>,
 }
 
 impl Default for MetricsCollector {
@@ -79,6 +410,10 @@ impl Default for MetricsCollector {
 
 impl MetricsCollector {
     pub fn new() -> Self {
+        Self::with_cardinality(Cardinality::default())
+    }
+
+    pub fn with_cardinality(cardinality: Cardinality) -> Self {
         // Initialize controller info metric
         CONTROLLER_INFO
             .with_label_values(&[
@@ -90,15 +425,37 @@ impl MetricsCollector {
 
         Self {
             start_time: Instant::now(),
+            cardinality,
+            series: Arc::new(Mutex::new(HashSet::new())),
+        }
+    }
+
+    /// Namespace and name labels for a MyApp's reconcile metrics
+    fn reconcile_labels(&self, namespace: &str, name: &str) -> (String, String) {
+        let labels = match self.cardinality.name_labels {
+            NameLabels::PerName => (namespace.to_string(), name.to_string()),
+            NameLabels::PerNamespace => (namespace.to_string(), String::new()),
+            NameLabels::None => (String::new(), String::new()),
+        };
+        let mut series = self.series.lock().unwrap();
+        let max = self.cardinality.max_series;
+        if series.contains(&labels) || max == 0 || series.len() < max {
+            series.insert(labels.clone());
+            labels
+        } else {
+            (OVERFLOW_LABEL.to_string(), OVERFLOW_LABEL.to_string())
         }
     }
 
     /// Start timing a reconciliation
     pub fn start_reconcile(&self, namespace: &str, name: &str) -> ReconcileTimer {
         ACTIVE_RECONCILES.with_label_values(&[namespace]).inc();
+        let (label_namespace, label_name) = self.reconcile_labels(namespace, name);
         ReconcileTimer {
             namespace: namespace.to_string(),
             name: name.to_string(),
+            label_namespace,
+            label_name,
             start: Instant::now(),
         }
     }
@@ -110,6 +467,167 @@ impl MetricsCollector {
             .inc();
     }
 
+    /// Record a write retried after a conflict (status, finalizer)
+    pub fn record_conflict_retry(&self, operation: &str) {
+        CONFLICT_RETRIES.with_label_values(&[operation]).inc();
+    }
+
+    /// Record a 429 from the API server and the Retry-After it advised
+    pub fn record_throttled(&self, retry_after: Duration) {
+        API_THROTTLED.inc();
+        API_THROTTLE_ADVISED.inc_by(retry_after.as_secs_f64());
+    }
+
+    /// Record time a reconcile was held back by API server throttling
+    pub fn record_throttle_wait(&self, waited: Duration) {
+        API_THROTTLE_WAIT.inc_by(waited.as_secs_f64());
+    }
+
+    /// Record an API request held back by the client-side QPS limit
+    pub fn record_client_rate_limited(&self, delay: Duration) {
+        CLIENT_RATE_LIMITED.inc();
+        CLIENT_RATE_LIMIT_WAIT.inc_by(delay.as_secs_f64());
+    }
+
+    /// Record an API request that timed out
+    pub fn record_client_timeout(&self) {
+        CLIENT_TIMEOUTS.inc();
+    }
+
+    /// Record an existing child that was updated or left alone as unchanged
+    pub fn record_child_apply(&self, kind: &str, applied: bool) {
+        let outcome = if applied { "applied" } else { "unchanged" };
+        CHILD_APPLIES.with_label_values(&[kind, outcome]).inc();
+    }
+
+    /// Record a child found to differ from its MyApp's spec
+    pub fn record_drift(&self, namespace: &str) {
+        DRIFT_DETECTED.with_label_values(&[namespace]).inc();
+    }
+
+    /// Set the number of drifted MyApps found by the last sweep
+    pub fn set_drifted(&self, count: usize) {
+        DRIFTED_MYAPPS.set(count as f64);
+    }
+
+    /// Record a newer image tag found for a MyApp's spec.imagePolicy
+    pub fn record_image_update(&self, namespace: &str, applied: bool) {
+        let outcome = if applied { "applied" } else { "reported" };
+        IMAGE_UPDATES.with_label_values(&[namespace, outcome]).inc();
+    }
+
+    /// Record a vulnerability gate decision
+    pub fn record_scan(&self, outcome: &str) {
+        IMAGE_SCANS.with_label_values(&[outcome]).inc();
+    }
+
+    /// Record a cosign verification of a MyApp's image
+    pub fn record_signature_check(&self, namespace: &str, verified: bool) {
+        let outcome = if verified { "verified" } else { "rejected" };
+        IMAGE_VERIFICATIONS
+            .with_label_values(&[namespace, outcome])
+            .inc();
+    }
+
+    /// Record a hardening default filled in by the mutating webhook
+    pub fn record_hardening_default(&self, field: &str) {
+        HARDENING_DEFAULTS.with_label_values(&[field]).inc();
+    }
+
+    /// Record an admission check skipped because the state it needs is not fresh
+    pub fn record_webhook_check_skipped(&self, check: &str) {
+        WEBHOOK_CHECKS_SKIPPED.with_label_values(&[check]).inc();
+    }
+
+    /// Record watch events absorbed by a single reconcile
+    pub fn record_coalesced(&self, namespace: &str, events: u64) {
+        RECONCILES_COALESCED
+            .with_label_values(&[namespace])
+            .inc_by(events as f64);
+    }
+
+    /// Record a reconcile deferred by its namespace's rate limit
+    pub fn record_namespace_rate_limited(&self, namespace: &str, delay: Duration) {
+        NAMESPACE_RATE_LIMITED.with_label_values(&[namespace]).inc();
+        NAMESPACE_RATE_DELAY
+            .with_label_values(&[namespace])
+            .inc_by(delay.as_secs_f64());
+    }
+
+    /// Count a reconcile waiting to start until the guard is dropped
+    pub fn enter_queue(&self) -> QueueGuard {
+        RECONCILE_QUEUE_DEPTH.inc();
+        QueueGuard
+    }
+
+    pub fn record_http_request(&self, server: &str, code: u16) {
+        HTTP_REQUESTS
+            .with_label_values(&[server, &code.to_string()])
+            .inc();
+    }
+
+    pub fn record_watcher_restart(&self, scope: &str) {
+        WATCHER_RESTARTS.with_label_values(&[scope]).inc();
+    }
+
+    /// Export the conditions just written to a MyApp's status
+    pub fn set_conditions(&self, namespace: &str, name: &str, conditions: &[Condition]) {
+        let key = (namespace.to_string(), name.to_string());
+        let current: BTreeSet<String> = conditions.iter().map(|c| c.r#type.clone()).collect();
+        let mut exported = CONDITION_TYPES.lock().unwrap();
+        for gone in exported.get(&key).into_iter().flatten() {
+            if !current.contains(gone) {
+                remove_condition(namespace, name, gone);
+            }
+        }
+
+        for condition in conditions {
+            for status in CONDITION_STATUSES {
+                RESOURCE_CONDITION
+                    .with_label_values(&[namespace, name, &condition.r#type, status])
+                    .set(if condition.status == status { 1.0 } else { 0.0 });
+            }
+        }
+        exported.insert(key, current);
+    }
+
+    /// Stop reporting lag and conditions for a deleted MyApp
+    pub fn forget_object(&self, namespace: &str, name: &str) {
+        let key = (namespace.to_string(), name.to_string());
+        for type_ in CONDITION_TYPES
+            .lock()
+            .unwrap()
+            .remove(&key)
+            .into_iter()
+            .flatten()
+        {
+            remove_condition(namespace, name, &type_);
+        }
+        LAST_SUCCESS.lock().unwrap().remove(&key);
+        let _ = RECONCILE_LAG.remove_label_values(&[namespace, name]);
+
+        // Per-name series of a deleted MyApp free their slot under the cap
+        if self.cardinality.name_labels == NameLabels::PerName
+            && self.series.lock().unwrap().remove(&key)
+        {
+            for result in ["success", "error"] {
+                let _ = RECONCILE_COUNTER.remove_label_values(&[namespace, name, result]);
+            }
+            let _ = RECONCILE_DURATION.remove_label_values(&[namespace, name]);
+            exemplars::forget(
+                &metric_name("reconcile_duration_seconds"),
+                &[("namespace", namespace), ("name", name)],
+            );
+        }
+    }
+
+    /// Record a MyApp that was deliberately not reconciled
+    pub fn record_skipped(&self, reason: &str, namespace: &str) {
+        SKIPPED_COUNTER
+            .with_label_values(&[reason, namespace])
+            .inc();
+    }
+
     /// Update managed resource count
     pub fn set_managed_resources(&self, resource_type: &str, namespace: &str, count: i64) {
         MANAGED_RESOURCES
@@ -117,6 +635,26 @@ impl MetricsCollector {
             .set(count as f64);
     }
 
+    /// Flag whether an app is running an outdated image
+    pub fn set_outdated_image(&self, namespace: &str, name: &str, image: &str, outdated: bool) {
+        OUTDATED_IMAGES
+            .with_label_values(&[namespace, name, image])
+            .set(if outdated { 1.0 } else { 0.0 });
+    }
+
+    /// Record the controller's own resource pressure and the resulting concurrency cap
+    pub fn set_self_pressure(&self, level: i64, concurrency: usize) {
+        SELF_PRESSURE.set(level as f64);
+        RECONCILE_CONCURRENCY.set(concurrency as f64);
+    }
+
+    /// Record usage of a cgroup limit, if the limit is known
+    pub fn set_resource_usage(&self, resource: &str, ratio: Option<f64>) {
+        if let Some(ratio) = ratio {
+            RESOURCE_USAGE.with_label_values(&[resource]).set(ratio);
+        }
+    }
+
     /// Start timing a webhook request
     pub fn start_webhook(&self, webhook_type: &str) -> WebhookTimer {
         WebhookTimer {
@@ -131,29 +669,84 @@ impl MetricsCollector {
     }
 }
 
+const CONDITION_STATUSES: [&str; 3] = ["True", "False", "Unknown"];
+
+fn remove_condition(namespace: &str, name: &str, type_: &str) {
+    for status in CONDITION_STATUSES {
+        let _ = RESOURCE_CONDITION.remove_label_values(&[namespace, name, type_, status]);
+    }
+}
+
+/// Decrements the queue depth when the reconcile leaves the queue
+pub struct QueueGuard;
+
+impl Drop for QueueGuard {
+    fn drop(&mut self) {
+        RECONCILE_QUEUE_DEPTH.dec();
+    }
+}
+
+/// Set the lag gauges from the last successful reconcile times
+fn refresh_lag() {
+    let now = Instant::now();
+    for ((namespace, name), last) in LAST_SUCCESS.lock().unwrap().iter() {
+        RECONCILE_LAG
+            .with_label_values(&[namespace, name])
+            .set(now.saturating_duration_since(*last).as_secs_f64());
+    }
+}
+
+/// Observe a duration, keeping the current trace as an exemplar when spans are exported
+fn observe_traced(histogram: &HistogramVec, name: &str, labels: &[(&str, &str)], value: f64) {
+    let values: Vec<&str> = labels.iter().map(|(_, v)| *v).collect();
+    histogram.with_label_values(&values).observe(value);
+    if let Some(trace_id) = telemetry::current_trace_id() {
+        exemplars::record(&metric_name(name), labels, value, trace_id);
+    }
+}
+
 /// Timer for tracking reconciliation duration
 pub struct ReconcileTimer {
     namespace: String,
     name: String,
+    /// Labels after cardinality limits
+    label_namespace: String,
+    label_name: String,
     start: Instant,
 }
 
 impl ReconcileTimer {
+    fn observe(&self, duration: f64) {
+        let labels = [
+            ("namespace", self.label_namespace.as_str()),
+            ("name", self.label_name.as_str()),
+        ];
+        observe_traced(
+            &RECONCILE_DURATION,
+            "reconcile_duration_seconds",
+            &labels,
+            duration,
+        );
+    }
+
     /// Complete the reconciliation with success
     pub fn success(self) {
         let duration = self.start.elapsed().as_secs_f64();
 
         RECONCILE_COUNTER
-            .with_label_values(&[self.namespace.as_str(), self.name.as_str(), "success"])
+            .with_label_values(&[&self.label_namespace, &self.label_name, "success"])
             .inc();
 
-        RECONCILE_DURATION
-            .with_label_values(&[self.namespace.as_str(), self.name.as_str()])
-            .observe(duration);
+        self.observe(duration);
 
         ACTIVE_RECONCILES
             .with_label_values(&[self.namespace.as_str()])
             .dec();
+
+        LAST_SUCCESS
+            .lock()
+            .unwrap()
+            .insert((self.namespace, self.name), Instant::now());
     }
 
     /// Complete the reconciliation with error
@@ -161,12 +754,10 @@ impl ReconcileTimer {
         let duration = self.start.elapsed().as_secs_f64();
 
         RECONCILE_COUNTER
-            .with_label_values(&[self.namespace.as_str(), self.name.as_str(), "error"])
+            .with_label_values(&[&self.label_namespace, &self.label_name, "error"])
             .inc();
 
-        RECONCILE_DURATION
-            .with_label_values(&[self.namespace.as_str(), self.name.as_str()])
-            .observe(duration);
+        self.observe(duration);
 
         ERROR_COUNTER
             .with_label_values(&[error_type, self.namespace.as_str()])
@@ -185,72 +776,84 @@ pub struct WebhookTimer {
 }
 
 impl WebhookTimer {
-    /// Complete the webhook request with success
-    pub fn success(self) {
+    /// Complete the webhook request; `result` is allowed, denied or invalid (a request
+    /// that could not be decoded)
+    pub fn finish(self, result: &str) {
         let duration = self.start.elapsed().as_secs_f64();
 
         WEBHOOK_COUNTER
-            .with_label_values(&[self.webhook_type.as_str(), "success"])
+            .with_label_values(&[self.webhook_type.as_str(), result])
             .inc();
 
-        WEBHOOK_DURATION
-            .with_label_values(&[&self.webhook_type])
-            .observe(duration);
-    }
-
-    /// Complete the webhook request with error
-    pub fn error(self) {
-        let duration = self.start.elapsed().as_secs_f64();
-
-        WEBHOOK_COUNTER
-            .with_label_values(&[self.webhook_type.as_str(), "error"])
-            .inc();
-
-        WEBHOOK_DURATION
-            .with_label_values(&[self.webhook_type.as_str()])
-            .observe(duration);
+        let labels = [("webhook_type", self.webhook_type.as_str())];
+        observe_traced(
+            &WEBHOOK_DURATION,
+            "webhook_duration_seconds",
+            &labels,
+            duration,
+        );
     }
 }
 
-/// Create metrics endpoint for Prometheus scraping
-pub fn metrics_handler() -> impl Filter<Extract = impl Reply, Error = warp::Rejection> + Clone {
+/// Create metrics endpoint for Prometheus scraping; OpenMetrics scrapes also get exemplars
+pub fn metrics_handler() -> impl Filter<Extract = (impl Reply,), Error = warp::Rejection> + Clone {
     warp::path("metrics")
         .and(warp::get())
-        .map(|| {
-            let encoder = TextEncoder::new();
+        .and(warp::header::optional::<String>("accept"))
+        .map(|accept: Option<String>| {
+            refresh_lag();
             let metric_families = prometheus::gather();
+            if exemplars::wants_openmetrics(accept.as_deref()) {
+                return (exemplars::encode(&metric_families), exemplars::CONTENT_TYPE);
+            }
+            let encoder = TextEncoder::new();
             let mut buffer = Vec::new();
             encoder.encode(&metric_families, &mut buffer).unwrap();
-            String::from_utf8(buffer).unwrap()
-        })
-        .map(|metrics: String| {
-            warp::reply::with_header(
-                metrics,
-                "content-type",
+            (
+                String::from_utf8(buffer).unwrap(),
                 "text/plain; version=0.0.4; charset=utf-8",
             )
         })
+        .map(|(metrics, content_type): (String, &'static str)| {
+            warp::reply::with_header(metrics, "content-type", content_type)
+        })
 }
 
-/// Health check endpoint
-pub fn health_handler() -> impl Filter<Extract = impl Reply, Error = warp::Rejection> + Clone {
-    warp::path("health").and(warp::get()).map(|| {
-        warp::reply::json(&serde_json::json!({
-            "status": "healthy",
-            "timestamp": chrono::Utc::now().to_rfc3339(),
-            "version": env!("CARGO_PKG_VERSION")
-        }))
+/// Health check endpoint; 503 once the watchdog sees a wedged controller
+pub fn health_handler(
+    watchdog: Watchdog,
+) -> impl Filter<Extract = impl Reply, Error = warp::Rejection> + Clone {
+    warp::path("health").and(warp::get()).map(move || {
+        let (mut body, status) = match watchdog.check() {
+            Ok(()) => (serde_json::json!({ "status": "healthy" }), StatusCode::OK),
+            Err(reason) => (
+                serde_json::json!({ "status": "unhealthy", "reason": reason }),
+                StatusCode::SERVICE_UNAVAILABLE,
+            ),
+        };
+        body["timestamp"] = chrono::Utc::now().to_rfc3339().into();
+        body["version"] = env!("CARGO_PKG_VERSION").into();
+        warp::reply::with_status(warp::reply::json(&body), status)
     })
 }
 
-/// Readiness check endpoint
-pub fn ready_handler() -> impl Filter<Extract = impl Reply, Error = warp::Rejection> + Clone {
-    warp::path("ready").and(warp::get()).map(|| {
-        // Add readiness checks here (e.g., Kubernetes API connectivity)
-        warp::reply::json(&serde_json::json!({
-            "status": "ready",
-            "timestamp": chrono::Utc::now().to_rfc3339()
-        }))
+/// Readiness check endpoint; 503 with the reason until every check passes
+pub fn ready_handler(
+    readiness: Readiness,
+) -> impl Filter<Extract = impl Reply, Error = warp::Rejection> + Clone {
+    warp::path("ready").and(warp::get()).then(move || {
+        let readiness = readiness.clone();
+        async move {
+            let (mut body, status) = match readiness.check().await {
+                Ok(()) => (serde_json::json!({ "status": "ready" }), StatusCode::OK),
+                Err(reason) => (
+                    serde_json::json!({ "status": "not ready", "reason": reason }),
+                    StatusCode::SERVICE_UNAVAILABLE,
+                ),
+            };
+            body["timestamp"] = chrono::Utc::now().to_rfc3339().into();
+            warp::reply::with_status(warp::reply::json(&body), status)
+        }
     })
 }
 
@@ -276,6 +879,104 @@ mod tests {
         // Verify metrics exist (basic smoke test)
         let metrics = prometheus::gather();
         assert!(!metrics.is_empty());
+
+        // Lag is reported from the successful reconcile until the object is forgotten
+        refresh_lag();
+        assert!(RECONCILE_LAG
+            .get_metric_with_label_values(&["default", "test-app"])
+            .is_ok());
+        collector.forget_object("default", "test-app");
+        assert!(!LAST_SUCCESS
+            .lock()
+            .unwrap()
+            .contains_key(&("default".to_string(), "test-app".to_string())));
+    }
+
+    #[test]
+    fn test_metrics_config() {
+        assert_eq!(
+            parse_buckets("0.05, 0.25,1,5"),
+            Ok(vec![0.05, 0.25, 1.0, 5.0])
+        );
+        assert!(parse_buckets("1,0.5").is_err());
+        assert!(parse_buckets("0,1").is_err());
+        assert!(parse_buckets("fast").is_err());
+
+        assert!(valid_prefix("acme_platform"));
+        assert!(!valid_prefix("9lives"));
+        assert!(!valid_prefix("acme-platform"));
+        assert_eq!(metric_name("reconcile_total"), "myapp_reconcile_total");
+    }
+
+    #[test]
+    fn test_cardinality_limits() {
+        let capped = MetricsCollector::with_cardinality(Cardinality {
+            name_labels: NameLabels::PerName,
+            max_series: 2,
+        });
+        assert_eq!(capped.reconcile_labels("a", "x"), ("a".into(), "x".into()));
+        assert_eq!(capped.reconcile_labels("a", "y"), ("a".into(), "y".into()));
+        assert_eq!(
+            capped.reconcile_labels("b", "z"),
+            (OVERFLOW_LABEL.into(), OVERFLOW_LABEL.into())
+        );
+        assert_eq!(capped.reconcile_labels("a", "x"), ("a".into(), "x".into()));
+
+        // Deleting a MyApp frees its slot
+        capped.forget_object("a", "y");
+        assert_eq!(capped.reconcile_labels("b", "z"), ("b".into(), "z".into()));
+
+        let by_namespace = MetricsCollector::with_cardinality(Cardinality {
+            name_labels: NameLabels::PerNamespace,
+            max_series: 1,
+        });
+        assert_eq!(
+            by_namespace.reconcile_labels("a", "x"),
+            ("a".into(), String::new())
+        );
+        assert_eq!(
+            by_namespace.reconcile_labels("a", "y"),
+            ("a".into(), String::new())
+        );
+    }
+
+    #[test]
+    fn test_condition_gauges() {
+        let collector = MetricsCollector::new();
+        let value = |type_: &str, status: &str| {
+            RESOURCE_CONDITION
+                .with_label_values(&["shop", "web", type_, status])
+                .get()
+        };
+
+        collector.set_conditions(
+            "shop",
+            "web",
+            &[
+                Condition::ready(true, "ReconcileSuccess", "ok"),
+                Condition::new("Degraded", true, "CanaryAborted", "failed"),
+            ],
+        );
+        assert_eq!(value("Ready", "True"), 1.0);
+        assert_eq!(value("Ready", "False"), 0.0);
+        assert_eq!(value("Degraded", "True"), 1.0);
+
+        // Degraded cleared: its series go away
+        collector.set_conditions(
+            "shop",
+            "web",
+            &[Condition::ready(true, "ReconcileSuccess", "ok")],
+        );
+        assert!(
+            !CONDITION_TYPES.lock().unwrap()[&("shop".to_string(), "web".to_string())]
+                .contains("Degraded")
+        );
+
+        collector.forget_object("shop", "web");
+        assert!(!CONDITION_TYPES
+            .lock()
+            .unwrap()
+            .contains_key(&("shop".to_string(), "web".to_string())));
     }
 
     #[test]
@@ -284,9 +985,15 @@ mod tests {
 
         let timer = collector.start_webhook("validate");
         std::thread::sleep(std::time::Duration::from_millis(5));
-        timer.success();
-
-        let timer = collector.start_webhook("mutate");
-        timer.error();
+        timer.finish("allowed");
+
+        let denied = || {
+            WEBHOOK_COUNTER
+                .with_label_values(&["mutate", "denied"])
+                .get()
+        };
+        let before = denied();
+        collector.start_webhook("mutate").finish("denied");
+        assert_eq!(denied(), before + 1.0);
     }
 }
@@ -0,0 +1,264 @@
+// Metrics endpoint authentication for MyApp Controller
+// Optionally requires a bearer token on /metrics, either a shared secret or a
+// Kubernetes token checked with TokenReview and SubjectAccessReview (kube-rbac-proxy style)
+
+use k8s_openapi::api::authentication::v1::{TokenReview, TokenReviewSpec};
+use k8s_openapi::api::authorization::v1::{
+    NonResourceAttributes, SubjectAccessReview, SubjectAccessReviewSpec,
+};
+use kube::api::{Api, PostParams};
+use kube::Client;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use warp::http::StatusCode;
+use warp::{Filter, Rejection, Reply};
+
+/// How long a TokenReview verdict is reused for the same token
+const REVIEW_TTL: Duration = Duration::from_secs(60);
+
+/// First path segments of the admin routes, which all share the `/metrics` auth
+const ADMIN_PATHS: &[&str] = &["metrics", "debug", "myapps"];
+
+/// Request refused by the metrics authentication
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Denied(pub StatusCode);
+
+impl warp::reject::Reject for Denied {}
+
+/// Who may scrape `/metrics`
+#[derive(Clone)]
+pub enum MetricsAuth {
+    /// Anyone who can reach the port
+    Disabled,
+    /// Callers presenting this bearer token
+    StaticToken(Arc<String>),
+    /// Kubernetes identities allowed to `get` the `/metrics` non-resource URL
+    TokenReview(Reviewer),
+}
+
+impl MetricsAuth {
+    /// Read `MYAPP_METRICS_AUTH` (none, token or tokenreview); `token` takes the secret from
+    /// `MYAPP_METRICS_TOKEN_FILE` or `MYAPP_METRICS_TOKEN`
+    pub fn from_env(client: Client) -> Result<Self, String> {
+        match std::env::var("MYAPP_METRICS_AUTH").as_deref() {
+            Err(_) | Ok("none") => Ok(Self::Disabled),
+            Ok("token") => {
+                let token = match std::env::var("MYAPP_METRICS_TOKEN_FILE") {
+                    Ok(path) => std::fs::read_to_string(&path)
+                        .map_err(|e| format!("reading metrics token {}: {}", path, e))?,
+                    Err(_) => std::env::var("MYAPP_METRICS_TOKEN").unwrap_or_default(),
+                };
+                let token = token.trim();
+                if token.is_empty() {
+                    return Err("MYAPP_METRICS_AUTH=token needs a non-empty token".to_string());
+                }
+                Ok(Self::StaticToken(Arc::new(token.to_string())))
+            }
+            Ok("tokenreview") => Ok(Self::TokenReview(Reviewer {
+                client,
+                verdicts: Arc::new(Mutex::new(HashMap::new())),
+            })),
+            Ok(other) => Err(format!("unknown MYAPP_METRICS_AUTH '{}'", other)),
+        }
+    }
+
+    /// Check the `Authorization` header of a scrape
+    pub async fn check(&self, authorization: Option<&str>) -> Result<(), Denied> {
+        if matches!(self, Self::Disabled) {
+            return Ok(());
+        }
+        let token = authorization
+            .and_then(bearer_token)
+            .ok_or(Denied(StatusCode::UNAUTHORIZED))?;
+        match self {
+            Self::Disabled => Ok(()),
+            Self::StaticToken(expected) if constant_time_eq(token, expected) => Ok(()),
+            Self::StaticToken(_) => Err(Denied(StatusCode::UNAUTHORIZED)),
+            Self::TokenReview(reviewer) => reviewer.check(token).await,
+        }
+    }
+
+    /// Filter rejecting scrapes that fail the check
+    pub fn filter(self) -> impl Filter<Extract = (), Error = Rejection> + Clone {
+        warp::header::optional::<String>("authorization")
+            .and_then(move |header: Option<String>| {
+                let auth = self.clone();
+                async move {
+                    auth.check(header.as_deref())
+                        .await
+                        .map_err(warp::reject::custom)
+                }
+            })
+            .untuple_one()
+    }
+
+    /// `routes` behind the check; other paths fall through to route sets sharing the listener
+    pub fn protect<F, R>(self, routes: F) -> impl Filter<Extract = (R,), Error = Rejection> + Clone
+    where
+        F: Filter<Extract = (R,), Error = Rejection> + Clone + Send + Sync,
+        R: Reply,
+    {
+        warp::path::peek()
+            .and_then(|peek: warp::path::Peek| async move {
+                match peek.segments().next() {
+                    Some(first) if ADMIN_PATHS.contains(&first) => Ok(()),
+                    _ => Err(warp::reject::not_found()),
+                }
+            })
+            .untuple_one()
+            .and(self.filter())
+            .and(routes)
+    }
+}
+
+/// Cached verdict per bearer token
+type Verdicts = HashMap<String, (Instant, Result<(), Denied>)>;
+
+/// TokenReview/SubjectAccessReview client with a short-lived verdict cache
+#[derive(Clone)]
+pub struct Reviewer {
+    client: Client,
+    verdicts: Arc<Mutex<Verdicts>>,
+}
+
+impl Reviewer {
+    async fn check(&self, token: &str) -> Result<(), Denied> {
+        if let Some((at, verdict)) = self.verdicts.lock().unwrap().get(token) {
+            if at.elapsed() < REVIEW_TTL {
+                return *verdict;
+            }
+        }
+
+        // API errors are not cached so the next scrape asks again
+        let verdict = self.review(token).await.map_err(|e| {
+            tracing::warn!("Metrics token review failed: {}", e);
+            Denied(StatusCode::SERVICE_UNAVAILABLE)
+        })?;
+        let mut verdicts = self.verdicts.lock().unwrap();
+        verdicts.retain(|_, (at, _)| at.elapsed() < REVIEW_TTL);
+        verdicts.insert(token.to_string(), (Instant::now(), verdict));
+        verdict
+    }
+
+    async fn review(&self, token: &str) -> Result<Result<(), Denied>, kube::Error> {
+        let review = TokenReview {
+            spec: TokenReviewSpec {
+                token: Some(token.to_string()),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let status = Api::<TokenReview>::all(self.client.clone())
+            .create(&PostParams::default(), &review)
+            .await?
+            .status
+            .unwrap_or_default();
+        if status.authenticated != Some(true) {
+            return Ok(Err(Denied(StatusCode::UNAUTHORIZED)));
+        }
+
+        let user = status.user.unwrap_or_default();
+        let access = SubjectAccessReview {
+            spec: SubjectAccessReviewSpec {
+                user: user.username,
+                uid: user.uid,
+                groups: user.groups,
+                extra: user.extra,
+                non_resource_attributes: Some(NonResourceAttributes {
+                    path: Some("/metrics".to_string()),
+                    verb: Some("get".to_string()),
+                }),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let allowed = Api::<SubjectAccessReview>::all(self.client.clone())
+            .create(&PostParams::default(), &access)
+            .await?
+            .status
+            .is_some_and(|s| s.allowed);
+        Ok(if allowed {
+            Ok(())
+        } else {
+            Err(Denied(StatusCode::FORBIDDEN))
+        })
+    }
+}
+
+/// Token from an `Authorization: Bearer <token>` header
+fn bearer_token(header: &str) -> Option<&str> {
+    let (scheme, token) = header.trim().split_once(' ')?;
+    let token = token.trim();
+    (scheme.eq_ignore_ascii_case("bearer") && !token.is_empty()).then_some(token)
+}
+
+fn constant_time_eq(a: &str, b: &str) -> bool {
+    a.len() == b.len()
+        && a.bytes()
+            .zip(b.bytes())
+            .fold(0u8, |acc, (x, y)| acc | (x ^ y))
+            == 0
+}
+
+/// Turn refused scrapes into 401/403 responses
+pub async fn handle_rejection(err: Rejection) -> Result<impl Reply, Rejection> {
+    match err.find::<Denied>() {
+        Some(Denied(status)) => Ok(warp::reply::with_status(
+            warp::reply::json(&serde_json::json!({
+                "error": status.canonical_reason().unwrap_or("denied")
+            })),
+            *status,
+        )),
+        None => Err(err),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bearer_token() {
+        assert_eq!(bearer_token("Bearer abc"), Some("abc"));
+        assert_eq!(bearer_token("bearer  abc "), Some("abc"));
+        assert_eq!(bearer_token("Basic abc"), None);
+        assert_eq!(bearer_token("Bearer"), None);
+    }
+
+    #[tokio::test]
+    async fn test_static_token() {
+        let auth = MetricsAuth::StaticToken(Arc::new("s3cret".to_string()));
+        assert_eq!(auth.check(Some("Bearer s3cret")).await, Ok(()));
+        assert_eq!(
+            auth.check(Some("Bearer guess")).await,
+            Err(Denied(StatusCode::UNAUTHORIZED))
+        );
+        assert_eq!(
+            auth.check(None).await,
+            Err(Denied(StatusCode::UNAUTHORIZED))
+        );
+        assert_eq!(MetricsAuth::Disabled.check(None).await, Ok(()));
+    }
+
+    #[tokio::test]
+    async fn test_protect() {
+        let auth = MetricsAuth::StaticToken(Arc::new("s3cret".to_string()));
+        let admin = warp::path!("debug" / "loglevel").map(|| "admin");
+        let routes = auth
+            .protect(admin)
+            .or(warp::path("health").map(|| "ok"))
+            .recover(handle_rejection);
+
+        let put = |path: &str| warp::test::request().method("PUT").path(path);
+        let refused = put("/debug/loglevel").reply(&routes).await;
+        assert_eq!(refused.status(), StatusCode::UNAUTHORIZED);
+        let allowed = put("/debug/loglevel")
+            .header("authorization", "Bearer s3cret")
+            .reply(&routes)
+            .await;
+        assert_eq!(allowed.body(), "admin");
+        // A health route sharing the listener stays open
+        assert_eq!(put("/health").reply(&routes).await.body(), "ok");
+    }
+}
@@ -0,0 +1,127 @@
+// Storage version migration for MyApp Controller
+// Rewrites every MyApp through the current storage version, then prunes old
+// versions from the CRD's status.storedVersions so they can be removed
+
+use crate::MyApp;
+use k8s_openapi::apiextensions_apiserver::pkg::apis::apiextensions::v1::CustomResourceDefinition;
+use kube::api::{Api, ListParams, Patch, PatchParams};
+use kube::{CustomResourceExt, ResourceExt};
+use serde_json::json;
+
+const PAGE_SIZE: u32 = 500;
+
+/// Name of the version marked `storage: true` on the CRD
+pub fn storage_version(crd: &CustomResourceDefinition) -> Option<String> {
+    crd.spec
+        .versions
+        .iter()
+        .find(|v| v.storage)
+        .map(|v| v.name.clone())
+}
+
+/// Stored versions that no longer need to be kept once every object is rewritten
+pub fn stale_versions(crd: &CustomResourceDefinition, storage: &str) -> Vec<String> {
+    crd.status
+        .as_ref()
+        .and_then(|s| s.stored_versions.clone())
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|v| v != storage)
+        .collect()
+}
+
+/// Rewrite every MyApp with an empty merge patch; the API server re-encodes
+/// the object in the storage version whenever its stored bytes differ
+async fn rewrite_all(api: &Api<MyApp>) -> Result<usize, kube::Error> {
+    let mut rewritten = 0;
+    let mut params = ListParams::default().limit(PAGE_SIZE);
+
+    loop {
+        let page = api.list(&params).await?;
+        for app in &page.items {
+            let ns = app.namespace().unwrap_or_default();
+            let namespaced: Api<MyApp> = Api::namespaced(api.clone().into_client(), &ns);
+            match namespaced
+                .patch(
+                    &app.name_any(),
+                    &PatchParams::default(),
+                    &Patch::Merge(json!({})),
+                )
+                .await
+            {
+                Ok(_) => rewritten += 1,
+                // Deleted since the list; nothing left to migrate
+                Err(kube::Error::Api(e)) if e.code == 404 => {}
+                Err(e) => return Err(e),
+            }
+        }
+
+        match page.metadata.continue_.filter(|c| !c.is_empty()) {
+            Some(token) => params = params.continue_token(&token),
+            None => return Ok(rewritten),
+        }
+    }
+}
+
+/// `migrate-storage [--dry-run]`
+pub async fn run(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let mut dry_run = false;
+    for arg in args {
+        match arg.as_str() {
+            "--dry-run" => dry_run = true,
+            other => return Err(format!("unknown migrate-storage argument: {}", other).into()),
+        }
+    }
+
+    let client = crate::kubectl::client().await?;
+    let crds: Api<CustomResourceDefinition> = Api::all(client.clone());
+    let name = MyApp::crd_name();
+    let crd = crds.get(name).await?;
+    let storage =
+        storage_version(&crd).ok_or_else(|| format!("{} has no storage version", name))?;
+    let stale = stale_versions(&crd, &storage);
+
+    if dry_run {
+        println!(
+            "Would rewrite all MyApps as {} and prune stored versions {:?}",
+            storage, stale
+        );
+        return Ok(());
+    }
+
+    let rewritten = rewrite_all(&Api::all(client)).await?;
+    println!("Rewrote {} MyApps as {}", rewritten, storage);
+
+    if stale.is_empty() {
+        println!("status.storedVersions already only contains {}", storage);
+        return Ok(());
+    }
+
+    crds.patch_status(
+        name,
+        &PatchParams::default(),
+        &Patch::Merge(json!({ "status": { "storedVersions": [storage] } })),
+    )
+    .await?;
+    println!("Pruned stored versions {:?} from {}", stale, name);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use k8s_openapi::apiextensions_apiserver::pkg::apis::apiextensions::v1::CustomResourceDefinitionStatus;
+
+    #[test]
+    fn test_stale_versions() {
+        let mut crd = MyApp::crd();
+        assert_eq!(storage_version(&crd).as_deref(), Some("v1"));
+
+        crd.status = Some(CustomResourceDefinitionStatus {
+            stored_versions: Some(vec!["v1alpha1".to_string(), "v1".to_string()]),
+            ..Default::default()
+        });
+        assert_eq!(stale_versions(&crd, "v1"), vec!["v1alpha1".to_string()]);
+    }
+}
@@ -0,0 +1,325 @@
+// Prometheus Operator integration for MyApp Controller
+// Generates a ServiceMonitor and a PrometheusRule with availability alerts for each app
+
+use crate::{child_name, create_owner_reference, MyApp};
+use kube::api::{
+    Api, ApiResource, DeleteParams, DynamicObject, GroupVersionKind, Patch, PatchParams,
+};
+use kube::{Client, ResourceExt};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+const GROUP_VERSION: &str = "monitoring.coreos.com/v1";
+
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct MonitoringConfig {
+    /// Container port serving metrics (default 80)
+    #[serde(default)]
+    pub port: Option<i32>,
+
+    /// Metrics path (default /metrics)
+    #[serde(default)]
+    pub path: Option<String>,
+
+    /// Scrape interval, e.g. 30s (default 30s)
+    #[serde(default)]
+    pub interval: Option<String>,
+
+    /// Availability alert thresholds
+    #[serde(default)]
+    pub alerts: Option<AlertConfig>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct AlertConfig {
+    /// How long a condition must hold before alerting, e.g. 5m (default 5m)
+    #[serde(default, rename = "for")]
+    pub for_: Option<String>,
+
+    /// Alert when fewer than this percentage of replicas are available (default 100)
+    #[serde(default)]
+    pub min_available_percent: Option<u8>,
+}
+
+impl MonitoringConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if let Some(port) = self.port {
+            if !(1..=65535).contains(&port) {
+                return Err("monitoring.port must be between 1 and 65535".to_string());
+            }
+        }
+        if let Some(path) = &self.path {
+            if !path.starts_with('/') {
+                return Err("monitoring.path must start with /".to_string());
+            }
+        }
+        if let Some(interval) = &self.interval {
+            check_duration("monitoring.interval", interval)?;
+        }
+        if let Some(alerts) = &self.alerts {
+            if let Some(for_) = &alerts.for_ {
+                check_duration("monitoring.alerts.for", for_)?;
+            }
+            if alerts
+                .min_available_percent
+                .is_some_and(|p| p == 0 || p > 100)
+            {
+                return Err(
+                    "monitoring.alerts.minAvailablePercent must be between 1 and 100".to_string(),
+                );
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Prometheus durations such as 30s, 5m or 1h
+fn check_duration(field: &str, value: &str) -> Result<(), String> {
+    let valid = value
+        .strip_suffix(['s', 'm', 'h'])
+        .is_some_and(|n| !n.is_empty() && n.chars().all(|c| c.is_ascii_digit()));
+    if valid {
+        Ok(())
+    } else {
+        Err(format!("{} must be a duration like 30s, 5m or 1h", field))
+    }
+}
+
+/// Prometheus Operator kinds served by the cluster
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MonitoringCrds {
+    pub service_monitors: bool,
+    pub prometheus_rules: bool,
+}
+
+/// Look for the monitoring.coreos.com CRDs
+pub async fn detect(client: Client) -> MonitoringCrds {
+    match client.list_api_group_resources(GROUP_VERSION).await {
+        Ok(list) => {
+            let served = |plural: &str| list.resources.iter().any(|r| r.name == plural);
+            MonitoringCrds {
+                service_monitors: served("servicemonitors"),
+                prometheus_rules: served("prometheusrules"),
+            }
+        }
+        Err(_) => MonitoringCrds::default(),
+    }
+}
+
+pub fn service_monitor_resource() -> ApiResource {
+    let gvk = GroupVersionKind::gvk("monitoring.coreos.com", "v1", "ServiceMonitor");
+    ApiResource::from_gvk_with_plural(&gvk, "servicemonitors")
+}
+
+pub fn prometheus_rule_resource() -> ApiResource {
+    let gvk = GroupVersionKind::gvk("monitoring.coreos.com", "v1", "PrometheusRule");
+    ApiResource::from_gvk_with_plural(&gvk, "prometheusrules")
+}
+
+fn owned(myapp: &MyApp, name: String, resource: &ApiResource) -> DynamicObject {
+    let mut object = DynamicObject::new(&name, resource).within(&myapp.namespace().unwrap());
+    object.metadata.labels = Some(BTreeMap::from([
+        ("app".to_string(), myapp.name_any()),
+        ("managed-by".to_string(), "myapp-controller".to_string()),
+    ]));
+    object.metadata.owner_references = Some(vec![create_owner_reference(myapp)]);
+    object
+}
+
+/// ServiceMonitor scraping the app's pods through its Service
+pub fn service_monitor(myapp: &MyApp, config: &MonitoringConfig) -> DynamicObject {
+    let mut monitor = owned(
+        myapp,
+        child_name(myapp, "monitor"),
+        &service_monitor_resource(),
+    );
+    monitor.data = serde_json::json!({
+        "spec": {
+            "selector": { "matchLabels": { "app": myapp.name_any() } },
+            "namespaceSelector": { "matchNames": [myapp.namespace()] },
+            "endpoints": [{
+                "targetPort": config.port.unwrap_or(80),
+                "path": config.path.as_deref().unwrap_or("/metrics"),
+                "interval": config.interval.as_deref().unwrap_or("30s"),
+            }],
+        }
+    });
+    monitor
+}
+
+/// PrometheusRule alerting when the app's Deployment or scrape target is unavailable
+pub fn prometheus_rule(
+    myapp: &MyApp,
+    config: &MonitoringConfig,
+    deployment: &str,
+) -> DynamicObject {
+    let alerts = config.alerts.clone().unwrap_or_default();
+    let for_ = alerts.for_.as_deref().unwrap_or("5m");
+    let min_percent = alerts.min_available_percent.unwrap_or(100);
+    let ns = myapp.namespace().unwrap_or_default();
+    let name = myapp.name_any();
+    let selector = format!("namespace=\"{}\",deployment=\"{}\"", ns, deployment);
+
+    let mut rule = owned(
+        myapp,
+        child_name(myapp, "alerts"),
+        &prometheus_rule_resource(),
+    );
+    rule.data = serde_json::json!({
+        "spec": {
+            "groups": [{
+                "name": format!("myapp.{}.{}", ns, name),
+                "rules": [
+                    {
+                        "alert": "MyAppReplicasUnavailable",
+                        "expr": format!(
+                            "100 * kube_deployment_status_replicas_available{{{0}}} / kube_deployment_spec_replicas{{{0}}} < {1}",
+                            selector, min_percent
+                        ),
+                        "for": for_,
+                        "labels": { "severity": "warning" },
+                        "annotations": {
+                            "summary": format!("MyApp {}/{} has unavailable replicas", ns, name),
+                            "description": format!(
+                                "Fewer than {}% of the replicas of {} are available",
+                                min_percent, deployment
+                            ),
+                        },
+                    },
+                    {
+                        "alert": "MyAppTargetDown",
+                        "expr": format!(
+                            "sum(up{{namespace=\"{}\",service=\"{}\"}}) == 0",
+                            ns,
+                            child_name(myapp, "service")
+                        ),
+                        "for": for_,
+                        "labels": { "severity": "critical" },
+                        "annotations": {
+                            "summary": format!("MyApp {}/{} cannot be scraped", ns, name),
+                            "description": "No metrics target of the app is up",
+                        },
+                    },
+                ],
+            }],
+        }
+    });
+    rule
+}
+
+/// Apply the monitoring children of a Deployment-backed MyApp, or remove them once
+/// spec.monitoring is unset
+pub async fn reconcile(
+    myapp: &MyApp,
+    crds: MonitoringCrds,
+    deployment: &str,
+    client: Client,
+) -> Result<(), kube::Error> {
+    let ns = myapp.namespace().unwrap();
+    let monitors: Api<DynamicObject> =
+        Api::namespaced_with(client.clone(), &ns, &service_monitor_resource());
+    let rules: Api<DynamicObject> = Api::namespaced_with(client, &ns, &prometheus_rule_resource());
+    let params = PatchParams::apply("myapp-controller").force();
+
+    let Some(config) = &myapp.spec.monitoring else {
+        if crds.service_monitors {
+            delete_if_present(&monitors, &child_name(myapp, "monitor")).await?;
+        }
+        if crds.prometheus_rules {
+            delete_if_present(&rules, &child_name(myapp, "alerts")).await?;
+        }
+        return Ok(());
+    };
+
+    if !crds.service_monitors {
+        tracing::info!(
+            "MyApp {}/{} requests monitoring but the ServiceMonitor CRD is not installed",
+            ns,
+            myapp.name_any()
+        );
+        return Ok(());
+    }
+    let monitor = service_monitor(myapp, config);
+    monitors
+        .patch(&monitor.name_any(), &params, &Patch::Apply(&monitor))
+        .await?;
+
+    if crds.prometheus_rules {
+        let rule = prometheus_rule(myapp, config, deployment);
+        rules
+            .patch(&rule.name_any(), &params, &Patch::Apply(&rule))
+            .await?;
+    }
+    Ok(())
+}
+
+async fn delete_if_present(api: &Api<DynamicObject>, name: &str) -> Result<(), kube::Error> {
+    match api.delete(name, &DeleteParams::default()).await {
+        Ok(_) => Ok(()),
+        Err(kube::Error::Api(e)) if e.code == 404 => Ok(()),
+        Err(e) => Err(e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn myapp(monitoring: MonitoringConfig) -> MyApp {
+        let mut myapp = MyApp::new("web", Default::default());
+        myapp.metadata.namespace = Some("shop".to_string());
+        myapp.metadata.uid = Some("uid-1".to_string());
+        myapp.spec.monitoring = Some(monitoring);
+        myapp
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(MonitoringConfig::default().validate().is_ok());
+        let invalid = |config: MonitoringConfig| config.validate().is_err();
+        assert!(invalid(MonitoringConfig {
+            port: Some(0),
+            ..Default::default()
+        }));
+        assert!(invalid(MonitoringConfig {
+            interval: Some("30 seconds".to_string()),
+            ..Default::default()
+        }));
+        assert!(invalid(MonitoringConfig {
+            alerts: Some(AlertConfig {
+                min_available_percent: Some(150),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }));
+    }
+
+    #[test]
+    fn test_children() {
+        let config = MonitoringConfig {
+            port: Some(9090),
+            alerts: Some(AlertConfig {
+                for_: Some("10m".to_string()),
+                min_available_percent: Some(50),
+            }),
+            ..Default::default()
+        };
+        let app = myapp(config.clone());
+
+        let monitor = service_monitor(&app, &config);
+        assert_eq!(monitor.name_any(), "web-monitor");
+        assert_eq!(monitor.data["spec"]["endpoints"][0]["targetPort"], 9090);
+        assert_eq!(monitor.data["spec"]["endpoints"][0]["path"], "/metrics");
+
+        let rule = prometheus_rule(&app, &config, "web-deployment");
+        let alert = &rule.data["spec"]["groups"][0]["rules"][0];
+        assert_eq!(alert["for"], "10m");
+        assert!(alert["expr"]
+            .as_str()
+            .unwrap()
+            .contains("deployment=\"web-deployment\"} < 50"));
+    }
+}
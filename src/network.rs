@@ -0,0 +1,135 @@
+// Service networking for MyApp Controller
+// IP family configuration for IPv6 and dual-stack Services
+
+use k8s_openapi::api::core::v1::Node;
+use kube::api::{Api, ListParams};
+use kube::Client;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+
+const IP_FAMILIES: [&str; 2] = ["IPv4", "IPv6"];
+const IP_FAMILY_POLICIES: [&str; 3] = ["SingleStack", "PreferDualStack", "RequireDualStack"];
+
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ServiceConfig {
+    /// IP families for the Service in order of preference (IPv4, IPv6)
+    #[serde(default)]
+    pub ip_families: Vec<String>,
+
+    /// SingleStack, PreferDualStack or RequireDualStack
+    #[serde(default)]
+    pub ip_family_policy: Option<String>,
+}
+
+impl ServiceConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if let Some(family) = self
+            .ip_families
+            .iter()
+            .find(|f| !IP_FAMILIES.contains(&f.as_str()))
+        {
+            return Err(format!(
+                "service.ipFamilies: unknown family {} (expected {})",
+                family,
+                IP_FAMILIES.join(" or ")
+            ));
+        }
+        if self.ip_families.len() > 2
+            || (self.ip_families.len() == 2 && self.ip_families[0] == self.ip_families[1])
+        {
+            return Err("service.ipFamilies may list each family at most once".to_string());
+        }
+
+        match self.ip_family_policy.as_deref() {
+            Some(policy) if !IP_FAMILY_POLICIES.contains(&policy) => Err(format!(
+                "service.ipFamilyPolicy must be one of {}",
+                IP_FAMILY_POLICIES.join(", ")
+            )),
+            Some("SingleStack") if self.ip_families.len() > 1 => {
+                Err("service.ipFamilyPolicy SingleStack allows only one ipFamily".to_string())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Check the requested families against what the cluster supports (empty = unknown)
+    pub fn check_supported(&self, supported: &BTreeSet<String>) -> Result<(), String> {
+        if supported.is_empty() {
+            return Ok(());
+        }
+        if let Some(family) = self.ip_families.iter().find(|f| !supported.contains(*f)) {
+            return Err(format!(
+                "service.ipFamilies: cluster does not support {}",
+                family
+            ));
+        }
+        if self.ip_family_policy.as_deref() == Some("RequireDualStack") && supported.len() < 2 {
+            return Err(
+                "service.ipFamilyPolicy RequireDualStack needs a dual-stack cluster".to_string(),
+            );
+        }
+        Ok(())
+    }
+}
+
+pub fn cidr_family(cidr: &str) -> &'static str {
+    if cidr.contains(':') {
+        "IPv6"
+    } else {
+        "IPv4"
+    }
+}
+
+/// IP families available to pods, from the nodes' pod CIDRs
+pub async fn detect_ip_families(client: Client) -> Result<BTreeSet<String>, kube::Error> {
+    let nodes: Api<Node> = Api::all(client);
+    Ok(nodes
+        .list(&ListParams::default())
+        .await?
+        .items
+        .iter()
+        .filter_map(|n| n.spec.as_ref()?.pod_cidrs.clone())
+        .flatten()
+        .map(|cidr| cidr_family(&cidr).to_string())
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(families: &[&str], policy: Option<&str>) -> ServiceConfig {
+        ServiceConfig {
+            ip_families: families.iter().map(|f| f.to_string()).collect(),
+            ip_family_policy: policy.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(config(&["IPv6", "IPv4"], Some("PreferDualStack"))
+            .validate()
+            .is_ok());
+        assert!(config(&["IPv6", "IPv6"], None).validate().is_err());
+        assert!(config(&["IPv5"], None).validate().is_err());
+        assert!(config(&["IPv4", "IPv6"], Some("SingleStack"))
+            .validate()
+            .is_err());
+        assert!(config(&[], Some("DualStack")).validate().is_err());
+    }
+
+    #[test]
+    fn test_check_supported() {
+        let ipv4_only = BTreeSet::from(["IPv4".to_string()]);
+        assert!(config(&["IPv6"], None).check_supported(&ipv4_only).is_err());
+        assert!(config(&[], Some("RequireDualStack"))
+            .check_supported(&ipv4_only)
+            .is_err());
+        assert!(config(&["IPv6"], None)
+            .check_supported(&BTreeSet::new())
+            .is_ok());
+        assert_eq!(cidr_family("fd00:10:244::/64"), "IPv6");
+    }
+}
@@ -0,0 +1,271 @@
+// NetworkPolicies for MyApp Controller
+// With spec.networkPolicy.isolation: strict the controller applies `<myapp>-default-deny`,
+// which selects the app's pods and allows no traffic in or out, and `<myapp>-allow`, which
+// opens the declared ports to the named peer apps and lets the pods reach their own peers and
+// DNS. Peers are other MyApps, matched by their pods' app label. Turning isolation off
+// deletes both policies.
+
+use crate::deletion::{owned, MANAGED_BY_SELECTOR};
+use crate::{app_labels, child_name, create_owner_reference, MyApp, ReconcileError};
+use k8s_openapi::api::networking::v1::{
+    NetworkPolicy, NetworkPolicyEgressRule, NetworkPolicyIngressRule, NetworkPolicyPeer,
+    NetworkPolicyPort, NetworkPolicySpec,
+};
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::{LabelSelector, ObjectMeta};
+use k8s_openapi::apimachinery::pkg::util::intstr::IntOrString;
+use kube::api::{Api, DeleteParams, Patch, PatchParams};
+use kube::{Client, ResourceExt};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Port the generated Service targets, opened when spec.networkPolicy.ports is empty
+const DEFAULT_PORT: i32 = 80;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, JsonSchema, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Isolation {
+    /// No policies; the namespace's own policies apply (default)
+    #[default]
+    None,
+    /// Deny all traffic except what the allow rules name
+    Strict,
+}
+
+/// Another MyApp, in the same namespace unless one is given
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PeerApp {
+    pub name: String,
+
+    #[serde(default)]
+    pub namespace: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, JsonSchema, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct NetworkPolicyConfig {
+    #[serde(default)]
+    pub isolation: Isolation,
+
+    /// TCP ports the pods accept connections on (the Service's port 80 when empty)
+    #[serde(default)]
+    pub ports: Vec<i32>,
+
+    /// Apps allowed to connect to the declared ports
+    #[serde(default)]
+    pub allow_from: Vec<PeerApp>,
+
+    /// Apps the pods may connect to; DNS is always allowed
+    #[serde(default)]
+    pub allow_to: Vec<PeerApp>,
+}
+
+impl NetworkPolicyConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if let Some(port) = self.ports.iter().find(|p| !(1..=65535).contains(*p)) {
+            return Err(format!("networkPolicy.ports: {} is not a valid port", port));
+        }
+        if self
+            .allow_from
+            .iter()
+            .chain(&self.allow_to)
+            .any(|peer| peer.name.is_empty())
+        {
+            return Err("networkPolicy peers need a name".to_string());
+        }
+        Ok(())
+    }
+}
+
+fn app_selector(name: &str) -> LabelSelector {
+    LabelSelector {
+        match_labels: Some(BTreeMap::from([("app".to_string(), name.to_string())])),
+        ..Default::default()
+    }
+}
+
+fn peer(peer: &PeerApp) -> NetworkPolicyPeer {
+    NetworkPolicyPeer {
+        pod_selector: Some(app_selector(&peer.name)),
+        namespace_selector: peer.namespace.as_ref().map(|ns| LabelSelector {
+            match_labels: Some(BTreeMap::from([(
+                "kubernetes.io/metadata.name".to_string(),
+                ns.clone(),
+            )])),
+            ..Default::default()
+        }),
+        ..Default::default()
+    }
+}
+
+fn port(protocol: &str, number: i32) -> NetworkPolicyPort {
+    NetworkPolicyPort {
+        protocol: Some(protocol.to_string()),
+        port: Some(IntOrString::Int(number)),
+        ..Default::default()
+    }
+}
+
+fn policy(myapp: &MyApp, suffix: &str, spec: NetworkPolicySpec) -> NetworkPolicy {
+    NetworkPolicy {
+        metadata: ObjectMeta {
+            name: Some(child_name(myapp, suffix)),
+            namespace: myapp.namespace(),
+            labels: Some(app_labels(myapp)),
+            owner_references: Some(vec![create_owner_reference(myapp)]),
+            ..Default::default()
+        },
+        spec: Some(spec),
+    }
+}
+
+/// The default-deny and allow policies for a strictly isolated MyApp
+pub fn build_policies(myapp: &MyApp, config: &NetworkPolicyConfig) -> [NetworkPolicy; 2] {
+    let pod_selector = app_selector(&myapp.name_any());
+    let policy_types = Some(vec!["Ingress".to_string(), "Egress".to_string()]);
+
+    let deny = policy(
+        myapp,
+        "default-deny",
+        NetworkPolicySpec {
+            pod_selector: pod_selector.clone(),
+            policy_types: policy_types.clone(),
+            ..Default::default()
+        },
+    );
+
+    let ports = match config.ports.is_empty() {
+        true => vec![DEFAULT_PORT],
+        false => config.ports.clone(),
+    };
+    let ingress = (!config.allow_from.is_empty()).then(|| {
+        vec![NetworkPolicyIngressRule {
+            from: Some(config.allow_from.iter().map(peer).collect()),
+            ports: Some(ports.iter().map(|p| port("TCP", *p)).collect()),
+        }]
+    });
+    let mut egress = vec![NetworkPolicyEgressRule {
+        to: None,
+        ports: Some(vec![port("UDP", 53), port("TCP", 53)]),
+    }];
+    if !config.allow_to.is_empty() {
+        egress.push(NetworkPolicyEgressRule {
+            to: Some(config.allow_to.iter().map(peer).collect()),
+            ports: None,
+        });
+    }
+    let allow = policy(
+        myapp,
+        "allow",
+        NetworkPolicySpec {
+            pod_selector,
+            policy_types,
+            ingress,
+            egress: Some(egress),
+        },
+    );
+
+    [deny, allow]
+}
+
+/// Apply the MyApp's policies under strict isolation and delete them otherwise
+pub async fn reconcile(myapp: &MyApp, client: Client) -> Result<(), ReconcileError> {
+    let ns = myapp.namespace().unwrap();
+    let api: Api<NetworkPolicy> = Api::namespaced(client, &ns);
+
+    let wanted = match &myapp.spec.network_policy {
+        Some(config) if config.isolation == Isolation::Strict => {
+            build_policies(myapp, config).to_vec()
+        }
+        _ => Vec::new(),
+    };
+    let params = PatchParams::apply("myapp-controller").force();
+    for policy in &wanted {
+        api.patch(&policy.name_any(), &params, &Patch::Apply(policy))
+            .await?;
+    }
+
+    let uid = myapp.uid().unwrap_or_default();
+    for stale in owned(&api, MANAGED_BY_SELECTOR, &uid).await? {
+        if !wanted.iter().any(|p| p.name_any() == stale.name_any()) {
+            match api
+                .delete(&stale.name_any(), &DeleteParams::default())
+                .await
+            {
+                Ok(_) => tracing::info!("Deleted NetworkPolicy {}/{}", ns, stale.name_any()),
+                Err(kube::Error::Api(e)) if e.code == 404 => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strict_policies() {
+        let mut myapp = MyApp::new("web", Default::default());
+        myapp.metadata.namespace = Some("shop".to_string());
+        myapp.metadata.uid = Some("uid-1".to_string());
+        let config = NetworkPolicyConfig {
+            isolation: Isolation::Strict,
+            ports: vec![8080],
+            allow_from: vec![PeerApp {
+                name: "frontend".to_string(),
+                namespace: None,
+            }],
+            allow_to: vec![PeerApp {
+                name: "postgres".to_string(),
+                namespace: Some("data".to_string()),
+            }],
+        };
+        let [deny, allow] = build_policies(&myapp, &config);
+        assert_eq!(deny.name_any(), "web-default-deny");
+        let deny = deny.spec.unwrap();
+        assert!(deny.ingress.is_none() && deny.egress.is_none());
+        assert_eq!(deny.policy_types.unwrap(), ["Ingress", "Egress"]);
+
+        let allow = allow.spec.unwrap();
+        let ingress = &allow.ingress.unwrap()[0];
+        assert_eq!(
+            ingress.ports.as_ref().unwrap()[0].port,
+            Some(IntOrString::Int(8080))
+        );
+        assert!(ingress.from.as_ref().unwrap()[0]
+            .namespace_selector
+            .is_none());
+        let egress = allow.egress.unwrap();
+        assert_eq!(egress.len(), 2);
+        assert_eq!(
+            egress[1].to.as_ref().unwrap()[0]
+                .namespace_selector
+                .as_ref()
+                .unwrap()
+                .match_labels
+                .as_ref()
+                .unwrap()["kubernetes.io/metadata.name"],
+            "data"
+        );
+    }
+
+    #[test]
+    fn test_validate() {
+        let config = NetworkPolicyConfig {
+            ports: vec![0],
+            ..Default::default()
+        };
+        assert!(config.validate().is_err());
+        let config = NetworkPolicyConfig {
+            allow_to: vec![PeerApp {
+                name: String::new(),
+                namespace: None,
+            }],
+            ..Default::default()
+        };
+        assert!(config.validate().is_err());
+    }
+}
@@ -0,0 +1,294 @@
+// Offline validation for MyApp manifests
+// Checks manifests against the CRD schema and the validating webhook's rules and reports
+// every violation, so CI can reject a manifest before it reaches a cluster. `lint` is the
+// same check with the advisory limit:request ratio switched on
+
+use crate::qos::RatioPolicy;
+use crate::{cel, warnings, MyApp};
+use kube::ResourceExt;
+use serde::Deserialize;
+use serde_json::Value;
+
+/// Problems found in one MyApp document
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Report {
+    pub name: String,
+    pub violations: Vec<String>,
+    pub warnings: Vec<String>,
+}
+
+/// The openAPIV3Schema of the served version, as JSON
+fn crd_schema() -> Value {
+    let crd = cel::crd();
+    let schema = crd.spec.versions[0]
+        .schema
+        .as_ref()
+        .and_then(|s| s.open_api_v3_schema.as_ref());
+    serde_json::to_value(schema).unwrap_or_default()
+}
+
+fn type_matches(value: &Value, schema: &Value) -> bool {
+    if schema["x-kubernetes-int-or-string"] == true {
+        return value.is_i64() || value.is_u64() || value.is_string();
+    }
+    match schema["type"].as_str() {
+        Some("object") => value.is_object(),
+        Some("array") => value.is_array(),
+        Some("string") => value.is_string(),
+        Some("integer") => value.is_i64() || value.is_u64(),
+        Some("number") => value.is_number(),
+        Some("boolean") => value.is_boolean(),
+        _ => true,
+    }
+}
+
+/// Structural checks the API server applies: types, required fields, enums, patterns and ranges
+pub fn schema_violations(value: &Value, schema: &Value, path: &str, out: &mut Vec<String>) {
+    if value.is_null() {
+        if schema["nullable"] != true && schema.get("type").is_some() {
+            out.push(format!("{}: must not be null", path));
+        }
+        return;
+    }
+    if !type_matches(value, schema) {
+        out.push(format!(
+            "{}: expected {}",
+            path,
+            schema["type"].as_str().unwrap_or("?")
+        ));
+        return;
+    }
+
+    if let Some(allowed) = schema["enum"].as_array() {
+        if !allowed.contains(value) {
+            out.push(format!(
+                "{}: must be one of {}",
+                path,
+                Value::Array(allowed.clone())
+            ));
+        }
+    }
+    if let (Some(pattern), Some(s)) = (schema["pattern"].as_str(), value.as_str()) {
+        match regex::Regex::new(pattern) {
+            Ok(re) if !re.is_match(s) => {
+                out.push(format!("{}: {:?} does not match {}", path, s, pattern))
+            }
+            _ => {}
+        }
+    }
+    if let Some(n) = value.as_f64().filter(|_| value.is_number()) {
+        if let Some(min) = schema["minimum"].as_f64().filter(|min| n < *min) {
+            out.push(format!("{}: must be at least {}", path, min));
+        }
+        if let Some(max) = schema["maximum"].as_f64().filter(|max| n > *max) {
+            out.push(format!("{}: must be at most {}", path, max));
+        }
+    }
+
+    match value {
+        Value::Object(fields) => {
+            for required in schema["required"].as_array().into_iter().flatten() {
+                if let Some(key) = required.as_str().filter(|k| !fields.contains_key(*k)) {
+                    out.push(format!("{}.{}: required", path, key));
+                }
+            }
+            for (key, field) in fields {
+                let field_path = format!("{}.{}", path, key);
+                if let Some(field_schema) = schema["properties"].get(key) {
+                    schema_violations(field, field_schema, &field_path, out);
+                } else if schema["additionalProperties"].is_object() {
+                    schema_violations(field, &schema["additionalProperties"], &field_path, out);
+                }
+            }
+        }
+        Value::Array(items) if schema["items"].is_object() => {
+            for (i, item) in items.iter().enumerate() {
+                schema_violations(item, &schema["items"], &format!("{}[{}]", path, i), out);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Rules the validating webhook applies without cluster state
+pub fn admission_violations(myapp: &MyApp, policy: &RatioPolicy) -> Vec<String> {
+    let mut violations = Vec::new();
+    if let Err(e) = myapp.validate() {
+        violations.push(e);
+    }
+    if myapp.spec.image.contains("latest") {
+        violations.push("Image tag 'latest' is not allowed".to_string());
+    }
+    violations.extend(policy.check(&myapp.spec));
+    violations
+}
+
+/// Everything the validating webhook checks without cluster state, plus the CRD schema
+pub fn check_document(value: serde_json::Value, policy: &RatioPolicy) -> Report {
+    let mut report = Report {
+        name: value["metadata"]["name"]
+            .as_str()
+            .unwrap_or("<unnamed>")
+            .to_string(),
+        ..Default::default()
+    };
+
+    let schema = crd_schema();
+    schema_violations(
+        &value["spec"],
+        &schema["properties"]["spec"],
+        "spec",
+        &mut report.violations,
+    );
+    if !report.violations.is_empty() {
+        // Typed checks below would only repeat the structural errors
+        return report;
+    }
+
+    let myapp: MyApp = match serde_json::from_value(value) {
+        Ok(myapp) => myapp,
+        Err(e) => {
+            report.violations.push(e.to_string());
+            return report;
+        }
+    };
+    report.name = myapp.name_any();
+
+    report.violations = admission_violations(&myapp, policy);
+    report.warnings = warnings::check(&myapp.spec);
+    report
+}
+
+/// Reports for every MyApp document in a multi-document YAML string
+pub fn check_manifests(yaml: &str, policy: &RatioPolicy) -> Result<Vec<Report>, serde_yaml::Error> {
+    let mut reports = Vec::new();
+    for document in serde_yaml::Deserializer::from_str(yaml) {
+        let value = serde_json::Value::deserialize(document)?;
+        if value["kind"] == "MyApp" {
+            reports.push(check_document(value, policy));
+        }
+    }
+    Ok(reports)
+}
+
+/// `validate <file>...`: prints every violation and fails if there are any
+pub fn run(paths: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    check_files("validate", paths, &RatioPolicy::from_env())
+}
+
+/// `lint <file>...`: `validate`, reporting limits above the advisory ratio unless one is set
+pub fn lint(paths: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    check_files(
+        "lint",
+        paths,
+        &RatioPolicy::from_env_or(RatioPolicy::advisory()),
+    )
+}
+
+fn check_files(
+    command: &str,
+    paths: &[String],
+    policy: &RatioPolicy,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if paths.is_empty() {
+        return Err(format!("usage: {} <file>...", command).into());
+    }
+
+    let mut total = 0;
+    for path in paths {
+        let yaml = std::fs::read_to_string(path)?;
+        for report in check_manifests(&yaml, policy)? {
+            for violation in &report.violations {
+                println!("{}: {}: {}", path, report.name, violation);
+            }
+            for warning in &report.warnings {
+                println!("{}: {}: warning: {}", path, report.name, warning);
+            }
+            total += report.violations.len();
+        }
+    }
+
+    if total > 0 {
+        return Err(format!("{} violation(s)", total).into());
+    }
+    println!("All MyApps are valid");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reports_all_schema_violations() {
+        let yaml = r#"
+apiVersion: example.com/v1
+kind: MyApp
+metadata:
+  name: broken
+spec:
+  replicas: 500
+  image: "Nginx"
+  workloadType: Daemon
+"#;
+        let reports = check_manifests(yaml, &RatioPolicy::default()).unwrap();
+        let violations = &reports[0].violations;
+
+        assert!(violations
+            .iter()
+            .any(|v| v.starts_with("spec.replicas: must be at most")));
+        assert!(violations.iter().any(|v| v.starts_with("spec.image:")));
+        assert!(violations
+            .iter()
+            .any(|v| v.starts_with("spec.workloadType: must be one of")));
+    }
+
+    #[test]
+    fn test_webhook_rules_apply() {
+        let yaml = r#"
+apiVersion: example.com/v1
+kind: MyApp
+metadata:
+  name: web
+spec:
+  replicas: 1
+  image: nginx:latest
+"#;
+        let report = &check_manifests(yaml, &RatioPolicy::default()).unwrap()[0];
+        assert_eq!(report.name, "web");
+        assert_eq!(report.violations, vec!["Image tag 'latest' is not allowed"]);
+        assert!(!report.warnings.is_empty());
+    }
+
+    #[test]
+    fn test_advisory_ratio() {
+        let yaml = r#"
+apiVersion: v1
+kind: ConfigMap
+metadata:
+  name: ignored
+---
+apiVersion: example.com/v1
+kind: MyApp
+metadata:
+  name: bursty
+spec:
+  replicas: 2
+  image: nginx:1.25
+  resources:
+    cpu: 100m
+    memory: 128Mi
+    limits:
+      cpu: "1"
+      memory: 128Mi
+"#;
+        assert!(check_manifests(yaml, &RatioPolicy::default()).unwrap()[0]
+            .violations
+            .is_empty());
+
+        let reports = check_manifests(yaml, &RatioPolicy::advisory()).unwrap();
+        assert_eq!(reports.len(), 1);
+        assert_eq!(reports[0].name, "bursty");
+        assert!(reports[0].violations[0].starts_with("cpu limit is 10.0x"));
+    }
+}
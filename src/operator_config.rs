@@ -0,0 +1,794 @@
+// Operator configuration for MyApp Controller
+// A cluster-scoped MyAppOperatorConfig (named `default` unless MYAPP_OPERATOR_CONFIG says
+// otherwise) tunes requeue intervals, concurrency, default resources and labels, feature
+// flags, namespace filters, the log level and webhook toggles; the controller and webhook
+// watch it and apply changes without a restart. With MYAPP_CONFIG_FILE set, the same spec
+// is read from a mounted file (e.g. a ConfigMap) instead and re-read periodically.
+// The controller reports on the resource's status: the generation it applied, an Accepted
+// condition summarizing the settings in effect, and per replica conditions for its own health
+
+use crate::app_rbac::{self, RbacRule};
+use crate::logging::LogLevel;
+use crate::namespace_rate::Rate;
+use crate::scope::NamespaceFilter;
+use crate::{Condition, ResourceRequirements};
+use futures::{Stream, StreamExt};
+use kube::api::{Patch, PatchParams};
+use kube::runtime::{watcher, WatchStreamExt};
+use kube::{Api, Client, CustomResource};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::watch;
+
+#[derive(CustomResource, Serialize, Deserialize, Debug, Clone, JsonSchema, Default)]
+#[kube(
+    group = "example.com",
+    version = "v1",
+    kind = "MyAppOperatorConfig",
+    shortname = "myappcfg",
+    status = "MyAppOperatorConfigStatus",
+    printcolumn = r#"{"name":"Accepted", "type":"string", "jsonPath":".status.conditions[?(@.type==\"Accepted\")].status"}"#,
+    printcolumn = r#"{"name":"Age", "type":"date", "jsonPath":".metadata.creationTimestamp"}"#
+)]
+#[serde(rename_all = "camelCase")]
+pub struct MyAppOperatorConfigSpec {
+    /// How often MyApps are reconciled again
+    #[serde(default)]
+    pub requeue: RequeueSpec,
+
+    /// Upper bound on concurrent reconciles, within MYAPP_MAX_CONCURRENT_RECONCILES
+    #[serde(default)]
+    #[schemars(range(min = 1))]
+    pub max_concurrent_reconciles: Option<u32>,
+
+    /// Resources the mutating webhook sets on MyApps that specify none
+    #[serde(default)]
+    pub default_resources: Option<ResourceRequirements>,
+
+    /// Optional subsystems switched on or off by name (service-monitor, canary, all);
+    /// overrides MYAPP_FEATURES
+    #[serde(default)]
+    pub features: BTreeMap<String, bool>,
+
+    /// Only these namespaces are managed (empty = all). This can only narrow
+    /// MYAPP_INCLUDE_NAMESPACES and --namespace: the watch is fixed at startup, so a
+    /// namespace outside it is not picked up until the controller restarts
+    #[serde(default)]
+    pub include_namespaces: Vec<String>,
+
+    /// These namespaces are never managed, in addition to MYAPP_EXCLUDE_NAMESPACES; namespaces
+    /// excluded there stay excluded whatever is listed here
+    #[serde(default)]
+    pub exclude_namespaces: Vec<String>,
+
+    /// Log filter directives such as `info,kube_runtime=debug`
+    #[serde(default)]
+    pub log_level: Option<String>,
+
+    /// Labels the mutating webhook adds to MyApps that do not set them
+    #[serde(default)]
+    pub default_labels: BTreeMap<String, String>,
+
+    /// Admission checks to switch off without removing the webhook configurations
+    #[serde(default)]
+    pub webhooks: WebhookToggles,
+
+    /// Reconcile rate per namespace, overriding MYAPP_NAMESPACE_RECONCILE_RATE
+    #[serde(default)]
+    pub namespace_rate_limits: NamespaceRateLimits,
+
+    /// Limits on the permissions MyApps may request through spec.rbac
+    #[serde(default)]
+    pub app_rbac_policy: AppRbacPolicy,
+}
+
+/// Written by the controller; the webhook only reads the config
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct MyAppOperatorConfigStatus {
+    /// Generation of the spec the controller last applied
+    #[serde(default)]
+    pub observed_generation: Option<i64>,
+
+    /// Accepted, with the settings in effect
+    #[serde(default)]
+    pub conditions: Vec<Condition>,
+
+    /// Health of each controller replica (SelfPressure, Throttled), by pod name
+    #[serde(default)]
+    pub replicas: BTreeMap<String, ReplicaStatus>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ReplicaStatus {
+    /// Refreshed while the replica runs; entries of replicas gone for a while are removed
+    pub last_heartbeat_time: String,
+
+    #[serde(default)]
+    pub conditions: Vec<Condition>,
+}
+
+/// How often a replica refreshes its entry without a condition change
+const HEARTBEAT: Duration = Duration::from_secs(300);
+
+/// Entries not refreshed for this long belong to replicas that are gone
+const REPLICA_GONE: Duration = Duration::from_secs(900);
+
+/// Whether a heartbeat is older than `age`, or unreadable
+fn older_than(heartbeat: &str, age: Duration, now: chrono::DateTime<chrono::Utc>) -> bool {
+    chrono::DateTime::parse_from_rfc3339(heartbeat).map_or(true, |t| {
+        now.signed_duration_since(t).to_std().unwrap_or_default() > age
+    })
+}
+
+/// Insert or replace the condition of the same type, keeping its transition time when the
+/// status is unchanged; returns whether anything changed
+pub fn set_condition(conditions: &mut Vec<Condition>, mut condition: Condition) -> bool {
+    match conditions.iter_mut().find(|c| c.r#type == condition.r#type) {
+        Some(existing)
+            if existing.status == condition.status
+                && existing.reason == condition.reason
+                && existing.message == condition.message =>
+        {
+            false
+        }
+        Some(existing) => {
+            if existing.status == condition.status {
+                condition.last_transition_time = existing.last_transition_time.clone();
+            }
+            *existing = condition;
+            true
+        }
+        None => {
+            conditions.push(condition);
+            true
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct AppRbacPolicy {
+    /// Rules no MyApp may overlap (default: Secrets, RBAC objects, escalate/bind/impersonate)
+    #[serde(default)]
+    pub denied_rules: Option<Vec<RbacRule>>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct NamespaceRateLimits {
+    /// Rate for namespaces not listed below
+    #[serde(default)]
+    pub default: Option<RateLimitSpec>,
+
+    /// Rates for individual namespaces
+    #[serde(default)]
+    pub namespaces: BTreeMap<String, RateLimitSpec>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct RateLimitSpec {
+    /// Sustained reconciles per second
+    pub per_second: f64,
+
+    /// Reconciles allowed back to back (default: one second's worth)
+    #[serde(default)]
+    pub burst: Option<u32>,
+}
+
+impl RateLimitSpec {
+    fn rate(&self) -> Option<Rate> {
+        Rate::new(self.per_second, self.burst)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct WebhookToggles {
+    /// Validate MyApps on create and update (default true); deletion protection stays on
+    #[serde(default)]
+    pub validate: Option<bool>,
+
+    /// Default and mutate MyApps (default true)
+    #[serde(default)]
+    pub mutate: Option<bool>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct RequeueSpec {
+    /// After a successful reconcile (default 300)
+    #[serde(default)]
+    #[schemars(range(min = 1))]
+    pub steady_seconds: Option<u64>,
+
+    /// While a rollout, blue-green preview or canary step is in progress (default 10)
+    #[serde(default)]
+    #[schemars(range(min = 1))]
+    pub rollout_seconds: Option<u64>,
+
+    /// While a Job or hook is running (default 30)
+    #[serde(default)]
+    #[schemars(range(min = 1))]
+    pub active_run_seconds: Option<u64>,
+}
+
+/// Requeue delays in effect
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Requeue {
+    pub steady: Duration,
+    pub rollout: Duration,
+    pub active_run: Duration,
+}
+
+/// Settings in effect: the built-in defaults overridden by the config resource
+#[derive(Debug, Clone, PartialEq)]
+pub struct Settings {
+    pub requeue: Requeue,
+    pub max_concurrent_reconciles: Option<usize>,
+    pub default_resources: ResourceRequirements,
+    pub features: BTreeMap<String, bool>,
+    pub namespace_filter: NamespaceFilter,
+    /// None leaves the filter set by RUST_LOG or `/debug/loglevel`
+    pub log_level: Option<String>,
+    pub default_labels: BTreeMap<String, String>,
+    pub validate: bool,
+    pub mutate: bool,
+    pub default_namespace_rate: Option<Rate>,
+    pub namespace_rates: BTreeMap<String, Rate>,
+    pub app_rbac_denied: Vec<RbacRule>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            requeue: Requeue {
+                steady: Duration::from_secs(300),
+                rollout: Duration::from_secs(10),
+                active_run: Duration::from_secs(30),
+            },
+            max_concurrent_reconciles: None,
+            default_resources: ResourceRequirements {
+                cpu: "100m".to_string(),
+                memory: "128Mi".to_string(),
+                limits: None,
+            },
+            features: BTreeMap::new(),
+            namespace_filter: NamespaceFilter::default(),
+            log_level: None,
+            default_labels: BTreeMap::new(),
+            validate: true,
+            mutate: true,
+            default_namespace_rate: None,
+            namespace_rates: BTreeMap::new(),
+            app_rbac_denied: app_rbac::default_denied(),
+        }
+    }
+}
+
+impl Settings {
+    /// One-line description of the settings, for logs and the Accepted condition
+    pub fn summary(&self) -> String {
+        format!(
+            "requeue {:?}, max concurrent reconciles {:?}, features {:?}, namespaces {:?}, log level {:?}, webhooks validate={} mutate={}",
+            self.requeue,
+            self.max_concurrent_reconciles,
+            self.features,
+            self.namespace_filter,
+            self.log_level,
+            self.validate,
+            self.mutate
+        )
+    }
+
+    pub fn from_spec(spec: &MyAppOperatorConfigSpec) -> Self {
+        let default = Self::default();
+        let secs = |value: Option<u64>, default: Duration| {
+            value
+                .filter(|s| *s > 0)
+                .map_or(default, Duration::from_secs)
+        };
+        Self {
+            requeue: Requeue {
+                steady: secs(spec.requeue.steady_seconds, default.requeue.steady),
+                rollout: secs(spec.requeue.rollout_seconds, default.requeue.rollout),
+                active_run: secs(spec.requeue.active_run_seconds, default.requeue.active_run),
+            },
+            max_concurrent_reconciles: spec
+                .max_concurrent_reconciles
+                .filter(|n| *n > 0)
+                .map(|n| n as usize),
+            default_resources: spec
+                .default_resources
+                .clone()
+                .unwrap_or(default.default_resources),
+            features: spec.features.clone(),
+            namespace_filter: NamespaceFilter {
+                include: spec.include_namespaces.clone(),
+                exclude: spec.exclude_namespaces.clone(),
+            },
+            log_level: spec.log_level.clone().filter(|l| !l.trim().is_empty()),
+            default_labels: spec.default_labels.clone(),
+            validate: spec.webhooks.validate.unwrap_or(true),
+            mutate: spec.webhooks.mutate.unwrap_or(true),
+            default_namespace_rate: spec
+                .namespace_rate_limits
+                .default
+                .as_ref()
+                .and_then(RateLimitSpec::rate),
+            namespace_rates: spec
+                .namespace_rate_limits
+                .namespaces
+                .iter()
+                .filter_map(|(ns, limit)| Some((ns.clone(), limit.rate()?)))
+                .collect(),
+            app_rbac_denied: spec
+                .app_rbac_policy
+                .denied_rules
+                .clone()
+                .unwrap_or(default.app_rbac_denied),
+        }
+    }
+
+    /// Configured reconcile rate for `namespace`, if any
+    pub fn namespace_rate(&self, namespace: &str) -> Option<Rate> {
+        self.namespace_rates
+            .get(namespace)
+            .copied()
+            .or(self.default_namespace_rate)
+    }
+}
+
+/// Name of the config resource to follow (`MYAPP_OPERATOR_CONFIG`, default "default")
+pub fn name_from_env() -> String {
+    std::env::var("MYAPP_OPERATOR_CONFIG")
+        .ok()
+        .filter(|v| !v.is_empty())
+        .unwrap_or_else(|| "default".to_string())
+}
+
+/// Config file to read instead of the resource (`MYAPP_CONFIG_FILE`), and how often to
+/// re-read it (`MYAPP_CONFIG_FILE_INTERVAL_SECS`, default 10)
+pub fn file_from_env() -> Option<(PathBuf, Duration)> {
+    let path = std::env::var("MYAPP_CONFIG_FILE")
+        .ok()
+        .filter(|v| !v.is_empty())?;
+    let secs = std::env::var("MYAPP_CONFIG_FILE_INTERVAL_SECS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .filter(|s| *s > 0)
+        .unwrap_or(10);
+    Some((path.into(), Duration::from_secs(secs)))
+}
+
+/// Parse a config file holding a MyAppOperatorConfig spec
+pub fn parse_file(content: &str) -> Result<Settings, serde_yaml::Error> {
+    let spec: Option<MyAppOperatorConfigSpec> = serde_yaml::from_str(content)?;
+    Ok(Settings::from_spec(&spec.unwrap_or_default()))
+}
+
+/// Replace the published settings if they changed
+fn publish(sender: &watch::Sender<Arc<Settings>>, source: &str, next: Settings) {
+    sender.send_if_modified(|current| {
+        if **current == next {
+            return false;
+        }
+        tracing::info!("Operator config {} applied: {}", source, next.summary());
+        *current = Arc::new(next);
+        true
+    });
+}
+
+/// Live view of the operator settings
+#[derive(Clone)]
+pub struct OperatorConfig {
+    settings: watch::Receiver<Arc<Settings>>,
+    /// Generation of the config resource the settings came from
+    generation: watch::Receiver<Option<i64>>,
+}
+
+impl Default for OperatorConfig {
+    /// Built-in defaults that never change
+    fn default() -> Self {
+        let (_, settings) = watch::channel(Arc::new(Settings::default()));
+        let (_, generation) = watch::channel(None);
+        Self {
+            settings,
+            generation,
+        }
+    }
+}
+
+impl OperatorConfig {
+    pub fn get(&self) -> Arc<Settings> {
+        self.settings.borrow().clone()
+    }
+
+    /// Yields whenever the settings change
+    pub fn changes(&self) -> impl Stream<Item = ()> + Send + Sync + 'static {
+        futures::stream::unfold(self.settings.clone(), |mut settings| async move {
+            settings.changed().await.ok()?;
+            Some(((), settings))
+        })
+    }
+
+    /// Follow the named config resource in the background; without one the defaults apply
+    pub fn spawn(client: Client, name: String) -> Self {
+        let (sender, settings) = watch::channel(Arc::new(Settings::default()));
+        let (generations, generation) = watch::channel(None);
+        let api: Api<MyAppOperatorConfig> = Api::all(client);
+        let config = watcher::Config::default().fields(&format!("metadata.name={}", name));
+
+        // A relist is applied once it completes; with no InitApply the resource is gone
+        let mut relisted = None;
+        let stream = watcher(api, config)
+            .default_backoff()
+            .for_each(move |event| {
+                let config = match event {
+                    Ok(watcher::Event::Init) => {
+                        relisted = None;
+                        return futures::future::ready(());
+                    }
+                    Ok(watcher::Event::InitApply(config)) => {
+                        relisted = Some(config);
+                        return futures::future::ready(());
+                    }
+                    Ok(watcher::Event::InitDone) => relisted.take(),
+                    Ok(watcher::Event::Apply(config)) => Some(config),
+                    Ok(watcher::Event::Delete(_)) => None,
+                    Err(e) => {
+                        tracing::warn!("Operator config watch failed: {}", e);
+                        return futures::future::ready(());
+                    }
+                };
+                let next = config
+                    .as_ref()
+                    .map(|c| Settings::from_spec(&c.spec))
+                    .unwrap_or_default();
+                publish(&sender, &name, next);
+                // Settings first, so a new generation is reported with the settings it produced
+                let observed = config.and_then(|c| c.metadata.generation);
+                generations
+                    .send_if_modified(|current| std::mem::replace(current, observed) != observed);
+                futures::future::ready(())
+            });
+        tokio::spawn(stream);
+
+        Self {
+            settings,
+            generation,
+        }
+    }
+
+    /// Re-read `path` every `interval`; a missing or invalid file keeps the last settings
+    pub fn watch_file(path: PathBuf, interval: Duration) -> Self {
+        let (sender, settings) = watch::channel(Arc::new(Settings::default()));
+        let (_, generation) = watch::channel(None);
+        let source = path.display().to_string();
+
+        tokio::spawn(async move {
+            // ConfigMap volumes swap a symlink, so compare contents rather than mtimes
+            let mut last = None;
+            loop {
+                match tokio::fs::read_to_string(&path).await {
+                    Ok(content) if last.as_ref() != Some(&content) => {
+                        match parse_file(&content) {
+                            Ok(next) => publish(&sender, &source, next),
+                            Err(e) => {
+                                tracing::warn!("Ignoring invalid config file {}: {}", source, e)
+                            }
+                        }
+                        last = Some(content);
+                    }
+                    Ok(_) => {}
+                    Err(e) => tracing::warn!("Could not read config file {}: {}", source, e),
+                }
+                tokio::time::sleep(interval).await;
+            }
+        });
+
+        Self {
+            settings,
+            generation,
+        }
+    }
+
+    /// The config file if MYAPP_CONFIG_FILE is set, else the MyAppOperatorConfig resource
+    pub fn from_env(client: Client) -> Self {
+        match file_from_env() {
+            Some((path, interval)) => Self::watch_file(path, interval),
+            None => Self::spawn(client, name_from_env()),
+        }
+    }
+
+    /// Record each generation of the config resource as accepted, with the settings in effect
+    pub fn report_status(&self, status: ConfigStatus) {
+        let config = self.clone();
+        let mut generation = self.generation.clone();
+        tokio::spawn(async move {
+            loop {
+                let observed = *generation.borrow_and_update();
+                if observed.is_some() {
+                    status.accepted(observed, &config.get()).await;
+                }
+                if generation.changed().await.is_err() {
+                    return;
+                }
+            }
+        });
+    }
+
+    /// Keep the log filter in line with the configured level
+    pub fn apply_log_level(&self, level: LogLevel) {
+        let config = self.clone();
+        let mut changes = Box::pin(self.changes());
+        tokio::spawn(async move {
+            loop {
+                if let Some(directives) = &config.get().log_level {
+                    if *directives != level.current() {
+                        if let Err(e) = level.set(directives) {
+                            tracing::warn!("Ignoring configured log level '{}': {}", directives, e);
+                        }
+                    }
+                }
+                if changes.next().await.is_none() {
+                    return;
+                }
+            }
+        });
+    }
+}
+
+/// Writer for the config resource's status; does nothing when settings come from a file.
+/// Each replica writes its health under its own pod name, so replicas do not overwrite each other
+#[derive(Clone, Default)]
+pub struct ConfigStatus {
+    writer: Option<Arc<StatusWriter>>,
+}
+
+struct StatusWriter {
+    api: Api<MyAppOperatorConfig>,
+    name: String,
+    /// Pod name of this replica
+    replica: String,
+    /// Last status written; `replicas` only holds this replica's entry
+    status: tokio::sync::Mutex<MyAppOperatorConfigStatus>,
+}
+
+impl ConfigStatus {
+    pub fn new(client: Client, name: String, replica: String) -> Self {
+        Self {
+            writer: Some(Arc::new(StatusWriter {
+                api: Api::all(client),
+                name,
+                replica,
+                status: Default::default(),
+            })),
+        }
+    }
+
+    /// A writer for the MyAppOperatorConfig resource unless MYAPP_CONFIG_FILE is set
+    pub fn from_env(client: Client) -> Self {
+        match file_from_env() {
+            Some(_) => Self::default(),
+            None => Self::new(
+                client,
+                name_from_env(),
+                std::env::var("HOSTNAME").unwrap_or_else(|_| "controller".to_string()),
+            ),
+        }
+    }
+
+    /// Record that `generation` was applied, producing `settings`
+    pub async fn accepted(&self, generation: Option<i64>, settings: &Settings) {
+        let condition = Condition::new("Accepted", true, "Applied", &settings.summary());
+        self.update(|status| {
+            let observed = std::mem::replace(&mut status.observed_generation, generation);
+            set_condition(&mut status.conditions, condition) || observed != generation
+        })
+        .await;
+    }
+
+    /// Set one condition of this replica, writing the status only if it changed or the
+    /// heartbeat is due; entries of replicas that are gone are removed along the way
+    pub async fn set_replica_condition(&self, condition: Condition) {
+        let Some(writer) = &self.writer else {
+            return;
+        };
+        let now = chrono::Utc::now();
+        let mut gone = Vec::new();
+        let heartbeat_due = writer
+            .status
+            .lock()
+            .await
+            .replicas
+            .get(&writer.replica)
+            .is_none_or(|r| older_than(&r.last_heartbeat_time, HEARTBEAT, now));
+        if heartbeat_due {
+            if let Ok(live) = writer.api.get_status(&writer.name).await {
+                gone = live
+                    .status
+                    .map(|s| s.replicas)
+                    .unwrap_or_default()
+                    .into_iter()
+                    .filter(|(name, r)| {
+                        *name != writer.replica
+                            && older_than(&r.last_heartbeat_time, REPLICA_GONE, now)
+                    })
+                    .map(|(name, _)| name)
+                    .collect();
+            }
+        }
+        let change = |status: &mut MyAppOperatorConfigStatus| {
+            let replica = status.replicas.entry(writer.replica.clone()).or_default();
+            let changed = set_condition(&mut replica.conditions, condition);
+            if changed || heartbeat_due {
+                replica.last_heartbeat_time = now.to_rfc3339();
+            }
+            changed || heartbeat_due
+        };
+        // Only this replica's entry: a merge patch merges the map by key, leaving the
+        // other replicas' entries and the Accepted condition alone
+        let patch = |status: &MyAppOperatorConfigStatus| {
+            let mut replicas =
+                serde_json::json!({ &writer.replica: status.replicas[&writer.replica] });
+            for name in gone {
+                replicas[name] = serde_json::Value::Null;
+            }
+            serde_json::json!({ "status": { "replicas": replicas } })
+        };
+        self.write(change, patch).await;
+    }
+
+    async fn update(&self, change: impl FnOnce(&mut MyAppOperatorConfigStatus) -> bool) {
+        self.write(change, |status| serde_json::json!({ "status": status }))
+            .await;
+    }
+
+    /// Apply `change` and, if it changed anything, write the merge patch `patch` makes of the result
+    async fn write(
+        &self,
+        change: impl FnOnce(&mut MyAppOperatorConfigStatus) -> bool,
+        patch: impl FnOnce(&MyAppOperatorConfigStatus) -> serde_json::Value,
+    ) {
+        let Some(writer) = &self.writer else {
+            return;
+        };
+        let mut current = writer.status.lock().await;
+        let mut next = current.clone();
+        if !change(&mut next) {
+            return;
+        }
+        let patch = patch(&next);
+        match writer
+            .api
+            .patch_status(&writer.name, &PatchParams::default(), &Patch::Merge(&patch))
+            .await
+        {
+            Ok(_) => *current = next,
+            // Retried with the next change or report
+            Err(e) => tracing::warn!(
+                "Could not update status of MyAppOperatorConfig {}: {}",
+                writer.name,
+                e
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_settings_from_spec() {
+        let spec: MyAppOperatorConfigSpec = serde_yaml::from_str(
+            r#"
+requeue:
+  steadySeconds: 60
+maxConcurrentReconciles: 4
+features:
+  canary: false
+excludeNamespaces: [kube-system]
+namespaceRateLimits:
+  default: { perSecond: 5 }
+  namespaces:
+    batch: { perSecond: 0.5, burst: 10 }
+"#,
+        )
+        .unwrap();
+
+        let settings = Settings::from_spec(&spec);
+        assert_eq!(settings.requeue.steady, Duration::from_secs(60));
+        assert_eq!(
+            settings.requeue.rollout,
+            Settings::default().requeue.rollout
+        );
+        assert_eq!(settings.max_concurrent_reconciles, Some(4));
+        assert_eq!(settings.default_resources.cpu, "100m");
+        assert_eq!(settings.features.get("canary"), Some(&false));
+        assert!(!settings.namespace_filter.allows("kube-system"));
+        assert_eq!(settings.namespace_rate("batch"), Rate::new(0.5, Some(10)));
+        assert_eq!(settings.namespace_rate("web"), Rate::new(5.0, None));
+    }
+
+    #[test]
+    fn test_parse_file() {
+        let settings = parse_file(
+            r#"
+logLevel: debug
+defaultLabels:
+  team: platform
+webhooks:
+  mutate: false
+appRbacPolicy:
+  deniedRules:
+    - resources: [pods/exec]
+      verbs: [create]
+"#,
+        )
+        .unwrap();
+        assert_eq!(settings.log_level.as_deref(), Some("debug"));
+        assert_eq!(settings.app_rbac_denied[0].resources, ["pods/exec"]);
+        assert_eq!(settings.default_labels["team"], "platform");
+        assert!(settings.validate);
+        assert!(!settings.mutate);
+
+        assert_eq!(parse_file("").unwrap(), Settings::default());
+        assert!(parse_file("requeue: often").is_err());
+    }
+
+    #[test]
+    fn test_set_condition() {
+        let mut conditions = Vec::new();
+        assert!(set_condition(
+            &mut conditions,
+            Condition::new("Throttled", false, "NotThrottled", "ok")
+        ));
+        let mut unchanged = Condition::new("Throttled", false, "NotThrottled", "ok");
+        unchanged.last_transition_time = "later".to_string();
+        assert!(!set_condition(&mut conditions, unchanged));
+
+        let since = conditions[0].last_transition_time.clone();
+        let mut reworded = Condition::new("Throttled", false, "NotThrottled", "still ok");
+        reworded.last_transition_time = "later".to_string();
+        assert!(set_condition(&mut conditions, reworded));
+        assert_eq!(conditions[0].last_transition_time, since);
+
+        assert!(set_condition(
+            &mut conditions,
+            Condition::new("Throttled", true, "TooManyRequests", "429")
+        ));
+        assert_eq!(conditions.len(), 1);
+        assert_eq!(conditions[0].status, "True");
+    }
+
+    #[test]
+    fn test_replica_heartbeat_age() {
+        let now = chrono::Utc::now();
+        let recent = (now - chrono::Duration::seconds(60)).to_rfc3339();
+        let old = (now - chrono::Duration::seconds(1000)).to_rfc3339();
+        assert!(!older_than(&recent, HEARTBEAT, now));
+        assert!(older_than(&old, HEARTBEAT, now));
+        assert!(older_than(&old, REPLICA_GONE, now));
+        assert!(older_than("", REPLICA_GONE, now));
+    }
+
+    #[test]
+    fn test_crd_is_cluster_scoped() {
+        use kube::{CustomResourceExt, ResourceExt};
+        let crd = MyAppOperatorConfig::crd();
+        assert_eq!(crd.spec.scope, "Cluster");
+        assert!(crd.spec.versions[0]
+            .subresources
+            .as_ref()
+            .is_some_and(|s| s.status.is_some()));
+        assert_eq!(crd.name_any(), "myappoperatorconfigs.example.com");
+    }
+}
@@ -0,0 +1,320 @@
+// Reconcile plan for MyApp Controller
+// Renders the children reconcile would write, server-side dry-runs them and diffs the
+// result against the live objects, so changes can be reviewed before anything is applied
+
+use crate::shared_store::{CachedChild, SharedStores};
+use crate::workload::{self, WorkloadType};
+use crate::{app_labels, build_deployment, build_pod_spec, build_service, child_name, MyApp};
+use k8s_openapi::api::apps::v1::Deployment;
+use k8s_openapi::api::batch::v1::{CronJob, Job};
+use k8s_openapi::api::core::v1::Service;
+use kube::api::{Api, Patch, PatchParams};
+use kube::{Client, Resource, ResourceExt};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+use std::fmt::{self, Debug};
+
+/// Annotation asking the controller to record a plan instead of reconciling
+pub const PLAN_ONLY_ANNOTATION: &str = "myapps.example.com/plan-only";
+
+/// What reconcile would do to one child
+#[derive(Debug, Clone, PartialEq)]
+pub enum Change {
+    Create,
+    /// Changed fields as (path, live, planned)
+    Update(Vec<(String, String, String)>),
+    Unchanged,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChildPlan {
+    pub kind: String,
+    pub name: String,
+    pub change: Change,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Plan {
+    pub namespace: String,
+    pub children: Vec<ChildPlan>,
+}
+
+/// Whether the plan-only annotation is set to "true"
+pub fn is_plan_only(myapp: &MyApp) -> bool {
+    myapp
+        .annotations()
+        .get(PLAN_ONLY_ANNOTATION)
+        .is_some_and(|v| v == "true")
+}
+
+impl Plan {
+    fn count(&self, matches: impl Fn(&Change) -> bool) -> usize {
+        self.children.iter().filter(|c| matches(&c.change)).count()
+    }
+
+    /// One-line summary, e.g. "1 to create, 1 to change"
+    pub fn summary(&self) -> String {
+        let create = self.count(|c| *c == Change::Create);
+        let change = self.count(|c| matches!(c, Change::Update(_)));
+        if create + change == 0 {
+            return "No changes".to_string();
+        }
+        format!("{} to create, {} to change", create, change)
+    }
+}
+
+impl fmt::Display for Plan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for child in &self.children {
+            let id = format!("{} {}/{}", child.kind, self.namespace, child.name);
+            match &child.change {
+                Change::Create => writeln!(f, "  + {} will be created", id)?,
+                Change::Unchanged => writeln!(f, "    {} is up to date", id)?,
+                Change::Update(fields) => {
+                    writeln!(f, "  ~ {} will be updated in-place", id)?;
+                    for (path, live, planned) in fields {
+                        writeln!(f, "      ~ {}: {} -> {}", path, live, planned)?;
+                    }
+                }
+            }
+        }
+        writeln!(f, "\nPlan: {}.", self.summary())
+    }
+}
+
+/// Leaf-level differences between two objects, limited to what reconcile owns
+pub fn diff(live: &Value, planned: &Value) -> Vec<(String, String, String)> {
+    let mut out = Vec::new();
+    for root in ["metadata.labels", "metadata.annotations", "spec"] {
+        let pick = |v: &Value| {
+            root.split('.').fold(v.clone(), |v, key| {
+                v.get(key).cloned().unwrap_or(Value::Null)
+            })
+        };
+        diff_values(&pick(live), &pick(planned), root, &mut out);
+    }
+    out
+}
+
+fn diff_values(live: &Value, planned: &Value, path: &str, out: &mut Vec<(String, String, String)>) {
+    match (live, planned) {
+        (Value::Object(a), Value::Object(b)) => {
+            let keys: std::collections::BTreeSet<&String> = a.keys().chain(b.keys()).collect();
+            for key in keys {
+                let child = format!("{}.{}", path, key);
+                diff_values(
+                    a.get(key).unwrap_or(&Value::Null),
+                    b.get(key).unwrap_or(&Value::Null),
+                    &child,
+                    out,
+                );
+            }
+        }
+        (Value::Array(a), Value::Array(b)) if a.len() == b.len() => {
+            for (i, (x, y)) in a.iter().zip(b).enumerate() {
+                diff_values(x, y, &format!("{}[{}]", path, i), out);
+            }
+        }
+        (a, b) if a != b => out.push((path.to_string(), a.to_string(), b.to_string())),
+        _ => {}
+    }
+}
+
+async fn plan_child<K>(
+    api: Api<K>,
+    mut desired: K,
+    live: Option<K>,
+) -> Result<ChildPlan, kube::Error>
+where
+    K: Resource<DynamicType = ()> + Clone + Debug + Serialize + DeserializeOwned,
+{
+    // A MyApp read from a file has no UID to own its children by
+    let owner_uid_missing = desired
+        .meta()
+        .owner_references
+        .iter()
+        .flatten()
+        .any(|o| o.uid.is_empty());
+    if owner_uid_missing {
+        desired.meta_mut().owner_references = None;
+    }
+
+    let name = desired.name_any();
+    let kind = K::kind(&()).to_string();
+    let change = match live {
+        None => Change::Create,
+        Some(live) => {
+            let params = PatchParams::apply("myapp-controller").force().dry_run();
+            let planned = api.patch(&name, &params, &Patch::Apply(&desired)).await?;
+            let to_value =
+                |object: &K| serde_json::to_value(object).map_err(kube::Error::SerdeError);
+            let fields = diff(&to_value(&live)?, &to_value(&planned)?);
+            if fields.is_empty() {
+                Change::Unchanged
+            } else {
+                Change::Update(fields)
+            }
+        }
+    };
+
+    Ok(ChildPlan { kind, name, change })
+}
+
+/// Live copy of `desired`, from the controller's stores when they cover its namespace
+async fn live<K>(
+    api: &Api<K>,
+    desired: &K,
+    stores: Option<&SharedStores>,
+) -> Result<Option<K>, kube::Error>
+where
+    K: CachedChild + Debug + DeserializeOwned,
+{
+    let (ns, name) = (desired.namespace().unwrap_or_default(), desired.name_any());
+    match stores.and_then(|s| s.get::<K>(&ns, &name)) {
+        Some(cached) => Ok(cached.map(|object| (*object).clone())),
+        None => api.get_opt(&name).await,
+    }
+}
+
+/// Plan the children of `myapp` without changing the cluster
+pub async fn plan(myapp: &MyApp, client: Client) -> Result<Plan, kube::Error> {
+    plan_cached(myapp, client, None).await
+}
+
+/// Plan the children of `myapp`, reading live children from `stores` where possible
+pub async fn plan_cached(
+    myapp: &MyApp,
+    client: Client,
+    stores: Option<&SharedStores>,
+) -> Result<Plan, kube::Error> {
+    let ns = myapp
+        .namespace()
+        .unwrap_or_else(|| client.default_namespace().to_string());
+    let mut children = Vec::new();
+
+    match myapp.spec.workload_type {
+        WorkloadType::Deployment => {
+            let name = child_name(myapp, "deployment");
+            let mut deployment = build_deployment(myapp, &name, app_labels(myapp));
+            deployment.metadata.namespace = Some(ns.clone());
+            let api = Api::<Deployment>::namespaced(client.clone(), &ns);
+            let current = live(&api, &deployment, stores).await?;
+            children.push(plan_child(api, deployment, current).await?);
+
+            // Services are not watched by the controller, so they are always read from the API
+            let service = build_service(myapp);
+            let api = Api::<Service>::namespaced(client, &ns);
+            let current = api.get_opt(&service.name_any()).await?;
+            children.push(plan_child(api, service, current).await?);
+        }
+        WorkloadType::Job => {
+            let mut job = workload::build_job(myapp, build_pod_spec(myapp));
+            job.metadata.namespace = Some(ns.clone());
+            let api = Api::<Job>::namespaced(client, &ns);
+            let current = live(&api, &job, stores).await?;
+            children.push(plan_child(api, job, current).await?);
+        }
+        WorkloadType::CronJob => {
+            let mut cronjob = workload::build_cronjob(myapp, build_pod_spec(myapp));
+            cronjob.metadata.namespace = Some(ns.clone());
+            let api = Api::<CronJob>::namespaced(client, &ns);
+            let current = live(&api, &cronjob, stores).await?;
+            children.push(plan_child(api, cronjob, current).await?);
+        }
+    }
+
+    Ok(Plan {
+        namespace: ns,
+        children,
+    })
+}
+
+/// `plan <name> [-n <namespace>]` or `plan -f <file>`
+pub async fn run(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let mut namespace = None;
+    let mut file = None;
+    let mut name = None;
+
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "-n" | "--namespace" => namespace = iter.next().cloned(),
+            "-f" | "--filename" => file = iter.next().cloned(),
+            other if !other.starts_with('-') && name.is_none() => name = Some(other.to_string()),
+            other => return Err(format!("unknown plan argument: {}", other).into()),
+        }
+    }
+
+    let client = crate::kubectl::client().await?;
+    let mut myapp: MyApp = match (file, name) {
+        (Some(path), None) => serde_yaml::from_str(&std::fs::read_to_string(path)?)?,
+        (None, Some(name)) => {
+            let ns = namespace
+                .clone()
+                .unwrap_or_else(|| client.default_namespace().to_string());
+            Api::<MyApp>::namespaced(client.clone(), &ns)
+                .get(&name)
+                .await?
+        }
+        _ => return Err("usage: plan <name> [-n <namespace>] | plan -f <file>".into()),
+    };
+    if namespace.is_some() {
+        myapp.metadata.namespace = namespace;
+    }
+
+    print!("{}", plan(&myapp, client).await?);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_diff_reports_changed_leaves() {
+        let live = json!({
+            "metadata": { "labels": { "app": "web" }, "resourceVersion": "1" },
+            "spec": { "replicas": 2, "template": { "image": "nginx:1.24" } },
+            "status": { "readyReplicas": 2 }
+        });
+        let planned = json!({
+            "metadata": { "labels": { "app": "web" }, "resourceVersion": "2" },
+            "spec": { "replicas": 3, "template": { "image": "nginx:1.24" } },
+            "status": { "readyReplicas": 1 }
+        });
+
+        assert_eq!(
+            diff(&live, &planned),
+            vec![(
+                "spec.replicas".to_string(),
+                "2".to_string(),
+                "3".to_string()
+            )]
+        );
+    }
+
+    #[test]
+    fn test_plan_summary() {
+        let plan = Plan {
+            namespace: "shop".to_string(),
+            children: vec![
+                ChildPlan {
+                    kind: "Deployment".to_string(),
+                    name: "web-deployment".to_string(),
+                    change: Change::Create,
+                },
+                ChildPlan {
+                    kind: "Service".to_string(),
+                    name: "web-service".to_string(),
+                    change: Change::Unchanged,
+                },
+            ],
+        };
+        assert_eq!(plan.summary(), "1 to create, 0 to change");
+        assert!(plan
+            .to_string()
+            .contains("+ Deployment shop/web-deployment will be created"));
+    }
+}
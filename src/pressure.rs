@@ -0,0 +1,342 @@
+// Self-monitoring for MyApp Controller
+// Watches the controller's own cgroup usage and lowers reconcile concurrency and trims the watch caches
+// before it hits its limits, reporting the pressure level as a SelfPressure condition of this replica on
+// the MyAppOperatorConfig status
+
+use crate::metrics::MetricsCollector;
+use crate::operator_config::{ConfigStatus, OperatorConfig};
+use crate::Condition;
+use kube::Resource;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::{Notify, OwnedSemaphorePermit, Semaphore};
+
+const CGROUP_ROOT: &str = "/sys/fs/cgroup";
+
+const LAST_APPLIED: &str = "kubectl.kubernetes.io/last-applied-configuration";
+
+/// Set above Normal pressure; cached objects then drop the fields the controller never reads
+static SLIM_CACHE: AtomicBool = AtomicBool::new(false);
+
+/// Drop managedFields and the last-applied annotation from a watched object while under pressure;
+/// objects cached before the pressure rose keep them until their next event
+pub fn slim<K: Resource>(object: &mut K) {
+    if !SLIM_CACHE.load(Ordering::Relaxed) {
+        return;
+    }
+    let meta = object.meta_mut();
+    meta.managed_fields = None;
+    if let Some(annotations) = meta.annotations.as_mut() {
+        annotations.remove(LAST_APPLIED);
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum PressureLevel {
+    Normal,
+    Elevated,
+    Critical,
+}
+
+impl PressureLevel {
+    pub fn from_ratio(ratio: f64) -> Self {
+        if ratio >= 0.9 {
+            PressureLevel::Critical
+        } else if ratio >= 0.8 {
+            PressureLevel::Elevated
+        } else {
+            PressureLevel::Normal
+        }
+    }
+
+    /// Reconciles allowed at this level out of `max`
+    pub fn concurrency(&self, max: usize) -> usize {
+        match self {
+            PressureLevel::Normal => max,
+            PressureLevel::Elevated => (max / 2).max(1),
+            PressureLevel::Critical => 1,
+        }
+    }
+
+    /// SelfPressure condition; True above Normal, with the level as the reason
+    pub fn condition(&self, limit: usize, ceiling: usize) -> Condition {
+        Condition::new(
+            "SelfPressure",
+            *self != PressureLevel::Normal,
+            &format!("{:?}", self),
+            &format!(
+                "Reconcile concurrency at {} of {} for the controller's cgroup usage",
+                limit, ceiling
+            ),
+        )
+    }
+}
+
+/// Parse a cgroup limit file; "max" means unlimited
+pub fn parse_limit(content: &str) -> Option<u64> {
+    let value = content.trim();
+    if value == "max" {
+        return None;
+    }
+    // cgroup v1 reports "unlimited" as a huge page-aligned number
+    value.parse().ok().filter(|v| *v < i64::MAX as u64 / 2)
+}
+
+/// Parse cgroup v2 `cpu.max` ("<quota> <period>") into cores
+pub fn parse_cpu_max(content: &str) -> Option<f64> {
+    let mut parts = content.split_whitespace();
+    let quota = parts.next()?.parse::<f64>().ok()?;
+    let period = parts.next()?.parse::<f64>().ok()?;
+    (period > 0.0).then(|| quota / period)
+}
+
+/// Parse `usage_usec` out of cgroup v2 `cpu.stat`
+pub fn parse_cpu_usage_usec(content: &str) -> Option<u64> {
+    content
+        .lines()
+        .find_map(|line| line.strip_prefix("usage_usec "))
+        .and_then(|v| v.trim().parse().ok())
+}
+
+fn read(file: &str) -> Option<String> {
+    std::fs::read_to_string(format!("{}/{}", CGROUP_ROOT, file)).ok()
+}
+
+fn memory_ratio() -> Option<f64> {
+    let (usage, limit) = match read("memory.current") {
+        Some(usage) => (usage, read("memory.max")?),
+        None => (
+            read("memory/memory.usage_in_bytes")?,
+            read("memory/memory.limit_in_bytes")?,
+        ),
+    };
+    let usage = usage.trim().parse::<u64>().ok()?;
+    Some(usage as f64 / parse_limit(&limit)? as f64)
+}
+
+/// CPU usage against the cgroup quota since the previous sample
+struct CpuSampler {
+    last: Option<(u64, Instant)>,
+}
+
+impl CpuSampler {
+    fn sample(&mut self) -> Option<f64> {
+        let cores = read("cpu.max").as_deref().and_then(parse_cpu_max)?;
+        let usage = read("cpu.stat").as_deref().and_then(parse_cpu_usage_usec)?;
+        let now = Instant::now();
+
+        let ratio = self.last.map(|(prev, at)| {
+            let used = usage.saturating_sub(prev) as f64 / 1_000_000.0;
+            used / (at.elapsed().as_secs_f64() * cores)
+        });
+        self.last = Some((usage, now));
+        ratio
+    }
+}
+
+/// A reconcile slot; returning it wakes reconciles waiting for one
+pub struct ReconcilePermit {
+    _permit: OwnedSemaphorePermit,
+    released: Arc<Notify>,
+}
+
+impl Drop for ReconcilePermit {
+    fn drop(&mut self) {
+        self.released.notify_waiters();
+    }
+}
+
+/// Caps concurrent reconciles; the cap can be lowered and raised at runtime
+#[derive(Clone)]
+pub struct ReconcileLimiter {
+    semaphore: Arc<Semaphore>,
+    /// Permits taken out of circulation while under pressure
+    withheld: Arc<Mutex<Vec<OwnedSemaphorePermit>>>,
+    max: usize,
+    /// Notified whenever slots may have become available
+    released: Arc<Notify>,
+}
+
+impl ReconcileLimiter {
+    pub fn new(max: usize) -> Self {
+        Self {
+            semaphore: Arc::new(Semaphore::new(max)),
+            withheld: Arc::new(Mutex::new(Vec::new())),
+            max,
+            released: Arc::new(Notify::new()),
+        }
+    }
+
+    /// Read `MYAPP_MAX_CONCURRENT_RECONCILES` (default 16)
+    pub fn from_env() -> Self {
+        let max = std::env::var("MYAPP_MAX_CONCURRENT_RECONCILES")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .filter(|n| *n > 0)
+            .unwrap_or(16);
+        Self::new(max)
+    }
+
+    async fn acquire(&self) -> OwnedSemaphorePermit {
+        self.semaphore
+            .clone()
+            .acquire_owned()
+            .await
+            .expect("reconcile semaphore is never closed")
+    }
+
+    /// A slot if one is free right now
+    pub fn try_acquire(&self) -> Option<ReconcilePermit> {
+        let permit = self.semaphore.clone().try_acquire_owned().ok()?;
+        Some(ReconcilePermit {
+            _permit: permit,
+            released: self.released.clone(),
+        })
+    }
+
+    /// Notified when a slot is returned or the cap is raised
+    pub fn released(&self) -> &Notify {
+        &self.released
+    }
+
+    pub fn limit(&self) -> usize {
+        self.max - self.withheld.lock().unwrap().len()
+    }
+
+    /// Move the cap towards `limit`, waiting for in-flight reconciles to finish when lowering it
+    pub async fn set_limit(&self, limit: usize) {
+        let limit = limit.clamp(1, self.max);
+        let current = self.limit();
+        if limit > current {
+            let mut withheld = self.withheld.lock().unwrap();
+            let keep = withheld.len() - (limit - current);
+            withheld.truncate(keep);
+            self.released.notify_waiters();
+        } else {
+            for _ in limit..current {
+                let permit = self.acquire().await;
+                self.withheld.lock().unwrap().push(permit);
+            }
+        }
+    }
+}
+
+/// Periodically sample cgroup usage and adapt the reconcile limiter, within the operator
+/// config's concurrency cap
+pub async fn run_monitor(
+    limiter: ReconcileLimiter,
+    config: OperatorConfig,
+    status: ConfigStatus,
+    metrics: MetricsCollector,
+    interval: Duration,
+) {
+    let mut cpu = CpuSampler { last: None };
+    let mut level = PressureLevel::Normal;
+
+    loop {
+        let memory = memory_ratio();
+        let cpu_ratio = cpu.sample();
+        metrics.set_resource_usage("memory", memory);
+        metrics.set_resource_usage("cpu", cpu_ratio);
+
+        let new_level = [memory, cpu_ratio]
+            .into_iter()
+            .flatten()
+            .map(PressureLevel::from_ratio)
+            .max()
+            .unwrap_or(PressureLevel::Normal);
+
+        if new_level != level {
+            tracing::info!(
+                "Controller resource pressure changed from {:?} to {:?} (memory {:?}, cpu {:?})",
+                level,
+                new_level,
+                memory,
+                cpu_ratio
+            );
+            level = new_level;
+            SLIM_CACHE.store(level > PressureLevel::Normal, Ordering::Relaxed);
+        }
+
+        let ceiling = config
+            .get()
+            .max_concurrent_reconciles
+            .map_or(limiter.max, |n| n.min(limiter.max));
+        limiter.set_limit(level.concurrency(ceiling)).await;
+        metrics.set_self_pressure(level as i64, limiter.limit());
+        status
+            .set_replica_condition(level.condition(limiter.limit(), ceiling))
+            .await;
+
+        tokio::time::sleep(interval).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_cgroup_files() {
+        assert_eq!(parse_limit("max\n"), None);
+        assert_eq!(parse_limit("536870912\n"), Some(536870912));
+        assert_eq!(parse_limit("9223372036854771712"), None);
+        assert_eq!(parse_cpu_max("50000 100000"), Some(0.5));
+        assert_eq!(parse_cpu_max("max 100000"), None);
+        assert_eq!(
+            parse_cpu_usage_usec("usage_usec 1234\nuser_usec 1000\n"),
+            Some(1234)
+        );
+    }
+
+    #[test]
+    fn test_pressure_levels() {
+        assert_eq!(PressureLevel::from_ratio(0.5), PressureLevel::Normal);
+        assert_eq!(PressureLevel::from_ratio(0.85), PressureLevel::Elevated);
+        assert_eq!(PressureLevel::from_ratio(0.95), PressureLevel::Critical);
+        assert_eq!(PressureLevel::Elevated.concurrency(16), 8);
+        assert_eq!(PressureLevel::Critical.concurrency(16), 1);
+
+        let condition = PressureLevel::Elevated.condition(8, 16);
+        assert_eq!(
+            (condition.status.as_str(), condition.reason.as_str()),
+            ("True", "Elevated")
+        );
+        assert_eq!(PressureLevel::Normal.condition(16, 16).status, "False");
+    }
+
+    #[test]
+    fn test_slim_only_under_pressure() {
+        use k8s_openapi::api::apps::v1::Deployment;
+        use k8s_openapi::apimachinery::pkg::apis::meta::v1::ManagedFieldsEntry;
+
+        let mut deployment = Deployment::default();
+        deployment.metadata.managed_fields = Some(vec![ManagedFieldsEntry::default()]);
+        deployment.metadata.annotations = Some(
+            [(LAST_APPLIED.to_string(), "{}".to_string())]
+                .into_iter()
+                .collect(),
+        );
+        slim(&mut deployment);
+        assert!(deployment.metadata.managed_fields.is_some());
+
+        SLIM_CACHE.store(true, Ordering::Relaxed);
+        slim(&mut deployment);
+        SLIM_CACHE.store(false, Ordering::Relaxed);
+        assert_eq!(deployment.metadata.managed_fields, None);
+        assert_eq!(deployment.metadata.annotations, Some(Default::default()));
+    }
+
+    #[tokio::test]
+    async fn test_limiter_adjusts_cap() {
+        let limiter = ReconcileLimiter::new(4);
+        limiter.set_limit(1).await;
+        assert_eq!(limiter.limit(), 1);
+        assert_eq!(limiter.semaphore.available_permits(), 1);
+
+        limiter.set_limit(3).await;
+        assert_eq!(limiter.limit(), 3);
+        assert_eq!(limiter.semaphore.available_permits(), 3);
+    }
+}
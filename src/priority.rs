@@ -0,0 +1,146 @@
+// Priority scheduling of reconciles for MyApp Controller
+// When every reconcile slot is busy, waiting reconciles are admitted highest priority
+// first, so critical apps converge ahead of best-effort ones when the queue is deep
+
+use crate::pressure::{ReconcileLimiter, ReconcilePermit};
+use crate::MyApp;
+use kube::ResourceExt;
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+
+/// Annotation holding a MyApp's priority: an integer or one of the names below
+pub const PRIORITY_ANNOTATION: &str = "myapps.example.com/priority";
+
+/// Named priorities; unannotated MyApps are "normal"
+const NAMED: &[(&str, i32)] = &[
+    ("critical", 1000),
+    ("high", 100),
+    ("normal", 0),
+    ("low", -100),
+    ("best-effort", -1000),
+];
+
+pub fn parse_priority(value: &str) -> Option<i32> {
+    let value = value.trim();
+    NAMED
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case(value))
+        .map(|(_, priority)| *priority)
+        .or_else(|| value.parse().ok())
+}
+
+/// Priority of `myapp`; missing or unparsable annotations count as normal
+pub fn priority(myapp: &MyApp) -> i32 {
+    myapp
+        .annotations()
+        .get(PRIORITY_ANNOTATION)
+        .and_then(|v| parse_priority(v))
+        .unwrap_or(0)
+}
+
+/// Hands out reconcile slots to the highest-priority waiter first
+#[derive(Clone)]
+pub struct PriorityLimiter {
+    limiter: ReconcileLimiter,
+    /// Number of reconciles waiting at each priority
+    waiting: Arc<Mutex<BTreeMap<i32, usize>>>,
+}
+
+/// Registration of one waiting reconcile
+struct Waiting<'a> {
+    gate: &'a PriorityLimiter,
+    priority: i32,
+}
+
+impl Drop for Waiting<'_> {
+    fn drop(&mut self) {
+        let mut waiting = self.gate.waiting.lock().unwrap();
+        if let Some(count) = waiting.get_mut(&self.priority) {
+            *count -= 1;
+            if *count == 0 {
+                waiting.remove(&self.priority);
+            }
+        }
+        drop(waiting);
+        // Lower priorities may now be first in line
+        self.gate.limiter.released().notify_waiters();
+    }
+}
+
+impl PriorityLimiter {
+    pub fn new(limiter: ReconcileLimiter) -> Self {
+        Self {
+            limiter,
+            waiting: Arc::new(Mutex::new(BTreeMap::new())),
+        }
+    }
+
+    fn is_first_in_line(&self, priority: i32) -> bool {
+        self.waiting
+            .lock()
+            .unwrap()
+            .keys()
+            .next_back()
+            .is_none_or(|highest| *highest <= priority)
+    }
+
+    /// Wait for a slot behind every waiter with a higher priority
+    pub async fn acquire(&self, priority: i32) -> ReconcilePermit {
+        *self.waiting.lock().unwrap().entry(priority).or_default() += 1;
+        let _waiting = Waiting {
+            gate: self,
+            priority,
+        };
+
+        loop {
+            // Register for the wakeup before checking so a release in between is not missed
+            let released = self.limiter.released().notified();
+            tokio::pin!(released);
+            released.as_mut().enable();
+
+            if self.is_first_in_line(priority) {
+                if let Some(permit) = self.limiter.try_acquire() {
+                    return permit;
+                }
+            }
+            released.await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_parse_priority() {
+        assert_eq!(parse_priority("High"), Some(100));
+        assert_eq!(parse_priority(" 42 "), Some(42));
+        assert_eq!(parse_priority("urgent"), None);
+    }
+
+    #[tokio::test]
+    async fn test_higher_priority_admitted_first() {
+        let gate = PriorityLimiter::new(ReconcileLimiter::new(1));
+        let held = gate.acquire(0).await;
+
+        let order = Arc::new(Mutex::new(Vec::new()));
+        let mut tasks = Vec::new();
+        for priority in [-100, 100, 0] {
+            let (gate, order) = (gate.clone(), order.clone());
+            tasks.push(tokio::spawn(async move {
+                let _permit = gate.acquire(priority).await;
+                order.lock().unwrap().push(priority);
+            }));
+            // Let each waiter register before the next one
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+
+        drop(held);
+        for task in tasks {
+            task.await.unwrap();
+        }
+        assert_eq!(*order.lock().unwrap(), vec![100, 0, -100]);
+    }
+}
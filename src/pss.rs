@@ -0,0 +1,412 @@
+// Pod Security Standards checks for MyApp Controller
+// With MYAPP_ENFORCE_PSS=true the validating webhook evaluates the pod spec the controller
+// would generate against the level in the namespace's `pod-security.kubernetes.io/enforce`
+// label, and denies the MyApp with the violations listed. Otherwise the API server admits the
+// MyApp and later rejects every pod of its Deployment, which only shows up in ReplicaSet events.
+
+use crate::cache::ClusterCache;
+use crate::{build_pod_spec, security_profiles, MyApp};
+use k8s_openapi::api::core::v1::{
+    Capabilities, Namespace, PodSecurityContext, PodSpec, SecurityContext,
+};
+use kube::ResourceExt;
+
+pub const ENFORCE_LABEL: &str = "pod-security.kubernetes.io/enforce";
+
+/// Pod Security Standards profiles, least to most restrictive
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub enum Level {
+    #[default]
+    Privileged,
+    Baseline,
+    Restricted,
+}
+
+impl Level {
+    pub fn parse(level: &str) -> Option<Self> {
+        match level {
+            "privileged" => Some(Self::Privileged),
+            "baseline" => Some(Self::Baseline),
+            "restricted" => Some(Self::Restricted),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Privileged => "privileged",
+            Self::Baseline => "baseline",
+            Self::Restricted => "restricted",
+        }
+    }
+}
+
+/// Capabilities the baseline profile allows containers to add
+const BASELINE_CAPABILITIES: &[&str] = &[
+    "AUDIT_WRITE",
+    "CHOWN",
+    "DAC_OVERRIDE",
+    "FOWNER",
+    "FSETID",
+    "KILL",
+    "MKNOD",
+    "NET_BIND_SERVICE",
+    "SETFCAP",
+    "SETGID",
+    "SETPCAP",
+    "SETUID",
+    "SYS_CHROOT",
+];
+
+const SAFE_SYSCTLS: &[&str] = &[
+    "kernel.shm_rmid_forced",
+    "net.ipv4.ip_local_port_range",
+    "net.ipv4.ip_local_reserved_ports",
+    "net.ipv4.ip_unprivileged_port_start",
+    "net.ipv4.ping_group_range",
+    "net.ipv4.tcp_syncookies",
+    "net.ipv4.tcp_keepalive_time",
+    "net.ipv4.tcp_fin_timeout",
+    "net.ipv4.tcp_keepalive_intvl",
+    "net.ipv4.tcp_keepalive_probes",
+];
+
+const SELINUX_TYPES: &[&str] = &[
+    "",
+    "container_t",
+    "container_init_t",
+    "container_kvm_t",
+    "container_engine_t",
+];
+
+/// A container's name and security context, whichever list it comes from
+fn containers(spec: &PodSpec) -> Vec<(&str, Option<&SecurityContext>)> {
+    let regular = spec
+        .containers
+        .iter()
+        .chain(spec.init_containers.iter().flatten())
+        .map(|c| (c.name.as_str(), c.security_context.as_ref()));
+    let ephemeral = spec
+        .ephemeral_containers
+        .iter()
+        .flatten()
+        .map(|c| (c.name.as_str(), c.security_context.as_ref()));
+    regular.chain(ephemeral).collect()
+}
+
+fn baseline(spec: &PodSpec, pod: Option<&PodSecurityContext>) -> Vec<String> {
+    let mut violations = Vec::new();
+    let containers = containers(spec);
+
+    for (field, set) in [
+        ("hostNetwork", spec.host_network),
+        ("hostPID", spec.host_pid),
+        ("hostIPC", spec.host_ipc),
+    ] {
+        if set == Some(true) {
+            violations.push(format!("{}=true", field));
+        }
+    }
+    let host_process = pod
+        .and_then(|p| p.windows_options.as_ref())
+        .and_then(|w| w.host_process)
+        == Some(true);
+    if host_process
+        || containers.iter().any(|(_, c)| {
+            c.and_then(|c| c.windows_options.as_ref())
+                .and_then(|w| w.host_process)
+                == Some(true)
+        })
+    {
+        violations.push("windowsOptions.hostProcess=true".to_string());
+    }
+
+    for volume in spec.volumes.iter().flatten() {
+        if volume.host_path.is_some() {
+            violations.push(format!("hostPath volume {}", volume.name));
+        }
+    }
+    for container in &spec.containers {
+        for port in container.ports.iter().flatten() {
+            if port.host_port.is_some_and(|p| p != 0) {
+                violations.push(format!(
+                    "hostPort {} in container {}",
+                    port.host_port.unwrap_or_default(),
+                    container.name
+                ));
+            }
+        }
+    }
+    for sysctl in pod.and_then(|p| p.sysctls.as_ref()).into_iter().flatten() {
+        if !SAFE_SYSCTLS.contains(&sysctl.name.as_str()) {
+            violations.push(format!("forbidden sysctl {}", sysctl.name));
+        }
+    }
+
+    let pod_selinux = pod.and_then(|p| p.se_linux_options.as_ref());
+    let pod_seccomp = pod.and_then(|p| p.seccomp_profile.as_ref());
+    let pod_apparmor = pod.and_then(|p| p.app_armor_profile.as_ref());
+    let selinux_ok = |o: &k8s_openapi::api::core::v1::SELinuxOptions| {
+        SELINUX_TYPES.contains(&o.type_.as_deref().unwrap_or_default())
+            && o.user.as_deref().unwrap_or_default().is_empty()
+            && o.role.as_deref().unwrap_or_default().is_empty()
+    };
+    if pod_selinux.is_some_and(|o| !selinux_ok(o)) {
+        violations.push("pod seLinuxOptions".to_string());
+    }
+    if pod_seccomp.is_some_and(|s| s.type_ == "Unconfined") {
+        violations.push("pod seccompProfile type Unconfined".to_string());
+    }
+    if pod_apparmor.is_some_and(|a| a.type_ == "Unconfined") {
+        violations.push("pod appArmorProfile type Unconfined".to_string());
+    }
+
+    for (name, context) in &containers {
+        let Some(context) = context else {
+            continue;
+        };
+        if context.privileged == Some(true) {
+            violations.push(format!("container {} is privileged", name));
+        }
+        let forbidden: Vec<&str> = context
+            .capabilities
+            .as_ref()
+            .and_then(|c| c.add.as_ref())
+            .into_iter()
+            .flatten()
+            .map(String::as_str)
+            .filter(|c| !BASELINE_CAPABILITIES.contains(c))
+            .collect();
+        if !forbidden.is_empty() {
+            violations.push(format!(
+                "container {} adds capabilities {}",
+                name,
+                forbidden.join(", ")
+            ));
+        }
+        if context
+            .se_linux_options
+            .as_ref()
+            .is_some_and(|o| !selinux_ok(o))
+        {
+            violations.push(format!("container {} seLinuxOptions", name));
+        }
+        if context
+            .proc_mount
+            .as_deref()
+            .is_some_and(|m| m != "Default")
+        {
+            violations.push(format!("container {} procMount", name));
+        }
+        if context
+            .seccomp_profile
+            .as_ref()
+            .is_some_and(|s| s.type_ == "Unconfined")
+        {
+            violations.push(format!("container {} seccompProfile type Unconfined", name));
+        }
+        if context
+            .app_armor_profile
+            .as_ref()
+            .is_some_and(|a| a.type_ == "Unconfined")
+        {
+            violations.push(format!(
+                "container {} appArmorProfile type Unconfined",
+                name
+            ));
+        }
+    }
+    violations
+}
+
+fn restricted(spec: &PodSpec, pod: Option<&PodSecurityContext>) -> Vec<String> {
+    let mut violations = Vec::new();
+
+    for volume in spec.volumes.iter().flatten() {
+        let allowed = volume.config_map.is_some()
+            || volume.csi.is_some()
+            || volume.downward_api.is_some()
+            || volume.empty_dir.is_some()
+            || volume.ephemeral.is_some()
+            || volume.persistent_volume_claim.is_some()
+            || volume.projected.is_some()
+            || volume.secret.is_some();
+        if !allowed {
+            violations.push(format!("volume {} has a restricted type", volume.name));
+        }
+    }
+
+    let pod_non_root = pod.and_then(|p| p.run_as_non_root);
+    let pod_seccomp = pod
+        .and_then(|p| p.seccomp_profile.as_ref())
+        .map(|s| s.type_.as_str());
+    if pod.and_then(|p| p.run_as_user) == Some(0) {
+        violations.push("pod runAsUser=0".to_string());
+    }
+
+    for (name, context) in containers(spec) {
+        let context = context.cloned().unwrap_or_default();
+        if context.allow_privilege_escalation != Some(false) {
+            violations.push(format!(
+                "container {} must set allowPrivilegeEscalation=false",
+                name
+            ));
+        }
+        if context.run_as_non_root.or(pod_non_root) != Some(true) {
+            violations.push(format!("container {} must set runAsNonRoot=true", name));
+        }
+        if context.run_as_user == Some(0) {
+            violations.push(format!("container {} runAsUser=0", name));
+        }
+        let seccomp = context
+            .seccomp_profile
+            .as_ref()
+            .map(|s| s.type_.as_str())
+            .or(pod_seccomp);
+        if !matches!(seccomp, Some("RuntimeDefault" | "Localhost")) {
+            violations.push(format!(
+                "container {} must set seccompProfile type RuntimeDefault or Localhost",
+                name
+            ));
+        }
+        let Capabilities { add, drop } = context.capabilities.unwrap_or_default();
+        if !drop.iter().flatten().any(|c| c == "ALL") {
+            violations.push(format!("container {} must drop ALL capabilities", name));
+        }
+        let added: Vec<String> = add
+            .into_iter()
+            .flatten()
+            .filter(|c| c != "NET_BIND_SERVICE")
+            .collect();
+        if !added.is_empty() {
+            violations.push(format!(
+                "container {} may only add NET_BIND_SERVICE, not {}",
+                name,
+                added.join(", ")
+            ));
+        }
+    }
+    violations
+}
+
+/// Ways `spec` falls short of `level`
+pub fn violations(spec: &PodSpec, level: Level) -> Vec<String> {
+    let pod = spec.security_context.as_ref();
+    let mut violations = Vec::new();
+    if level >= Level::Baseline {
+        violations.extend(baseline(spec, pod));
+    }
+    if level >= Level::Restricted {
+        violations.extend(restricted(spec, pod));
+    }
+    violations
+}
+
+/// Namespace labels for the validating webhook
+#[derive(Clone)]
+pub struct PssChecker {
+    pub namespaces: ClusterCache<Namespace>,
+    /// Level of namespaces without the enforce label, like the API server's admission defaults
+    pub default_level: Level,
+}
+
+impl PssChecker {
+    /// Enabled with `MYAPP_ENFORCE_PSS=true`
+    pub fn is_enabled_from_env() -> bool {
+        std::env::var("MYAPP_ENFORCE_PSS").is_ok_and(|v| v == "true" || v == "1")
+    }
+
+    /// `MYAPP_PSS_DEFAULT_LEVEL` (default privileged)
+    pub fn default_level_from_env() -> Level {
+        std::env::var("MYAPP_PSS_DEFAULT_LEVEL")
+            .ok()
+            .and_then(|v| Level::parse(&v))
+            .unwrap_or_default()
+    }
+
+    /// Deny MyApps whose pods the namespace's enforced level would reject
+    pub fn check(&self, myapp: &MyApp, namespace: &str) -> Result<(), String> {
+        let Some(namespaces) = self.namespaces.fresh_state() else {
+            return Ok(());
+        };
+        let level = namespaces
+            .iter()
+            .find(|ns| ns.name_any() == namespace)
+            .and_then(|ns| ns.labels().get(ENFORCE_LABEL).cloned())
+            .and_then(|level| Level::parse(&level))
+            .unwrap_or(self.default_level);
+
+        let mut violations = violations(&build_pod_spec(myapp), level);
+        // AppArmor is set through a pod template annotation rather than the pod spec
+        if level >= Level::Baseline
+            && security_profiles::apparmor_value(myapp).as_deref() == Some("unconfined")
+        {
+            violations.push("appArmor profile unconfined".to_string());
+        }
+        match violations.is_empty() {
+            true => Ok(()),
+            false => Err(format!(
+                "pods of this MyApp would violate PodSecurity \"{}\" enforced in namespace {}: {}",
+                level.as_str(),
+                namespace,
+                violations.join("; ")
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use k8s_openapi::api::core::v1::{Container, SeccompProfile};
+
+    fn pod(context: Option<SecurityContext>) -> PodSpec {
+        PodSpec {
+            containers: vec![Container {
+                name: "app".to_string(),
+                security_context: context,
+                ..Default::default()
+            }],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_restricted_needs_hardened_context() {
+        let plain = pod(None);
+        assert!(violations(&plain, Level::Baseline).is_empty());
+        assert_eq!(violations(&plain, Level::Restricted).len(), 4);
+
+        let hardened = pod(Some(SecurityContext {
+            allow_privilege_escalation: Some(false),
+            run_as_non_root: Some(true),
+            seccomp_profile: Some(SeccompProfile {
+                type_: "RuntimeDefault".to_string(),
+                ..Default::default()
+            }),
+            capabilities: Some(Capabilities {
+                drop: Some(vec!["ALL".to_string()]),
+                add: Some(vec!["NET_BIND_SERVICE".to_string()]),
+            }),
+            ..Default::default()
+        }));
+        assert!(violations(&hardened, Level::Restricted).is_empty());
+    }
+
+    #[test]
+    fn test_baseline_rejects_privileged() {
+        let mut spec = pod(Some(SecurityContext {
+            privileged: Some(true),
+            capabilities: Some(Capabilities {
+                add: Some(vec!["SYS_ADMIN".to_string(), "CHOWN".to_string()]),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }));
+        spec.host_network = Some(true);
+        let found = violations(&spec, Level::Baseline);
+        assert_eq!(found.len(), 3, "{:?}", found);
+        assert!(found[2].ends_with("SYS_ADMIN"));
+        assert!(violations(&spec, Level::Privileged).is_empty());
+    }
+}
@@ -0,0 +1,203 @@
+// Image pull secret checks for MyApp Controller
+// With MYAPP_CHECK_PULL_SECRETS=true the validating webhook looks up the Secrets named in
+// spec.imagePullSecrets and asks the image's registry for its manifest with the credentials
+// they hold. A missing Secret or rejected credentials are refused at admission rather than
+// surfacing later as pods stuck in ImagePullBackOff. Unreachable registries are not held
+// against the MyApp.
+
+use crate::arch::{Credentials, ImageRef, RegistryClient, RegistryError};
+use base64::Engine;
+use k8s_openapi::api::core::v1::Secret;
+use kube::api::Api;
+use kube::{Client, ResourceExt};
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+pub fn enabled_from_env() -> bool {
+    std::env::var("MYAPP_CHECK_PULL_SECRETS").is_ok_and(|v| v == "true" || v == "1")
+}
+
+pub fn validate(names: &[String]) -> Result<(), String> {
+    for (i, name) in names.iter().enumerate() {
+        if name.is_empty() {
+            return Err("imagePullSecrets entries cannot be empty".to_string());
+        }
+        if names[..i].contains(name) {
+            return Err(format!("imagePullSecrets lists {} twice", name));
+        }
+    }
+    Ok(())
+}
+
+/// Registry host of a docker config key, without scheme or path; Docker Hub's aliases
+/// become docker.io like in image references
+fn registry_host(key: &str) -> &str {
+    let host = key.split_once("://").map_or(key, |(_, rest)| rest);
+    let host = host.split('/').next().unwrap_or_default();
+    match host {
+        "index.docker.io" | "registry-1.docker.io" | "registry.hub.docker.com" => "docker.io",
+        other => other,
+    }
+}
+
+fn entry_credentials(entry: &Value) -> Option<Credentials> {
+    if let (Some(username), Some(password)) =
+        (entry["username"].as_str(), entry["password"].as_str())
+    {
+        return Some(Credentials {
+            username: username.to_string(),
+            password: password.to_string(),
+        });
+    }
+    let auth = base64::engine::general_purpose::STANDARD
+        .decode(entry["auth"].as_str()?)
+        .ok()?;
+    let (username, password) = std::str::from_utf8(&auth).ok()?.split_once(':')?;
+    Some(Credentials {
+        username: username.to_string(),
+        password: password.to_string(),
+    })
+}
+
+/// Credentials per registry host in a dockerconfigjson or legacy dockercfg Secret
+pub fn docker_credentials(secret: &Secret) -> Result<BTreeMap<String, Credentials>, String> {
+    let data = secret.data.clone().unwrap_or_default();
+    let (config, auths) = match (data.get(".dockerconfigjson"), data.get(".dockercfg")) {
+        (Some(config), _) => (config, "auths"),
+        (None, Some(config)) => (config, ""),
+        (None, None) => {
+            return Err(format!(
+                "imagePullSecret {} has no .dockerconfigjson key",
+                secret.name_any()
+            ))
+        }
+    };
+    let config: Value = serde_json::from_slice(&config.0).map_err(|e| {
+        format!(
+            "imagePullSecret {} is not valid JSON: {}",
+            secret.name_any(),
+            e
+        )
+    })?;
+    let entries = match auths {
+        "" => &config,
+        key => &config[key],
+    };
+    Ok(entries
+        .as_object()
+        .into_iter()
+        .flatten()
+        .filter_map(|(key, entry)| {
+            Some((registry_host(key).to_string(), entry_credentials(entry)?))
+        })
+        .collect())
+}
+
+/// Secret and registry lookups for the validating webhook
+#[derive(Clone)]
+pub struct PullSecretChecker {
+    pub client: Client,
+    pub registry: RegistryClient,
+}
+
+impl PullSecretChecker {
+    /// Refuse pull secrets that are missing or whose credentials the registry rejects
+    pub async fn check(
+        &self,
+        image: &str,
+        names: &[String],
+        namespace: &str,
+    ) -> Result<(), String> {
+        let registry = ImageRef::parse(image).registry;
+        let api: Api<Secret> = Api::namespaced(self.client.clone(), namespace);
+
+        let mut candidates = Vec::new();
+        for name in names {
+            let secret = match api.get_opt(name).await {
+                Ok(Some(secret)) => secret,
+                Ok(None) => {
+                    return Err(format!(
+                        "imagePullSecret {} does not exist in namespace {}",
+                        name, namespace
+                    ))
+                }
+                Err(e) => {
+                    tracing::warn!(
+                        "Could not read imagePullSecret {}/{}: {}",
+                        namespace,
+                        name,
+                        e
+                    );
+                    return Ok(());
+                }
+            };
+            if let Some(credentials) = docker_credentials(&secret)?.remove(&registry) {
+                candidates.push((name, credentials));
+            }
+        }
+
+        // Without credentials for this registry the kubelet pulls anonymously
+        let mut rejected = Vec::new();
+        for (name, credentials) in &candidates {
+            match self.registry.check_access(image, Some(credentials)).await {
+                Ok(()) => return Ok(()),
+                Err(RegistryError::Status { status, .. }) => {
+                    rejected.push(format!("{}: registry returned {}", name, status))
+                }
+                Err(e) => {
+                    tracing::warn!("Could not check pull access to {}: {}", image, e);
+                    return Ok(());
+                }
+            }
+        }
+        match rejected.is_empty() {
+            true => Ok(()),
+            false => Err(format!(
+                "{} cannot be pulled with its imagePullSecrets ({})",
+                image,
+                rejected.join("; ")
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use k8s_openapi::ByteString;
+
+    fn secret(key: &str, config: Value) -> Secret {
+        Secret {
+            data: Some(BTreeMap::from([(
+                key.to_string(),
+                ByteString(config.to_string().into_bytes()),
+            )])),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_docker_credentials() {
+        let config = serde_json::json!({ "auths": {
+            "https://index.docker.io/v1/": { "auth": "Ym90OnMzY3JldA==" },
+            "ghcr.io": { "username": "ci", "password": "token" }
+        } });
+        let credentials = docker_credentials(&secret(".dockerconfigjson", config)).unwrap();
+        assert_eq!(credentials["docker.io"].username, "bot");
+        assert_eq!(credentials["docker.io"].password, "s3cret");
+        assert_eq!(credentials["ghcr.io"].password, "token");
+
+        let legacy = serde_json::json!({ "localhost:5000": { "auth": "YTpi" } });
+        let credentials = docker_credentials(&secret(".dockercfg", legacy)).unwrap();
+        assert_eq!(credentials["localhost:5000"].username, "a");
+
+        assert!(docker_credentials(&secret("token", Value::Null)).is_err());
+    }
+
+    #[test]
+    fn test_validate_names() {
+        assert!(validate(&["regcred".to_string()]).is_ok());
+        assert!(validate(&["regcred".to_string(), "regcred".to_string()]).is_err());
+        assert!(validate(&[String::new()]).is_err());
+    }
+}
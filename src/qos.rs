@@ -0,0 +1,177 @@
+// QoS and resource ratio policy for MyApp Controller
+// Computes the pod QoS class and bounds how far limits may burst above requests
+
+use crate::quantity::{parse_cpu, parse_memory};
+use crate::{MyAppSpec, ResourceRequirements};
+
+pub const GUARANTEED: &str = "Guaranteed";
+pub const BURSTABLE: &str = "Burstable";
+pub const BEST_EFFORT: &str = "BestEffort";
+
+/// QoS class Kubernetes will assign to the app's pods
+pub fn qos_class(resources: Option<&ResourceRequirements>) -> &'static str {
+    let Some(resources) = resources else {
+        return BEST_EFFORT;
+    };
+    let Some(limits) = &resources.limits else {
+        return BURSTABLE;
+    };
+
+    let cpu_equal = limits
+        .cpu
+        .as_deref()
+        .and_then(parse_cpu)
+        .zip(parse_cpu(&resources.cpu))
+        .is_some_and(|(l, r)| l == r);
+    let memory_equal = limits
+        .memory
+        .as_deref()
+        .and_then(parse_memory)
+        .zip(parse_memory(&resources.memory))
+        .is_some_and(|(l, r)| l == r);
+
+    if cpu_equal && memory_equal {
+        GUARANTEED
+    } else {
+        BURSTABLE
+    }
+}
+
+/// Ratio `lint` reports against when none is configured; advisory only
+pub const ADVISORY_MAX_RATIO: f64 = 4.0;
+
+/// Limits-to-requests ratio policy, enforced by the validating webhook and `lint`
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct RatioPolicy {
+    /// Maximum limit:request ratio for cpu and memory (None disables the check)
+    pub max_ratio: Option<f64>,
+    /// Require Guaranteed QoS (limits equal requests) for `tier: prod`
+    pub prod_requires_guaranteed: bool,
+}
+
+impl RatioPolicy {
+    /// Policy `lint` applies: the ratio check is on at the advisory maximum
+    pub fn advisory() -> Self {
+        Self {
+            max_ratio: Some(ADVISORY_MAX_RATIO),
+            ..Default::default()
+        }
+    }
+
+    /// Read `MYAPP_MAX_LIMIT_REQUEST_RATIO` (unset or 0 disables) and
+    /// `MYAPP_PROD_REQUIRE_GUARANTEED`
+    pub fn from_env() -> Self {
+        Self::from_env_or(Self::default())
+    }
+
+    /// Like `from_env`, falling back to `default` for unset variables
+    pub fn from_env_or(default: Self) -> Self {
+        let max_ratio = match std::env::var("MYAPP_MAX_LIMIT_REQUEST_RATIO") {
+            Ok(v) => v.parse::<f64>().ok().filter(|r| *r > 0.0),
+            Err(_) => default.max_ratio,
+        };
+        let prod_requires_guaranteed = std::env::var("MYAPP_PROD_REQUIRE_GUARANTEED")
+            .map(|v| v == "true" || v == "1")
+            .unwrap_or(default.prod_requires_guaranteed);
+
+        Self {
+            max_ratio,
+            prod_requires_guaranteed,
+        }
+    }
+
+    /// Return every violation of the policy
+    pub fn check(&self, spec: &MyAppSpec) -> Vec<String> {
+        let mut violations = Vec::new();
+        let resources = spec.resources.as_ref();
+
+        if let (Some(max), Some(resources)) = (self.max_ratio, resources) {
+            let limits = resources.limits.clone().unwrap_or_default();
+            let pairs = [
+                (
+                    "cpu",
+                    limits.cpu.as_deref().and_then(parse_cpu),
+                    parse_cpu(&resources.cpu),
+                ),
+                (
+                    "memory",
+                    limits.memory.as_deref().and_then(parse_memory),
+                    parse_memory(&resources.memory),
+                ),
+            ];
+            for (name, limit, request) in pairs {
+                if let (Some(limit), Some(request)) = (limit, request) {
+                    if request > 0.0 && limit / request > max {
+                        violations.push(format!(
+                            "{} limit is {:.1}x the request, exceeding the maximum of {}x",
+                            name,
+                            limit / request,
+                            max
+                        ));
+                    }
+                }
+            }
+        }
+
+        if self.prod_requires_guaranteed
+            && spec.tier.as_deref() == Some("prod")
+            && qos_class(resources) != GUARANTEED
+        {
+            violations.push(
+                "tier prod requires Guaranteed QoS: set cpu and memory limits equal to requests"
+                    .to_string(),
+            );
+        }
+
+        violations
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ResourceLimits;
+
+    fn resources(cpu_limit: &str, memory_limit: &str) -> ResourceRequirements {
+        ResourceRequirements {
+            cpu: "250m".to_string(),
+            memory: "256Mi".to_string(),
+            limits: Some(ResourceLimits {
+                cpu: Some(cpu_limit.to_string()),
+                memory: Some(memory_limit.to_string()),
+            }),
+        }
+    }
+
+    #[test]
+    fn test_qos_class() {
+        assert_eq!(qos_class(None), BEST_EFFORT);
+        assert_eq!(qos_class(Some(&resources("250m", "256Mi"))), GUARANTEED);
+        assert_eq!(qos_class(Some(&resources("0.25", "256Mi"))), GUARANTEED);
+        assert_eq!(qos_class(Some(&resources("1", "256Mi"))), BURSTABLE);
+    }
+
+    #[test]
+    fn test_ratio_policy() {
+        let policy = RatioPolicy {
+            max_ratio: Some(4.0),
+            prod_requires_guaranteed: true,
+        };
+
+        let mut spec = MyAppSpec {
+            resources: Some(resources("2", "512Mi")),
+            ..Default::default()
+        };
+        let violations = policy.check(&spec);
+        assert_eq!(violations.len(), 1);
+        // The ratio cap is opt-in
+        assert!(RatioPolicy::default().check(&spec).is_empty());
+        assert!(violations[0].starts_with("cpu limit is 8.0x"));
+
+        spec.resources = Some(resources("500m", "512Mi"));
+        assert!(policy.check(&spec).is_empty());
+
+        spec.tier = Some("prod".to_string());
+        assert_eq!(policy.check(&spec).len(), 1);
+    }
+}
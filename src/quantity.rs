@@ -0,0 +1,54 @@
+// Resource quantity helpers for MyApp Controller
+// Parses Kubernetes CPU and memory quantities into plain numbers for comparisons and estimates
+
+/// Parse a CPU quantity ("500m", "2", "0.5") into cores
+pub fn parse_cpu(value: &str) -> Option<f64> {
+    let value = value.trim();
+    match value.strip_suffix('m') {
+        Some(millis) => millis.parse::<f64>().ok().map(|m| m / 1000.0),
+        None => value.parse::<f64>().ok(),
+    }
+}
+
+/// Parse a memory quantity ("128Mi", "1G", "1048576") into bytes
+pub fn parse_memory(value: &str) -> Option<f64> {
+    const SUFFIXES: &[(&str, f64)] = &[
+        ("Ki", 1024.0),
+        ("Mi", 1024.0 * 1024.0),
+        ("Gi", 1024.0 * 1024.0 * 1024.0),
+        ("Ti", 1024.0 * 1024.0 * 1024.0 * 1024.0),
+        ("k", 1e3),
+        ("K", 1e3),
+        ("M", 1e6),
+        ("G", 1e9),
+        ("T", 1e12),
+    ];
+
+    let value = value.trim();
+    for (suffix, factor) in SUFFIXES {
+        if let Some(number) = value.strip_suffix(suffix) {
+            return number.parse::<f64>().ok().map(|n| n * factor);
+        }
+    }
+    value.parse::<f64>().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_cpu() {
+        assert_eq!(parse_cpu("500m"), Some(0.5));
+        assert_eq!(parse_cpu("2"), Some(2.0));
+        assert_eq!(parse_cpu("abc"), None);
+    }
+
+    #[test]
+    fn test_parse_memory() {
+        assert_eq!(parse_memory("128Mi"), Some(128.0 * 1024.0 * 1024.0));
+        assert_eq!(parse_memory("1G"), Some(1e9));
+        assert_eq!(parse_memory("1024"), Some(1024.0));
+        assert_eq!(parse_memory("lots"), None);
+    }
+}
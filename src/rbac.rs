@@ -0,0 +1,329 @@
+// RBAC for MyApp Controller
+// Every API call the controller makes is listed in RULES; `generate-rbac` builds the
+// ServiceAccount, roles and bindings from it so permissions follow the code
+
+use crate::scope::WatchScope;
+use k8s_openapi::api::core::v1::ServiceAccount;
+use k8s_openapi::api::rbac::v1::{
+    ClusterRole, ClusterRoleBinding, PolicyRule, Role, RoleBinding, RoleRef, Subject,
+};
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta;
+use kube::Resource;
+use std::collections::BTreeMap;
+
+pub const NAME: &str = "myapp-controller";
+pub const METRICS_READER: &str = "myapp-controller-metrics-reader";
+
+const CRUD: &[&str] = &[
+    "get", "list", "watch", "create", "update", "patch", "delete",
+];
+
+/// Permissions needed by one part of the controller
+#[derive(Debug, Clone, Copy)]
+pub struct Rule {
+    pub api_group: &'static str,
+    pub resources: &'static [&'static str],
+    pub verbs: &'static [&'static str],
+    /// Cluster-scoped rules stay in the ClusterRole when watching a namespace list
+    pub cluster_scoped: bool,
+}
+
+const fn namespaced(
+    api_group: &'static str,
+    resources: &'static [&'static str],
+    verbs: &'static [&'static str],
+) -> Rule {
+    Rule {
+        api_group,
+        resources,
+        verbs,
+        cluster_scoped: false,
+    }
+}
+
+const fn cluster(
+    api_group: &'static str,
+    resources: &'static [&'static str],
+    verbs: &'static [&'static str],
+) -> Rule {
+    Rule {
+        api_group,
+        resources,
+        verbs,
+        cluster_scoped: true,
+    }
+}
+
+pub const RULES: &[Rule] = &[
+    // Watching MyApps, writing status, finalizers and pause/rollback annotations
+    namespaced(
+        "example.com",
+        &["myapps"],
+        &["get", "list", "watch", "update", "patch"],
+    ),
+    namespaced(
+        "example.com",
+        &["myapps/status"],
+        &["get", "update", "patch"],
+    ),
+    namespaced("example.com", &["myapps/finalizers"], &["update"]),
+    // Child workloads
+    namespaced("apps", &["deployments"], CRUD),
+    namespaced("", &["services"], CRUD),
+    namespaced("batch", &["jobs", "cronjobs"], CRUD),
+    // spec.generatedSecrets (written once, replaced on rotation) and spec.vaultSecrets
+    namespaced(
+        "",
+        &["secrets"],
+        &["get", "list", "create", "update", "patch", "delete"],
+    ),
+    // Tokens for the default service account, to log in to Vault as the app's namespace
+    namespaced("", &["serviceaccounts/token"], &["create"]),
+    // ServiceMonitors and PrometheusRules for spec.monitoring (Prometheus Operator)
+    namespaced(
+        "monitoring.coreos.com",
+        &["servicemonitors", "prometheusrules"],
+        &["get", "list", "create", "patch", "delete"],
+    ),
+    // ExternalSecrets for spec.externalSecrets (External Secrets Operator)
+    namespaced(
+        "external-secrets.io",
+        &["externalsecrets"],
+        &["get", "list", "create", "patch", "delete"],
+    ),
+    // Default-deny and allow policies for spec.networkPolicy
+    namespaced(
+        "networking.k8s.io",
+        &["networkpolicies"],
+        &["get", "list", "create", "patch", "delete"],
+    ),
+    // ServiceAccount, Role and RoleBinding for spec.rbac; without escalate and bind the API
+    // server only lets the controller grant permissions it holds itself
+    namespaced(
+        "",
+        &["serviceaccounts"],
+        &["get", "list", "create", "patch", "delete"],
+    ),
+    namespaced(
+        "rbac.authorization.k8s.io",
+        &["roles"],
+        &["get", "list", "create", "patch", "delete"],
+    ),
+    namespaced(
+        "rbac.authorization.k8s.io",
+        &["rolebindings"],
+        &["get", "list", "create", "patch", "delete"],
+    ),
+    // Releasing PVCs under the Orphan and Retain deletion policies
+    namespaced("", &["persistentvolumeclaims"], &["get", "list", "patch"]),
+    // Reconcile events
+    namespaced("", &["events"], &["create", "patch"]),
+    namespaced("events.k8s.io", &["events"], &["create", "patch"]),
+    // Following the cluster-scoped MyAppOperatorConfig and reporting on its status
+    cluster(
+        "example.com",
+        &["myappoperatorconfigs"],
+        &["get", "list", "watch"],
+    ),
+    cluster(
+        "example.com",
+        &["myappoperatorconfigs/status"],
+        &["get", "patch"],
+    ),
+    // Node pod CIDRs, to detect IPv4/IPv6 support at startup
+    cluster("", &["nodes"], &["get", "list", "watch"]),
+    // Checking scrapers' tokens when MYAPP_METRICS_AUTH=tokenreview
+    cluster("authentication.k8s.io", &["tokenreviews"], &["create"]),
+    cluster(
+        "authorization.k8s.io",
+        &["subjectaccessreviews"],
+        &["create"],
+    ),
+];
+
+fn policy_rule(rule: &Rule) -> PolicyRule {
+    let strings = |items: &[&str]| items.iter().map(|s| s.to_string()).collect();
+    PolicyRule {
+        api_groups: Some(vec![rule.api_group.to_string()]),
+        resources: Some(strings(rule.resources)),
+        verbs: strings(rule.verbs),
+        ..Default::default()
+    }
+}
+
+fn metadata(name: &str, namespace: Option<&str>) -> ObjectMeta {
+    ObjectMeta {
+        name: Some(name.to_string()),
+        namespace: namespace.map(str::to_string),
+        labels: Some(BTreeMap::from([
+            ("app.kubernetes.io/name".to_string(), NAME.to_string()),
+            (
+                "app.kubernetes.io/component".to_string(),
+                "controller".to_string(),
+            ),
+        ])),
+        ..Default::default()
+    }
+}
+
+fn role_ref<K: Resource<DynamicType = ()>>(name: &str) -> RoleRef {
+    RoleRef {
+        api_group: K::group(&()).to_string(),
+        kind: K::kind(&()).to_string(),
+        name: name.to_string(),
+    }
+}
+
+fn subject(namespace: &str) -> Subject {
+    Subject {
+        kind: ServiceAccount::kind(&()).to_string(),
+        name: NAME.to_string(),
+        namespace: Some(namespace.to_string()),
+        ..Default::default()
+    }
+}
+
+/// Manifests for a controller running as `myapp-controller` in `namespace`.
+/// Cluster-wide watches get one ClusterRole; a namespace list gets a Role per
+/// namespace and keeps only the cluster-scoped rules in the ClusterRole.
+pub fn manifests(
+    namespace: &str,
+    scope: &WatchScope,
+) -> Result<Vec<serde_yaml::Value>, serde_yaml::Error> {
+    let cluster_wide = *scope == WatchScope::All;
+    let rules = |for_cluster_role: bool| -> Vec<PolicyRule> {
+        RULES
+            .iter()
+            .filter(|r| {
+                if for_cluster_role {
+                    r.cluster_scoped || cluster_wide
+                } else {
+                    !r.cluster_scoped
+                }
+            })
+            .map(policy_rule)
+            .collect()
+    };
+
+    let mut docs = vec![
+        serde_yaml::to_value(ServiceAccount {
+            metadata: metadata(NAME, Some(namespace)),
+            ..Default::default()
+        })?,
+        serde_yaml::to_value(ClusterRole {
+            metadata: metadata(NAME, None),
+            rules: Some(rules(true)),
+            ..Default::default()
+        })?,
+        // Bind to Prometheus' service account to scrape /metrics when MYAPP_METRICS_AUTH=tokenreview
+        serde_yaml::to_value(ClusterRole {
+            metadata: metadata(METRICS_READER, None),
+            rules: Some(vec![PolicyRule {
+                non_resource_urls: Some(vec!["/metrics".to_string()]),
+                verbs: vec!["get".to_string()],
+                ..Default::default()
+            }]),
+            ..Default::default()
+        })?,
+        serde_yaml::to_value(ClusterRoleBinding {
+            metadata: metadata(NAME, None),
+            role_ref: role_ref::<ClusterRole>(NAME),
+            subjects: Some(vec![subject(namespace)]),
+        })?,
+    ];
+
+    if let WatchScope::Namespaces(watched) = scope {
+        for ns in watched {
+            docs.push(serde_yaml::to_value(Role {
+                metadata: metadata(NAME, Some(ns)),
+                rules: Some(rules(false)),
+            })?);
+            docs.push(serde_yaml::to_value(RoleBinding {
+                metadata: metadata(NAME, Some(ns)),
+                role_ref: role_ref::<Role>(NAME),
+                subjects: Some(vec![subject(namespace)]),
+            })?);
+        }
+    }
+
+    Ok(docs)
+}
+
+/// `generate-rbac [--namespace <ns>] [--watch-namespace a,b]`
+pub fn run(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let mut namespace = "default".to_string();
+    let mut scope = WatchScope::All;
+
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--namespace" => {
+                namespace = iter.next().cloned().ok_or("--namespace needs a value")?;
+            }
+            "--watch-namespace" => {
+                scope = WatchScope::parse(iter.next().ok_or("--watch-namespace needs a value")?);
+            }
+            other => return Err(format!("unknown generate-rbac argument: {}", other).into()),
+        }
+    }
+
+    for doc in manifests(&namespace, &scope)? {
+        print!("---\n{}", serde_yaml::to_string(&doc)?);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MyApp;
+    use serde::Deserialize;
+
+    fn grants(api_group: &str, resource: &str, verb: &str) -> bool {
+        RULES.iter().any(|r| {
+            r.api_group == api_group && r.resources.contains(&resource) && r.verbs.contains(&verb)
+        })
+    }
+
+    #[test]
+    fn test_rules_cover_myapp_reconcile() {
+        let group = MyApp::group(&());
+        let plural = MyApp::plural(&());
+        assert!(grants(&group, &plural, "watch"));
+        assert!(grants(&group, &format!("{}/status", plural), "patch"));
+        assert!(grants(&group, &format!("{}/finalizers", plural), "update"));
+    }
+
+    #[test]
+    fn test_namespaced_scope_splits_rules() {
+        let scope = WatchScope::parse("team-a,team-b");
+        let docs = manifests("myapp-system", &scope).unwrap();
+
+        let roles: Vec<&serde_yaml::Value> = docs.iter().filter(|d| d["kind"] == "Role").collect();
+        assert_eq!(roles.len(), 2);
+        assert_eq!(roles[0]["metadata"]["namespace"], "team-a");
+
+        let cluster_role = &docs[1]["rules"];
+        let cluster_resources: Vec<&str> = cluster_role
+            .as_sequence()
+            .unwrap()
+            .iter()
+            .flat_map(|r| r["resources"].as_sequence().unwrap())
+            .filter_map(|r| r.as_str())
+            .collect();
+        assert!(cluster_resources.contains(&"nodes"));
+        assert!(!cluster_resources.contains(&"deployments"));
+    }
+
+    /// k8s/rbac.yaml is the output of `generate-rbac --namespace '${NAMESPACE}'`
+    #[test]
+    fn test_k8s_manifest_matches() {
+        let manifest = include_str!("../k8s/rbac.yaml");
+        let checked_in: Vec<serde_yaml::Value> = serde_yaml::Deserializer::from_str(manifest)
+            .map(serde_yaml::Value::deserialize)
+            .collect::<Result<_, _>>()
+            .unwrap();
+        let generated = manifests("${NAMESPACE}", &WatchScope::All).unwrap();
+        assert_eq!(checked_in, generated);
+    }
+}
@@ -0,0 +1,105 @@
+// Readiness checks for MyApp Controller
+// Ready once the API server answers, the MyApp CRD is served and every MyApp watch has listed
+
+use crate::cache::ClusterCache;
+use crate::MyApp;
+use futures::FutureExt;
+use kube::runtime::reflector::Store;
+use kube::{Client, Resource};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Longest a readiness probe waits on the API server
+const API_TIMEOUT: Duration = Duration::from_secs(2);
+
+type SyncCheck = Arc<dyn Fn() -> bool + Send + Sync>;
+
+#[derive(Clone)]
+pub struct Readiness {
+    client: Client,
+    /// Named MyApp watches that must finish their initial list
+    watches: Vec<(String, SyncCheck)>,
+}
+
+impl Readiness {
+    pub fn new(client: Client) -> Self {
+        Self {
+            client,
+            watches: Vec::new(),
+        }
+    }
+
+    /// Require a controller's reflector store to be populated
+    pub fn store(mut self, name: &str, store: Store<MyApp>) -> Self {
+        let synced = move || store.wait_until_ready().now_or_never().is_some();
+        self.watches.push((name.to_string(), Arc::new(synced)));
+        self
+    }
+
+    /// Require an admission cache to be synced
+    pub fn cache(mut self, name: &str, cache: ClusterCache<MyApp>) -> Self {
+        let synced = move || cache.freshness().synced;
+        self.watches.push((name.to_string(), Arc::new(synced)));
+        self
+    }
+
+    /// Why the process is not ready, if it isn't
+    pub async fn check(&self) -> Result<(), String> {
+        let api_version = MyApp::api_version(&());
+        let resources = tokio::time::timeout(
+            API_TIMEOUT,
+            self.client.list_api_group_resources(&api_version),
+        )
+        .await
+        .map_err(|_| "API server did not answer in time".to_string())?;
+        match resources {
+            Ok(list) if list.resources.iter().any(|r| r.name == MyApp::plural(&())) => {}
+            Ok(_) | Err(kube::Error::Api(kube::core::ErrorResponse { code: 404, .. })) => {
+                return Err(format!("MyApp CRD is not installed ({})", api_version));
+            }
+            Err(e) => return Err(format!("API server unreachable: {}", e)),
+        }
+
+        unsynced(&self.watches)
+    }
+}
+
+fn unsynced(watches: &[(String, SyncCheck)]) -> Result<(), String> {
+    let waiting: Vec<&str> = watches
+        .iter()
+        .filter(|(_, synced)| !synced())
+        .map(|(name, _)| name.as_str())
+        .collect();
+    if waiting.is_empty() {
+        Ok(())
+    } else {
+        Err(format!(
+            "waiting for the initial list of MyApps in {}",
+            waiting.join(", ")
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use kube::runtime::reflector;
+    use kube::runtime::watcher::Event;
+
+    #[test]
+    fn test_unsynced_watches() {
+        let (store, mut writer) = reflector::store::<MyApp>();
+        let synced = move || store.wait_until_ready().now_or_never().is_some();
+        let watches: Vec<(String, SyncCheck)> = vec![
+            ("shop".to_string(), Arc::new(|| true)),
+            ("billing".to_string(), Arc::new(synced)),
+        ];
+        assert_eq!(
+            unsynced(&watches),
+            Err("waiting for the initial list of MyApps in billing".to_string())
+        );
+
+        writer.apply_watcher_event(&Event::InitDone);
+        assert_eq!(unsynced(&watches), Ok(()));
+    }
+}
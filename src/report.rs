@@ -0,0 +1,223 @@
+// Fleet report for MyApp Controller
+// Aggregates every MyApp into a single JSON or CSV report for platform reviews
+
+use crate::quantity::{parse_cpu, parse_memory};
+use crate::MyApp;
+use kube::api::{Api, ListParams};
+use kube::ResourceExt;
+use serde::Serialize;
+
+/// Monthly prices used to estimate the cost of an app's requests
+#[derive(Debug, Clone, Copy)]
+pub struct Pricing {
+    pub cpu_core_month: f64,
+    pub memory_gib_month: f64,
+}
+
+impl Default for Pricing {
+    fn default() -> Self {
+        Self {
+            cpu_core_month: 25.0,
+            memory_gib_month: 3.5,
+        }
+    }
+}
+
+impl Pricing {
+    /// Read prices from `MYAPP_COST_CPU_CORE_MONTH` and `MYAPP_COST_MEMORY_GIB_MONTH`
+    pub fn from_env() -> Self {
+        let default = Self::default();
+        let read = |key: &str, fallback: f64| {
+            std::env::var(key)
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(fallback)
+        };
+
+        Self {
+            cpu_core_month: read("MYAPP_COST_CPU_CORE_MONTH", default.cpu_core_month),
+            memory_gib_month: read("MYAPP_COST_MEMORY_GIB_MONTH", default.memory_gib_month),
+        }
+    }
+}
+
+/// One line of the fleet report
+#[derive(Debug, Clone, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ReportRow {
+    pub namespace: String,
+    pub name: String,
+    pub image: String,
+    pub replicas: i32,
+    pub tier: String,
+    pub phase: String,
+    pub cpu_request: String,
+    pub memory_request: String,
+    pub estimated_monthly_cost: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    Json,
+    Csv,
+}
+
+pub fn build_row(myapp: &MyApp, pricing: &Pricing) -> ReportRow {
+    let (cpu, memory) = myapp
+        .spec
+        .resources
+        .as_ref()
+        .map(|r| (r.cpu.clone(), r.memory.clone()))
+        .unwrap_or_default();
+
+    let cores = parse_cpu(&cpu).unwrap_or(0.0);
+    let gib = parse_memory(&memory).unwrap_or(0.0) / (1024.0 * 1024.0 * 1024.0);
+    let per_replica = cores * pricing.cpu_core_month + gib * pricing.memory_gib_month;
+    let cost = per_replica * myapp.spec.replicas as f64;
+
+    ReportRow {
+        namespace: myapp.namespace().unwrap_or_default(),
+        name: myapp.name_any(),
+        image: myapp.spec.image.clone(),
+        replicas: myapp.spec.replicas,
+        tier: myapp.spec.tier.clone().unwrap_or_default(),
+        phase: myapp
+            .status
+            .as_ref()
+            .map(|s| s.state.clone())
+            .unwrap_or_else(|| "Pending".to_string()),
+        cpu_request: cpu,
+        memory_request: memory,
+        estimated_monthly_cost: (cost * 100.0).round() / 100.0,
+    }
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+pub fn render(rows: &[ReportRow], format: OutputFormat) -> Result<String, serde_json::Error> {
+    match format {
+        OutputFormat::Json => serde_json::to_string_pretty(rows),
+        OutputFormat::Csv => {
+            let mut out = String::from(
+                "namespace,name,image,replicas,tier,phase,cpuRequest,memoryRequest,estimatedMonthlyCost\n",
+            );
+            for row in rows {
+                let fields = [
+                    csv_field(&row.namespace),
+                    csv_field(&row.name),
+                    csv_field(&row.image),
+                    row.replicas.to_string(),
+                    csv_field(&row.tier),
+                    csv_field(&row.phase),
+                    csv_field(&row.cpu_request),
+                    csv_field(&row.memory_request),
+                    format!("{:.2}", row.estimated_monthly_cost),
+                ];
+                out.push_str(&fields.join(","));
+                out.push('\n');
+            }
+            Ok(out)
+        }
+    }
+}
+
+/// `report [--all-namespaces | -n <namespace>] [-o json|csv]`
+pub async fn run(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let mut all_namespaces = false;
+    let mut namespace = None;
+    let mut format = OutputFormat::Json;
+
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--all-namespaces" | "-A" => all_namespaces = true,
+            "-n" | "--namespace" => namespace = iter.next().cloned(),
+            "-o" | "--output" => {
+                format = match iter.next().map(String::as_str) {
+                    Some("json") => OutputFormat::Json,
+                    Some("csv") => OutputFormat::Csv,
+                    other => return Err(format!("unsupported output format: {:?}", other).into()),
+                }
+            }
+            other => return Err(format!("unknown report argument: {}", other).into()),
+        }
+    }
+
+    let client = crate::kubectl::client().await?;
+    let api: Api<MyApp> = if all_namespaces {
+        Api::all(client)
+    } else {
+        let ns = namespace.unwrap_or_else(|| client.default_namespace().to_string());
+        Api::namespaced(client, &ns)
+    };
+
+    let pricing = Pricing::from_env();
+    let mut rows: Vec<ReportRow> = api
+        .list(&ListParams::default())
+        .await?
+        .items
+        .iter()
+        .map(|app| build_row(app, &pricing))
+        .collect();
+    rows.sort_by(|a, b| (&a.namespace, &a.name).cmp(&(&b.namespace, &b.name)));
+
+    print!("{}", render(&rows, format)?);
+    if format == OutputFormat::Json {
+        println!();
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{MyAppSpec, ResourceRequirements};
+
+    fn sample() -> MyApp {
+        let mut app = MyApp::new(
+            "web",
+            MyAppSpec {
+                replicas: 2,
+                image: "nginx:1.25".to_string(),
+                tier: Some("prod".to_string()),
+                resources: Some(ResourceRequirements {
+                    cpu: "500m".to_string(),
+                    memory: "1Gi".to_string(),
+                    limits: None,
+                }),
+                ..Default::default()
+            },
+        );
+        app.metadata.namespace = Some("shop".to_string());
+        app
+    }
+
+    #[test]
+    fn test_cost_estimate() {
+        let row = build_row(&sample(), &Pricing::default());
+
+        // 2 replicas * (0.5 cores * 25 + 1 GiB * 3.5)
+        assert_eq!(row.estimated_monthly_cost, 32.0);
+        assert_eq!(row.phase, "Pending");
+        assert_eq!(row.tier, "prod");
+    }
+
+    #[test]
+    fn test_render_csv() {
+        let rows = vec![build_row(&sample(), &Pricing::default())];
+        let csv = render(&rows, OutputFormat::Csv).unwrap();
+
+        let mut lines = csv.lines();
+        assert!(lines.next().unwrap().starts_with("namespace,name,image"));
+        assert_eq!(
+            lines.next().unwrap(),
+            "shop,web,nginx:1.25,2,prod,Pending,500m,1Gi,32.00"
+        );
+    }
+}
@@ -0,0 +1,86 @@
+// Conflict retries for MyApp Controller
+// Writes that lose an optimistic-concurrency race are re-read and retried a few times
+
+use crate::metrics::MetricsCollector;
+
+/// Attempts before giving up on a contended write
+pub const MAX_ATTEMPTS: usize = 3;
+
+/// 409 Conflict, or a 422 that reports a conflict or a failed JSON Patch test operation;
+/// other 422s are validation errors that a retry cannot fix
+pub fn is_conflict(err: &kube::Error) -> bool {
+    match err {
+        kube::Error::Api(resp) if resp.code == 409 => true,
+        kube::Error::Api(resp) if resp.code == 422 => {
+            resp.reason == "Conflict" || resp.message.contains("test failed")
+        }
+        _ => false,
+    }
+}
+
+/// Whether a failed write should be retried after re-reading the object; counts the retry
+pub fn retry_conflict(
+    err: &kube::Error,
+    attempt: usize,
+    operation: &str,
+    name: &str,
+    metrics: &MetricsCollector,
+) -> bool {
+    if !is_conflict(err) || attempt >= MAX_ATTEMPTS {
+        return false;
+    }
+    tracing::info!(
+        "{} of MyApp {} changed concurrently, retrying ({}/{})",
+        operation,
+        name,
+        attempt,
+        MAX_ATTEMPTS
+    );
+    metrics.record_conflict_retry(operation);
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use kube::core::ErrorResponse;
+
+    fn api_error(code: u16) -> kube::Error {
+        api_error_with(code, "", "")
+    }
+
+    fn api_error_with(code: u16, reason: &str, message: &str) -> kube::Error {
+        kube::Error::Api(ErrorResponse {
+            status: "Failure".to_string(),
+            message: message.to_string(),
+            reason: reason.to_string(),
+            code,
+        })
+    }
+
+    #[test]
+    fn test_retry_conflict() {
+        let metrics = MetricsCollector::new();
+        let retries =
+            |code, attempt| retry_conflict(&api_error(code), attempt, "status", "web", &metrics);
+        assert!(retries(409, 1));
+        assert!(!retries(409, MAX_ATTEMPTS));
+        assert!(!retries(404, 1));
+    }
+
+    #[test]
+    fn test_validation_errors_are_not_conflicts() {
+        assert!(!is_conflict(&api_error(422)));
+        assert!(!is_conflict(&api_error_with(
+            422,
+            "Invalid",
+            "MyApp.example.com \"web\" is invalid: status.state: Unsupported value"
+        )));
+        assert!(is_conflict(&api_error_with(422, "Conflict", "")));
+        assert!(is_conflict(&api_error_with(
+            422,
+            "Invalid",
+            "testing value /metadata/resourceVersion failed: test failed"
+        )));
+    }
+}
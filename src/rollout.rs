@@ -0,0 +1,156 @@
+// Deployment rollout tracking for MyApp Controller
+// After applying the Deployment the reconcile reads its rollout status: while new pods are
+// still coming up the MyApp is Progressing and requeued at the rollout interval, once every
+// updated replica is available it is Running, and when the Deployment reports
+// ProgressDeadlineExceeded it is Stalled until the next spec change.
+
+use crate::hooks::deployment_available;
+use crate::Condition;
+use k8s_openapi::api::apps::v1::Deployment;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rollout {
+    Progressing,
+    Available,
+    /// The Deployment gave up after its progressDeadlineSeconds
+    Stalled,
+}
+
+impl Rollout {
+    pub fn of(deployment: &Deployment) -> Self {
+        let deadline_exceeded = deployment
+            .status
+            .as_ref()
+            .and_then(|s| s.conditions.as_ref())
+            .into_iter()
+            .flatten()
+            .any(|c| {
+                c.type_ == "Progressing"
+                    && c.status == "False"
+                    && c.reason.as_deref() == Some("ProgressDeadlineExceeded")
+            });
+        if deployment_available(deployment) {
+            Rollout::Available
+        } else if deadline_exceeded {
+            Rollout::Stalled
+        } else {
+            Rollout::Progressing
+        }
+    }
+
+    /// Phase shown in status.state
+    pub fn state(self) -> &'static str {
+        match self {
+            Rollout::Progressing => "Progressing",
+            Rollout::Available => "Running",
+            Rollout::Stalled => "Stalled",
+        }
+    }
+
+    /// Ready only once the rollout is Available
+    pub fn ready_condition(self, deployment_name: &str) -> Condition {
+        match self {
+            Rollout::Available => {
+                Condition::ready(true, "ReconcileSuccess", "Resource reconciled successfully")
+            }
+            Rollout::Progressing => Condition::ready(
+                false,
+                "Progressing",
+                &format!("Deployment {} is still rolling out", deployment_name),
+            ),
+            Rollout::Stalled => Condition::ready(
+                false,
+                "Stalled",
+                &format!(
+                    "Deployment {} exceeded its progress deadline",
+                    deployment_name
+                ),
+            ),
+        }
+    }
+
+    /// Progressing and Available conditions mirroring the Deployment's
+    pub fn conditions(self, deployment_name: &str) -> Vec<Condition> {
+        let (progressing, reason) = match self {
+            Rollout::Progressing => (true, "RolloutInProgress"),
+            Rollout::Available => (false, "RolloutComplete"),
+            Rollout::Stalled => (false, "ProgressDeadlineExceeded"),
+        };
+        let available = self == Rollout::Available;
+        vec![
+            Condition::new(
+                "Progressing",
+                progressing,
+                reason,
+                &format!("Deployment {}: {}", deployment_name, reason),
+            ),
+            Condition::new(
+                "Available",
+                available,
+                if available {
+                    "MinimumReplicasAvailable"
+                } else {
+                    "MinimumReplicasUnavailable"
+                },
+                &format!(
+                    "Deployment {} {} all updated replicas available",
+                    deployment_name,
+                    if available { "has" } else { "does not have" }
+                ),
+            ),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use k8s_openapi::api::apps::v1::{DeploymentCondition, DeploymentSpec, DeploymentStatus};
+
+    fn deployment(available: i32, conditions: Vec<DeploymentCondition>) -> Deployment {
+        let mut deployment = Deployment {
+            spec: Some(DeploymentSpec {
+                replicas: Some(2),
+                ..Default::default()
+            }),
+            status: Some(DeploymentStatus {
+                observed_generation: Some(1),
+                updated_replicas: Some(2),
+                available_replicas: Some(available),
+                conditions: Some(conditions),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        deployment.metadata.generation = Some(1);
+        deployment
+    }
+
+    #[test]
+    fn test_rollout_of_deployment() {
+        assert_eq!(Rollout::of(&deployment(2, Vec::new())), Rollout::Available);
+        assert_eq!(
+            Rollout::of(&deployment(1, Vec::new())),
+            Rollout::Progressing
+        );
+
+        let exceeded = DeploymentCondition {
+            type_: "Progressing".to_string(),
+            status: "False".to_string(),
+            reason: Some("ProgressDeadlineExceeded".to_string()),
+            ..Default::default()
+        };
+        let stalled = Rollout::of(&deployment(1, vec![exceeded]));
+        assert_eq!(stalled, Rollout::Stalled);
+        assert_eq!(stalled.state(), "Stalled");
+        assert_eq!(stalled.conditions("web-deployment")[0].status, "False");
+
+        let ready = stalled.ready_condition("web-deployment");
+        assert_eq!(
+            (ready.status.as_str(), ready.reason.as_str()),
+            ("False", "Stalled")
+        );
+        assert_eq!(Rollout::Progressing.ready_condition("web").status, "False");
+        assert_eq!(Rollout::Available.ready_condition("web").status, "True");
+    }
+}
@@ -0,0 +1,149 @@
+// Projected service account tokens for MyApp Controller
+// Mounts audience-scoped tokens for apps that authenticate to external identity providers
+
+use k8s_openapi::api::core::v1::{
+    ProjectedVolumeSource, ServiceAccountTokenProjection, Volume, VolumeMount, VolumeProjection,
+};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+pub const VOLUME_NAME: &str = "myapp-sa-token";
+pub const DEFAULT_MOUNT_PATH: &str = "/var/run/secrets/tokens";
+
+/// The kubelet refuses projected tokens that expire sooner than this
+const MIN_EXPIRATION_SECONDS: i64 = 600;
+
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ServiceAccountTokenConfig {
+    /// Audiences to issue tokens for; each gets its own file under mountPath
+    #[serde(default)]
+    pub audiences: Vec<String>,
+
+    /// Requested token lifetime in seconds (minimum 600, default 3600)
+    #[serde(default)]
+    pub expiration_seconds: Option<i64>,
+
+    /// Directory the tokens are mounted in (default /var/run/secrets/tokens)
+    #[serde(default)]
+    pub mount_path: Option<String>,
+}
+
+/// File name a token for `audience` is written to, e.g. "sts.amazonaws.com" -> "sts.amazonaws.com-token"
+pub fn token_file(audience: &str) -> String {
+    let name: String = audience
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '.' || c == '-' {
+                c
+            } else {
+                '-'
+            }
+        })
+        .collect();
+    format!("{}-token", name.trim_matches('-'))
+}
+
+impl ServiceAccountTokenConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.audiences.is_empty() {
+            return Err("serviceAccountToken.audiences must not be empty".to_string());
+        }
+        if self.audiences.iter().any(|a| a.trim().is_empty()) {
+            return Err("serviceAccountToken.audiences must not contain empty values".to_string());
+        }
+        if self
+            .expiration_seconds
+            .is_some_and(|s| s < MIN_EXPIRATION_SECONDS)
+        {
+            return Err(format!(
+                "serviceAccountToken.expirationSeconds must be at least {}",
+                MIN_EXPIRATION_SECONDS
+            ));
+        }
+        if self
+            .mount_path
+            .as_deref()
+            .is_some_and(|p| !p.starts_with('/'))
+        {
+            return Err("serviceAccountToken.mountPath must be an absolute path".to_string());
+        }
+        Ok(())
+    }
+
+    /// Projected volume holding one token per audience
+    pub fn volume(&self) -> Volume {
+        Volume {
+            name: VOLUME_NAME.to_string(),
+            projected: Some(ProjectedVolumeSource {
+                sources: Some(
+                    self.audiences
+                        .iter()
+                        .map(|audience| VolumeProjection {
+                            service_account_token: Some(ServiceAccountTokenProjection {
+                                audience: Some(audience.clone()),
+                                expiration_seconds: self.expiration_seconds,
+                                path: token_file(audience),
+                            }),
+                            ..Default::default()
+                        })
+                        .collect(),
+                ),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+
+    pub fn volume_mount(&self) -> VolumeMount {
+        VolumeMount {
+            name: VOLUME_NAME.to_string(),
+            mount_path: self
+                .mount_path
+                .clone()
+                .unwrap_or_else(|| DEFAULT_MOUNT_PATH.to_string()),
+            read_only: Some(true),
+            ..Default::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate() {
+        let mut config = ServiceAccountTokenConfig {
+            audiences: vec!["vault".to_string()],
+            ..Default::default()
+        };
+        assert!(config.validate().is_ok());
+
+        config.expiration_seconds = Some(60);
+        assert!(config.validate().is_err());
+
+        config.expiration_seconds = None;
+        config.mount_path = Some("tokens".to_string());
+        assert!(config.validate().is_err());
+
+        assert!(ServiceAccountTokenConfig::default().validate().is_err());
+    }
+
+    #[test]
+    fn test_volume_per_audience() {
+        let config = ServiceAccountTokenConfig {
+            audiences: vec!["sts.amazonaws.com".to_string(), "https://idp/x".to_string()],
+            expiration_seconds: Some(7200),
+            mount_path: None,
+        };
+
+        let sources = config.volume().projected.unwrap().sources.unwrap();
+        let paths: Vec<_> = sources
+            .iter()
+            .map(|s| s.service_account_token.as_ref().unwrap().path.as_str())
+            .collect();
+        assert_eq!(paths, ["sts.amazonaws.com-token", "https---idp-x-token"]);
+        assert_eq!(config.volume_mount().mount_path, DEFAULT_MOUNT_PATH);
+    }
+}
@@ -0,0 +1,196 @@
+// Vulnerability scan gate for MyApp Controller
+// With MYAPP_SCAN_URL set, a MyApp's image is sent to the scan service before it is first
+// rolled out. Images with more critical findings than MYAPP_SCAN_MAX_CRITICAL are held back
+// with a Degraded condition until fixed, or until the MyApp carries the skip annotation.
+// The service receives `{"image": ...}` and answers with Trivy's JSON report or a summary
+// such as `{"critical": 2, "high": 7}`.
+
+use crate::metrics::MetricsCollector;
+use crate::MyApp;
+use kube::ResourceExt;
+use serde_json::Value;
+use std::time::Duration;
+
+/// Emergency override; the value should say why the scan is skipped
+pub const SKIP_ANNOTATION: &str = "myapps.example.com/skip-vulnerability-scan";
+
+/// Finding counts of one scan
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Summary {
+    pub critical: u64,
+    pub high: u64,
+}
+
+/// Counts from a summary answer or a Trivy report's Results[].Vulnerabilities[].Severity
+pub fn summarize(report: &Value) -> Option<Summary> {
+    if let Some(critical) = report["critical"].as_u64() {
+        return Some(Summary {
+            critical,
+            high: report["high"].as_u64().unwrap_or(0),
+        });
+    }
+    let results = report["Results"].as_array()?;
+    let mut summary = Summary::default();
+    for vulnerability in results
+        .iter()
+        .filter_map(|r| r["Vulnerabilities"].as_array())
+        .flatten()
+    {
+        match vulnerability["Severity"].as_str() {
+            Some("CRITICAL") => summary.critical += 1,
+            Some("HIGH") => summary.high += 1,
+            _ => {}
+        }
+    }
+    Some(summary)
+}
+
+/// Outcome of the gate for one image
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Verdict {
+    Pass,
+    /// Condition reason and message explaining why the rollout waits
+    Blocked(&'static str, String),
+}
+
+fn judge(image: &str, summary: Summary, max_critical: u64) -> Verdict {
+    if summary.critical <= max_critical {
+        return Verdict::Pass;
+    }
+    Verdict::Blocked(
+        "VulnerabilityScanFailed",
+        format!(
+            "{} has {} critical and {} high vulnerabilities (at most {} critical allowed); \
+             set {} to roll out anyway",
+            image, summary.critical, summary.high, max_critical, SKIP_ANNOTATION
+        ),
+    )
+}
+
+#[derive(Clone)]
+pub struct ScanGate {
+    http: reqwest::Client,
+    url: String,
+    max_critical: u64,
+    fail_open: bool,
+    /// How soon a blocked MyApp is checked again
+    pub retry: Duration,
+}
+
+impl ScanGate {
+    /// `MYAPP_SCAN_URL`, `MYAPP_SCAN_MAX_CRITICAL` (default 0), `MYAPP_SCAN_FAIL_OPEN`
+    /// (default false) and `MYAPP_SCAN_RETRY_SECS` (default 300); None when no URL is set
+    pub fn from_env() -> Option<Self> {
+        let url = std::env::var("MYAPP_SCAN_URL").ok()?;
+        let number = |name: &str, default: u64| {
+            std::env::var(name)
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(default)
+        };
+        Some(Self {
+            http: reqwest::Client::builder()
+                .timeout(Duration::from_secs(120))
+                .build()
+                .expect("failed to build scan HTTP client"),
+            url,
+            max_critical: number("MYAPP_SCAN_MAX_CRITICAL", 0),
+            fail_open: std::env::var("MYAPP_SCAN_FAIL_OPEN").is_ok_and(|v| v == "true"),
+            retry: Duration::from_secs(number("MYAPP_SCAN_RETRY_SECS", 300)),
+        })
+    }
+
+    async fn scan(&self, image: &str) -> Result<Summary, String> {
+        let resp = self
+            .http
+            .post(&self.url)
+            .json(&serde_json::json!({ "image": image }))
+            .send()
+            .await
+            .map_err(|e| e.to_string())?;
+        if !resp.status().is_success() {
+            return Err(format!("scan service returned {}", resp.status()));
+        }
+        let report: Value = resp.json().await.map_err(|e| e.to_string())?;
+        summarize(&report).ok_or_else(|| "unrecognized scan report".to_string())
+    }
+
+    /// Whether the MyApp's image may be rolled out
+    pub async fn check(&self, myapp: &MyApp, metrics: &MetricsCollector) -> Verdict {
+        let image = &myapp.spec.image;
+        if let Some(reason) = myapp.annotations().get(SKIP_ANNOTATION) {
+            tracing::info!(
+                "Skipping vulnerability scan of {} for MyApp {}: {}",
+                image,
+                myapp.name_any(),
+                reason
+            );
+            metrics.record_scan("overridden");
+            return Verdict::Pass;
+        }
+        let verdict = match self.scan(image).await {
+            Ok(summary) => judge(image, summary, self.max_critical),
+            Err(e) if self.fail_open => {
+                tracing::warn!(
+                    "Vulnerability scan of {} failed, admitting it: {}",
+                    image,
+                    e
+                );
+                Verdict::Pass
+            }
+            Err(e) => Verdict::Blocked(
+                "ScanUnavailable",
+                format!("Vulnerability scan of {} failed: {}", image, e),
+            ),
+        };
+        metrics.record_scan(match &verdict {
+            Verdict::Pass => "passed",
+            Verdict::Blocked("ScanUnavailable", _) => "error",
+            Verdict::Blocked(..) => "blocked",
+        });
+        verdict
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_summarize_trivy_report() {
+        let report = json!({ "Results": [
+            { "Target": "debian", "Vulnerabilities": [
+                { "VulnerabilityID": "CVE-1", "Severity": "CRITICAL" },
+                { "VulnerabilityID": "CVE-2", "Severity": "HIGH" },
+                { "VulnerabilityID": "CVE-3", "Severity": "LOW" }
+            ] },
+            { "Target": "app" }
+        ] });
+        assert_eq!(
+            summarize(&report),
+            Some(Summary {
+                critical: 1,
+                high: 1
+            })
+        );
+        assert_eq!(
+            summarize(&json!({ "critical": 0 })),
+            Some(Summary::default())
+        );
+        assert_eq!(summarize(&json!({ "error": "timeout" })), None);
+    }
+
+    #[test]
+    fn test_judge_threshold() {
+        let two = Summary {
+            critical: 2,
+            high: 0,
+        };
+        assert_eq!(judge("nginx:1.25", two, 2), Verdict::Pass);
+        assert!(matches!(
+            judge("nginx:1.25", two, 1),
+            Verdict::Blocked("VulnerabilityScanFailed", _)
+        ));
+    }
+}
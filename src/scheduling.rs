@@ -1,8 +1,16 @@
 // Simplified scheduling module for MyApp Controller
+use k8s_openapi::api::core::v1::{
+    Affinity, NodeAffinity, NodeSelector, NodeSelectorRequirement, NodeSelectorTerm,
+    PodAffinityTerm, PodAntiAffinity, PreferredSchedulingTerm, TopologySpreadConstraint,
+    WeightedPodAffinityTerm,
+};
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::LabelSelector;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use std::collections::BTreeMap;
 
+const NODE_OPERATORS: [&str; 6] = ["In", "NotIn", "Exists", "DoesNotExist", "Gt", "Lt"];
+
 /// Advanced scheduling configuration for MyApp resources
 #[derive(Serialize, Deserialize, Debug, Clone, JsonSchema, Default)]
 #[serde(rename_all = "camelCase")]
@@ -18,26 +26,368 @@ pub struct SchedulingConfig {
     /// Scheduler name (for custom schedulers)
     #[serde(default)]
     pub scheduler_name: Option<String>,
+
+    /// Node architectures (kubernetes.io/arch) the pods may run on; detected from the image when empty
+    #[serde(default)]
+    pub architectures: Vec<String>,
+
+    /// Node affinity rules
+    #[serde(default)]
+    pub node_affinity: Option<NodeAffinityConfig>,
+
+    /// Keep replicas apart from each other
+    #[serde(default)]
+    pub pod_anti_affinity: Option<PodAntiAffinityConfig>,
+
+    /// Spread replicas evenly across topology domains
+    #[serde(default)]
+    pub topology_spread: Vec<TopologySpreadConfig>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct NodeAffinityConfig {
+    /// Required node affinity (hard constraint); a node must match one of the terms
+    #[serde(default)]
+    pub required: Vec<NodeSelectorConfig>,
+
+    /// Preferred node affinity (soft constraint with weights)
+    #[serde(default)]
+    pub preferred: Vec<PreferredNodeSelectorConfig>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct NodeSelectorConfig {
+    /// Label key to match (may be empty when only matchFields is used)
+    #[serde(default)]
+    pub key: String,
+
+    /// Operator (In, NotIn, Exists, DoesNotExist, Gt, Lt)
+    #[serde(default)]
+    pub operator: String,
+
+    /// Values to match (optional for Exists/DoesNotExist)
+    #[serde(default)]
+    pub values: Vec<String>,
+
+    /// Node field requirements, e.g. pinning to a node by metadata.name
+    #[serde(default)]
+    pub match_fields: Vec<NodeFieldSelector>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct NodeFieldSelector {
+    /// Node field; Kubernetes only supports metadata.name
+    #[schemars(regex(pattern = r"^metadata\.name$"))]
+    pub key: String,
+
+    /// In or NotIn
+    #[schemars(regex(pattern = r"^(In|NotIn)$"))]
+    pub operator: String,
+
+    /// Node names
+    pub values: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PreferredNodeSelectorConfig {
+    /// Weight for this preference (1-100)
+    #[schemars(range(min = 1, max = 100))]
+    pub weight: i32,
+
+    /// Node selector terms
+    pub selector: NodeSelectorConfig,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct PodAntiAffinityConfig {
+    /// Pods matching these terms must not share a topology domain (hard constraint)
+    #[serde(default)]
+    pub required: Vec<PodAffinityTermConfig>,
+
+    /// Pods matching these terms should not share a topology domain (soft constraint)
+    #[serde(default)]
+    pub preferred: Vec<WeightedPodAffinityTermConfig>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct PodAffinityTermConfig {
+    /// Labels of the pods to keep apart from
+    pub label_selector: BTreeMap<String, String>,
+
+    /// Topology key, e.g. kubernetes.io/hostname or topology.kubernetes.io/zone
+    pub topology_key: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct WeightedPodAffinityTermConfig {
+    /// Weight for this preference (1-100)
+    #[schemars(range(min = 1, max = 100))]
+    pub weight: i32,
+
+    pub term: PodAffinityTermConfig,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct TopologySpreadConfig {
+    /// Largest allowed difference in matching pods between two domains
+    pub max_skew: i32,
+
+    /// Topology key to spread across
+    pub topology_key: String,
+
+    /// DoNotSchedule or ScheduleAnyway
+    pub when_unsatisfiable: String,
+
+    /// Labels of the pods counted for the spread
+    pub label_selector: BTreeMap<String, String>,
+}
+
+impl PodAffinityTermConfig {
+    fn validate(&self) -> Result<(), String> {
+        if self.topology_key.is_empty() {
+            return Err("podAntiAffinity terms need a topologyKey".to_string());
+        }
+        Ok(())
+    }
+
+    fn to_term(&self) -> PodAffinityTerm {
+        PodAffinityTerm {
+            label_selector: Some(label_selector(&self.label_selector)),
+            topology_key: self.topology_key.clone(),
+            ..Default::default()
+        }
+    }
+}
+
+impl TopologySpreadConfig {
+    fn validate(&self) -> Result<(), String> {
+        if self.max_skew < 1 {
+            return Err("topologySpread maxSkew must be at least 1".to_string());
+        }
+        if self.topology_key.is_empty() {
+            return Err("topologySpread entries need a topologyKey".to_string());
+        }
+        if !["DoNotSchedule", "ScheduleAnyway"].contains(&self.when_unsatisfiable.as_str()) {
+            return Err(format!(
+                "topologySpread whenUnsatisfiable must be DoNotSchedule or ScheduleAnyway, got '{}'",
+                self.when_unsatisfiable
+            ));
+        }
+        Ok(())
+    }
+
+    pub fn to_constraint(&self) -> TopologySpreadConstraint {
+        TopologySpreadConstraint {
+            max_skew: self.max_skew,
+            topology_key: self.topology_key.clone(),
+            when_unsatisfiable: self.when_unsatisfiable.clone(),
+            label_selector: Some(label_selector(&self.label_selector)),
+            ..Default::default()
+        }
+    }
+}
+
+fn label_selector(labels: &BTreeMap<String, String>) -> LabelSelector {
+    LabelSelector {
+        match_labels: Some(labels.clone()),
+        ..Default::default()
+    }
+}
+
+impl NodeSelectorConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.key.is_empty() && self.match_fields.is_empty() {
+            return Err("nodeAffinity terms need a key or matchFields".to_string());
+        }
+        if !self.key.is_empty() && !NODE_OPERATORS.contains(&self.operator.as_str()) {
+            return Err(format!(
+                "nodeAffinity operator must be one of {}",
+                NODE_OPERATORS.join(", ")
+            ));
+        }
+        for field in &self.match_fields {
+            if field.key != "metadata.name" {
+                return Err("nodeAffinity matchFields only supports metadata.name".to_string());
+            }
+            if field.operator != "In" && field.operator != "NotIn" {
+                return Err("nodeAffinity matchFields operator must be In or NotIn".to_string());
+            }
+            if field.values.is_empty() {
+                return Err("nodeAffinity matchFields needs at least one value".to_string());
+            }
+        }
+        Ok(())
+    }
+
+    fn to_term(&self) -> NodeSelectorTerm {
+        let non_empty = |v: &Vec<String>| (!v.is_empty()).then(|| v.clone());
+        NodeSelectorTerm {
+            match_expressions: (!self.key.is_empty()).then(|| {
+                vec![NodeSelectorRequirement {
+                    key: self.key.clone(),
+                    operator: self.operator.clone(),
+                    values: non_empty(&self.values),
+                }]
+            }),
+            match_fields: (!self.match_fields.is_empty()).then(|| {
+                self.match_fields
+                    .iter()
+                    .map(|f| NodeSelectorRequirement {
+                        key: f.key.clone(),
+                        operator: f.operator.clone(),
+                        values: Some(f.values.clone()),
+                    })
+                    .collect()
+            }),
+        }
+    }
+}
+
+impl SchedulingConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if let Some(affinity) = &self.node_affinity {
+            affinity
+                .required
+                .iter()
+                .chain(affinity.preferred.iter().map(|p| &p.selector))
+                .try_for_each(NodeSelectorConfig::validate)?;
+        }
+        if let Some(anti) = &self.pod_anti_affinity {
+            if anti
+                .preferred
+                .iter()
+                .any(|p| !(1..=100).contains(&p.weight))
+            {
+                return Err("podAntiAffinity weights must be between 1 and 100".to_string());
+            }
+            anti.required
+                .iter()
+                .chain(anti.preferred.iter().map(|p| &p.term))
+                .try_for_each(PodAffinityTermConfig::validate)?;
+        }
+        self.topology_spread
+            .iter()
+            .try_for_each(TopologySpreadConfig::validate)
+    }
+
+    /// Pod affinity from nodeAffinity and podAntiAffinity, with the architecture
+    /// requirement added to every required node term
+    pub fn build_affinity(&self) -> Option<Affinity> {
+        let node_affinity = self.build_node_affinity();
+        let pod_anti_affinity = self.pod_anti_affinity.as_ref().map(|anti| PodAntiAffinity {
+            required_during_scheduling_ignored_during_execution: (!anti.required.is_empty()).then(
+                || {
+                    anti.required
+                        .iter()
+                        .map(PodAffinityTermConfig::to_term)
+                        .collect()
+                },
+            ),
+            preferred_during_scheduling_ignored_during_execution: (!anti.preferred.is_empty())
+                .then(|| {
+                    anti.preferred
+                        .iter()
+                        .map(|p| WeightedPodAffinityTerm {
+                            weight: p.weight,
+                            pod_affinity_term: p.term.to_term(),
+                        })
+                        .collect()
+                }),
+        });
+        if node_affinity.is_none() && pod_anti_affinity.is_none() {
+            return None;
+        }
+        Some(Affinity {
+            node_affinity,
+            pod_anti_affinity,
+            ..Default::default()
+        })
+    }
+
+    fn build_node_affinity(&self) -> Option<NodeAffinity> {
+        let config = self.node_affinity.clone().unwrap_or_default();
+        let arch = crate::arch::arch_requirement(&self.architectures);
+
+        let mut required: Vec<NodeSelectorTerm> =
+            config.required.iter().map(|r| r.to_term()).collect();
+        if let Some(arch) = arch {
+            if required.is_empty() {
+                required.push(NodeSelectorTerm::default());
+            }
+            for term in &mut required {
+                term.match_expressions
+                    .get_or_insert_with(Vec::new)
+                    .push(arch.clone());
+            }
+        }
+
+        let preferred: Vec<PreferredSchedulingTerm> = config
+            .preferred
+            .iter()
+            .map(|p| PreferredSchedulingTerm {
+                weight: p.weight,
+                preference: p.selector.to_term(),
+            })
+            .collect();
+
+        if required.is_empty() && preferred.is_empty() {
+            return None;
+        }
+
+        Some(NodeAffinity {
+            required_during_scheduling_ignored_during_execution: (!required.is_empty()).then_some(
+                NodeSelector {
+                    node_selector_terms: required,
+                },
+            ),
+            preferred_during_scheduling_ignored_during_execution: (!preferred.is_empty())
+                .then_some(preferred),
+        })
+    }
 }
 
 /// Scheduler implementation for advanced placement strategies
-#[allow(dead_code)]
 pub struct AdvancedScheduler;
 
-#[allow(dead_code)]
 impl AdvancedScheduler {
-    /// Generate intelligent placement recommendations
-    pub fn recommend_placement(
-        _app_name: &str,
-        _namespace: &str,
-        _replicas: i32,
-        _existing_apps: &[String],
-    ) -> SchedulingConfig {
-        SchedulingConfig {
-            node_selector: BTreeMap::new(),
-            priority_class: None,
-            scheduler_name: None,
+    /// High-availability placement for an app without explicit scheduling: replicas prefer
+    /// distinct nodes, and from three replicas on spread across zones. Zone spread uses
+    /// ScheduleAnyway so single-zone clusters still schedule every replica.
+    pub fn recommend_placement(app_name: &str, replicas: i32) -> Option<SchedulingConfig> {
+        if replicas <= 1 {
+            return None;
+        }
+        let selector = BTreeMap::from([("app".to_string(), app_name.to_string())]);
+        let mut config = SchedulingConfig {
+            pod_anti_affinity: Some(PodAntiAffinityConfig {
+                required: Vec::new(),
+                preferred: vec![WeightedPodAffinityTermConfig {
+                    weight: 100,
+                    term: PodAffinityTermConfig {
+                        label_selector: selector.clone(),
+                        topology_key: "kubernetes.io/hostname".to_string(),
+                    },
+                }],
+            }),
+            ..Default::default()
+        };
+        if replicas >= 3 {
+            config.topology_spread = vec![TopologySpreadConfig {
+                max_skew: 1,
+                topology_key: "topology.kubernetes.io/zone".to_string(),
+                when_unsatisfiable: "ScheduleAnyway".to_string(),
+                label_selector: selector,
+            }];
         }
+        Some(config)
     }
 }
 
@@ -45,10 +395,76 @@ impl AdvancedScheduler {
 mod tests {
     use super::*;
 
+    fn pin_to_node(node: &str) -> NodeSelectorConfig {
+        NodeSelectorConfig {
+            match_fields: vec![NodeFieldSelector {
+                key: "metadata.name".to_string(),
+                operator: "In".to_string(),
+                values: vec![node.to_string()],
+            }],
+            ..Default::default()
+        }
+    }
+
     #[test]
     fn test_placement_recommendations() {
-        let config = AdvancedScheduler::recommend_placement("test-app", "default", 5, &[]);
+        assert!(AdvancedScheduler::recommend_placement("test-app", 1).is_none());
+        let two = AdvancedScheduler::recommend_placement("test-app", 2).unwrap();
+        assert!(two.topology_spread.is_empty());
 
+        let config = AdvancedScheduler::recommend_placement("test-app", 5).unwrap();
+        assert!(config.validate().is_ok());
         assert!(config.node_selector.is_empty());
+        assert_eq!(config.topology_spread.len(), 1);
+
+        let affinity = config.build_affinity().unwrap();
+        assert!(affinity.node_affinity.is_none());
+        let preferred = affinity
+            .pod_anti_affinity
+            .unwrap()
+            .preferred_during_scheduling_ignored_during_execution
+            .unwrap();
+        assert_eq!(
+            preferred[0].pod_affinity_term.topology_key,
+            "kubernetes.io/hostname"
+        );
+    }
+
+    #[test]
+    fn test_match_fields_affinity() {
+        let config = SchedulingConfig {
+            architectures: vec!["arm64".to_string()],
+            node_affinity: Some(NodeAffinityConfig {
+                required: vec![pin_to_node("node-1")],
+                preferred: vec![],
+            }),
+            ..Default::default()
+        };
+        assert!(config.validate().is_ok());
+
+        let affinity = config.build_affinity().unwrap().node_affinity.unwrap();
+        let terms = affinity
+            .required_during_scheduling_ignored_during_execution
+            .unwrap()
+            .node_selector_terms;
+        let fields = terms[0].match_fields.as_ref().unwrap();
+        assert_eq!(fields[0].key, "metadata.name");
+        assert_eq!(
+            fields[0].values.as_deref(),
+            Some(&["node-1".to_string()][..])
+        );
+        // The architecture requirement is ANDed into the term
+        let expressions = terms[0].match_expressions.as_ref().unwrap();
+        assert_eq!(expressions[0].key, "kubernetes.io/arch");
+    }
+
+    #[test]
+    fn test_match_fields_validation() {
+        let mut term = pin_to_node("node-1");
+        term.match_fields[0].key = "metadata.labels".to_string();
+        assert!(term.validate().is_err());
+
+        assert!(NodeSelectorConfig::default().validate().is_err());
+        assert!(SchedulingConfig::default().build_affinity().is_none());
     }
 }
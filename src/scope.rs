@@ -0,0 +1,247 @@
+// Watch scope for MyApp Controller
+// Cluster-wide by default, or limited to a list of namespaces for namespace-scoped RBAC
+
+use kube::api::ListParams;
+use kube::runtime::watcher;
+use kube::{Api, Client, Resource};
+
+fn parse_list(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|ns| !ns.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Value following any of `names` on the command line
+fn flag_value<'a>(args: &'a [String], names: &[&str]) -> Option<&'a String> {
+    args.iter()
+        .position(|a| names.contains(&a.as_str()))
+        .and_then(|i| args.get(i + 1))
+}
+
+/// `--watch-label-selector team=payments`, else `WATCH_LABEL_SELECTOR`
+pub fn label_selector_from_args_env(args: &[String]) -> Option<String> {
+    flag_value(args, &["--watch-label-selector"])
+        .cloned()
+        .or_else(|| std::env::var("WATCH_LABEL_SELECTOR").ok())
+        .filter(|s| !s.trim().is_empty())
+}
+
+/// Operator-wide namespace allow- and deny-lists
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct NamespaceFilter {
+    /// Only these namespaces are managed (empty = all)
+    pub include: Vec<String>,
+    /// These namespaces are never managed
+    pub exclude: Vec<String>,
+}
+
+impl NamespaceFilter {
+    /// Read `MYAPP_INCLUDE_NAMESPACES` and `MYAPP_EXCLUDE_NAMESPACES` (comma-separated)
+    pub fn from_env() -> Self {
+        let list = |var| parse_list(&std::env::var(var).unwrap_or_default());
+        Self {
+            include: list("MYAPP_INCLUDE_NAMESPACES"),
+            exclude: list("MYAPP_EXCLUDE_NAMESPACES"),
+        }
+    }
+
+    pub fn allows(&self, namespace: &str) -> bool {
+        (self.include.is_empty() || self.include.iter().any(|ns| ns == namespace))
+            && !self.exclude.iter().any(|ns| ns == namespace)
+    }
+
+    /// Server-side field selector dropping excluded namespaces
+    pub fn field_selector(&self) -> Option<String> {
+        (!self.exclude.is_empty()).then(|| {
+            self.exclude
+                .iter()
+                .map(|ns| format!("metadata.namespace!={}", ns))
+                .collect::<Vec<_>>()
+                .join(",")
+        })
+    }
+}
+
+/// Watcher config restricted to matching MyApps
+pub fn watcher_config(label_selector: Option<&str>, filter: &NamespaceFilter) -> watcher::Config {
+    let mut config = watcher::Config::default();
+    if let Some(selector) = label_selector {
+        config = config.labels(selector);
+    }
+    if let Some(fields) = filter.field_selector() {
+        config = config.fields(&fields);
+    }
+    config
+}
+
+/// List parameters equivalent to `watcher_config`
+pub fn list_params(label_selector: Option<&str>, filter: &NamespaceFilter) -> ListParams {
+    let mut params = ListParams::default();
+    if let Some(selector) = label_selector {
+        params = params.labels(selector);
+    }
+    if let Some(fields) = filter.field_selector() {
+        params = params.fields(&fields);
+    }
+    params
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum WatchScope {
+    #[default]
+    All,
+    Namespaces(Vec<String>),
+}
+
+impl WatchScope {
+    /// Parse a comma-separated namespace list; empty means cluster-wide
+    pub fn parse(value: &str) -> Self {
+        let namespaces = parse_list(value);
+        if namespaces.is_empty() {
+            WatchScope::All
+        } else {
+            WatchScope::Namespaces(namespaces)
+        }
+    }
+
+    /// `--namespace a,b` on the command line, else `WATCH_NAMESPACE`
+    pub fn from_args_env(args: &[String]) -> Self {
+        match flag_value(args, &["--namespace", "-n"]) {
+            Some(value) => Self::parse(value),
+            None => Self::parse(&std::env::var("WATCH_NAMESPACE").unwrap_or_default()),
+        }
+    }
+
+    /// Apply the operator-wide namespace filter
+    pub fn restrict(self, filter: &NamespaceFilter) -> Self {
+        match self {
+            WatchScope::All if filter.include.is_empty() => WatchScope::All,
+            WatchScope::All => WatchScope::Namespaces(
+                filter
+                    .include
+                    .iter()
+                    .filter(|ns| filter.allows(ns))
+                    .cloned()
+                    .collect(),
+            ),
+            WatchScope::Namespaces(namespaces) => WatchScope::Namespaces(
+                namespaces
+                    .into_iter()
+                    .filter(|ns| filter.allows(ns))
+                    .collect(),
+            ),
+        }
+    }
+
+    /// Each watched namespace, or a single None for cluster-wide
+    pub fn namespaces(&self) -> Vec<Option<&str>> {
+        match self {
+            WatchScope::All => vec![None],
+            WatchScope::Namespaces(namespaces) => {
+                namespaces.iter().map(|ns| Some(ns.as_str())).collect()
+            }
+        }
+    }
+
+    /// One Api per watched namespace, or a single cluster-wide Api
+    pub fn apis<K>(&self, client: Client) -> Vec<Api<K>>
+    where
+        K: Resource<Scope = k8s_openapi::NamespaceResourceScope>,
+        <K as Resource>::DynamicType: Default,
+    {
+        self.namespaces()
+            .into_iter()
+            .map(|ns| api(client.clone(), ns))
+            .collect()
+    }
+}
+
+/// Api in a namespace, or across all namespaces
+pub fn api<K>(client: Client, namespace: Option<&str>) -> Api<K>
+where
+    K: Resource<Scope = k8s_openapi::NamespaceResourceScope>,
+    <K as Resource>::DynamicType: Default,
+{
+    match namespace {
+        Some(ns) => Api::namespaced(client, ns),
+        None => Api::all(client),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_scope() {
+        assert_eq!(WatchScope::parse(""), WatchScope::All);
+        assert_eq!(
+            WatchScope::parse("team-a, team-b,"),
+            WatchScope::Namespaces(vec!["team-a".to_string(), "team-b".to_string()])
+        );
+
+        let args: Vec<String> = ["myapp-controller", "--namespace", "shop"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        assert_eq!(
+            WatchScope::from_args_env(&args),
+            WatchScope::Namespaces(vec!["shop".to_string()])
+        );
+        assert_eq!(
+            WatchScope::from_args_env(&args).namespaces(),
+            vec![Some("shop")]
+        );
+        assert_eq!(WatchScope::All.namespaces(), vec![None]);
+    }
+
+    #[test]
+    fn test_label_selector() {
+        let args: Vec<String> = [
+            "myapp-controller",
+            "--watch-label-selector",
+            "team=payments",
+        ]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+        assert_eq!(
+            label_selector_from_args_env(&args).as_deref(),
+            Some("team=payments")
+        );
+        assert_eq!(
+            watcher_config(Some("team=payments"), &NamespaceFilter::default())
+                .label_selector
+                .as_deref(),
+            Some("team=payments")
+        );
+    }
+
+    #[test]
+    fn test_namespace_filter() {
+        let filter = NamespaceFilter {
+            include: vec![],
+            exclude: vec!["kube-system".to_string(), "kube-public".to_string()],
+        };
+        assert!(filter.allows("shop"));
+        assert!(!filter.allows("kube-system"));
+        assert_eq!(
+            filter.field_selector().as_deref(),
+            Some("metadata.namespace!=kube-system,metadata.namespace!=kube-public")
+        );
+        assert_eq!(WatchScope::All.restrict(&filter), WatchScope::All);
+
+        let allow = NamespaceFilter {
+            include: vec!["shop".to_string(), "billing".to_string()],
+            exclude: vec!["billing".to_string()],
+        };
+        assert_eq!(WatchScope::All.restrict(&allow), WatchScope::parse("shop"));
+        assert_eq!(
+            WatchScope::parse("shop,kube-system").restrict(&allow),
+            WatchScope::parse("shop")
+        );
+    }
+}
@@ -0,0 +1,303 @@
+// Operator-generated Secrets for MyApp Controller
+// spec.generatedSecrets declares Secrets by name; the controller fills them with random
+// passwords or keypairs once and never rewrites a value it already generated. Values are
+// only regenerated when the MyApp's rotate-secrets annotation changes.
+
+use crate::{app_labels, child_name, create_owner_reference, MyApp, ReconcileError};
+use k8s_openapi::api::core::v1::Secret;
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta;
+use k8s_openapi::ByteString;
+use kube::api::{Api, PostParams};
+use kube::{Client, ResourceExt};
+use rand::distributions::{Alphanumeric, DistString};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Changing this annotation on a MyApp regenerates every value of its generated Secrets
+pub const ROTATE_ANNOTATION: &str = "myapps.example.com/rotate-secrets";
+/// Rotate annotation value a Secret's values were generated under
+const ROTATED_ANNOTATION: &str = "myapps.example.com/rotated-for";
+
+const DEFAULT_LENGTH: u32 = 32;
+const MIN_LENGTH: u32 = 8;
+const MAX_LENGTH: u32 = 256;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, JsonSchema, Default)]
+pub enum SecretKind {
+    /// Random alphanumeric string (default)
+    #[default]
+    Password,
+    /// ECDSA P-256 keypair stored as `<name>.key` and `<name>.pub` in PEM
+    KeyPair,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct GeneratedKey {
+    pub name: String,
+
+    #[serde(default)]
+    pub kind: SecretKind,
+
+    /// Password length (default 32)
+    #[serde(default)]
+    pub length: Option<u32>,
+}
+
+/// A Secret named `<myapp>-<name>` the controller creates and fills
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct GeneratedSecret {
+    pub name: String,
+
+    pub keys: Vec<GeneratedKey>,
+}
+
+impl GeneratedKey {
+    /// Data entries this key occupies in the Secret
+    fn entries(&self) -> Vec<String> {
+        match self.kind {
+            SecretKind::Password => vec![self.name.clone()],
+            SecretKind::KeyPair => vec![format!("{}.key", self.name), format!("{}.pub", self.name)],
+        }
+    }
+
+    fn generate(&self) -> Result<Vec<String>, rcgen::Error> {
+        Ok(match self.kind {
+            SecretKind::Password => {
+                let length = self.length.unwrap_or(DEFAULT_LENGTH) as usize;
+                vec![Alphanumeric.sample_string(&mut rand::thread_rng(), length)]
+            }
+            SecretKind::KeyPair => {
+                let pair = rcgen::KeyPair::generate()?;
+                vec![pair.serialize_pem(), pair.public_key_pem()]
+            }
+        })
+    }
+}
+
+fn valid_key(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-._".contains(c))
+}
+
+pub fn validate(secrets: &[GeneratedSecret]) -> Result<(), String> {
+    for (i, secret) in secrets.iter().enumerate() {
+        if secret.name.is_empty()
+            || !secret
+                .name
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+        {
+            return Err(format!(
+                "generatedSecrets: '{}' must be lowercase letters, digits and '-'",
+                secret.name
+            ));
+        }
+        if secrets[..i].iter().any(|s| s.name == secret.name) {
+            return Err(format!("generatedSecrets: {} is listed twice", secret.name));
+        }
+        if secret.keys.is_empty() {
+            return Err(format!(
+                "generatedSecrets: {} needs at least one key",
+                secret.name
+            ));
+        }
+        let mut entries: Vec<String> = Vec::new();
+        for key in &secret.keys {
+            if !valid_key(&key.name) {
+                return Err(format!(
+                    "generatedSecrets: {} has an invalid key name '{}'",
+                    secret.name, key.name
+                ));
+            }
+            match (key.kind, key.length) {
+                (SecretKind::Password, Some(length))
+                    if !(MIN_LENGTH..=MAX_LENGTH).contains(&length) =>
+                {
+                    return Err(format!(
+                        "generatedSecrets: {}.{} length must be between {} and {}",
+                        secret.name, key.name, MIN_LENGTH, MAX_LENGTH
+                    ));
+                }
+                (SecretKind::KeyPair, Some(_)) => {
+                    return Err(format!(
+                        "generatedSecrets: {}.{} is a KeyPair and takes no length",
+                        secret.name, key.name
+                    ));
+                }
+                _ => {}
+            }
+            for entry in key.entries() {
+                if entries.contains(&entry) {
+                    return Err(format!(
+                        "generatedSecrets: {} has two keys writing {}",
+                        secret.name, entry
+                    ));
+                }
+                entries.push(entry);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// `existing` with values for every key it lacks, or for every key when rotating.
+/// None when nothing needs writing.
+fn fill(
+    secret: &GeneratedSecret,
+    existing: &BTreeMap<String, ByteString>,
+    rotate: bool,
+) -> Result<Option<BTreeMap<String, ByteString>>, rcgen::Error> {
+    let mut data = existing.clone();
+    let mut changed = false;
+    for key in &secret.keys {
+        let entries = key.entries();
+        if !rotate && entries.iter().all(|e| data.contains_key(e)) {
+            continue;
+        }
+        for (entry, value) in entries.into_iter().zip(key.generate()?) {
+            data.insert(entry, ByteString(value.into_bytes()));
+        }
+        changed = true;
+    }
+    Ok(changed.then_some(data))
+}
+
+fn rotated_for(secret: &Secret) -> &str {
+    secret
+        .annotations()
+        .get(ROTATED_ANNOTATION)
+        .map(String::as_str)
+        .unwrap_or_default()
+}
+
+/// Create missing generated Secrets and fill in new or rotated keys; existing values are kept
+pub async fn reconcile(myapp: &MyApp, client: Client) -> Result<(), ReconcileError> {
+    let ns = myapp.namespace().unwrap();
+    let api: Api<Secret> = Api::namespaced(client, &ns);
+    let token = myapp
+        .annotations()
+        .get(ROTATE_ANNOTATION)
+        .cloned()
+        .unwrap_or_default();
+    let generation_failed = |e: rcgen::Error| ReconcileError::SecretError(e.to_string());
+
+    for generated in &myapp.spec.generated_secrets {
+        let name = child_name(myapp, &generated.name);
+        match api.get_opt(&name).await? {
+            None => {
+                let data = fill(generated, &BTreeMap::new(), true)
+                    .map_err(generation_failed)?
+                    .unwrap_or_default();
+                let secret = Secret {
+                    metadata: ObjectMeta {
+                        name: Some(name.clone()),
+                        namespace: Some(ns.clone()),
+                        labels: Some(app_labels(myapp)),
+                        annotations: Some(BTreeMap::from([(
+                            ROTATED_ANNOTATION.to_string(),
+                            token.clone(),
+                        )])),
+                        owner_references: Some(vec![create_owner_reference(myapp)]),
+                        ..Default::default()
+                    },
+                    type_: Some("Opaque".to_string()),
+                    data: Some(data),
+                    ..Default::default()
+                };
+                api.create(&PostParams::default(), &secret).await?;
+                tracing::info!("Generated Secret {}/{}", ns, name);
+            }
+            Some(mut existing) => {
+                let owned = existing
+                    .owner_references()
+                    .iter()
+                    .any(|o| Some(&o.uid) == myapp.uid().as_ref());
+                if !owned {
+                    tracing::info!(
+                        "Secret {}/{} exists and is not owned by MyApp {}, leaving it alone",
+                        ns,
+                        name,
+                        myapp.name_any()
+                    );
+                    continue;
+                }
+                let rotate = rotated_for(&existing) != token;
+                let current = existing.data.clone().unwrap_or_default();
+                let Some(data) = fill(generated, &current, rotate).map_err(generation_failed)?
+                else {
+                    continue;
+                };
+                existing.data = Some(data);
+                existing
+                    .annotations_mut()
+                    .insert(ROTATED_ANNOTATION.to_string(), token.clone());
+                // Replace carries the resourceVersion, so a concurrent edit is a conflict
+                // rather than lost
+                api.replace(&name, &PostParams::default(), &existing)
+                    .await?;
+                if rotate {
+                    tracing::info!("Rotated Secret {}/{}", ns, name);
+                } else {
+                    tracing::info!("Added new keys to Secret {}/{}", ns, name);
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn secret(keys: &[(&str, SecretKind)]) -> GeneratedSecret {
+        GeneratedSecret {
+            name: "db".to_string(),
+            keys: keys
+                .iter()
+                .map(|(name, kind)| GeneratedKey {
+                    name: name.to_string(),
+                    kind: *kind,
+                    length: None,
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_fill_never_rewrites_existing_values() {
+        let spec = secret(&[("password", SecretKind::Password)]);
+        let first = fill(&spec, &BTreeMap::new(), true).unwrap().unwrap();
+        assert_eq!(first["password"].0.len(), DEFAULT_LENGTH as usize);
+        assert!(fill(&spec, &first, false).unwrap().is_none());
+
+        // A key added later is filled in, the old one kept
+        let spec = secret(&[
+            ("password", SecretKind::Password),
+            ("signing", SecretKind::KeyPair),
+        ]);
+        let second = fill(&spec, &first, false).unwrap().unwrap();
+        assert_eq!(second["password"], first["password"]);
+        assert!(String::from_utf8_lossy(&second["signing.key"].0).contains("PRIVATE KEY"));
+
+        let rotated = fill(&spec, &second, true).unwrap().unwrap();
+        assert_ne!(rotated["password"], first["password"]);
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(validate(&[secret(&[("password", SecretKind::Password)])]).is_ok());
+        assert!(validate(&[secret(&[])]).is_err());
+        // `tls` as a keypair writes tls.key, clashing with a password named tls.key
+        assert!(validate(&[secret(&[
+            ("tls", SecretKind::KeyPair),
+            ("tls.key", SecretKind::Password)
+        ])])
+        .is_err());
+    }
+}
@@ -0,0 +1,155 @@
+// Seccomp and AppArmor profiles for MyApp Controller
+// spec.securityProfiles.seccomp becomes the pod-level seccompProfile; appArmor is written as
+// the per-container annotation on the pod template, which every supported cluster version
+// reads. The mutating webhook fills in RuntimeDefault seccomp for MyApps that leave it unset.
+
+use crate::MyApp;
+use k8s_openapi::api::core::v1::SeccompProfile;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+const APPARMOR_ANNOTATION_PREFIX: &str = "container.apparmor.security.beta.kubernetes.io/";
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, JsonSchema, Default)]
+pub enum ProfileType {
+    /// The container runtime's default profile
+    #[default]
+    RuntimeDefault,
+    /// A profile loaded on the node, named by localhostProfile
+    Localhost,
+    Unconfined,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, JsonSchema, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct Profile {
+    #[serde(rename = "type")]
+    pub profile_type: ProfileType,
+
+    /// Profile name on the node, required for the Localhost type
+    #[serde(default)]
+    pub localhost_profile: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, JsonSchema, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct SecurityProfiles {
+    /// Defaulted to RuntimeDefault by the mutating webhook
+    #[serde(default)]
+    pub seccomp: Option<Profile>,
+
+    #[serde(default)]
+    pub app_armor: Option<Profile>,
+}
+
+impl Profile {
+    pub fn runtime_default() -> Self {
+        Self::default()
+    }
+
+    fn validate(&self, field: &str) -> Result<(), String> {
+        match (self.profile_type, &self.localhost_profile) {
+            (ProfileType::Localhost, None) => Err(format!(
+                "securityProfiles.{}.localhostProfile is required for the Localhost type",
+                field
+            )),
+            (ProfileType::Localhost, Some(_)) | (_, None) => Ok(()),
+            (_, Some(_)) => Err(format!(
+                "securityProfiles.{}.localhostProfile only applies to the Localhost type",
+                field
+            )),
+        }
+    }
+}
+
+impl SecurityProfiles {
+    pub fn validate(&self) -> Result<(), String> {
+        if let Some(seccomp) = &self.seccomp {
+            seccomp.validate("seccomp")?;
+        }
+        if let Some(app_armor) = &self.app_armor {
+            app_armor.validate("appArmor")?;
+        }
+        Ok(())
+    }
+}
+
+/// Pod-level seccomp profile of the MyApp
+pub fn seccomp_profile(myapp: &MyApp) -> Option<SeccompProfile> {
+    let profile = myapp.spec.security_profiles.as_ref()?.seccomp.as_ref()?;
+    Some(SeccompProfile {
+        type_: serde_json::to_value(profile.profile_type)
+            .ok()?
+            .as_str()?
+            .to_string(),
+        localhost_profile: profile.localhost_profile.clone(),
+    })
+}
+
+/// The MyApp's AppArmor profile in the annotation's `runtime/default` notation
+pub fn apparmor_value(myapp: &MyApp) -> Option<String> {
+    let profile = myapp.spec.security_profiles.as_ref()?.app_armor.as_ref()?;
+    Some(match profile.profile_type {
+        ProfileType::RuntimeDefault => "runtime/default".to_string(),
+        ProfileType::Localhost => format!(
+            "localhost/{}",
+            profile.localhost_profile.as_deref().unwrap_or_default()
+        ),
+        ProfileType::Unconfined => "unconfined".to_string(),
+    })
+}
+
+/// Pod template annotations applying the AppArmor profile to `container`
+pub fn template_annotations(myapp: &MyApp, container: &str) -> Option<BTreeMap<String, String>> {
+    let value = apparmor_value(myapp)?;
+    Some(BTreeMap::from([(
+        format!("{}{}", APPARMOR_ANNOTATION_PREFIX, container),
+        value,
+    )]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_profiles_render() {
+        let mut myapp = MyApp::new("web", Default::default());
+        myapp.spec.security_profiles = Some(SecurityProfiles {
+            seccomp: Some(Profile {
+                profile_type: ProfileType::Localhost,
+                localhost_profile: Some("profiles/web.json".to_string()),
+            }),
+            app_armor: Some(Profile::runtime_default()),
+        });
+        assert!(myapp
+            .spec
+            .security_profiles
+            .as_ref()
+            .unwrap()
+            .validate()
+            .is_ok());
+
+        let seccomp = seccomp_profile(&myapp).unwrap();
+        assert_eq!(seccomp.type_, "Localhost");
+        assert_eq!(
+            seccomp.localhost_profile.as_deref(),
+            Some("profiles/web.json")
+        );
+        assert_eq!(
+            template_annotations(&myapp, "app").unwrap()
+                ["container.apparmor.security.beta.kubernetes.io/app"],
+            "runtime/default"
+        );
+
+        let missing = SecurityProfiles {
+            app_armor: Some(Profile {
+                profile_type: ProfileType::Localhost,
+                localhost_profile: None,
+            }),
+            ..Default::default()
+        };
+        assert!(missing.validate().is_err());
+    }
+}
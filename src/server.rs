@@ -0,0 +1,339 @@
+// HTTP serving for MyApp Controller
+// Mounts route sets (metrics, health, webhooks) on configurable addresses, sharing a
+// listener when two sets use the same address, with request logging and metrics on all;
+// only listeners mounted with TLS settings (the webhooks) serve HTTPS
+
+use crate::metrics::MetricsCollector;
+use crate::shutdown::Shutdown;
+use crate::tls::{ReloadableCert, TlsConfig};
+use futures::{Stream, StreamExt};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinHandle;
+use tokio_rustls::server::TlsStream;
+use tokio_rustls::TlsAcceptor;
+use warp::filters::BoxedFilter;
+use warp::reply::Response;
+use warp::{Filter, Rejection, Reply};
+
+/// Routes of one mounted set, type-erased so sets can share a listener
+pub type Routes = BoxedFilter<(Response,)>;
+
+/// Box a route set for mounting
+pub fn routes<F, R>(filter: F) -> Routes
+where
+    F: Filter<Extract = (R,), Error = Rejection> + Clone + Send + Sync + 'static,
+    R: Reply + 'static,
+{
+    filter.map(Reply::into_response).boxed()
+}
+
+/// Bind addresses of the route sets
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ServerConfig {
+    pub metrics_addr: SocketAddr,
+    pub health_addr: SocketAddr,
+    pub webhook_addr: SocketAddr,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            metrics_addr: ([0, 0, 0, 0], 8080).into(),
+            health_addr: ([0, 0, 0, 0], 8081).into(),
+            webhook_addr: ([0, 0, 0, 0], 8443).into(),
+        }
+    }
+}
+
+impl ServerConfig {
+    /// Read `MYAPP_METRICS_ADDR`, `MYAPP_HEALTH_ADDR` and `MYAPP_WEBHOOK_ADDR`
+    /// (`host:port`, or `:port` for all interfaces)
+    pub fn from_env() -> Result<Self, String> {
+        let default = Self::default();
+        let addr = |var: &str, default: SocketAddr| match std::env::var(var) {
+            Ok(value) => parse_addr(&value).map_err(|e| format!("{}: {}", var, e)),
+            Err(_) => Ok(default),
+        };
+        Ok(Self {
+            metrics_addr: addr("MYAPP_METRICS_ADDR", default.metrics_addr)?,
+            health_addr: addr("MYAPP_HEALTH_ADDR", default.health_addr)?,
+            webhook_addr: addr("MYAPP_WEBHOOK_ADDR", default.webhook_addr)?,
+        })
+    }
+}
+
+pub fn parse_addr(value: &str) -> Result<SocketAddr, String> {
+    let value = value.trim();
+    let value = match value.strip_prefix(':') {
+        Some(port) => format!("0.0.0.0:{}", port),
+        None => value.to_string(),
+    };
+    value
+        .parse()
+        .map_err(|_| format!("invalid bind address '{}'", value))
+}
+
+/// How a listener terminates TLS
+#[derive(Debug, Clone)]
+enum ListenerTls {
+    /// Certificate files read once at bind
+    Files(TlsConfig),
+    /// Certificate swapped in place on renewal
+    Reloadable(Arc<ReloadableCert>),
+}
+
+impl PartialEq for ListenerTls {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::Files(a), Self::Files(b)) => a == b,
+            (Self::Reloadable(a), Self::Reloadable(b)) => Arc::ptr_eq(a, b),
+            _ => false,
+        }
+    }
+}
+
+struct Listener {
+    addr: SocketAddr,
+    names: Vec<&'static str>,
+    routes: Routes,
+    tls: Option<ListenerTls>,
+}
+
+/// Route sets grouped by bind address
+#[derive(Default)]
+pub struct HttpServer {
+    listeners: Vec<Listener>,
+    /// Sets that cannot share their address, reported by `start`
+    conflicts: Vec<String>,
+}
+
+impl HttpServer {
+    /// Serve `routes` over plain HTTP on `addr`, next to any set already mounted there
+    pub fn mount(self, name: &'static str, addr: SocketAddr, routes: Routes) -> Self {
+        self.mount_tls(name, addr, routes, None)
+    }
+
+    /// Serve `routes` on `addr`, over HTTPS when `tls` is set; sets sharing an address
+    /// must agree on TLS
+    pub fn mount_tls(
+        self,
+        name: &'static str,
+        addr: SocketAddr,
+        routes: Routes,
+        tls: Option<TlsConfig>,
+    ) -> Self {
+        self.mount_with(name, addr, routes, tls.map(ListenerTls::Files))
+    }
+
+    /// Serve `routes` over HTTPS on `addr` with a certificate that may be replaced at runtime
+    pub fn mount_reloadable_tls(
+        self,
+        name: &'static str,
+        addr: SocketAddr,
+        routes: Routes,
+        cert: Arc<ReloadableCert>,
+    ) -> Self {
+        self.mount_with(name, addr, routes, Some(ListenerTls::Reloadable(cert)))
+    }
+
+    fn mount_with(
+        mut self,
+        name: &'static str,
+        addr: SocketAddr,
+        routes: Routes,
+        tls: Option<ListenerTls>,
+    ) -> Self {
+        match self.listeners.iter_mut().find(|l| l.addr == addr) {
+            Some(listener) if listener.tls != tls => self.conflicts.push(format!(
+                "{} and {} share {} but not their TLS settings",
+                listener.names.join("+"),
+                name,
+                addr
+            )),
+            Some(listener) => {
+                let merged = listener.routes.clone().or(routes).unify().boxed();
+                listener.routes = merged;
+                listener.names.push(name);
+            }
+            None => self.listeners.push(Listener {
+                addr,
+                names: vec![name],
+                routes,
+                tls,
+            }),
+        }
+        self
+    }
+
+    /// Bind every listener and serve until shutdown
+    pub fn start(
+        self,
+        metrics: MetricsCollector,
+        shutdown: Shutdown,
+    ) -> Result<Vec<JoinHandle<()>>, String> {
+        if !self.conflicts.is_empty() {
+            return Err(self.conflicts.join("; "));
+        }
+        let mut servers = Vec::new();
+        for listener in self.listeners {
+            let name = listener.names.join("+");
+            let metrics = metrics.clone();
+            let log_name = name.clone();
+            let logged = listener.routes.with(warp::log::custom(move |info| {
+                let status = info.status();
+                metrics.record_http_request(&log_name, status.as_u16());
+                if status.is_server_error() {
+                    tracing::warn!(
+                        "{} server: {} {} returned {}",
+                        log_name,
+                        info.method(),
+                        info.path(),
+                        status
+                    );
+                }
+            }));
+
+            let bind_error =
+                |e: warp::Error| format!("{} server on {}: {}", name, listener.addr, e);
+            let signal = shutdown.clone().wait();
+            let server = warp::serve(logged);
+            match listener.tls {
+                Some(ListenerTls::Reloadable(cert)) => {
+                    let tcp = std::net::TcpListener::bind(listener.addr)
+                        .and_then(|tcp| tcp.set_nonblocking(true).map(|_| tcp))
+                        .and_then(TcpListener::from_std)
+                        .map_err(|e| format!("{} server on {}: {}", name, listener.addr, e))?;
+                    let addr = tcp.local_addr().unwrap_or(listener.addr);
+                    tracing::info!("Starting {} server on {} (TLS, reloadable)", name, addr);
+                    let incoming = tls_incoming(tcp, cert.acceptor());
+                    servers.push(spawn(
+                        name,
+                        server.serve_incoming_with_graceful_shutdown(incoming, signal),
+                    ));
+                }
+                Some(ListenerTls::Files(tls)) => {
+                    let mut server = server
+                        .tls()
+                        .cert_path(&tls.cert_path)
+                        .key_path(&tls.key_path);
+                    server = match (&tls.client_ca_path, tls.client_auth_required) {
+                        (Some(ca), true) => server.client_auth_required_path(ca),
+                        (Some(ca), false) => server.client_auth_optional_path(ca),
+                        (None, _) => server,
+                    };
+                    let (addr, server) = server
+                        .try_bind_with_graceful_shutdown(listener.addr, signal)
+                        .map_err(bind_error)?;
+                    tracing::info!("Starting {} server on {} (TLS)", name, addr);
+                    servers.push(spawn(name, server));
+                }
+                None => {
+                    let (addr, server) = server
+                        .try_bind_with_graceful_shutdown(listener.addr, signal)
+                        .map_err(bind_error)?;
+                    tracing::info!("Starting {} server on {}", name, addr);
+                    servers.push(spawn(name, server));
+                }
+            }
+        }
+        Ok(servers)
+    }
+}
+
+/// Connections that completed their TLS handshake; handshakes run concurrently and
+/// failures are logged and dropped rather than ending the stream
+fn tls_incoming(
+    listener: TcpListener,
+    acceptor: TlsAcceptor,
+) -> impl Stream<Item = Result<TlsStream<TcpStream>, std::io::Error>> {
+    let (tx, rx) = futures::channel::mpsc::unbounded();
+    tokio::spawn(async move {
+        while !tx.is_closed() {
+            let (stream, peer) = match listener.accept().await {
+                Ok(accepted) => accepted,
+                Err(e) => {
+                    tracing::warn!("Accepting TLS connection failed: {}", e);
+                    tokio::time::sleep(Duration::from_millis(100)).await;
+                    continue;
+                }
+            };
+            let acceptor = acceptor.clone();
+            let tx = tx.clone();
+            tokio::spawn(async move {
+                let handshake = acceptor.accept(stream);
+                match tokio::time::timeout(Duration::from_secs(10), handshake).await {
+                    Ok(Ok(tls)) => {
+                        let _ = tx.unbounded_send(tls);
+                    }
+                    Ok(Err(e)) => tracing::warn!("TLS handshake with {} failed: {}", peer, e),
+                    Err(_) => tracing::warn!("TLS handshake with {} timed out", peer),
+                }
+            });
+        }
+    });
+    rx.map(Ok)
+}
+
+fn spawn(
+    name: String,
+    server: impl std::future::Future<Output = ()> + Send + 'static,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        server.await;
+        tracing::info!("{} server stopped", name);
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_addr() {
+        assert_eq!(parse_addr(":9090"), Ok(([0, 0, 0, 0], 9090).into()));
+        assert_eq!(
+            parse_addr("127.0.0.1:8080"),
+            Ok(([127, 0, 0, 1], 8080).into())
+        );
+        assert!(parse_addr("localhost").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_mount_shares_listener() {
+        let addr: SocketAddr = ([127, 0, 0, 1], 8080).into();
+        let server = HttpServer::default()
+            .mount("metrics", addr, routes(warp::path("metrics").map(|| "m")))
+            .mount("health", addr, routes(warp::path("health").map(|| "h")))
+            .mount(
+                "webhook",
+                ([127, 0, 0, 1], 8443).into(),
+                routes(warp::path("validate").map(|| "w")),
+            );
+        assert_eq!(server.listeners.len(), 2);
+        assert_eq!(server.listeners[0].names, vec!["metrics", "health"]);
+        assert!(server.conflicts.is_empty());
+
+        let tls = TlsConfig {
+            cert_path: "tls.crt".into(),
+            key_path: "tls.key".into(),
+            client_ca_path: None,
+            client_auth_required: true,
+        };
+        let clash = HttpServer::default()
+            .mount("health", addr, routes(warp::path("health").map(|| "h")))
+            .mount_tls(
+                "webhook",
+                addr,
+                routes(warp::path("validate").map(|| "w")),
+                Some(tls),
+            );
+        assert_eq!(clash.conflicts.len(), 1);
+
+        let shared = server.listeners[0].routes.clone();
+        let res = warp::test::request().path("/health").reply(&shared).await;
+        assert_eq!(res.body(), "h");
+    }
+}
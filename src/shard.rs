@@ -0,0 +1,103 @@
+// Horizontal sharding for MyApp Controller
+// With `MYAPP_SHARDS=N`, each of N controller replicas reconciles only the MyApps whose
+// namespace/name hashes to its shard, so throughput scales with replicas. The shard index
+// comes from `MYAPP_SHARD_INDEX` or the StatefulSet ordinal at the end of the pod's hostname
+
+use crate::spec_hash::fnv1a;
+use crate::MyApp;
+use kube::ResourceExt;
+
+/// The slice of MyApps this replica reconciles
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Shard {
+    pub index: u32,
+    pub count: u32,
+}
+
+impl Default for Shard {
+    /// A single shard owning everything
+    fn default() -> Self {
+        Self { index: 0, count: 1 }
+    }
+}
+
+/// StatefulSet ordinal of a pod name, e.g. 2 for `myapp-controller-2`
+pub fn ordinal(hostname: &str) -> Option<u32> {
+    hostname.rsplit_once('-')?.1.parse().ok()
+}
+
+impl Shard {
+    pub fn new(index: u32, count: u32) -> Result<Self, String> {
+        if count == 0 || index >= count {
+            return Err(format!(
+                "shard index {} is out of range for {} shards",
+                index, count
+            ));
+        }
+        Ok(Self { index, count })
+    }
+
+    /// Read `MYAPP_SHARDS` and `MYAPP_SHARD_INDEX`, falling back to the ordinal in `HOSTNAME`
+    pub fn from_env() -> Result<Self, String> {
+        let count = match std::env::var("MYAPP_SHARDS") {
+            Ok(v) => v
+                .parse()
+                .map_err(|_| format!("MYAPP_SHARDS: invalid value '{}'", v))?,
+            Err(_) => return Ok(Self::default()),
+        };
+        let index = match std::env::var("MYAPP_SHARD_INDEX") {
+            Ok(v) => v
+                .parse()
+                .map_err(|_| format!("MYAPP_SHARD_INDEX: invalid value '{}'", v))?,
+            Err(_) => std::env::var("HOSTNAME")
+                .ok()
+                .and_then(|h| ordinal(&h))
+                .ok_or("MYAPP_SHARDS is set but neither MYAPP_SHARD_INDEX nor a StatefulSet ordinal in HOSTNAME gives the shard index")?,
+        };
+        Self::new(index, count)
+    }
+
+    /// Stable hash, so replicas of different versions agree during a rollout
+    pub fn owns(&self, namespace: &str, name: &str) -> bool {
+        self.count <= 1
+            || fnv1a(format!("{}/{}", namespace, name).as_bytes()) % self.count as u64
+                == self.index as u64
+    }
+
+    pub fn owns_myapp(&self, myapp: &MyApp) -> bool {
+        self.owns(&myapp.namespace().unwrap_or_default(), &myapp.name_any())
+    }
+}
+
+impl std::fmt::Display for Shard {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}/{}", self.index, self.count)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ordinal() {
+        assert_eq!(ordinal("myapp-controller-2"), Some(2));
+        assert_eq!(ordinal("myapp-controller-7d9f8b-x2k4q"), None);
+        assert_eq!(ordinal("controller"), None);
+    }
+
+    #[test]
+    fn test_every_myapp_has_exactly_one_shard() {
+        let shards: Vec<Shard> = (0..3).map(|i| Shard::new(i, 3).unwrap()).collect();
+        let mut per_shard = [0; 3];
+        for n in 0..300 {
+            let name = format!("app-{}", n);
+            let owners: Vec<&Shard> = shards.iter().filter(|s| s.owns("team", &name)).collect();
+            assert_eq!(owners.len(), 1);
+            per_shard[owners[0].index as usize] += 1;
+        }
+        // Roughly even spread
+        assert!(per_shard.iter().all(|n| *n > 60), "{:?}", per_shard);
+        assert!(Shard::new(3, 3).is_err());
+    }
+}
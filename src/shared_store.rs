@@ -0,0 +1,229 @@
+// Shared reflector caches for MyApp Controller
+// The controller's watches of MyApps and their children feed stores that the status and plan
+// endpoints read, so serving them costs no API GETs; only a plan's dry-runs reach the server
+
+use crate::plan;
+use crate::summary::{self, SummaryRow};
+use crate::MyApp;
+use futures::FutureExt;
+use k8s_openapi::api::apps::v1::Deployment;
+use k8s_openapi::api::batch::v1::{CronJob, Job};
+use kube::runtime::reflector::{ObjectRef, Store};
+use kube::{Client, Resource};
+use std::sync::Arc;
+use warp::http::StatusCode;
+use warp::{Filter, Reply};
+
+/// Stores fed by one controller's watches
+#[derive(Clone)]
+pub struct ControllerStores {
+    /// Namespace the controller watches; None for the whole cluster
+    pub namespace: Option<String>,
+    pub myapps: Store<MyApp>,
+    pub deployments: Store<Deployment>,
+    pub jobs: Store<Job>,
+    pub cronjobs: Store<CronJob>,
+}
+
+/// A child kind the controllers keep a store of
+pub trait CachedChild: Resource<DynamicType = ()> + Clone + 'static {
+    fn store(stores: &ControllerStores) -> &Store<Self>;
+}
+
+impl CachedChild for Deployment {
+    fn store(stores: &ControllerStores) -> &Store<Self> {
+        &stores.deployments
+    }
+}
+
+impl CachedChild for Job {
+    fn store(stores: &ControllerStores) -> &Store<Self> {
+        &stores.jobs
+    }
+}
+
+impl CachedChild for CronJob {
+    fn store(stores: &ControllerStores) -> &Store<Self> {
+        &stores.cronjobs
+    }
+}
+
+fn is_ready<K: Resource<DynamicType = ()> + Clone + 'static>(store: &Store<K>) -> bool {
+    matches!(store.wait_until_ready().now_or_never(), Some(Ok(())))
+}
+
+impl ControllerStores {
+    fn covers(&self, namespace: &str) -> bool {
+        self.namespace.as_deref().is_none_or(|ns| ns == namespace)
+    }
+}
+
+/// Stores of every controller; each namespace is watched by exactly one of them
+#[derive(Clone, Default)]
+pub struct SharedStores(Vec<ControllerStores>);
+
+impl SharedStores {
+    pub fn push(&mut self, stores: ControllerStores) {
+        self.0.push(stores);
+    }
+
+    fn covering(&self, namespace: &str) -> Option<&ControllerStores> {
+        self.0.iter().find(|s| s.covers(namespace))
+    }
+
+    /// MyApps in `namespace` (or everywhere), or None until every MyApp watch has listed
+    pub fn myapps(&self, namespace: Option<&str>) -> Option<Vec<Arc<MyApp>>> {
+        if !self.0.iter().all(|s| is_ready(&s.myapps)) {
+            return None;
+        }
+        Some(
+            self.0
+                .iter()
+                .flat_map(|s| s.myapps.state())
+                .filter(|m| namespace.is_none_or(|ns| m.meta().namespace.as_deref() == Some(ns)))
+                .collect(),
+        )
+    }
+
+    /// A cached child; the outer None means no synced store covers `namespace`, so the
+    /// caller should ask the API server instead
+    pub fn get<K: CachedChild>(&self, namespace: &str, name: &str) -> Option<Option<Arc<K>>> {
+        lookup(K::store(self.covering(namespace)?), namespace, name)
+    }
+
+    pub fn myapp(&self, namespace: &str, name: &str) -> Option<Option<Arc<MyApp>>> {
+        lookup(&self.covering(namespace)?.myapps, namespace, name)
+    }
+}
+
+fn lookup<K>(store: &Store<K>, namespace: &str, name: &str) -> Option<Option<Arc<K>>>
+where
+    K: Resource<DynamicType = ()> + Clone + 'static,
+{
+    is_ready(store).then(|| store.get(&ObjectRef::new(name).within(namespace)))
+}
+
+fn json_error(status: StatusCode, message: String) -> warp::reply::Response {
+    warp::reply::with_status(
+        warp::reply::json(&serde_json::json!({ "error": message })),
+        status,
+    )
+    .into_response()
+}
+
+/// `GET /myapps[?namespace=ns]` and `GET /myapps/{namespace}/{name}/plan`, from the stores
+pub fn handler(
+    stores: SharedStores,
+    client: Client,
+) -> impl Filter<Extract = (warp::reply::Response,), Error = warp::Rejection> + Clone {
+    let list = {
+        let stores = stores.clone();
+        warp::path!("myapps")
+            .and(warp::get())
+            .and(warp::query::<std::collections::HashMap<String, String>>())
+            .map(move |query: std::collections::HashMap<String, String>| {
+                let namespace = query.get("namespace").map(String::as_str);
+                match stores.myapps(namespace) {
+                    Some(myapps) => {
+                        let now = chrono::Utc::now();
+                        let mut rows: Vec<SummaryRow> =
+                            myapps.iter().map(|m| summary::build_row(m, now)).collect();
+                        rows.sort_by(|a, b| (&a.namespace, &a.name).cmp(&(&b.namespace, &b.name)));
+                        warp::reply::json(&rows).into_response()
+                    }
+                    None => json_error(
+                        StatusCode::SERVICE_UNAVAILABLE,
+                        "MyApp caches are not synced yet".to_string(),
+                    ),
+                }
+            })
+    };
+    let plan = warp::path!("myapps" / String / String / "plan")
+        .and(warp::get())
+        .then(move |namespace: String, name: String| {
+            let (stores, client) = (stores.clone(), client.clone());
+            async move {
+                let myapp = match stores.myapp(&namespace, &name) {
+                    Some(Some(myapp)) => myapp,
+                    Some(None) => {
+                        return json_error(
+                            StatusCode::NOT_FOUND,
+                            format!("MyApp {}/{} not found", namespace, name),
+                        )
+                    }
+                    None => {
+                        return json_error(
+                            StatusCode::SERVICE_UNAVAILABLE,
+                            format!("no synced cache covers namespace {}", namespace),
+                        )
+                    }
+                };
+                match plan::plan_cached(&myapp, client, Some(&stores)).await {
+                    Ok(plan) => plan.to_string().into_response(),
+                    Err(e) => json_error(StatusCode::BAD_GATEWAY, e.to_string()),
+                }
+            }
+        });
+    list.or(plan).unify()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use kube::runtime::reflector::store::Writer;
+    use kube::runtime::watcher::Event;
+
+    fn synced<K>(objects: Vec<K>) -> Store<K>
+    where
+        K: Resource<DynamicType = ()> + Clone + 'static,
+    {
+        let mut writer = Writer::<K>::default();
+        writer.apply_watcher_event(&Event::Init);
+        for object in objects {
+            writer.apply_watcher_event(&Event::InitApply(object));
+        }
+        writer.apply_watcher_event(&Event::InitDone);
+        writer.as_reader()
+    }
+
+    fn deployment(namespace: &str, name: &str) -> Deployment {
+        let mut deployment = Deployment::default();
+        deployment.metadata.namespace = Some(namespace.to_string());
+        deployment.metadata.name = Some(name.to_string());
+        deployment
+    }
+
+    #[test]
+    fn test_get_distinguishes_missing_from_unknown() {
+        let stores = |namespace: &str, deployments| ControllerStores {
+            namespace: Some(namespace.to_string()),
+            myapps: synced(Vec::new()),
+            deployments: synced(deployments),
+            jobs: synced(Vec::new()),
+            cronjobs: synced(Vec::new()),
+        };
+
+        let mut cluster = SharedStores::default();
+        cluster.push(stores("team-a", vec![deployment("team-a", "web")]));
+        cluster.push(stores("team-b", Vec::new()));
+        assert!(matches!(
+            cluster.get::<Deployment>("team-a", "web"),
+            Some(Some(_))
+        ));
+        assert!(matches!(
+            cluster.get::<Deployment>("team-b", "web"),
+            Some(None)
+        ));
+        // Not watched, so the cache cannot say it does not exist
+        assert!(cluster.get::<Deployment>("team-c", "web").is_none());
+
+        let listing = Writer::default();
+        let unsynced = ControllerStores {
+            deployments: listing.as_reader(),
+            ..stores("team-a", Vec::new())
+        };
+        let mut starting = SharedStores::default();
+        starting.push(unsynced);
+        assert!(starting.get::<Deployment>("team-a", "web").is_none());
+    }
+}
@@ -0,0 +1,90 @@
+// Graceful shutdown for MyApp Controller
+// Turns SIGTERM/SIGINT into a signal that servers and controllers can drain on
+
+use std::time::Duration;
+use tokio::sync::watch;
+
+/// Resolves once shutdown has been requested; cheap to clone into every server
+#[derive(Clone)]
+pub struct Shutdown {
+    rx: watch::Receiver<bool>,
+}
+
+impl Shutdown {
+    pub fn channel() -> (watch::Sender<bool>, Self) {
+        let (tx, rx) = watch::channel(false);
+        (tx, Self { rx })
+    }
+
+    /// Listen for SIGTERM and SIGINT
+    pub fn install() -> Self {
+        Self::install_with_trigger().1
+    }
+
+    /// Like `install`, also returning a sender that requests shutdown from inside the process
+    pub fn install_with_trigger() -> (watch::Sender<bool>, Self) {
+        let (tx, shutdown) = Self::channel();
+        let trigger = tx.clone();
+        tokio::spawn(async move {
+            wait_for_signal().await;
+            tracing::info!("Shutdown requested, draining in-flight work");
+            let _ = tx.send(true);
+        });
+        (trigger, shutdown)
+    }
+
+    pub async fn wait(mut self) {
+        let _ = self.rx.wait_for(|requested| *requested).await;
+    }
+
+    /// Resolves `timeout` after shutdown was requested
+    pub async fn deadline(self, timeout: Duration) {
+        self.wait().await;
+        tokio::time::sleep(timeout).await;
+    }
+}
+
+/// How long in-flight reconciles get to finish (`MYAPP_SHUTDOWN_TIMEOUT_SECS`, default 25)
+pub fn timeout_from_env() -> Duration {
+    let secs = std::env::var("MYAPP_SHUTDOWN_TIMEOUT_SECS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(25);
+    Duration::from_secs(secs)
+}
+
+#[cfg(unix)]
+async fn wait_for_signal() {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let mut term = signal(SignalKind::terminate()).expect("failed to install SIGTERM handler");
+    tokio::select! {
+        _ = term.recv() => {}
+        _ = tokio::signal::ctrl_c() => {}
+    }
+}
+
+#[cfg(not(unix))]
+async fn wait_for_signal() {
+    let _ = tokio::signal::ctrl_c().await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_wait_resolves_after_trigger() {
+        let (tx, shutdown) = Shutdown::channel();
+        let waiter = tokio::spawn(shutdown.clone().wait());
+
+        tokio::task::yield_now().await;
+        assert!(!waiter.is_finished());
+
+        tx.send(true).unwrap();
+        tokio::time::timeout(Duration::from_secs(1), waiter)
+            .await
+            .expect("shutdown should resolve")
+            .unwrap();
+    }
+}
@@ -0,0 +1,79 @@
+// Spec hashes for MyApp Controller children
+// Rendered children carry a hash of themselves in an annotation; reconcile only patches a
+// child whose stored hash differs, so unchanged MyApps cost no writes and no rollouts
+
+use kube::Resource;
+use serde::Serialize;
+
+pub const SPEC_HASH_ANNOTATION: &str = "myapps.example.com/spec-hash";
+
+/// FNV-1a, stable across builds and releases unlike std's hasher
+pub fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+/// Hash of a rendered child, ignoring any hash annotation it already carries
+pub fn spec_hash<K: Resource + Serialize + Clone>(object: &K) -> String {
+    let mut object = object.clone();
+    let meta = object.meta_mut();
+    if let Some(annotations) = meta.annotations.as_mut() {
+        annotations.remove(SPEC_HASH_ANNOTATION);
+        if annotations.is_empty() {
+            meta.annotations = None;
+        }
+    }
+    let json = serde_json::to_vec(&object).unwrap_or_default();
+    format!("{:016x}", fnv1a(&json))
+}
+
+/// Record the hash of `object` on itself
+pub fn annotate<K: Resource + Serialize + Clone>(mut object: K) -> K {
+    let hash = spec_hash(&object);
+    object
+        .meta_mut()
+        .annotations
+        .get_or_insert_with(Default::default)
+        .insert(SPEC_HASH_ANNOTATION.to_string(), hash);
+    object
+}
+
+/// Whether `live` was last written from the same rendering as `desired`
+pub fn is_current<K: Resource>(live: &K, desired: &K) -> bool {
+    let hash = |object: &K| {
+        object
+            .meta()
+            .annotations
+            .as_ref()
+            .and_then(|a| a.get(SPEC_HASH_ANNOTATION))
+            .cloned()
+    };
+    hash(live).is_some() && hash(live) == hash(desired)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use k8s_openapi::api::apps::v1::{Deployment, DeploymentSpec};
+
+    fn deployment(replicas: i32) -> Deployment {
+        Deployment {
+            spec: Some(DeploymentSpec {
+                replicas: Some(replicas),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_hash_tracks_rendered_spec() {
+        let desired = annotate(deployment(3));
+        // Re-annotating is stable
+        assert!(is_current(&annotate(desired.clone()), &desired));
+        assert!(!is_current(&desired, &annotate(deployment(4))));
+        // Children written before hashing always get one update
+        assert!(!is_current(&deployment(3), &desired));
+    }
+}
@@ -0,0 +1,165 @@
+// Status writer for MyApp Controller
+// Guards status updates with JSON Patch test operations so stale writers cannot clobber newer status
+
+use crate::metrics::MetricsCollector;
+use crate::retry::{self, MAX_ATTEMPTS};
+use crate::{MyApp, MyAppStatus};
+use json_patch::{AddOperation, Patch as JsonPatch, PatchOperation, TestOperation};
+use kube::api::{Api, Patch, PatchParams};
+use kube::ResourceExt;
+
+/// How a status write should proceed given the status last read from the server
+#[derive(Debug, Clone, PartialEq)]
+pub enum StatusWrite {
+    /// Apply the patch; it fails if observedGeneration changed since the read
+    Apply(JsonPatch),
+    /// The stored status already reflects a newer generation
+    Skip,
+}
+
+pub fn plan_status_write(current: Option<&MyAppStatus>, new: &MyAppStatus) -> StatusWrite {
+    let current_generation = current.and_then(|s| s.observed_generation);
+    if current_generation > new.observed_generation {
+        return StatusWrite::Skip;
+    }
+
+    let mut ops = Vec::new();
+    if let Some(generation) = current_generation {
+        ops.push(PatchOperation::Test(TestOperation {
+            path: "/status/observedGeneration".parse().unwrap(),
+            value: generation.into(),
+        }));
+    }
+    ops.push(PatchOperation::Add(AddOperation {
+        path: "/status".parse().unwrap(),
+        value: serde_json::to_value(new).unwrap(),
+    }));
+
+    StatusWrite::Apply(JsonPatch(ops))
+}
+
+/// Developer mode: print what a status write changes
+fn log_diff(name: &str, current: Option<&MyAppStatus>, new: &MyAppStatus) {
+    let before = serde_json::to_value(current).unwrap_or_default();
+    let after = serde_json::to_value(new).unwrap_or_default();
+    let diff = json_patch::diff(&before, &after);
+    if !diff.0.is_empty() {
+        tracing::info!(
+            "Status diff for MyApp {}: {}",
+            name,
+            serde_json::to_string(&diff).unwrap_or_default()
+        );
+    }
+}
+
+/// Write `new` as the status of `myapp`, re-reading and retrying if another writer got there first
+pub async fn patch_status(
+    api: &Api<MyApp>,
+    myapp: &MyApp,
+    new: &MyAppStatus,
+    metrics: &MetricsCollector,
+) -> Result<(), kube::Error> {
+    let name = myapp.name_any();
+    let mut current = myapp.status.clone();
+
+    for attempt in 1..=MAX_ATTEMPTS {
+        let patch = match plan_status_write(current.as_ref(), new) {
+            StatusWrite::Apply(patch) => patch,
+            StatusWrite::Skip => {
+                tracing::info!("Skipping stale status write for MyApp {}", name);
+                return Ok(());
+            }
+        };
+        if crate::dev::sandbox().is_some() {
+            log_diff(&name, current.as_ref(), new);
+        }
+
+        match api
+            .patch_status(&name, &PatchParams::default(), &Patch::Json::<()>(patch))
+            .await
+        {
+            Ok(_) => {
+                let ns = myapp.namespace().unwrap_or_default();
+                metrics.set_conditions(&ns, &name, &new.conditions);
+                return Ok(());
+            }
+            Err(e) if retry::retry_conflict(&e, attempt, "status", &name, metrics) => {
+                current = api.get_status(&name).await?.status;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::Value;
+
+    fn status(state: &str, generation: i64) -> MyAppStatus {
+        MyAppStatus {
+            state: state.to_string(),
+            observed_generation: Some(generation),
+            ..Default::default()
+        }
+    }
+
+    /// Minimal stand-in for the API server applying a JSON patch to the stored object
+    fn server_apply(stored: &mut Value, write: &StatusWrite) -> Result<(), json_patch::PatchError> {
+        match write {
+            StatusWrite::Apply(patch) => json_patch::patch(stored, patch),
+            StatusWrite::Skip => Ok(()),
+        }
+    }
+
+    fn read(stored: &Value) -> Option<MyAppStatus> {
+        serde_json::from_value(stored["status"].clone()).ok()
+    }
+
+    #[test]
+    fn test_stale_writer_loses_race() {
+        let mut stored = serde_json::json!({ "status": status("Running", 1) });
+
+        // Both writers read generation 1
+        let snapshot = read(&stored);
+        let newer = plan_status_write(snapshot.as_ref(), &status("Running", 3));
+        let older = plan_status_write(snapshot.as_ref(), &status("Degraded", 2));
+
+        assert!(server_apply(&mut stored, &newer).is_ok());
+        assert!(server_apply(&mut stored, &older).is_err());
+
+        // The losing writer re-reads and sees it is stale
+        let reread = read(&stored);
+        let retry = plan_status_write(reread.as_ref(), &status("Degraded", 2));
+        assert_eq!(retry, StatusWrite::Skip);
+        assert_eq!(read(&stored).unwrap().observed_generation, Some(3));
+    }
+
+    #[test]
+    fn test_retry_after_reread_succeeds() {
+        let mut stored = serde_json::json!({ "status": status("Running", 1) });
+
+        let snapshot = read(&stored);
+        let first = plan_status_write(snapshot.as_ref(), &status("Running", 2));
+        let second = plan_status_write(snapshot.as_ref(), &status("Degraded", 2));
+
+        assert!(server_apply(&mut stored, &first).is_ok());
+        assert!(server_apply(&mut stored, &second).is_err());
+
+        // Same generation after re-read: the write goes through
+        let retry = plan_status_write(read(&stored).as_ref(), &status("Degraded", 2));
+        assert!(server_apply(&mut stored, &retry).is_ok());
+        assert_eq!(read(&stored).unwrap().state, "Degraded");
+    }
+
+    #[test]
+    fn test_first_status_write_has_no_test_op() {
+        let mut stored = serde_json::json!({ "spec": {} });
+
+        let write = plan_status_write(None, &status("Running", 1));
+        assert!(server_apply(&mut stored, &write).is_ok());
+        assert_eq!(read(&stored).unwrap().observed_generation, Some(1));
+    }
+}
@@ -0,0 +1,184 @@
+// Rollout strategy module for MyApp Controller
+// Maps spec.strategy onto the Deployment update strategy
+
+use k8s_openapi::api::apps::v1::{DeploymentStrategy, RollingUpdateDeployment};
+use k8s_openapi::apimachinery::pkg::util::intstr::IntOrString;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::canary::CanaryConfig;
+
+/// How a MyApp's pods are replaced on update
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, JsonSchema, Default)]
+pub enum StrategyType {
+    /// Gradually replace pods (default)
+    #[default]
+    RollingUpdate,
+    /// Terminate all pods before creating new ones
+    Recreate,
+    /// Run the new spec in a preview Deployment and switch the Service once it is healthy
+    BlueGreen,
+    /// Shift traffic to a canary Deployment in weighted steps before promoting it
+    Canary,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct StrategyConfig {
+    /// Strategy type
+    #[serde(default, rename = "type")]
+    pub type_: StrategyType,
+
+    /// Rolling update parameters (RollingUpdate and Canary)
+    #[serde(default)]
+    pub rolling_update: Option<RollingUpdateConfig>,
+
+    /// Canary steps (Canary only)
+    #[serde(default)]
+    pub canary: Option<CanaryConfig>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct RollingUpdateConfig {
+    /// Pods created above the desired count, as a number or percentage (e.g. "1", "25%")
+    #[serde(default)]
+    #[schemars(regex(pattern = r"^[0-9]+%?$"))]
+    pub max_surge: Option<String>,
+
+    /// Pods that may be unavailable during the update, as a number or percentage
+    #[serde(default)]
+    #[schemars(regex(pattern = r"^[0-9]+%?$"))]
+    pub max_unavailable: Option<String>,
+}
+
+/// Parse "25%" or "1" into an IntOrString
+pub fn int_or_percent(value: &str) -> Result<IntOrString, String> {
+    let value = value.trim();
+    if let Some(percent) = value.strip_suffix('%') {
+        percent
+            .parse::<u32>()
+            .map(|_| IntOrString::String(value.to_string()))
+            .map_err(|_| format!("invalid percentage: {}", value))
+    } else {
+        value
+            .parse::<i32>()
+            .map(IntOrString::Int)
+            .map_err(|_| format!("invalid number or percentage: {}", value))
+    }
+}
+
+impl StrategyConfig {
+    pub fn validate(&self, replicas: i32) -> Result<(), String> {
+        match (&self.canary, self.type_) {
+            (Some(canary), StrategyType::Canary) => canary.validate()?,
+            (None, StrategyType::Canary) => {
+                return Err("strategy.canary is required for Canary".to_string())
+            }
+            (Some(_), _) => return Err("strategy.canary is only valid for Canary".to_string()),
+            (None, _) => {}
+        }
+
+        let Some(rolling) = &self.rolling_update else {
+            return Ok(());
+        };
+
+        if !matches!(
+            self.type_,
+            StrategyType::RollingUpdate | StrategyType::Canary
+        ) {
+            return Err(
+                "strategy.rollingUpdate is only valid for RollingUpdate and Canary".to_string(),
+            );
+        }
+
+        if let Some(surge) = &rolling.max_surge {
+            int_or_percent(surge)?;
+        }
+
+        if let Some(unavailable) = &rolling.max_unavailable {
+            let all_unavailable = match int_or_percent(unavailable)? {
+                IntOrString::Int(n) => n >= replicas,
+                IntOrString::String(p) => {
+                    p.trim_end_matches('%').parse::<u32>().unwrap_or(0) >= 100
+                }
+            };
+            if replicas > 1 && all_unavailable {
+                return Err(
+                    "strategy.rollingUpdate.maxUnavailable must leave at least one replica available"
+                        .to_string(),
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Convert into the Deployment strategy
+    pub fn to_deployment_strategy(&self) -> DeploymentStrategy {
+        match self.type_ {
+            // Each blue-green color is replaced wholesale by the controller
+            StrategyType::Recreate | StrategyType::BlueGreen => DeploymentStrategy {
+                type_: Some("Recreate".to_string()),
+                rolling_update: None,
+            },
+            // Canary promotes by rolling the stable Deployment
+            StrategyType::RollingUpdate | StrategyType::Canary => DeploymentStrategy {
+                type_: Some("RollingUpdate".to_string()),
+                rolling_update: self
+                    .rolling_update
+                    .as_ref()
+                    .map(|r| RollingUpdateDeployment {
+                        max_surge: r.max_surge.as_deref().and_then(|v| int_or_percent(v).ok()),
+                        max_unavailable: r
+                            .max_unavailable
+                            .as_deref()
+                            .and_then(|v| int_or_percent(v).ok()),
+                    }),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rolling(max_unavailable: &str) -> StrategyConfig {
+        StrategyConfig {
+            type_: StrategyType::RollingUpdate,
+            rolling_update: Some(RollingUpdateConfig {
+                max_surge: Some("25%".to_string()),
+                max_unavailable: Some(max_unavailable.to_string()),
+            }),
+            canary: None,
+        }
+    }
+
+    #[test]
+    fn test_max_unavailable_validation() {
+        assert!(rolling("100%").validate(3).is_err());
+        assert!(rolling("3").validate(3).is_err());
+        assert!(rolling("100%").validate(1).is_ok());
+        assert!(rolling("1").validate(3).is_ok());
+    }
+
+    #[test]
+    fn test_to_deployment_strategy() {
+        let strategy = rolling("1").to_deployment_strategy();
+        let rolling_update = strategy.rolling_update.unwrap();
+
+        assert_eq!(strategy.type_.as_deref(), Some("RollingUpdate"));
+        assert_eq!(
+            rolling_update.max_surge,
+            Some(IntOrString::String("25%".to_string()))
+        );
+        assert_eq!(rolling_update.max_unavailable, Some(IntOrString::Int(1)));
+
+        let recreate = StrategyConfig {
+            type_: StrategyType::Recreate,
+            ..Default::default()
+        };
+        assert!(recreate.to_deployment_strategy().rolling_update.is_none());
+    }
+}
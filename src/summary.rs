@@ -0,0 +1,219 @@
+// Status summary for MyApp Controller
+// `status` lists MyApps with their state, readiness, age and last error for triage from a terminal
+
+use crate::MyApp;
+use chrono::{DateTime, Utc};
+use kube::api::{Api, ListParams};
+use kube::ResourceExt;
+use serde::Serialize;
+
+/// One MyApp in the summary
+#[derive(Debug, Clone, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct SummaryRow {
+    pub namespace: String,
+    pub name: String,
+    pub state: String,
+    pub ready: String,
+    pub age: String,
+    pub last_error: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    Table,
+    Json,
+    Yaml,
+}
+
+/// Coarse age in the style of kubectl, e.g. 42s, 7m, 3h, 12d
+pub fn format_age(created: DateTime<Utc>, now: DateTime<Utc>) -> String {
+    let secs = (now - created).num_seconds().max(0);
+    match secs {
+        s if s < 60 => format!("{}s", s),
+        s if s < 3600 => format!("{}m", s / 60),
+        s if s < 86_400 => format!("{}h", s / 3600),
+        s => format!("{}d", s / 86_400),
+    }
+}
+
+/// Reason and message of the most recently changed False condition
+pub fn last_error(myapp: &MyApp) -> Option<String> {
+    myapp
+        .status
+        .as_ref()?
+        .conditions
+        .iter()
+        .filter(|c| c.status == "False")
+        .max_by(|a, b| a.last_transition_time.cmp(&b.last_transition_time))
+        .map(|c| format!("{}: {}", c.reason, c.message))
+}
+
+pub fn build_row(myapp: &MyApp, now: DateTime<Utc>) -> SummaryRow {
+    let status = myapp.status.as_ref();
+
+    SummaryRow {
+        namespace: myapp.namespace().unwrap_or_default(),
+        name: myapp.name_any(),
+        state: status
+            .map(|s| s.state.clone())
+            .unwrap_or_else(|| "Pending".to_string()),
+        ready: status
+            .and_then(|s| s.ready.clone())
+            .unwrap_or_else(|| format!("0/{}", myapp.spec.replicas)),
+        age: myapp
+            .creation_timestamp()
+            .map(|t| format_age(t.0, now))
+            .unwrap_or_else(|| "-".to_string()),
+        last_error: last_error(myapp),
+    }
+}
+
+fn table(rows: &[SummaryRow]) -> String {
+    let header = ["NAMESPACE", "NAME", "STATE", "READY", "AGE", "LAST ERROR"];
+    let cells: Vec<[&str; 6]> = rows
+        .iter()
+        .map(|r| {
+            [
+                r.namespace.as_str(),
+                r.name.as_str(),
+                r.state.as_str(),
+                r.ready.as_str(),
+                r.age.as_str(),
+                r.last_error.as_deref().unwrap_or("<none>"),
+            ]
+        })
+        .collect();
+
+    let mut widths = header.map(str::len);
+    for row in &cells {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.len());
+        }
+    }
+
+    std::iter::once(header)
+        .chain(cells)
+        .map(|row| {
+            let line: Vec<String> = row
+                .iter()
+                .zip(widths)
+                .map(|(cell, width)| format!("{:<width$}", cell, width = width))
+                .collect();
+            format!("{}\n", line.join("   ").trim_end())
+        })
+        .collect()
+}
+
+pub fn render(
+    rows: &[SummaryRow],
+    format: OutputFormat,
+) -> Result<String, Box<dyn std::error::Error>> {
+    Ok(match format {
+        OutputFormat::Table => table(rows),
+        OutputFormat::Json => serde_json::to_string_pretty(rows)? + "\n",
+        OutputFormat::Yaml => serde_yaml::to_string(rows)?,
+    })
+}
+
+/// `status [-n <namespace>] [-o table|json|yaml]`
+pub async fn run(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let mut namespace = None;
+    let mut format = OutputFormat::Table;
+
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--all-namespaces" | "-A" => namespace = None,
+            "-n" | "--namespace" => namespace = iter.next().cloned(),
+            "-o" | "--output" => {
+                format = match iter.next().map(String::as_str) {
+                    Some("table") => OutputFormat::Table,
+                    Some("json") => OutputFormat::Json,
+                    Some("yaml") => OutputFormat::Yaml,
+                    other => return Err(format!("unsupported output format: {:?}", other).into()),
+                }
+            }
+            other => return Err(format!("unknown status argument: {}", other).into()),
+        }
+    }
+
+    let client = crate::kubectl::client().await?;
+    let api: Api<MyApp> = match namespace {
+        Some(ns) => Api::namespaced(client, &ns),
+        None => Api::all(client),
+    };
+
+    let now = Utc::now();
+    let mut rows: Vec<SummaryRow> = api
+        .list(&ListParams::default())
+        .await?
+        .items
+        .iter()
+        .map(|app| build_row(app, now))
+        .collect();
+    rows.sort_by(|a, b| (&a.namespace, &a.name).cmp(&(&b.namespace, &b.name)));
+
+    print!("{}", render(&rows, format)?);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Condition, MyAppSpec, MyAppStatus};
+
+    fn sample() -> MyApp {
+        let mut app = MyApp::new(
+            "web",
+            MyAppSpec {
+                replicas: 3,
+                image: "nginx:1.25".to_string(),
+                ..Default::default()
+            },
+        );
+        app.metadata.namespace = Some("shop".to_string());
+        app
+    }
+
+    #[test]
+    fn test_last_error_picks_latest_false_condition() {
+        let mut app = sample();
+        let mut old = Condition::ready(false, "ImagePullBackOff", "pull failed");
+        old.last_transition_time = "2026-01-01T00:00:00Z".to_string();
+        let mut new = Condition::new("Available", false, "MinimumReplicas", "1/3 ready");
+        new.last_transition_time = "2026-01-02T00:00:00Z".to_string();
+        app.status = Some(MyAppStatus {
+            state: "Degraded".to_string(),
+            conditions: vec![old, new],
+            ..Default::default()
+        });
+
+        let row = build_row(&app, Utc::now());
+        assert_eq!(
+            row.last_error.as_deref(),
+            Some("MinimumReplicas: 1/3 ready")
+        );
+        assert_eq!(row.ready, "0/3");
+    }
+
+    #[test]
+    fn test_render_table() {
+        let rows = vec![build_row(&sample(), Utc::now())];
+        let out = render(&rows, OutputFormat::Table).unwrap();
+
+        let mut lines = out.lines();
+        assert!(lines
+            .next()
+            .unwrap()
+            .starts_with("NAMESPACE   NAME   STATE"));
+        assert_eq!(
+            lines.next().unwrap(),
+            "shop        web    Pending   0/3     -     <none>"
+        );
+        assert_eq!(
+            format_age(Utc::now() - chrono::Duration::hours(50), Utc::now()),
+            "2d"
+        );
+    }
+}
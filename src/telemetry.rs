@@ -0,0 +1,98 @@
+// OpenTelemetry tracing for MyApp Controller
+// Exports reconcile and webhook spans over OTLP/HTTP when OTEL_EXPORTER_OTLP_ENDPOINT is set;
+// their trace IDs become exemplars on the duration histograms
+
+use opentelemetry::trace::{TraceContextExt, TracerProvider as _};
+use opentelemetry_otlp::SpanExporter;
+use opentelemetry_sdk::trace::SdkTracerProvider;
+use opentelemetry_sdk::Resource;
+use std::sync::OnceLock;
+use tracing::{Level, Subscriber};
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+use tracing_subscriber::filter::Targets;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::Layer;
+
+static PROVIDER: OnceLock<SdkTracerProvider> = OnceLock::new();
+
+/// Span exporter for the controller's own spans, when `OTEL_EXPORTER_OTLP_ENDPOINT` is set;
+/// `OTEL_SERVICE_NAME` defaults to myapp-controller
+pub fn layer<S>() -> Option<impl Layer<S>>
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    std::env::var("OTEL_EXPORTER_OTLP_ENDPOINT").ok()?;
+    let exporter = match SpanExporter::builder().with_http().build() {
+        Ok(exporter) => exporter,
+        Err(e) => {
+            eprintln!(
+                "OpenTelemetry disabled, building the OTLP exporter failed: {}",
+                e
+            );
+            return None;
+        }
+    };
+    let service =
+        std::env::var("OTEL_SERVICE_NAME").unwrap_or_else(|_| "myapp-controller".to_string());
+    let provider = SdkTracerProvider::builder()
+        .with_batch_exporter(exporter)
+        .with_resource(Resource::builder().with_service_name(service).build())
+        .build();
+    let tracer = provider.tracer("myapp-controller");
+    let _ = PROVIDER.set(provider);
+
+    // Only this crate's spans; the exporter's own HTTP client must not trace itself
+    let targets = Targets::new().with_target(env!("CARGO_CRATE_NAME"), Level::INFO);
+    Some(
+        tracing_opentelemetry::layer()
+            .with_tracer(tracer)
+            .with_filter(targets),
+    )
+}
+
+/// Trace ID of the current span, if it is being exported
+pub fn current_trace_id() -> Option<String> {
+    let context = tracing::Span::current().context();
+    let span = context.span();
+    let span_context = span.span_context();
+    span_context
+        .is_sampled()
+        .then(|| span_context.trace_id().to_string())
+}
+
+/// Flush buffered spans before exit
+pub fn shutdown() {
+    if let Some(provider) = PROVIDER.get() {
+        if let Err(e) = provider.shutdown() {
+            tracing::warn!("Flushing OpenTelemetry spans failed: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_no_trace_without_exporter() {
+        let span = tracing::info_span!("reconcile");
+        let _entered = span.enter();
+        assert_eq!(current_trace_id(), None);
+    }
+
+    #[test]
+    fn test_trace_id_of_exported_span() {
+        use tracing_subscriber::layer::SubscriberExt;
+
+        let provider = SdkTracerProvider::builder().build();
+        let subscriber = tracing_subscriber::registry()
+            .with(tracing_opentelemetry::layer().with_tracer(provider.tracer("test")));
+        tracing::subscriber::with_default(subscriber, || {
+            let span = tracing::info_span!("reconcile");
+            let _entered = span.enter();
+            let trace_id = current_trace_id().unwrap();
+            assert_eq!(trace_id.len(), 32);
+            assert_ne!(trace_id, "0".repeat(32));
+        });
+    }
+}
@@ -0,0 +1,319 @@
+// API server throttling for MyApp Controller
+// Watches for 429 responses and holds back all reconciles for the advised Retry-After, and
+// applies the client-side request budget (QPS and burst) and timeouts. Pauses are reported
+// as a Throttled condition on the MyAppOperatorConfig status
+
+use crate::metrics::MetricsCollector;
+use crate::namespace_rate::{Bucket, Rate};
+use crate::operator_config::ConfigStatus;
+use crate::Condition;
+use http::{header::RETRY_AFTER, Response, StatusCode};
+use kube::client::ClientBuilder;
+use kube::{Client, Config};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{ready, Context, Poll};
+use std::time::{Duration, Instant};
+use tower::util::{MapErrLayer, MapResponseLayer};
+use tower::{BoxError, Layer, Service};
+
+/// Delay used when a 429 carries no usable Retry-After
+const DEFAULT_RETRY_AFTER: Duration = Duration::from_secs(1);
+
+/// Longest pause honored from a single Retry-After
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(60);
+
+/// How long Throttled stays True after a pause ends, so short pauses are still seen
+const THROTTLED_HOLD: Duration = Duration::from_secs(60);
+
+pub const THROTTLED_CONDITION: &str = "Throttled";
+
+/// Retry-After in seconds; HTTP-date values fall back to the default
+pub fn parse_retry_after(value: Option<&str>) -> Duration {
+    value
+        .and_then(|v| v.trim().parse::<u64>().ok())
+        .map(Duration::from_secs)
+        .unwrap_or(DEFAULT_RETRY_AFTER)
+        .clamp(DEFAULT_RETRY_AFTER, MAX_RETRY_AFTER)
+}
+
+/// Client-side request budget and timeouts of the controller's API client
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ClientTuning {
+    /// Requests per second and burst; None leaves requests unthrottled
+    pub rate: Option<Rate>,
+    /// None keeps kube's defaults (30s connect, 295s read and write)
+    pub connect_timeout: Option<Duration>,
+    pub read_timeout: Option<Duration>,
+    pub write_timeout: Option<Duration>,
+}
+
+impl ClientTuning {
+    /// Read `MYAPP_CLIENT_QPS`, `MYAPP_CLIENT_BURST` (default 2x QPS) and
+    /// `MYAPP_CLIENT_{CONNECT,READ,WRITE}_TIMEOUT_SECS`
+    pub fn from_env() -> Result<Self, String> {
+        fn parse<T: std::str::FromStr>(var: &str) -> Result<Option<T>, String> {
+            match std::env::var(var) {
+                Ok(value) if !value.trim().is_empty() => value
+                    .trim()
+                    .parse()
+                    .map(Some)
+                    .map_err(|_| format!("{}: invalid value '{}'", var, value)),
+                _ => Ok(None),
+            }
+        }
+        let secs = |var: &str| Ok::<_, String>(parse::<u64>(var)?.map(Duration::from_secs));
+
+        let qps = parse::<f64>("MYAPP_CLIENT_QPS")?.unwrap_or(0.0);
+        let burst = parse::<u32>("MYAPP_CLIENT_BURST")?.or(Some((qps * 2.0).ceil() as u32));
+        Ok(Self {
+            rate: Rate::new(qps, burst),
+            connect_timeout: secs("MYAPP_CLIENT_CONNECT_TIMEOUT_SECS")?,
+            read_timeout: secs("MYAPP_CLIENT_READ_TIMEOUT_SECS")?,
+            write_timeout: secs("MYAPP_CLIENT_WRITE_TIMEOUT_SECS")?,
+        })
+    }
+
+    /// Apply the timeouts; watches are long polls, so a read timeout shorter than the
+    /// watch timeout (290s) makes them fail and restart
+    pub fn apply(&self, config: &mut Config) {
+        if let Some(timeout) = self.connect_timeout {
+            config.connect_timeout = Some(timeout);
+        }
+        if let Some(timeout) = self.read_timeout {
+            config.read_timeout = Some(timeout);
+        }
+        if let Some(timeout) = self.write_timeout {
+            config.write_timeout = Some(timeout);
+        }
+    }
+}
+
+/// Holds requests back until the client's token bucket has a token
+#[derive(Clone)]
+pub struct RateLimitLayer {
+    rate: Rate,
+    metrics: MetricsCollector,
+}
+
+pub struct RateLimited<S> {
+    inner: S,
+    rate: Rate,
+    bucket: Arc<Mutex<Bucket>>,
+    /// Waiting for the next token
+    sleep: Option<Pin<Box<tokio::time::Sleep>>>,
+    /// A token was taken for the next call
+    has_token: bool,
+    metrics: MetricsCollector,
+}
+
+impl<S> Layer<S> for RateLimitLayer {
+    type Service = RateLimited<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RateLimited {
+            inner,
+            rate: self.rate,
+            bucket: Arc::new(Mutex::new(Bucket::new(self.rate, Instant::now()))),
+            sleep: None,
+            has_token: false,
+            metrics: self.metrics.clone(),
+        }
+    }
+}
+
+impl<S, R> Service<R> for RateLimited<S>
+where
+    S: Service<R>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        while !self.has_token {
+            if let Some(sleep) = self.sleep.as_mut() {
+                ready!(sleep.as_mut().poll(cx));
+                self.sleep = None;
+            }
+            match self.bucket.lock().unwrap().take(self.rate, Instant::now()) {
+                Ok(()) => self.has_token = true,
+                Err(delay) => {
+                    self.metrics.record_client_rate_limited(delay);
+                    self.sleep = Some(Box::pin(tokio::time::sleep(delay)));
+                }
+            }
+        }
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: R) -> Self::Future {
+        self.has_token = false;
+        self.inner.call(request)
+    }
+}
+
+/// Whether a client error was a connect, read or write timeout
+pub fn is_timeout(error: &(dyn std::error::Error + 'static)) -> bool {
+    let mut source = Some(error);
+    while let Some(error) = source {
+        if error
+            .downcast_ref::<std::io::Error>()
+            .is_some_and(|e| e.kind() == std::io::ErrorKind::TimedOut)
+        {
+            return true;
+        }
+        source = error.source();
+    }
+    false
+}
+
+/// Pushback from the API server, shared by every request and reconcile
+#[derive(Clone, Default)]
+pub struct ApiThrottle {
+    until: Arc<Mutex<Option<Instant>>>,
+}
+
+impl ApiThrottle {
+    /// Hold back requests for `delay`, extending any pause already in effect
+    pub fn record(&self, delay: Duration) {
+        let deadline = Instant::now() + delay;
+        let mut until = self.until.lock().unwrap();
+        if until.is_none_or(|t| t < deadline) {
+            *until = Some(deadline);
+        }
+    }
+
+    /// Time left before the API server should be called again
+    pub fn remaining(&self) -> Option<Duration> {
+        self.until
+            .lock()
+            .unwrap()
+            .map(|t| t.saturating_duration_since(Instant::now()))
+            .filter(|d| !d.is_zero())
+    }
+
+    /// Throttled while a pause is in effect or ended less than a minute ago
+    pub fn condition(&self) -> Condition {
+        let until = *self.until.lock().unwrap();
+        if until.is_some_and(|t| Instant::now() < t + THROTTLED_HOLD) {
+            Condition::new(
+                THROTTLED_CONDITION,
+                true,
+                "TooManyRequests",
+                "The API server answered with 429 in the last minute; reconciles wait out its Retry-After",
+            )
+        } else {
+            Condition::new(
+                THROTTLED_CONDITION,
+                false,
+                "NotThrottled",
+                "No 429 responses from the API server in the last minute",
+            )
+        }
+    }
+
+    /// Publish the Throttled condition every `interval`; only changes are written
+    pub async fn report(self, status: ConfigStatus, interval: Duration) {
+        loop {
+            status.set_replica_condition(self.condition()).await;
+            tokio::time::sleep(interval).await;
+        }
+    }
+
+    /// Wait out the current pause, returning how long that took
+    pub async fn wait(&self) -> Duration {
+        let mut waited = Duration::ZERO;
+        while let Some(remaining) = self.remaining() {
+            tokio::time::sleep(remaining).await;
+            waited += remaining;
+        }
+        waited
+    }
+
+    /// Kube client that reports 429 responses to this throttle and keeps to `tuning`
+    pub async fn client(
+        &self,
+        tuning: ClientTuning,
+        metrics: MetricsCollector,
+    ) -> Result<Client, kube::Error> {
+        let mut config = Config::infer().await.map_err(kube::Error::InferConfig)?;
+        tuning.apply(&mut config);
+        let timeouts = {
+            let metrics = metrics.clone();
+            MapErrLayer::new(move |error: BoxError| {
+                if is_timeout(error.as_ref()) {
+                    metrics.record_client_timeout();
+                }
+                error
+            })
+        };
+        let rate_limit = tuning.rate.map(|rate| RateLimitLayer {
+            rate,
+            metrics: metrics.clone(),
+        });
+        let throttle = self.clone();
+        let layer = MapResponseLayer::new(move |resp: Response<_>| {
+            if resp.status() == StatusCode::TOO_MANY_REQUESTS {
+                let retry_after = parse_retry_after(
+                    resp.headers()
+                        .get(RETRY_AFTER)
+                        .and_then(|v| v.to_str().ok()),
+                );
+                tracing::warn!(
+                    "API server throttled the controller, pausing {:?}",
+                    retry_after
+                );
+                metrics.record_throttled(retry_after);
+                throttle.record(retry_after);
+            }
+            resp
+        });
+        let builder = ClientBuilder::try_from(config)?
+            .with_layer(&layer)
+            .with_layer(&timeouts);
+        Ok(builder
+            .with_layer(&tower::util::option_layer(rate_limit))
+            .build())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_retry_after() {
+        assert_eq!(parse_retry_after(Some("5")), Duration::from_secs(5));
+        assert_eq!(parse_retry_after(Some("600")), MAX_RETRY_AFTER);
+        assert_eq!(parse_retry_after(Some("0")), DEFAULT_RETRY_AFTER);
+        assert_eq!(
+            parse_retry_after(Some("Wed, 21 Oct 2015 07:28:00 GMT")),
+            DEFAULT_RETRY_AFTER
+        );
+        assert_eq!(parse_retry_after(None), DEFAULT_RETRY_AFTER);
+    }
+
+    #[test]
+    fn test_is_timeout() {
+        let timeout = std::io::Error::new(std::io::ErrorKind::TimedOut, "read timed out");
+        let wrapped: BoxError = Box::new(kube::Error::Service(Box::new(timeout)));
+        assert!(is_timeout(wrapped.as_ref()));
+
+        let refused = std::io::Error::new(std::io::ErrorKind::ConnectionRefused, "refused");
+        assert!(!is_timeout(&refused));
+    }
+
+    #[tokio::test]
+    async fn test_throttle_extends_pause() {
+        let throttle = ApiThrottle::default();
+        assert!(throttle.remaining().is_none());
+        assert_eq!(throttle.condition().status, "False");
+
+        throttle.record(Duration::from_secs(10));
+        throttle.record(Duration::from_secs(1));
+        assert!(throttle.remaining().unwrap() > Duration::from_secs(5));
+        assert_eq!(throttle.condition().reason, "TooManyRequests");
+    }
+}
@@ -0,0 +1,175 @@
+// TLS settings for the admission webhook server
+// The API server only calls webhooks over HTTPS; certificates come from files,
+// usually a mounted Secret, or are swapped in at runtime as cert-manager renews them
+
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use tokio_rustls::rustls::crypto::ring;
+use tokio_rustls::rustls::server::{ClientHello, ResolvesServerCert};
+use tokio_rustls::rustls::sign::CertifiedKey;
+use tokio_rustls::rustls::ServerConfig;
+use tokio_rustls::TlsAcceptor;
+
+/// Directory of the mounted webhook certificate Secret
+const DEFAULT_CERT_DIR: &str = "/etc/webhook/certs";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TlsConfig {
+    pub cert_path: PathBuf,
+    pub key_path: PathBuf,
+    /// CA bundle used to verify client certificates
+    pub client_ca_path: Option<PathBuf>,
+    /// Reject clients without a certificate signed by the client CA
+    pub client_auth_required: bool,
+}
+
+impl TlsConfig {
+    /// Certificate files from `TLS_CERT_FILE`/`TLS_KEY_FILE`, defaulting to `tls.crt`/`tls.key`
+    /// in `TLS_CERT_DIR` (/etc/webhook/certs); `TLS_CLIENT_CA_FILE` turns on client
+    /// verification, `TLS_CLIENT_AUTH=optional` also admits clients without a certificate.
+    /// `MYAPP_WEBHOOK_INSECURE=true` serves plain HTTP, for local testing only.
+    pub fn from_env() -> Result<Option<Self>, String> {
+        let var = |name: &str| std::env::var(name).ok().filter(|v| !v.is_empty());
+        if var("MYAPP_WEBHOOK_INSECURE").is_some_and(|v| v == "true") {
+            tracing::warn!("Serving webhooks over plain HTTP; the API server will not call them");
+            return Ok(None);
+        }
+
+        let dir = PathBuf::from(var("TLS_CERT_DIR").unwrap_or_else(|| DEFAULT_CERT_DIR.into()));
+        let config = Self {
+            cert_path: var("TLS_CERT_FILE").map_or_else(|| dir.join("tls.crt"), PathBuf::from),
+            key_path: var("TLS_KEY_FILE").map_or_else(|| dir.join("tls.key"), PathBuf::from),
+            client_ca_path: var("TLS_CLIENT_CA_FILE").map(PathBuf::from),
+            client_auth_required: match var("TLS_CLIENT_AUTH").as_deref() {
+                None | Some("required") => true,
+                Some("optional") => false,
+                Some(other) => return Err(format!("unknown TLS_CLIENT_AUTH '{}'", other)),
+            },
+        };
+        config.check_files()?;
+        Ok(Some(config))
+    }
+
+    fn check_files(&self) -> Result<(), String> {
+        let paths = [
+            Some(&self.cert_path),
+            Some(&self.key_path),
+            self.client_ca_path.as_ref(),
+        ];
+        match paths.into_iter().flatten().find(|p| !Path::new(p).is_file()) {
+            Some(missing) => Err(format!(
+                "webhook TLS file {} not found (mount the certificate Secret or set TLS_CERT_FILE/TLS_KEY_FILE)",
+                missing.display()
+            )),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Where the webhook serving certificate comes from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CertSource {
+    /// Mounted files, see `TlsConfig::from_env`
+    Files,
+    /// Issued by the webhook itself, see `certs::CertBootstrap`
+    SelfSigned,
+    /// A cert-manager Certificate, see `cert_manager::CertManager`
+    CertManager,
+}
+
+impl CertSource {
+    /// `--cert-source=files|self-signed|cert-manager`; without the flag
+    /// `MYAPP_WEBHOOK_CERT_BOOTSTRAP=true` selects self-signed
+    pub fn from_args(args: &[String]) -> Result<Self, String> {
+        let flag = args.iter().find_map(|a| a.strip_prefix("--cert-source="));
+        match flag {
+            Some("files") => Ok(Self::Files),
+            Some("self-signed") => Ok(Self::SelfSigned),
+            Some("cert-manager") => Ok(Self::CertManager),
+            Some(other) => Err(format!("unknown --cert-source '{}'", other)),
+            None if std::env::var("MYAPP_WEBHOOK_CERT_BOOTSTRAP").is_ok_and(|v| v == "true") => {
+                Ok(Self::SelfSigned)
+            }
+            None => Ok(Self::Files),
+        }
+    }
+}
+
+/// Serving certificate that can be replaced while the listener keeps running
+#[derive(Debug, Default)]
+pub struct ReloadableCert {
+    current: RwLock<Option<Arc<CertifiedKey>>>,
+}
+
+impl ReloadableCert {
+    /// Swap in a PEM certificate chain and private key; new handshakes use it at once
+    pub fn load_pem(&self, cert: &[u8], key: &[u8]) -> Result<(), String> {
+        let chain = rustls_pemfile::certs(&mut &cert[..])
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("invalid certificate: {}", e))?;
+        if chain.is_empty() {
+            return Err("no certificate in PEM data".to_string());
+        }
+        let key = rustls_pemfile::private_key(&mut &key[..])
+            .map_err(|e| format!("invalid private key: {}", e))?
+            .ok_or("no private key in PEM data")?;
+        let key = ring::sign::any_supported_type(&key)
+            .map_err(|e| format!("unsupported private key: {}", e))?;
+        *self.current.write().unwrap() = Some(Arc::new(CertifiedKey::new(chain, key)));
+        Ok(())
+    }
+
+    /// TLS acceptor resolving the current certificate on every handshake
+    pub fn acceptor(self: &Arc<Self>) -> TlsAcceptor {
+        let mut config = ServerConfig::builder_with_provider(Arc::new(ring::default_provider()))
+            .with_safe_default_protocol_versions()
+            .expect("ring supports the default TLS versions")
+            .with_no_client_auth()
+            .with_cert_resolver(self.clone());
+        config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+        TlsAcceptor::from(Arc::new(config))
+    }
+}
+
+impl ResolvesServerCert for ReloadableCert {
+    fn resolve(&self, _hello: ClientHello<'_>) -> Option<Arc<CertifiedKey>> {
+        self.current.read().unwrap().clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_files() {
+        let dir = std::env::temp_dir().join(format!("myapp-tls-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("tls.crt"), "cert").unwrap();
+        std::fs::write(dir.join("tls.key"), "key").unwrap();
+
+        let mut config = TlsConfig {
+            cert_path: dir.join("tls.crt"),
+            key_path: dir.join("tls.key"),
+            client_ca_path: None,
+            client_auth_required: true,
+        };
+        assert!(config.check_files().is_ok());
+
+        config.client_ca_path = Some(dir.join("ca.crt"));
+        assert!(config.check_files().unwrap_err().contains("ca.crt"));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_reloadable_cert() {
+        let names = vec!["myapp-webhook.default.svc".to_string()];
+        let bundle = crate::certs::issue(&names, None).unwrap();
+        let cert = ReloadableCert::default();
+        assert!(cert.load_pem(b"junk", b"junk").is_err());
+        assert!(cert.current.read().unwrap().is_none());
+        cert.load_pem(bundle.tls_cert.as_bytes(), bundle.tls_key.as_bytes())
+            .unwrap();
+        assert!(cert.current.read().unwrap().is_some());
+    }
+}
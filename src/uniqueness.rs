@@ -0,0 +1,135 @@
+// Uniqueness policy for MyApp admission
+// Detects MyApps that would collide with an existing app elsewhere in the cluster
+
+use crate::MyApp;
+use kube::ResourceExt;
+use std::sync::Arc;
+
+/// Cluster-wide uniqueness policy enforced by the validating webhook
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum UniquenessPolicy {
+    /// No uniqueness checks (default)
+    #[default]
+    Disabled,
+    /// App names must be unique across all namespaces
+    Name,
+    /// The combination of app name and image must be unique across all namespaces
+    ImageAndName,
+}
+
+impl UniquenessPolicy {
+    /// Read the policy from `MYAPP_UNIQUENESS_POLICY` (none, name, image-name)
+    pub fn from_env() -> Self {
+        std::env::var("MYAPP_UNIQUENESS_POLICY")
+            .map(|v| Self::parse(&v))
+            .unwrap_or_default()
+    }
+
+    pub fn parse(value: &str) -> Self {
+        match value.trim().to_lowercase().as_str() {
+            "name" => Self::Name,
+            "image-name" | "image+name" => Self::ImageAndName,
+            _ => Self::Disabled,
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        *self != Self::Disabled
+    }
+
+    /// Return a denial message if `candidate` collides with any of the `existing` apps
+    pub fn find_collision<'a>(
+        &self,
+        candidate: &MyApp,
+        existing: impl IntoIterator<Item = &'a Arc<MyApp>>,
+    ) -> Option<String> {
+        if !self.is_enabled() {
+            return None;
+        }
+
+        let name = candidate.name_any();
+        let namespace = candidate.namespace().unwrap_or_default();
+
+        existing
+            .into_iter()
+            .filter(|other| {
+                // The object being admitted is never a collision with itself
+                !(other.name_any() == name && other.namespace().unwrap_or_default() == namespace)
+            })
+            .find(|other| match self {
+                Self::Disabled => false,
+                Self::Name => other.name_any() == name,
+                Self::ImageAndName => {
+                    other.name_any() == name && other.spec.image == candidate.spec.image
+                }
+            })
+            .map(|other| {
+                format!(
+                    "MyApp {}/{} already uses name '{}'{}",
+                    other.namespace().unwrap_or_default(),
+                    other.name_any(),
+                    name,
+                    if *self == Self::ImageAndName {
+                        format!(" with image '{}'", candidate.spec.image)
+                    } else {
+                        String::new()
+                    }
+                )
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MyAppSpec;
+
+    fn app(namespace: &str, name: &str, image: &str) -> Arc<MyApp> {
+        let mut app = MyApp::new(
+            name,
+            MyAppSpec {
+                replicas: 1,
+                image: image.to_string(),
+                ..Default::default()
+            },
+        );
+        app.metadata.namespace = Some(namespace.to_string());
+        Arc::new(app)
+    }
+
+    #[test]
+    fn test_name_policy_detects_cross_namespace_collision() {
+        let existing = vec![app("team-a", "web", "nginx:1.25")];
+        let candidate = app("team-b", "web", "httpd:2.4");
+
+        assert!(UniquenessPolicy::Name
+            .find_collision(&candidate, &existing)
+            .is_some());
+        assert!(UniquenessPolicy::ImageAndName
+            .find_collision(&candidate, &existing)
+            .is_none());
+        assert!(UniquenessPolicy::Disabled
+            .find_collision(&candidate, &existing)
+            .is_none());
+    }
+
+    #[test]
+    fn test_same_object_is_not_a_collision() {
+        let existing = vec![app("team-a", "web", "nginx:1.25")];
+        let candidate = app("team-a", "web", "nginx:1.25");
+
+        assert!(UniquenessPolicy::ImageAndName
+            .find_collision(&candidate, &existing)
+            .is_none());
+    }
+
+    #[test]
+    fn test_parse_policy() {
+        assert_eq!(UniquenessPolicy::parse("name"), UniquenessPolicy::Name);
+        assert_eq!(
+            UniquenessPolicy::parse("image-name"),
+            UniquenessPolicy::ImageAndName
+        );
+        assert_eq!(UniquenessPolicy::parse(""), UniquenessPolicy::Disabled);
+    }
+}
@@ -0,0 +1,396 @@
+// HashiCorp Vault integration for MyApp Controller
+// spec.vaultSecrets names Vault paths whose keys are synced into an owned Secret
+// `<myapp>-<name>` that the app container reads through envFrom. The controller logs in
+// with Vault's Kubernetes auth method using a short-lived token for the app namespace's
+// default service account, so Vault policy decides what each namespace may read. Secrets
+// are re-read every MYAPP_VAULT_RESYNC_SECS, and a changed value restarts the pods.
+
+use crate::deletion::{owned, MANAGED_BY_SELECTOR};
+use crate::spec_hash::fnv1a;
+use crate::{app_labels, child_name, create_owner_reference, MyApp, ReconcileError};
+use k8s_openapi::api::apps::v1::Deployment;
+use k8s_openapi::api::authentication::v1::{TokenRequest, TokenRequestSpec};
+use k8s_openapi::api::core::v1::{Secret, ServiceAccount};
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta;
+use k8s_openapi::ByteString;
+use kube::api::{Api, Patch, PatchParams, PostParams};
+use kube::{Client, ResourceExt};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::time::Duration;
+use thiserror::Error;
+
+/// Hash of the synced values, on the Secret and on the pod template it restarts
+pub const VAULT_HASH_ANNOTATION: &str = "myapps.example.com/vault-hash";
+
+/// Separate field manager, so the Deployment's own apply leaves the restart stamp alone
+const RESTART_MANAGER: &str = "myapp-controller-vault";
+const SERVICE_ACCOUNT: &str = "default";
+const TOKEN_TTL_SECS: i64 = 600;
+
+#[derive(Error, Debug)]
+pub enum VaultError {
+    #[error("Vault request failed: {0}")]
+    Http(#[from] reqwest::Error),
+
+    #[error("Vault returned {status} for {path}")]
+    Status {
+        status: reqwest::StatusCode,
+        path: String,
+    },
+
+    #[error("Requesting a service account token failed: {0}")]
+    Kube(#[from] kube::Error),
+
+    #[error("{0}")]
+    Response(String),
+}
+
+/// One Vault path synced into a Secret named `<myapp>-<name>`
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct VaultSecret {
+    pub name: String,
+
+    /// Path under the Vault API, e.g. `secret/data/web` for KV v2
+    pub path: String,
+
+    /// Vault Kubernetes auth role bound to the namespace's default service account
+    pub role: String,
+
+    /// Keys to copy; all keys when empty
+    #[serde(default)]
+    pub keys: Vec<String>,
+}
+
+pub fn validate(secrets: &[VaultSecret], generated: &[String]) -> Result<(), String> {
+    for (i, secret) in secrets.iter().enumerate() {
+        if secret.name.is_empty()
+            || !secret
+                .name
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+        {
+            return Err(format!(
+                "vaultSecrets: '{}' must be lowercase letters, digits and '-'",
+                secret.name
+            ));
+        }
+        if secrets[..i].iter().any(|s| s.name == secret.name) || generated.contains(&secret.name) {
+            return Err(format!(
+                "vaultSecrets: {} is already used by another Secret of this MyApp",
+                secret.name
+            ));
+        }
+        if secret.path.trim_matches('/').is_empty() || secret.role.is_empty() {
+            return Err(format!(
+                "vaultSecrets: {} needs a path and a role",
+                secret.name
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Names of the Secrets the container should read, in spec order
+pub fn secret_names(myapp: &MyApp) -> Vec<String> {
+    myapp
+        .spec
+        .vault_secrets
+        .iter()
+        .map(|s| child_name(myapp, &s.name))
+        .collect()
+}
+
+/// Key/value pairs of a read response; KV v2 nests them under `data.data`
+pub fn secret_data(body: &Value) -> Option<BTreeMap<String, String>> {
+    let data = &body["data"];
+    let data = match (&data["data"], &data["metadata"]) {
+        (Value::Object(_), Value::Object(_)) => &data["data"],
+        _ => data,
+    };
+    Some(
+        data.as_object()?
+            .iter()
+            .map(|(k, v)| {
+                let value = match v {
+                    Value::String(s) => s.clone(),
+                    other => other.to_string(),
+                };
+                (k.clone(), value)
+            })
+            .collect(),
+    )
+}
+
+/// The requested keys of `data`; a missing key is an error rather than an empty variable
+fn select(
+    data: BTreeMap<String, String>,
+    keys: &[String],
+    path: &str,
+) -> Result<BTreeMap<String, String>, VaultError> {
+    if keys.is_empty() {
+        return Ok(data);
+    }
+    keys.iter()
+        .map(|key| match data.get(key) {
+            Some(value) => Ok((key.clone(), value.clone())),
+            None => Err(VaultError::Response(format!("{} has no key {}", path, key))),
+        })
+        .collect()
+}
+
+fn data_hash(data: &BTreeMap<String, String>) -> String {
+    let json = serde_json::to_vec(data).unwrap_or_default();
+    format!("{:016x}", fnv1a(&json))
+}
+
+/// Connection to Vault, configured through `MYAPP_VAULT_ADDR`
+#[derive(Clone)]
+pub struct VaultClient {
+    http: reqwest::Client,
+    addr: String,
+    auth_path: String,
+    audience: String,
+    /// How often synced Secrets are re-read
+    pub resync: Duration,
+}
+
+impl VaultClient {
+    /// `MYAPP_VAULT_ADDR`, `MYAPP_VAULT_AUTH_PATH` (default kubernetes),
+    /// `MYAPP_VAULT_AUDIENCE` (default vault) and `MYAPP_VAULT_RESYNC_SECS` (default 300);
+    /// None when no address is set
+    pub fn from_env() -> Option<Self> {
+        let addr = std::env::var("MYAPP_VAULT_ADDR").ok()?;
+        let var = |name: &str, default: &str| std::env::var(name).unwrap_or(default.to_string());
+        Some(Self {
+            http: reqwest::Client::builder()
+                .timeout(Duration::from_secs(10))
+                .build()
+                .expect("failed to build Vault HTTP client"),
+            addr: addr.trim_end_matches('/').to_string(),
+            auth_path: var("MYAPP_VAULT_AUTH_PATH", "kubernetes"),
+            audience: var("MYAPP_VAULT_AUDIENCE", "vault"),
+            resync: Duration::from_secs(
+                var("MYAPP_VAULT_RESYNC_SECS", "300").parse().unwrap_or(300),
+            ),
+        })
+    }
+
+    /// Short-lived token for the namespace's default service account
+    async fn service_account_token(&self, client: Client, ns: &str) -> Result<String, VaultError> {
+        let request = TokenRequest {
+            spec: TokenRequestSpec {
+                audiences: vec![self.audience.clone()],
+                expiration_seconds: Some(TOKEN_TTL_SECS),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let issued = Api::<ServiceAccount>::namespaced(client, ns)
+            .create_token_request(SERVICE_ACCOUNT, &PostParams::default(), &request)
+            .await?;
+        issued
+            .status
+            .map(|s| s.token)
+            .ok_or_else(|| VaultError::Response("token request returned no token".to_string()))
+    }
+
+    async fn login(&self, role: &str, jwt: &str) -> Result<String, VaultError> {
+        let path = format!("auth/{}/login", self.auth_path);
+        let resp = self
+            .http
+            .post(format!("{}/v1/{}", self.addr, path))
+            .json(&serde_json::json!({ "role": role, "jwt": jwt }))
+            .send()
+            .await?;
+        if !resp.status().is_success() {
+            return Err(VaultError::Status {
+                status: resp.status(),
+                path,
+            });
+        }
+        let body: Value = resp.json().await?;
+        body["auth"]["client_token"]
+            .as_str()
+            .map(str::to_string)
+            .ok_or_else(|| VaultError::Response(format!("{} returned no client token", path)))
+    }
+
+    async fn read(&self, token: &str, path: &str) -> Result<BTreeMap<String, String>, VaultError> {
+        let path = path.trim_matches('/');
+        let resp = self
+            .http
+            .get(format!("{}/v1/{}", self.addr, path))
+            .header("X-Vault-Token", token)
+            .send()
+            .await?;
+        if !resp.status().is_success() {
+            return Err(VaultError::Status {
+                status: resp.status(),
+                path: path.to_string(),
+            });
+        }
+        secret_data(&resp.json().await?)
+            .ok_or_else(|| VaultError::Response(format!("{} holds no key/value data", path)))
+    }
+
+    /// Values for every spec.vaultSecrets entry, logging in once per role
+    async fn fetch(
+        &self,
+        myapp: &MyApp,
+        client: Client,
+    ) -> Result<Vec<BTreeMap<String, String>>, VaultError> {
+        let ns = myapp.namespace().unwrap();
+        let jwt = self.service_account_token(client, &ns).await?;
+        let mut tokens: BTreeMap<&str, String> = BTreeMap::new();
+        let mut out = Vec::new();
+        for secret in &myapp.spec.vault_secrets {
+            let token = match tokens.get(secret.role.as_str()) {
+                Some(token) => token.clone(),
+                None => {
+                    let token = self.login(&secret.role, &jwt).await?;
+                    tokens.insert(&secret.role, token.clone());
+                    token
+                }
+            };
+            let data = self.read(&token, &secret.path).await?;
+            out.push(select(data, &secret.keys, &secret.path)?);
+        }
+        Ok(out)
+    }
+}
+
+/// Write `data` to the Secret; true when it replaced different values
+async fn sync_secret(
+    api: &Api<Secret>,
+    myapp: &MyApp,
+    name: &str,
+    data: BTreeMap<String, String>,
+) -> Result<bool, kube::Error> {
+    let hash = data_hash(&data);
+    let previous = api
+        .get_opt(name)
+        .await?
+        .and_then(|s| s.annotations().get(VAULT_HASH_ANNOTATION).cloned());
+    if previous.as_deref() == Some(hash.as_str()) {
+        return Ok(false);
+    }
+    let secret = Secret {
+        metadata: ObjectMeta {
+            name: Some(name.to_string()),
+            namespace: myapp.namespace(),
+            labels: Some(app_labels(myapp)),
+            annotations: Some(BTreeMap::from([(VAULT_HASH_ANNOTATION.to_string(), hash)])),
+            owner_references: Some(vec![create_owner_reference(myapp)]),
+            ..Default::default()
+        },
+        type_: Some("Opaque".to_string()),
+        data: Some(
+            data.into_iter()
+                .map(|(k, v)| (k, ByteString(v.into_bytes())))
+                .collect(),
+        ),
+        ..Default::default()
+    };
+    api.patch(
+        name,
+        &PatchParams::apply("myapp-controller").force(),
+        &Patch::Apply(&secret),
+    )
+    .await?;
+    tracing::info!("Synced Vault values into Secret {}", name);
+    Ok(previous.is_some())
+}
+
+/// Stamp the pod templates of the MyApp's Deployments so their pods pick up new values
+async fn restart(myapp: &MyApp, client: Client, stamp: &str) -> Result<(), kube::Error> {
+    let api: Api<Deployment> = Api::namespaced(client, &myapp.namespace().unwrap());
+    let uid = myapp.uid().unwrap_or_default();
+    for deployment in owned(&api, MANAGED_BY_SELECTOR, &uid).await? {
+        let patch = serde_json::json!({
+            "apiVersion": "apps/v1",
+            "kind": "Deployment",
+            "metadata": { "name": deployment.name_any() },
+            "spec": { "template": { "metadata": { "annotations": {
+                VAULT_HASH_ANNOTATION: stamp
+            } } } }
+        });
+        api.patch(
+            &deployment.name_any(),
+            &PatchParams::apply(RESTART_MANAGER).force(),
+            &Patch::Apply(&patch),
+        )
+        .await?;
+        tracing::info!(
+            "Restarting Deployment {} for rotated Vault secrets",
+            deployment.name_any()
+        );
+    }
+    Ok(())
+}
+
+/// Sync every spec.vaultSecrets entry and restart the pods if a value changed
+pub async fn reconcile(
+    myapp: &MyApp,
+    vault: Option<&VaultClient>,
+    client: Client,
+) -> Result<(), ReconcileError> {
+    if myapp.spec.vault_secrets.is_empty() {
+        return Ok(());
+    }
+    let Some(vault) = vault else {
+        return Err(ReconcileError::SecretError(
+            "spec.vaultSecrets is set but the controller has no MYAPP_VAULT_ADDR".to_string(),
+        ));
+    };
+    let values = vault
+        .fetch(myapp, client.clone())
+        .await
+        .map_err(|e| ReconcileError::SecretError(e.to_string()))?;
+
+    let api: Api<Secret> = Api::namespaced(client.clone(), &myapp.namespace().unwrap());
+    let mut stamp = Vec::new();
+    let mut rotated = false;
+    for (name, data) in secret_names(myapp).into_iter().zip(values) {
+        stamp.push(data_hash(&data));
+        rotated |= sync_secret(&api, myapp, &name, data).await?;
+    }
+    if rotated {
+        restart(myapp, client, &stamp.join(",")).await?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_secret_data_reads_kv_v1_and_v2() {
+        let v1 = json!({ "data": { "password": "hunter2", "port": 5432 } });
+        let v2 = json!({ "data": {
+            "data": { "password": "hunter2", "port": 5432 },
+            "metadata": { "version": 3 }
+        } });
+        let expected = BTreeMap::from([
+            ("password".to_string(), "hunter2".to_string()),
+            ("port".to_string(), "5432".to_string()),
+        ]);
+        assert_eq!(secret_data(&v1), Some(expected.clone()));
+        assert_eq!(secret_data(&v2), Some(expected.clone()));
+
+        let picked = select(expected, &["password".to_string()], "secret/data/db").unwrap();
+        assert_eq!(picked.len(), 1);
+    }
+
+    #[test]
+    fn test_select_reports_missing_keys() {
+        let data = BTreeMap::from([("user".to_string(), "app".to_string())]);
+        let err = select(data, &["password".to_string()], "secret/data/db").unwrap_err();
+        assert_eq!(err.to_string(), "secret/data/db has no key password");
+    }
+}
@@ -0,0 +1,82 @@
+// Admission warnings for MyApp Controller
+// Risky but legal configurations are admitted with warnings that kubectl prints, rather
+// than being rejected outright or going unnoticed
+
+use crate::workload::WorkloadType;
+use crate::MyAppSpec;
+use kube::core::admission::AdmissionResponse;
+
+/// Warnings for `spec`; kubectl shows each as "Warning: <message>"
+pub fn check(spec: &MyAppSpec) -> Vec<String> {
+    let mut warnings = Vec::new();
+
+    if spec.workload_type == WorkloadType::Deployment && spec.replicas == 1 {
+        warnings.push("spec.replicas=1 offers no high availability".to_string());
+    }
+
+    let limits = spec.resources.as_ref().and_then(|r| r.limits.as_ref());
+    match limits {
+        None => warnings.push(
+            "spec.resources.limits not set; pods may use unbounded CPU and memory".to_string(),
+        ),
+        Some(limits) if limits.memory.is_none() => warnings
+            .push("spec.resources.limits.memory not set; a leak can exhaust the node".to_string()),
+        Some(_) => {}
+    }
+
+    let name = spec.image.rsplit('/').next().unwrap_or_default();
+    if !name.contains(':') && !name.contains('@') {
+        warnings.push(format!(
+            "spec.image {} has no tag and resolves to latest; pin a tag or digest",
+            spec.image
+        ));
+    }
+
+    if spec.paused {
+        warnings.push("spec.paused is set; changes will not be rolled out".to_string());
+    }
+    warnings
+}
+
+/// Attach the warnings for `spec` to `res`
+pub fn attach(mut res: AdmissionResponse, spec: &MyAppSpec) -> AdmissionResponse {
+    let warnings = check(spec);
+    if !warnings.is_empty() {
+        res.warnings = Some(warnings);
+    }
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ResourceLimits, ResourceRequirements};
+
+    #[test]
+    fn test_check() {
+        let mut spec = MyAppSpec {
+            replicas: 1,
+            image: "nginx".to_string(),
+            ..Default::default()
+        };
+        let warnings = check(&spec);
+        assert_eq!(warnings.len(), 3);
+        assert!(warnings[0].contains("no high availability"));
+
+        spec.replicas = 3;
+        spec.image = "registry.local:5000/nginx:1.27".to_string();
+        spec.resources = Some(ResourceRequirements {
+            cpu: "100m".to_string(),
+            memory: "128Mi".to_string(),
+            limits: Some(ResourceLimits {
+                memory: Some("256Mi".to_string()),
+                ..Default::default()
+            }),
+        });
+        assert!(check(&spec).is_empty());
+
+        spec.workload_type = WorkloadType::Job;
+        spec.replicas = 1;
+        assert!(check(&spec).is_empty());
+    }
+}
@@ -0,0 +1,155 @@
+// Liveness watchdog for MyApp Controller
+// Fails /health when a controller stops reconciling or its watch keeps failing,
+// so the kubelet restarts a wedged process instead of it silently doing nothing
+
+use crate::MyApp;
+use kube::runtime::reflector::Store;
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Staleness after which the controller is reported dead, from
+/// `MYAPP_LIVENESS_STALENESS_SECS` (default 1h, longer than any requeue or backoff)
+pub fn staleness_from_env() -> Duration {
+    let secs = std::env::var("MYAPP_LIVENESS_STALENESS_SECS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(3600);
+    Duration::from_secs(secs)
+}
+
+/// MyApps a stream is expected to keep reconciling
+type Expected = Arc<dyn Fn(&MyApp) -> bool + Send + Sync>;
+
+struct StreamState {
+    last_reconcile: Instant,
+    watch_failing_since: Option<Instant>,
+    store: Option<(Store<MyApp>, Expected)>,
+}
+
+/// Progress of one controller stream
+#[derive(Clone)]
+pub struct StreamProbe(Arc<Mutex<StreamState>>);
+
+impl StreamProbe {
+    /// Only streams that hold MyApps this replica `expected` to requeue must keep reconciling;
+    /// paused apps or those of other shards may stay idle indefinitely
+    pub fn track(
+        &self,
+        store: Store<MyApp>,
+        expected: impl Fn(&MyApp) -> bool + Send + Sync + 'static,
+    ) {
+        self.0.lock().unwrap().store = Some((store, Arc::new(expected)));
+    }
+
+    /// A reconcile finished, successfully or not
+    pub fn reconciled(&self) {
+        self.0.lock().unwrap().last_reconcile = Instant::now();
+    }
+
+    pub fn watch_event(&self, ok: bool) {
+        let mut state = self.0.lock().unwrap();
+        if ok {
+            state.watch_failing_since = None;
+        } else {
+            state.watch_failing_since.get_or_insert_with(Instant::now);
+        }
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct Watchdog {
+    streams: Arc<Mutex<BTreeMap<String, StreamProbe>>>,
+    staleness: Duration,
+}
+
+impl Watchdog {
+    pub fn new(staleness: Duration) -> Self {
+        Self {
+            streams: Default::default(),
+            staleness,
+        }
+    }
+
+    /// Register a controller stream; startup counts as its first tick
+    pub fn stream(&self, name: &str) -> StreamProbe {
+        let probe = StreamProbe(Arc::new(Mutex::new(StreamState {
+            last_reconcile: Instant::now(),
+            watch_failing_since: None,
+            store: None,
+        })));
+        self.streams
+            .lock()
+            .unwrap()
+            .insert(name.to_string(), probe.clone());
+        probe
+    }
+
+    /// Why the controller is considered wedged, if it is
+    pub fn check(&self) -> Result<(), String> {
+        self.check_at(Instant::now())
+    }
+
+    fn check_at(&self, now: Instant) -> Result<(), String> {
+        for (name, probe) in self.streams.lock().unwrap().iter() {
+            let state = probe.0.lock().unwrap();
+            if let Some(since) = state.watch_failing_since {
+                let failing = now.saturating_duration_since(since);
+                if failing > self.staleness {
+                    return Err(format!(
+                        "MyApp watch in {} failing for {}s",
+                        name,
+                        failing.as_secs()
+                    ));
+                }
+            }
+            let idle = now.saturating_duration_since(state.last_reconcile);
+            let has_work = state
+                .store
+                .as_ref()
+                .is_some_and(|(store, expected)| store.state().iter().any(|m| expected(m)));
+            if has_work && idle > self.staleness {
+                return Err(format!("no reconcile in {} for {}s", name, idle.as_secs()));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use kube::runtime::reflector;
+    use kube::runtime::watcher::Event;
+
+    #[test]
+    fn test_watchdog() {
+        let watchdog = Watchdog::new(Duration::from_secs(60));
+        let probe = watchdog.stream("shop");
+        let later = Instant::now() + Duration::from_secs(120);
+
+        // Nothing to reconcile: idle is fine
+        let (store, mut writer) = reflector::store::<MyApp>();
+        probe.track(store, |myapp| !myapp.is_paused());
+        assert_eq!(watchdog.check_at(later), Ok(()));
+
+        // Paused apps wait for a change, so they do not need reconciles
+        let mut paused = MyApp::new("frozen", Default::default());
+        paused.spec.paused = true;
+        writer.apply_watcher_event(&Event::Apply(paused));
+        assert_eq!(watchdog.check_at(later), Ok(()));
+
+        writer.apply_watcher_event(&Event::Apply(MyApp::new("web", Default::default())));
+        assert!(watchdog
+            .check_at(later)
+            .unwrap_err()
+            .contains("no reconcile"));
+        probe.reconciled();
+        assert_eq!(watchdog.check_at(Instant::now()), Ok(()));
+
+        probe.watch_event(false);
+        assert!(watchdog.check_at(later).unwrap_err().contains("failing"));
+        probe.watch_event(true);
+        assert_eq!(watchdog.check_at(Instant::now()), Ok(()));
+    }
+}
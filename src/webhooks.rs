@@ -0,0 +1,205 @@
+// Admission webhook definitions for MyApp Controller
+// The handlers' paths, rules and failure behaviour live here; the routes are built from
+// them and `generate-webhooks` emits matching webhook configurations, so the two cannot drift
+
+use crate::certs::WebhookObjects;
+use crate::MyApp;
+use k8s_openapi::api::admissionregistration::v1::{
+    MutatingWebhook, MutatingWebhookConfiguration, RuleWithOperations, ServiceReference,
+    ValidatingWebhook, ValidatingWebhookConfiguration, WebhookClientConfig,
+};
+use kube::Resource;
+use std::collections::BTreeMap;
+
+/// One admission webhook served by the webhook process
+#[derive(Debug, Clone, Copy)]
+pub struct WebhookDef {
+    /// Fully qualified webhook name
+    pub name: &'static str,
+    /// Route segment, served at `/<path>`
+    pub path: &'static str,
+    pub operations: &'static [&'static str],
+    pub failure_policy: &'static str,
+    pub side_effects: &'static str,
+    pub timeout_seconds: i32,
+}
+
+pub const VALIDATE: WebhookDef = WebhookDef {
+    name: "validate.myapps.example.com",
+    path: "validate",
+    operations: &["CREATE", "UPDATE", "DELETE"],
+    failure_policy: "Fail",
+    // The audit trail writes Events and ConfigMaps, except for dry-run requests
+    side_effects: "NoneOnDryRun",
+    timeout_seconds: 10,
+};
+
+pub const MUTATE: WebhookDef = WebhookDef {
+    name: "mutate.myapps.example.com",
+    path: "mutate",
+    operations: &["CREATE", "UPDATE"],
+    failure_policy: "Fail",
+    side_effects: "None",
+    timeout_seconds: 10,
+};
+
+impl WebhookDef {
+    fn client_config(&self, objects: &WebhookObjects) -> WebhookClientConfig {
+        WebhookClientConfig {
+            service: Some(ServiceReference {
+                name: objects.service.clone(),
+                namespace: objects.namespace.clone(),
+                path: Some(format!("/{}", self.path)),
+                port: Some(443),
+            }),
+            ..Default::default()
+        }
+    }
+
+    /// Rules matching MyApp in its CRD group and version
+    fn rules(&self) -> Vec<RuleWithOperations> {
+        vec![RuleWithOperations {
+            api_groups: Some(vec![MyApp::group(&()).to_string()]),
+            api_versions: Some(vec![MyApp::version(&()).to_string()]),
+            resources: Some(vec![MyApp::plural(&()).to_string()]),
+            operations: Some(self.operations.iter().map(|o| o.to_string()).collect()),
+            scope: None,
+        }]
+    }
+}
+
+fn review_versions() -> Vec<String> {
+    vec!["v1".to_string(), "v1beta1".to_string()]
+}
+
+/// cert-manager's cainjector annotation, when the Certificate is managed by cert-manager
+fn annotations(objects: &WebhookObjects, cert_manager: bool) -> Option<BTreeMap<String, String>> {
+    cert_manager.then(|| {
+        BTreeMap::from([(
+            crate::cert_manager::INJECT_CA_ANNOTATION.to_string(),
+            format!("{}/{}", objects.namespace, objects.service),
+        )])
+    })
+}
+
+pub fn validating_configuration(
+    objects: &WebhookObjects,
+    cert_manager: bool,
+) -> ValidatingWebhookConfiguration {
+    let mut config = ValidatingWebhookConfiguration {
+        webhooks: Some(vec![ValidatingWebhook {
+            name: VALIDATE.name.to_string(),
+            admission_review_versions: review_versions(),
+            client_config: VALIDATE.client_config(objects),
+            rules: Some(VALIDATE.rules()),
+            failure_policy: Some(VALIDATE.failure_policy.to_string()),
+            side_effects: VALIDATE.side_effects.to_string(),
+            timeout_seconds: Some(VALIDATE.timeout_seconds),
+            ..Default::default()
+        }]),
+        ..Default::default()
+    };
+    config.metadata.name = Some(objects.validating_webhook.clone());
+    config.metadata.annotations = annotations(objects, cert_manager);
+    config
+}
+
+pub fn mutating_configuration(
+    objects: &WebhookObjects,
+    cert_manager: bool,
+) -> MutatingWebhookConfiguration {
+    let mut config = MutatingWebhookConfiguration {
+        webhooks: Some(vec![MutatingWebhook {
+            name: MUTATE.name.to_string(),
+            admission_review_versions: review_versions(),
+            client_config: MUTATE.client_config(objects),
+            rules: Some(MUTATE.rules()),
+            failure_policy: Some(MUTATE.failure_policy.to_string()),
+            side_effects: MUTATE.side_effects.to_string(),
+            timeout_seconds: Some(MUTATE.timeout_seconds),
+            ..Default::default()
+        }]),
+        ..Default::default()
+    };
+    config.metadata.name = Some(objects.mutating_webhook.clone());
+    config.metadata.annotations = annotations(objects, cert_manager);
+    config
+}
+
+/// `generate-webhooks [--namespace <ns>] [--service <name>] [--cert-manager]`; names
+/// default to the same environment variables the webhook server reads
+pub fn run(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let mut objects = WebhookObjects::from_env();
+    let mut cert_manager = false;
+
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--namespace" => {
+                objects.namespace = iter.next().cloned().ok_or("--namespace needs a value")?;
+            }
+            "--service" => {
+                objects.service = iter.next().cloned().ok_or("--service needs a value")?;
+            }
+            "--cert-manager" => cert_manager = true,
+            other => return Err(format!("unknown generate-webhooks argument: {}", other).into()),
+        }
+    }
+
+    let validating = validating_configuration(&objects, cert_manager);
+    let mutating = mutating_configuration(&objects, cert_manager);
+    print!(
+        "---\n{}---\n{}",
+        serde_yaml::to_string(&validating)?,
+        serde_yaml::to_string(&mutating)?
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+
+    /// deploy/webhook-manifests.yaml must agree with the handler definitions
+    #[test]
+    fn test_deploy_manifests_match() {
+        let objects = WebhookObjects::from_env();
+        let manifests = include_str!("../deploy/webhook-manifests.yaml");
+        let docs: Vec<serde_yaml::Value> = serde_yaml::Deserializer::from_str(manifests)
+            .map(serde_yaml::Value::deserialize)
+            .collect::<Result<_, _>>()
+            .unwrap();
+        let find = |kind: &str| docs.iter().find(|d| d["kind"] == kind).unwrap().clone();
+
+        let deployed: ValidatingWebhookConfiguration =
+            serde_yaml::from_value(find("ValidatingWebhookConfiguration")).unwrap();
+        let generated = validating_configuration(&objects, false);
+        let (deployed, generated) = (
+            &deployed.webhooks.unwrap()[0],
+            &generated.webhooks.unwrap()[0],
+        );
+        assert_eq!(deployed.name, generated.name);
+        assert_eq!(deployed.rules, generated.rules);
+        assert_eq!(deployed.failure_policy, generated.failure_policy);
+        assert_eq!(deployed.side_effects, generated.side_effects);
+        assert_eq!(deployed.timeout_seconds, generated.timeout_seconds);
+        assert_eq!(
+            deployed.client_config.service.as_ref().unwrap().path,
+            generated.client_config.service.as_ref().unwrap().path
+        );
+
+        let deployed: MutatingWebhookConfiguration =
+            serde_yaml::from_value(find("MutatingWebhookConfiguration")).unwrap();
+        let generated = mutating_configuration(&objects, false);
+        let (deployed, generated) = (
+            &deployed.webhooks.unwrap()[0],
+            &generated.webhooks.unwrap()[0],
+        );
+        assert_eq!(deployed.name, generated.name);
+        assert_eq!(deployed.rules, generated.rules);
+        assert_eq!(deployed.failure_policy, generated.failure_policy);
+        assert_eq!(deployed.side_effects, generated.side_effects);
+        assert_eq!(deployed.timeout_seconds, generated.timeout_seconds);
+    }
+}
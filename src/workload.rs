@@ -0,0 +1,389 @@
+// Workload module for MyApp Controller
+// Supports running a MyApp as a long-lived Deployment or as a one-shot Job / scheduled CronJob.
+// CronJobs are applied every reconcile; a Job's template is immutable, so a Job whose spec hash
+// no longer matches is deleted and created again
+
+use crate::failure::FailurePolicy;
+use crate::spec_hash::{fnv1a, SPEC_HASH_ANNOTATION};
+use crate::{child_name, create_owner_reference, Condition, MyApp};
+use k8s_openapi::api::batch::v1::{CronJob, CronJobSpec, Job, JobSpec, JobTemplateSpec};
+use k8s_openapi::api::core::v1::{PodSpec, PodTemplateSpec};
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta;
+use kube::api::{Api, DeleteParams, Patch, PatchParams, PostParams};
+use kube::{Client, ResourceExt};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Kind of workload generated for a MyApp
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, JsonSchema, Default)]
+pub enum WorkloadType {
+    /// Long-running Deployment fronted by a Service (default)
+    #[default]
+    Deployment,
+    /// Run-to-completion Job
+    Job,
+    /// Job created on a cron schedule
+    CronJob,
+}
+
+/// Settings for Job and CronJob workloads
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct JobConfig {
+    /// Cron schedule (required for CronJob workloads)
+    #[serde(default)]
+    pub schedule: Option<String>,
+
+    /// Number of retries before the Job is marked failed
+    #[serde(default)]
+    #[schemars(range(min = 0))]
+    pub backoff_limit: Option<i32>,
+
+    /// Seconds to keep a finished Job before it is garbage collected
+    #[serde(default)]
+    #[schemars(range(min = 0))]
+    pub ttl_seconds_after_finished: Option<i32>,
+
+    /// How overlapping CronJob runs are treated (Allow, Forbid, Replace)
+    #[serde(default)]
+    pub concurrency_policy: Option<String>,
+
+    /// Controller-managed retries, alerting and suspension for failed runs
+    #[serde(default)]
+    pub failure_policy: Option<FailurePolicy>,
+}
+
+/// Outcome of the most recent Job run
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct LastRunStatus {
+    /// Name of the Job that ran
+    #[serde(default)]
+    pub job_name: Option<String>,
+
+    /// When the run started
+    #[serde(default)]
+    pub start_time: Option<String>,
+
+    /// When the run finished
+    #[serde(default)]
+    pub completion_time: Option<String>,
+
+    /// Active, Succeeded or Failed
+    pub result: String,
+}
+
+impl LastRunStatus {
+    pub fn is_active(&self) -> bool {
+        self.result == "Active"
+    }
+
+    /// Condition describing the run (Complete or Failed)
+    pub fn condition(&self) -> Condition {
+        match self.result.as_str() {
+            "Succeeded" => Condition::new("Complete", true, "JobSucceeded", "Last run succeeded"),
+            "Failed" => Condition::new("Failed", true, "JobFailed", "Last run failed"),
+            _ => Condition::new("Complete", false, "JobActive", "Run in progress"),
+        }
+    }
+}
+
+const CONCURRENCY_POLICIES: &[&str] = &["Allow", "Forbid", "Replace"];
+
+/// Validate the workload-specific parts of the spec
+pub fn validate(workload_type: WorkloadType, job: Option<&JobConfig>) -> Result<(), String> {
+    if workload_type == WorkloadType::CronJob && job.and_then(|j| j.schedule.as_deref()).is_none() {
+        return Err("job.schedule is required for CronJob workloads".to_string());
+    }
+
+    if let Some(policy) = job.and_then(|j| j.concurrency_policy.as_deref()) {
+        if !CONCURRENCY_POLICIES.contains(&policy) {
+            return Err(format!(
+                "job.concurrencyPolicy must be one of {}",
+                CONCURRENCY_POLICIES.join(", ")
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+pub fn job_name(myapp: &MyApp) -> String {
+    child_name(myapp, "job")
+}
+
+pub fn cronjob_name(myapp: &MyApp) -> String {
+    child_name(myapp, "cronjob")
+}
+
+fn job_spec(myapp: &MyApp, labels: &BTreeMap<String, String>, pod_spec: PodSpec) -> JobSpec {
+    let config = myapp.spec.job.clone().unwrap_or_default();
+
+    JobSpec {
+        backoff_limit: config.backoff_limit,
+        ttl_seconds_after_finished: config.ttl_seconds_after_finished,
+        template: PodTemplateSpec {
+            metadata: Some(ObjectMeta {
+                labels: Some(labels.clone()),
+                annotations: crate::security_profiles::template_annotations(myapp, "app"),
+                ..Default::default()
+            }),
+            spec: Some(PodSpec {
+                restart_policy: Some("Never".to_string()),
+                ..pod_spec
+            }),
+        },
+        ..Default::default()
+    }
+}
+
+/// Hash of a Job's spec, stable across builds
+pub fn job_spec_hash(spec: Option<&JobSpec>) -> String {
+    format!(
+        "{:016x}",
+        fnv1a(&serde_json::to_vec(&spec).unwrap_or_default())
+    )
+}
+
+/// Job manifest for a MyApp, annotated with the hash of its spec
+pub fn build_job(myapp: &MyApp, pod_spec: PodSpec) -> Job {
+    let labels = crate::app_labels(myapp);
+    let spec = job_spec(myapp, &labels, pod_spec);
+
+    Job {
+        metadata: ObjectMeta {
+            name: Some(job_name(myapp)),
+            namespace: myapp.namespace(),
+            labels: Some(labels.clone()),
+            annotations: Some(BTreeMap::from([(
+                SPEC_HASH_ANNOTATION.to_string(),
+                job_spec_hash(Some(&spec)),
+            )])),
+            owner_references: Some(vec![create_owner_reference(myapp)]),
+            ..Default::default()
+        },
+        spec: Some(spec),
+        ..Default::default()
+    }
+}
+
+/// Whether `live` was created from a different spec than `desired`; Jobs created before
+/// spec hashes are left alone rather than run again
+pub fn job_outdated(live: &Job, desired: &Job) -> bool {
+    let hash = |job: &Job| job.annotations().get(SPEC_HASH_ANNOTATION).cloned();
+    hash(live).is_some() && hash(live) != hash(desired)
+}
+
+/// Create the MyApp's Job, replacing it when the spec changed. Returns None while an
+/// outdated Job is being deleted; the next reconcile creates its replacement
+pub async fn sync_job(
+    myapp: &MyApp,
+    pod_spec: PodSpec,
+    client: Client,
+) -> Result<Option<Job>, kube::Error> {
+    let api: Api<Job> = Api::namespaced(client, &myapp.namespace().unwrap());
+    let name = job_name(myapp);
+    let desired = build_job(myapp, pod_spec);
+
+    match api.get_opt(&name).await? {
+        None => {
+            let job = api.create(&PostParams::default(), &desired).await?;
+            tracing::info!("Created job {} with owner reference", name);
+            Ok(Some(job))
+        }
+        Some(live) if live.metadata.deletion_timestamp.is_some() => Ok(None),
+        Some(live) if job_outdated(&live, &desired) => {
+            // Foreground, so the old pods are gone before the new run starts
+            api.delete(&name, &DeleteParams::foreground()).await?;
+            tracing::info!("Deleting job {} to run the changed spec", name);
+            Ok(None)
+        }
+        Some(live) => Ok(Some(live)),
+    }
+}
+
+pub fn build_cronjob(myapp: &MyApp, pod_spec: PodSpec) -> CronJob {
+    let labels = crate::app_labels(myapp);
+    let config = myapp.spec.job.clone().unwrap_or_default();
+
+    CronJob {
+        metadata: ObjectMeta {
+            name: Some(cronjob_name(myapp)),
+            namespace: myapp.namespace(),
+            labels: Some(labels.clone()),
+            owner_references: Some(vec![create_owner_reference(myapp)]),
+            ..Default::default()
+        },
+        spec: Some(CronJobSpec {
+            schedule: config.schedule.clone().unwrap_or_default(),
+            concurrency_policy: config.concurrency_policy.clone(),
+            job_template: JobTemplateSpec {
+                metadata: Some(ObjectMeta {
+                    labels: Some(labels.clone()),
+                    ..Default::default()
+                }),
+                spec: Some(job_spec(myapp, &labels, pod_spec)),
+            },
+            ..Default::default()
+        }),
+        ..Default::default()
+    }
+}
+
+/// Create or update the MyApp's CronJob with server-side apply
+pub async fn apply_cronjob(
+    myapp: &MyApp,
+    pod_spec: PodSpec,
+    client: Client,
+) -> Result<CronJob, kube::Error> {
+    let api: Api<CronJob> = Api::namespaced(client, &myapp.namespace().unwrap());
+    api.patch(
+        &cronjob_name(myapp),
+        &PatchParams::apply("myapp-controller").force(),
+        &Patch::Apply(&build_cronjob(myapp, pod_spec)),
+    )
+    .await
+}
+
+/// Summarize the run state of a Job
+pub fn job_run_status(job: &Job) -> LastRunStatus {
+    let status = job.status.clone().unwrap_or_default();
+    let finished = |type_: &str| {
+        status
+            .conditions
+            .iter()
+            .flatten()
+            .any(|c| c.type_ == type_ && c.status == "True")
+    };
+
+    let result = if finished("Complete") {
+        "Succeeded"
+    } else if finished("Failed") {
+        "Failed"
+    } else {
+        "Active"
+    };
+
+    LastRunStatus {
+        job_name: job.metadata.name.clone(),
+        start_time: status.start_time.map(|t| t.0.to_rfc3339()),
+        completion_time: status.completion_time.map(|t| t.0.to_rfc3339()),
+        result: result.to_string(),
+    }
+}
+
+/// Summarize the most recent scheduled run of a CronJob, if it has run at all
+pub fn cronjob_run_status(cronjob: &CronJob) -> Option<LastRunStatus> {
+    let status = cronjob.status.clone().unwrap_or_default();
+    let scheduled = status.last_schedule_time?.0;
+    let active = status.active.iter().flatten().next().is_some();
+
+    let result = match &status.last_successful_time {
+        _ if active => "Active",
+        Some(t) if t.0 >= scheduled => "Succeeded",
+        _ => "Failed",
+    };
+
+    Some(LastRunStatus {
+        job_name: None,
+        start_time: Some(scheduled.to_rfc3339()),
+        completion_time: status
+            .last_successful_time
+            .filter(|_| result == "Succeeded")
+            .map(|t| t.0.to_rfc3339()),
+        result: result.to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use k8s_openapi::api::batch::v1::{CronJobStatus, JobCondition, JobStatus};
+    use k8s_openapi::apimachinery::pkg::apis::meta::v1::Time;
+
+    #[test]
+    fn test_cronjob_requires_schedule() {
+        assert!(validate(WorkloadType::CronJob, None).is_err());
+        assert!(validate(WorkloadType::Job, None).is_ok());
+
+        let config = JobConfig {
+            schedule: Some("*/5 * * * *".to_string()),
+            concurrency_policy: Some("Sometimes".to_string()),
+            ..Default::default()
+        };
+        assert!(validate(WorkloadType::CronJob, Some(&config)).is_err());
+    }
+
+    #[test]
+    fn test_changed_spec_replaces_job() {
+        let mut myapp = MyApp::new(
+            "batch",
+            crate::MyAppSpec {
+                image: "busybox:1.36".to_string(),
+                workload_type: WorkloadType::Job,
+                ..Default::default()
+            },
+        );
+        myapp.metadata.namespace = Some("default".to_string());
+        myapp.metadata.uid = Some("uid".to_string());
+        let live = build_job(&myapp, crate::build_pod_spec(&myapp));
+
+        // Unrelated edits render the same Job
+        myapp.spec.replicas = 3;
+        let desired = build_job(&myapp, crate::build_pod_spec(&myapp));
+        assert!(!job_outdated(&live, &desired));
+
+        myapp.spec.image = "busybox:1.37".to_string();
+        let desired = build_job(&myapp, crate::build_pod_spec(&myapp));
+        assert!(job_outdated(&live, &desired));
+
+        myapp.spec.job = Some(JobConfig {
+            backoff_limit: Some(2),
+            ..Default::default()
+        });
+        let changed = build_job(&myapp, crate::build_pod_spec(&myapp));
+        assert!(job_outdated(&desired, &changed));
+
+        // Jobs created before spec hashes are kept
+        let mut unhashed = live.clone();
+        unhashed.metadata.annotations = None;
+        assert!(!job_outdated(&unhashed, &desired));
+    }
+
+    #[test]
+    fn test_job_run_status() {
+        let job = Job {
+            status: Some(JobStatus {
+                conditions: Some(vec![JobCondition {
+                    type_: "Failed".to_string(),
+                    status: "True".to_string(),
+                    ..Default::default()
+                }]),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+
+        let run = job_run_status(&job);
+        assert_eq!(run.result, "Failed");
+        assert_eq!(run.condition().r#type, "Failed");
+    }
+
+    #[test]
+    fn test_cronjob_run_status() {
+        let now = chrono::Utc::now();
+        let cronjob = CronJob {
+            status: Some(CronJobStatus {
+                last_schedule_time: Some(Time(now)),
+                last_successful_time: Some(Time(now + chrono::Duration::seconds(30))),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+
+        let run = cronjob_run_status(&cronjob).unwrap();
+        assert_eq!(run.result, "Succeeded");
+        assert!(cronjob_run_status(&CronJob::default()).is_none());
+    }
+}